@@ -17,6 +17,15 @@ delete = Delete
 import = Import
 import-errors = Import errors
 
+## Sessions
+sessions = Sessions
+session-name = Session name
+save-session = Save
+saved-sessions = Saved sessions
+open-session = Open
+restore-last-session = Restore last session
+restore-last-session-description = Reopen the splits and tabs of the last closed window on startup
+
 ## Profiles
 profiles = Profiles
 name = Name
@@ -29,6 +38,11 @@ make-default = Make default
 working-directory = Working directory
 hold = Hold
 remain-open = Remain open after child process exits.
+profile-session-log = Log output
+profile-session-log-description = Tabs opened with this profile log their output to a file.
+profile-persist-scrollback = Keep scrollback after restarts
+profile-persist-scrollback-description = New tabs with this profile show the history of a tab from a previous run above their prompt.
+profile-default = Default
 
 ## Settings
 settings = Settings
@@ -43,6 +57,13 @@ syntax-dark = Color scheme dark
 syntax-light = Color scheme light
 default-zoom-step = Zoom steps
 opacity = Background opacity
+cursor-shape = Cursor shape
+cursor-block = Block
+cursor-underline = Underline
+cursor-beam = Beam
+cursor-blink = Blinking cursor
+cursor-blinking = Blinking
+cursor-steady = Steady
 
 ### Font
 font = Font
@@ -60,12 +81,80 @@ splits = Splits
 focus-follow-mouse = Typing focus follows mouse
 show-pane-borders = Show pane borders
 
+### Bell
+bell = Bell
+bell-visual = Flash the terminal
+bell-urgent = Request window attention
+bell-tab-badge = Mark inactive tabs
+bell-sound = Play sound
+copy-options = Copying
+copy-on-select = Copy on select
+copy-on-select-description = Also copy selected text to the clipboard, not only the primary selection
+copy-trim-trailing-whitespace = Trim trailing whitespace
+copy-join-wrapped-lines = Join wrapped lines
+copy-rich-text = Copy colors and styles
+copy-rich-text-description = Copy text as HTML and RTF as well, for pasting into documents
+session-logs = Session logs
+session-log-directory = Folder
+session-log-file-name = File name
+session-log-file-name-description = {"{"}date{"}"}, {"{"}time{"}"} (in UTC), {"{"}profile{"}"} and {"{"}title{"}"} are replaced
+session-log-strip-escapes = Remove escape sequences
+session-log-strip-escapes-description = Log only the text, without colors and cursor movements
+session-log-max-size = Start a new file after
+session-log-no-rotation = Never
+session-log-size = { $size } MB
+session-log-failed = Stopped logging output of { $title }
+notifications = Notifications
+notify-command-finished = Notify when commands finish
+notify-command-finished-description = Requires shell integration. Only commands in hidden tabs or windows notify.
+notify-min-duration = Minimum command duration
+notify-app-requests = Show notifications from applications
+notify-tab-badge = Mark inactive tabs
+command-finished = Command finished in { $tab }
+command-failed = Command failed in { $tab } with exit status { $status }
+command-duration = Ran for { $duration }
+notifications-suppressed = { $count ->
+    [one] 1 more notification from this tab was not shown
+    *[other] { $count } more notifications from this tab were not shown
+}
+duration-seconds = { $seconds ->
+    [one] 1 second
+    *[other] { $seconds } seconds
+}
+duration-minutes = { $minutes ->
+    [one] 1 minute
+    *[other] { $minutes } minutes
+}
+
 ### Advanced
 advanced = Advanced
 show-headerbar = Show header
 show-header-description = Reveal the header from the right-click menu
 tab-new-inherit-working-directory = New tabs and windows use current directory
-tab-new-inherit-working-directory-description = Open new tabs and windows in the active tab's working directory
+tab-new-inherit-working-directory-description = Open new tabs, splits and windows in the active tab's working directory
+tab-new-ssh-remote = Reconnect to remote hosts
+tab-new-ssh-remote-description = New tabs opened from a shell on another host connect to it with ssh. Requires the shell to report its directory.
+remote-control = Allow remote control
+remote-control-description = Programs run by your user can open, read and type into terminals with cosmic-term cli.
+clipboard-read = Clipboard access
+clipboard-read-description = Allow programs to read the clipboard
+allow = Allow
+ask = Ask
+deny = Deny
+scrollback-lines = Scrollback
+scrollback-lines-description = Lines of history kept for each terminal
+persist-scrollback-max-size = Saved scrollback
+persist-scrollback-max-size-description = History saved for each tab of profiles that keep it after restarts
+persist-scrollback-size = { $size } MB
+restored-history = Restored history
+scrollback-none = None
+scrollback-limited = {$lines} lines
+scrollback-unlimited = Unlimited
+word-separators = Word separators
+word-separators-description = Characters ending the words selected by double-clicks. Links, paths and email addresses are always selected whole.
+word-separators-inherit = Leave empty to use the default word separators
+clipboard-read-request = A program wants to read the clipboard
+selection-read-request = A program wants to read the primary selection
 
 ### Keyboard shortcuts
 add-another-keybinding = Add another keybinding
@@ -87,6 +176,7 @@ reset-to-default = Reset to default
 shortcut-capture-hint = Press the key combination
 shortcut-group-clipboard = Clipboard
 shortcut-group-other = Other
+shortcut-group-shell-integration = Shell integration
 shortcut-group-tabs = Tabs
 shortcut-group-window = Window
 shortcut-group-zoom = Zoom
@@ -100,6 +190,33 @@ type-to-search = Type to search...
 find-placeholder = Find...
 find-previous = Find previous
 find-next = Find next
+find-position = {$position} of {$count}
+find-count = { $count ->
+    [one] 1 match
+    *[other] { $count } matches
+}
+find-no-matches = No matches
+find-searching = { $count ->
+    [one] 1 match
+    *[other] { $count } matches
+}, searching { $percent }%
+find-invalid-regex = Invalid regular expression
+find-case-sensitive = Match case
+find-whole-word = Match whole words
+find-regex = Use regular expression
+
+# Hints
+hints-copy = Copy a link, path or hash
+hints-open = Open a link or path
+hints-paste = Paste a link, path or hash
+
+# Copy mode
+copy-mode-toggle = Select with keyboard
+copy-mode = Copy mode
+copy-mode-visual = Copy mode, selecting
+copy-mode-visual-line = Copy mode, selecting lines
+copy-mode-visual-block = Copy mode, selecting a block
+copy-mode-help = Move with hjkl, select with v, copy with y, search with /, leave with q
 
 # Menu
 
@@ -109,16 +226,33 @@ new-tab = New tab
 new-window = New window
 profile = Profile
 menu-profiles = Profiles...
+menu-sessions = Sessions...
+save-scrollback = Save scrollback as
+save-scrollback-html = Save scrollback as HTML
+save-scrollback-text = Save scrollback as plain text
+save-scrollback-ansi = Save scrollback with ANSI colors
+format-html = HTML
+format-text = Plain text
+format-ansi = ANSI colors
+session-log-toggle = Start or stop logging output
 close-tab = Close tab
 quit = Quit
 
 ## Edit
 edit = Edit
 copy = Copy
+copy-as = Copy as
+copy-as-html = Copy as HTML
+copy-as-text = Copy as plain text
+copy-as-ansi = Copy with ANSI colors
 paste = Paste
 select-all = Select all
 find = Find
 clear-scrollback = Clear scrollback
+previous-prompt = Previous prompt
+next-prompt = Next prompt
+select-last-command-output = Select last command output
+copy-last-command-output = Copy last command output
 
 ## Open
 open-link = Open Link
@@ -0,0 +1,24 @@
+# Shell integration for COSMIC Terminal, requires bash 4.4 or later
+#
+# Add this to ~/.bashrc:
+#   source /usr/share/cosmic-term/shell-integration/cosmic-term.bash
+#
+# Marks prompts, commands and their output with OSC 133 sequences so the terminal can jump
+# between prompts, select the output of the last command and show its exit status. The
+# working directory is reported with OSC 7 so new tabs can open in it.
+
+if [[ $- == *i* ]] && [[ -z "${__cosmic_term_integration:-}" ]]; then
+    __cosmic_term_integration=1
+
+    __cosmic_term_precmd() {
+        local exit_status=$?
+        printf '\e]133;D;%s\a' "$exit_status"
+        local cwd="${PWD//\%/%25}"
+        printf '\e]7;file://%s%s\a' "$HOSTNAME" "${cwd// /%20}"
+        return $exit_status
+    }
+
+    PROMPT_COMMAND="__cosmic_term_precmd${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+    PS0="\e]133;C\a${PS0:-}"
+    PS1="\[\e]133;A\a\]${PS1}\[\e]133;B\a\]"
+fi
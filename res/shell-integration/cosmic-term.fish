@@ -0,0 +1,35 @@
+# Shell integration for COSMIC Terminal
+#
+# Add this to ~/.config/fish/config.fish:
+#   source /usr/share/cosmic-term/shell-integration/cosmic-term.fish
+#
+# Marks prompts, commands and their output with OSC 133 sequences so the terminal can jump
+# between prompts, select the output of the last command and show its exit status. The
+# working directory is reported with OSC 7 so new tabs can open in it.
+
+if status is-interactive; and not set -q __cosmic_term_integration
+    set -g __cosmic_term_integration 1
+    set -g __cosmic_term_running 0
+
+    functions -c fish_prompt __cosmic_term_fish_prompt
+    function fish_prompt
+        set -l cwd (string replace -a '%' '%25' -- $PWD | string replace -a ' ' '%20')
+        printf '\e]7;file://%s%s\a' $hostname $cwd
+        printf '\e]133;A\a'
+        __cosmic_term_fish_prompt
+        printf '\e]133;B\a'
+    end
+
+    function __cosmic_term_preexec --on-event fish_preexec
+        set -g __cosmic_term_running 1
+        printf '\e]133;C\a'
+    end
+
+    function __cosmic_term_postexec --on-event fish_postexec
+        set -l exit_status $status
+        if test $__cosmic_term_running = 1
+            printf '\e]133;D;%s\a' $exit_status
+            set -g __cosmic_term_running 0
+        end
+    end
+end
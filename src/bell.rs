@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{
+    process, thread,
+    time::{Duration, Instant},
+};
+
+/// How long the visual bell flash is shown
+pub const VISUAL_BELL_DURATION: Duration = Duration::from_millis(150);
+
+/// Backend used to play the audible bell
+pub trait BellSound {
+    fn play(&self);
+}
+
+/// Plays the freedesktop "bell" sound event using canberra-gtk-play
+pub struct CanberraBellSound;
+
+impl BellSound for CanberraBellSound {
+    fn play(&self) {
+        // Wait for the player in a thread so it does not block the UI or linger as a zombie
+        thread::spawn(|| {
+            match process::Command::new("canberra-gtk-play")
+                .args(["--id", "bell"])
+                .stdin(process::Stdio::null())
+                .stdout(process::Stdio::null())
+                .stderr(process::Stdio::null())
+                .status()
+            {
+                Ok(status) => {
+                    if !status.success() {
+                        log::warn!("failed to play bell sound: {}", status);
+                    }
+                }
+                Err(err) => {
+                    log::warn!("failed to play bell sound: {}", err);
+                }
+            }
+        });
+    }
+}
+
+/// Drops bells that ring too soon after the previous one
+#[derive(Debug, Default)]
+pub struct BellLimiter {
+    last_opt: Option<Instant>,
+}
+
+impl BellLimiter {
+    /// Returns true if a bell ringing at `now` should be handled
+    pub fn check(&mut self, now: Instant, min_interval: Duration) -> bool {
+        if let Some(last) = self.last_opt
+            && now.saturating_duration_since(last) < min_interval
+        {
+            return false;
+        }
+        self.last_opt = Some(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BellLimiter;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn bell_limiter_drops_bells_inside_interval() {
+        // A flood of BEL characters must only be handled once per interval,
+        // while dropped bells do not extend the interval.
+        let interval = Duration::from_millis(100);
+        let start = Instant::now();
+        let mut limiter = BellLimiter::default();
+        assert!(limiter.check(start, interval));
+        assert!(!limiter.check(start + Duration::from_millis(50), interval));
+        assert!(!limiter.check(start + Duration::from_millis(99), interval));
+        assert!(limiter.check(start + Duration::from_millis(100), interval));
+    }
+
+    #[test]
+    fn bell_limiter_zero_interval_allows_all() {
+        let start = Instant::now();
+        let mut limiter = BellLimiter::default();
+        assert!(limiter.check(start, Duration::ZERO));
+        assert!(limiter.check(start, Duration::ZERO));
+    }
+}
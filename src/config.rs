@@ -11,7 +11,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::sync::OnceLock;
 
-use crate::{fl, localize::LANGUAGE_SORTER, shortcuts::Shortcuts};
+use crate::{fl, localize::LANGUAGE_SORTER, shortcuts::Shortcuts, terminal::URL_REGEX};
 
 pub const CONFIG_VERSION: u64 = 1;
 pub const COSMIC_THEME_DARK: &str = "COSMIC Dark";
@@ -42,6 +42,155 @@ impl AppTheme {
     }
 }
 
+/// How to handle programs reading the clipboard with OSC 52
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum ClipboardReadPolicy {
+    Allow,
+    #[default]
+    Ask,
+    Deny,
+}
+
+impl ClipboardReadPolicy {
+    pub const ALL: [Self; 3] = [Self::Allow, Self::Ask, Self::Deny];
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum CursorShape {
+    #[default]
+    Block,
+    Underline,
+    Beam,
+}
+
+impl CursorShape {
+    pub const ALL: [Self; 3] = [Self::Block, Self::Underline, Self::Beam];
+}
+
+/// Lines of history kept above the screen
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum ScrollbackLines {
+    Limited(u32),
+    /// Keep as many lines as fit in the scrollback memory limit
+    Unlimited,
+}
+
+impl Default for ScrollbackLines {
+    fn default() -> Self {
+        Self::Limited(100_000)
+    }
+}
+
+impl ScrollbackLines {
+    pub const PRESETS: [Self; 6] = [
+        Self::Limited(0),
+        Self::Limited(1_000),
+        Self::Limited(10_000),
+        Self::Limited(100_000),
+        Self::Limited(1_000_000),
+        Self::Unlimited,
+    ];
+}
+
+/// Paths with an optional line and column, like `src/main.rs:12:4`
+const PATH_REGEX: &str = "(?:~|\\.{1,2})?/?(?:[A-Za-z0-9_.+-]+/)+[A-Za-z0-9_.+-]+(?::[0-9]+){0,2}\
+                          |[A-Za-z0-9_+-][A-Za-z0-9_.+-]*\\.[A-Za-z0-9]+:[0-9]+(?::[0-9]+)?";
+
+/// Characters ending the words selected by double-clicks, the default of alacritty
+const WORD_SEPARATORS_DEFAULT: &str = ",│`|:\"' ()[]{}<>\t";
+
+/// What selecting a hint does with the matched text
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum HintAction {
+    Copy,
+    Open,
+    Paste,
+}
+
+/// Regular expression matched by hint mode
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct HintPattern {
+    pub name: String,
+    pub regex: String,
+    /// Hint actions showing matches of this pattern
+    pub actions: Vec<HintAction>,
+}
+
+impl HintPattern {
+    fn new(name: &str, regex: &str, actions: &[HintAction]) -> Self {
+        Self {
+            name: name.to_string(),
+            regex: regex.to_string(),
+            actions: actions.to_vec(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct HintConfig {
+    /// Characters used for hint labels, the first ones are used for the shortest labels
+    pub alphabet: String,
+    pub patterns: Vec<HintPattern>,
+}
+
+impl Default for HintConfig {
+    fn default() -> Self {
+        use HintAction::{Copy, Open, Paste};
+        Self {
+            alphabet: "jfkdlsahgurieowpq".to_string(),
+            patterns: vec![
+                HintPattern::new("url", URL_REGEX, &[Copy, Open, Paste]),
+                HintPattern::new("path", PATH_REGEX, &[Copy, Open, Paste]),
+                HintPattern::new(
+                    "uuid",
+                    "[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}",
+                    &[Copy, Paste],
+                ),
+                HintPattern::new("sha", "(?-u:\\b)[0-9a-f]{7,40}(?-u:\\b)", &[Copy, Paste]),
+                HintPattern::new(
+                    "ipv4",
+                    "(?-u:\\b)(?:[0-9]{1,3}\\.){3}[0-9]{1,3}(?::[0-9]+)?(?-u:\\b)",
+                    &[Copy, Paste],
+                ),
+                HintPattern::new(
+                    "ipv6",
+                    "(?:[0-9a-fA-F]{1,4}:){7}[0-9a-fA-F]{1,4}\
+                     |(?:[0-9a-fA-F]{1,4}:){1,6}:(?:[0-9a-fA-F]{1,4}:){0,5}[0-9a-fA-F]{1,4}",
+                    &[Copy, Paste],
+                ),
+            ],
+        }
+    }
+}
+
+/// Regular expression of text selected as a whole by a double-click on any part of it
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SmartSelectionRule {
+    pub name: String,
+    pub regex: String,
+}
+
+impl SmartSelectionRule {
+    fn new(name: &str, regex: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            regex: regex.to_string(),
+        }
+    }
+}
+
+fn default_smart_selection() -> Vec<SmartSelectionRule> {
+    vec![
+        SmartSelectionRule::new("url", URL_REGEX),
+        SmartSelectionRule::new("path", PATH_REGEX),
+        SmartSelectionRule::new(
+            "email",
+            "[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\\.[A-Za-z0-9-]+)*\\.[A-Za-z]{2,}",
+        ),
+    ]
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum ColorSchemeKind {
     Dark,
@@ -185,6 +334,126 @@ pub struct ColorScheme {
 #[serde(transparent)]
 pub struct ProfileId(pub u64);
 
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct BellConfig {
+    /// Flash the terminal pane
+    pub visual: bool,
+    /// Request attention for the window
+    pub urgent: bool,
+    /// Mark tabs that are not active
+    pub tab_badge: bool,
+    /// Play the bell sound
+    pub sound: bool,
+    /// Bells ringing sooner than this after the previous one are ignored
+    pub min_interval_ms: u64,
+}
+
+impl Default for BellConfig {
+    fn default() -> Self {
+        Self {
+            visual: true,
+            urgent: true,
+            tab_badge: true,
+            sound: false,
+            min_interval_ms: 200,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct CopyConfig {
+    /// Copy selections to the clipboard, not only to the primary selection
+    pub on_select: bool,
+    /// Remove whitespace from the end of copied lines
+    pub trim_trailing_whitespace: bool,
+    /// Copy lines wrapped by the terminal as one line
+    pub join_wrapped_lines: bool,
+    /// Also copy as HTML and RTF with the colors of the text
+    pub rich_text: bool,
+}
+
+impl Default for CopyConfig {
+    fn default() -> Self {
+        Self {
+            on_select: false,
+            trim_trailing_whitespace: false,
+            join_wrapped_lines: true,
+            rich_text: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct SessionLogConfig {
+    /// Directory of the logs, the state directory of the app if empty
+    pub directory: String,
+    /// Template of file names, with {date}, {time}, {profile} and {title} replaced
+    pub file_name: String,
+    /// Remove escape sequences and control characters, keeping only the text
+    pub strip_escapes: bool,
+    /// Logs growing beyond this are rotated, 0 never rotates them
+    pub max_size_mb: u64,
+    /// Number of rotated logs to keep next to the current one
+    pub keep_files: u32,
+}
+
+impl Default for SessionLogConfig {
+    fn default() -> Self {
+        Self {
+            directory: String::new(),
+            file_name: "{date}_{time}_{profile}_{title}.log".to_string(),
+            strip_escapes: false,
+            max_size_mb: 100,
+            keep_files: 5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// Notify when a command reported by shell integration finishes in a hidden tab
+    pub command_finished: bool,
+    /// Commands running for less than this do not notify
+    pub min_duration_secs: u64,
+    /// Show notifications requested by applications with OSC 9 and OSC 777
+    pub app_requests: bool,
+    /// Mark tabs that are not active
+    pub tab_badge: bool,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            command_finished: true,
+            min_duration_secs: 10,
+            app_requests: true,
+            tab_badge: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ImageConfig {
+    /// Show images sent with the sixel and kitty graphics protocols
+    pub enabled: bool,
+    /// Images stored for a terminal beyond this size evict the oldest ones
+    pub memory_limit_mb: u32,
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            memory_limit_mb: 320,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Profile {
     pub name: String,
@@ -200,6 +469,25 @@ pub struct Profile {
     pub working_directory: String,
     #[serde(default)]
     pub drain_on_exit: bool,
+    /// Overrides the bell settings of the app
+    #[serde(default)]
+    pub bell: Option<BellConfig>,
+    #[serde(default)]
+    pub cursor_shape: Option<CursorShape>,
+    #[serde(default)]
+    pub cursor_blink: Option<bool>,
+    #[serde(default)]
+    pub clipboard_read: Option<ClipboardReadPolicy>,
+    #[serde(default)]
+    pub scrollback_lines: Option<ScrollbackLines>,
+    #[serde(default)]
+    pub word_separators: Option<String>,
+    /// Log the output of tabs opened with this profile
+    #[serde(default)]
+    pub session_log: bool,
+    /// Save the scrollback of tabs opened with this profile and show it again after a restart
+    #[serde(default)]
+    pub persist_scrollback: bool,
 }
 
 impl Default for Profile {
@@ -212,6 +500,14 @@ impl Default for Profile {
             tab_title: String::new(),
             working_directory: String::new(),
             drain_on_exit: false,
+            bell: None,
+            cursor_shape: None,
+            cursor_blink: None,
+            clipboard_read: None,
+            scrollback_lines: None,
+            word_separators: None,
+            session_log: false,
+            persist_scrollback: false,
         }
     }
 }
@@ -238,40 +534,125 @@ pub struct Config {
     pub focus_follow_mouse: bool,
     #[serde(default)]
     pub tab_new_inherit_working_directory: bool,
+    /// New tabs inheriting the directory of a remote shell connect to its host with ssh
+    #[serde(default)]
+    pub tab_new_ssh_remote: bool,
     pub default_profile: Option<ProfileId>,
     #[serde(default)]
     pub shortcuts_custom: Shortcuts,
+    #[serde(default)]
+    pub bell: BellConfig,
+    #[serde(default)]
+    pub cursor_shape: CursorShape,
+    #[serde(default)]
+    pub cursor_blink: bool,
+    #[serde(default)]
+    pub clipboard_read: ClipboardReadPolicy,
+    #[serde(default)]
+    pub copy: CopyConfig,
+    #[serde(default)]
+    pub hints: HintConfig,
+    #[serde(default)]
+    pub images: ImageConfig,
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    #[serde(default)]
+    pub scrollback_lines: ScrollbackLines,
+    #[serde(default)]
+    pub session_log: SessionLogConfig,
+    /// Let `cosmic-term cli` control the window through its socket
+    #[serde(default)]
+    pub remote_control: bool,
+    /// Save the layout when the window closes and restore it on the next start
+    #[serde(default)]
+    pub restore_last_session: bool,
+    /// Unlimited scrollback keeps at most this much history in memory
+    #[serde(default = "default_scrollback_memory_limit_mb")]
+    pub scrollback_memory_limit_mb: u32,
+    /// Scrollback saved for each tab of profiles that keep it across restarts
+    #[serde(default = "default_persist_scrollback_max_mb")]
+    pub persist_scrollback_max_mb: u32,
+    /// Rules checked before word separators when double-clicking
+    #[serde(default = "default_smart_selection")]
+    pub smart_selection: Vec<SmartSelectionRule>,
+    #[serde(default = "default_word_separators")]
+    pub word_separators: String,
+}
+
+fn default_word_separators() -> String {
+    WORD_SEPARATORS_DEFAULT.to_string()
+}
+
+fn default_scrollback_memory_limit_mb() -> u32 {
+    1024
+}
+
+fn default_persist_scrollback_max_mb() -> u32 {
+    10
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             app_theme: AppTheme::System,
+            bell: BellConfig::default(),
             bold_font_weight: Weight::BOLD.0,
+            clipboard_read: ClipboardReadPolicy::default(),
             color_schemes_dark: BTreeMap::new(),
             color_schemes_light: BTreeMap::new(),
+            copy: CopyConfig::default(),
+            cursor_blink: false,
+            cursor_shape: CursorShape::default(),
             dim_font_weight: Weight::NORMAL.0,
             focus_follow_mouse: false,
             tab_new_inherit_working_directory: false,
+            tab_new_ssh_remote: false,
             font_name: "Noto Sans Mono".to_string(),
             font_size: 14,
             font_size_zoom_step_mul_100: 100,
             font_stretch: Stretch::Normal.to_number(),
             font_weight: Weight::NORMAL.0,
+            hints: HintConfig::default(),
+            images: ImageConfig::default(),
+            notifications: NotificationConfig::default(),
             opacity: 100,
             profiles: BTreeMap::new(),
+            scrollback_lines: ScrollbackLines::default(),
+            scrollback_memory_limit_mb: default_scrollback_memory_limit_mb(),
+            persist_scrollback_max_mb: default_persist_scrollback_max_mb(),
+            session_log: SessionLogConfig::default(),
+            remote_control: false,
+            restore_last_session: false,
             show_headerbar: true,
             show_pane_borders: false,
+            smart_selection: default_smart_selection(),
             syntax_theme_dark: COSMIC_THEME_DARK.to_string(),
             syntax_theme_light: COSMIC_THEME_LIGHT.to_string(),
             use_bright_bold: false,
             default_profile: None,
             shortcuts_custom: Shortcuts::default(),
+            word_separators: default_word_separators(),
         }
     }
 }
 
 impl Config {
+    // Get bell settings, using the profile override if it exists
+    pub fn bell(&self, profile_id_opt: Option<ProfileId>) -> BellConfig {
+        profile_id_opt
+            .and_then(|profile_id| self.profiles.get(&profile_id))
+            .and_then(|profile| profile.bell)
+            .unwrap_or(self.bell)
+    }
+
+    // Get clipboard read policy, using the profile override if it exists
+    pub fn clipboard_read(&self, profile_id_opt: Option<ProfileId>) -> ClipboardReadPolicy {
+        profile_id_opt
+            .and_then(|profile_id| self.profiles.get(&profile_id))
+            .and_then(|profile| profile.clipboard_read)
+            .unwrap_or(self.clipboard_read)
+    }
+
     pub fn color_schemes(
         &self,
         color_scheme_kind: ColorSchemeKind,
@@ -336,6 +717,19 @@ impl Config {
         (font_size + adj * adj_step).max(1.0)
     }
 
+    // Get default cursor shape and blinking, using the profile overrides if they exist
+    pub fn cursor_style(&self, profile_id_opt: Option<ProfileId>) -> (CursorShape, bool) {
+        let profile_opt = profile_id_opt.and_then(|profile_id| self.profiles.get(&profile_id));
+        (
+            profile_opt
+                .and_then(|profile| profile.cursor_shape)
+                .unwrap_or(self.cursor_shape),
+            profile_opt
+                .and_then(|profile| profile.cursor_blink)
+                .unwrap_or(self.cursor_blink),
+        )
+    }
+
     // Calculate metrics from font size
     pub fn metrics(&self, zoom_adj: i8) -> Metrics {
         let font_size = self.font_size_adjusted(zoom_adj);
@@ -347,6 +741,14 @@ impl Config {
         f32::from(self.opacity) / 100.0
     }
 
+    /// Profile with this name, the first one if several have it
+    pub fn profile_id(&self, name: &str) -> Option<ProfileId> {
+        self.profiles
+            .iter()
+            .find(|(_, profile)| profile.name == name)
+            .map(|(profile_id, _)| *profile_id)
+    }
+
     // Get a sorted and adjusted for duplicates list of profile names and ids
     pub fn profile_names(&self) -> Vec<(String, ProfileId)> {
         let mut profile_names = Vec::<(String, ProfileId)>::with_capacity(self.profiles.len());
@@ -365,6 +767,14 @@ impl Config {
         profile_names
     }
 
+    // Get scrollback size, using the profile override if it exists
+    pub fn scrollback_lines(&self, profile_id_opt: Option<ProfileId>) -> ScrollbackLines {
+        profile_id_opt
+            .and_then(|profile_id| self.profiles.get(&profile_id))
+            .and_then(|profile| profile.scrollback_lines)
+            .unwrap_or(self.scrollback_lines)
+    }
+
     // Get current syntax theme based on dark mode
     pub fn syntax_theme(
         &self,
@@ -403,4 +813,12 @@ impl Config {
             }
         })[&self.font_stretch]
     }
+
+    // Get word separators, using the profile override if it exists
+    pub fn word_separators(&self, profile_id_opt: Option<ProfileId>) -> &str {
+        profile_id_opt
+            .and_then(|profile_id| self.profiles.get(&profile_id))
+            .and_then(|profile| profile.word_separators.as_deref())
+            .unwrap_or(&self.word_separators)
+    }
 }
@@ -0,0 +1,171 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use alacritty_terminal::{selection::SelectionType, vi_mode::ViMotion};
+use cosmic::iced::keyboard::{Key, Modifiers, key::Named};
+use std::mem;
+
+/// Command of copy mode, which moves a cursor over the screen and scrollback to select text
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CopyModeCommand {
+    Motion(ViMotion),
+    /// Move to the first line of the scrollback
+    Top,
+    /// Move to the last line of the screen
+    Bottom,
+    /// Scroll half a screen up or down, moving the cursor along
+    ScrollHalfPage {
+        up: bool,
+    },
+    /// Start, change or stop selecting text
+    Select(SelectionType),
+    /// Copy the selection and leave copy mode
+    Yank,
+    /// Open the find bar to search forwards or backwards
+    Search {
+        forwards: bool,
+    },
+    /// Move to the next match of the last search, in the same or the opposite direction
+    SearchNext {
+        same: bool,
+    },
+    Exit,
+}
+
+/// Translates keys into copy mode commands, using the bindings of vi
+#[derive(Debug, Default)]
+pub struct CopyModeKeys {
+    /// The first key of `gg` was typed
+    pending_g: bool,
+}
+
+impl CopyModeKeys {
+    pub fn command(&mut self, modifiers: Modifiers, key: &Key) -> Option<CopyModeCommand> {
+        let pending_g = mem::take(&mut self.pending_g);
+        if modifiers.alt() || modifiers.logo() {
+            return None;
+        }
+
+        let command = match key {
+            Key::Named(named) => match named {
+                Named::ArrowLeft => CopyModeCommand::Motion(ViMotion::Left),
+                Named::ArrowDown => CopyModeCommand::Motion(ViMotion::Down),
+                Named::ArrowUp => CopyModeCommand::Motion(ViMotion::Up),
+                Named::ArrowRight => CopyModeCommand::Motion(ViMotion::Right),
+                Named::Home => CopyModeCommand::Motion(ViMotion::First),
+                Named::End => CopyModeCommand::Motion(ViMotion::Last),
+                Named::PageUp => CopyModeCommand::ScrollHalfPage { up: true },
+                Named::PageDown => CopyModeCommand::ScrollHalfPage { up: false },
+                Named::Enter => CopyModeCommand::Yank,
+                _ => return None,
+            },
+            Key::Character(c) if modifiers.control() => match c.as_str() {
+                "u" => CopyModeCommand::ScrollHalfPage { up: true },
+                "d" => CopyModeCommand::ScrollHalfPage { up: false },
+                "v" => CopyModeCommand::Select(SelectionType::Block),
+                _ => return None,
+            },
+            Key::Character(c) => match c.as_str() {
+                "h" => CopyModeCommand::Motion(ViMotion::Left),
+                "j" => CopyModeCommand::Motion(ViMotion::Down),
+                "k" => CopyModeCommand::Motion(ViMotion::Up),
+                "l" => CopyModeCommand::Motion(ViMotion::Right),
+                "w" => CopyModeCommand::Motion(ViMotion::SemanticRight),
+                "b" => CopyModeCommand::Motion(ViMotion::SemanticLeft),
+                "e" => CopyModeCommand::Motion(ViMotion::SemanticRightEnd),
+                "W" => CopyModeCommand::Motion(ViMotion::WordRight),
+                "B" => CopyModeCommand::Motion(ViMotion::WordLeft),
+                "E" => CopyModeCommand::Motion(ViMotion::WordRightEnd),
+                "0" => CopyModeCommand::Motion(ViMotion::First),
+                "^" => CopyModeCommand::Motion(ViMotion::FirstOccupied),
+                "$" => CopyModeCommand::Motion(ViMotion::Last),
+                "H" => CopyModeCommand::Motion(ViMotion::High),
+                "M" => CopyModeCommand::Motion(ViMotion::Middle),
+                "L" => CopyModeCommand::Motion(ViMotion::Low),
+                "{" => CopyModeCommand::Motion(ViMotion::ParagraphUp),
+                "}" => CopyModeCommand::Motion(ViMotion::ParagraphDown),
+                "%" => CopyModeCommand::Motion(ViMotion::Bracket),
+                "g" if pending_g => CopyModeCommand::Top,
+                "g" => {
+                    self.pending_g = true;
+                    return None;
+                }
+                "G" => CopyModeCommand::Bottom,
+                "v" => CopyModeCommand::Select(SelectionType::Simple),
+                "V" => CopyModeCommand::Select(SelectionType::Lines),
+                "y" => CopyModeCommand::Yank,
+                "/" => CopyModeCommand::Search { forwards: true },
+                "?" => CopyModeCommand::Search { forwards: false },
+                "n" => CopyModeCommand::SearchNext { same: true },
+                "N" => CopyModeCommand::SearchNext { same: false },
+                "q" => CopyModeCommand::Exit,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CopyModeCommand, CopyModeKeys};
+    use alacritty_terminal::{selection::SelectionType, vi_mode::ViMotion};
+    use cosmic::iced::keyboard::{Key, Modifiers, key::Named};
+
+    fn char_key(c: &str) -> Key {
+        Key::Character(c.into())
+    }
+
+    #[test]
+    fn vi_keys() {
+        let mut keys = CopyModeKeys::default();
+        let none = Modifiers::empty();
+        assert_eq!(
+            keys.command(none, &char_key("j")),
+            Some(CopyModeCommand::Motion(ViMotion::Down))
+        );
+        assert_eq!(
+            keys.command(none, &Key::Named(Named::ArrowLeft)),
+            Some(CopyModeCommand::Motion(ViMotion::Left))
+        );
+        assert_eq!(
+            keys.command(none, &char_key("V")),
+            Some(CopyModeCommand::Select(SelectionType::Lines))
+        );
+        assert_eq!(
+            keys.command(Modifiers::CTRL, &char_key("v")),
+            Some(CopyModeCommand::Select(SelectionType::Block))
+        );
+        assert_eq!(
+            keys.command(Modifiers::CTRL, &char_key("u")),
+            Some(CopyModeCommand::ScrollHalfPage { up: true })
+        );
+        assert_eq!(
+            keys.command(none, &char_key("?")),
+            Some(CopyModeCommand::Search { forwards: false })
+        );
+
+        // Other shortcuts keep working
+        assert_eq!(keys.command(Modifiers::CTRL, &char_key("c")), None);
+        assert_eq!(keys.command(Modifiers::ALT, &char_key("j")), None);
+    }
+
+    #[test]
+    fn gg_needs_two_keys() {
+        let mut keys = CopyModeKeys::default();
+        let none = Modifiers::empty();
+        assert_eq!(keys.command(none, &char_key("g")), None);
+        assert_eq!(
+            keys.command(none, &char_key("g")),
+            Some(CopyModeCommand::Top)
+        );
+
+        // Any other key cancels the first g
+        assert_eq!(keys.command(none, &char_key("g")), None);
+        assert_eq!(
+            keys.command(none, &char_key("j")),
+            Some(CopyModeCommand::Motion(ViMotion::Down))
+        );
+        assert_eq!(keys.command(none, &char_key("g")), None);
+    }
+}
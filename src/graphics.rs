@@ -0,0 +1,864 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//Images are anchored to the grid the same way as kitty's unicode placeholders:
+//https://sw.kovidgoyal.net/kitty/graphics-protocol/#unicode-placeholders
+//Every placed image is written to the PTY stream as placeholder cells carrying the image id in
+//their foreground color, the placement id in their underline color and the row and column
+//as diacritics, so images scroll, get erased and are stored in history like text.
+
+use alacritty_terminal::{event::WindowSize, vte::ansi::Color};
+use cosmic::iced::{Point, Rectangle, Size, core::image::Handle};
+use indexmap::IndexMap;
+use std::{
+    collections::HashMap,
+    mem,
+    sync::{Arc, Mutex},
+};
+
+use crate::{kitty_graphics, sixel::SixelDecoder};
+
+/// Character used for cells showing part of an image
+pub const PLACEHOLDER: char = '\u{10EEEE}';
+
+/// Images larger than this in either direction are rejected
+pub const MAX_IMAGE_DIMENSION: u32 = 10_000;
+
+/// Diacritics encoding the row, column and most significant id byte of placeholder cells
+#[rustfmt::skip]
+const ROW_COLUMN_DIACRITICS: [char; 297] = [
+    '\u{0305}', '\u{030D}', '\u{030E}', '\u{0310}', '\u{0312}', '\u{033D}', '\u{033E}',
+    '\u{033F}', '\u{0346}', '\u{034A}', '\u{034B}', '\u{034C}', '\u{0350}', '\u{0351}',
+    '\u{0352}', '\u{0357}', '\u{035B}', '\u{0363}', '\u{0364}', '\u{0365}', '\u{0366}',
+    '\u{0367}', '\u{0368}', '\u{0369}', '\u{036A}', '\u{036B}', '\u{036C}', '\u{036D}',
+    '\u{036E}', '\u{036F}', '\u{0483}', '\u{0484}', '\u{0485}', '\u{0486}', '\u{0487}',
+    '\u{0592}', '\u{0593}', '\u{0594}', '\u{0595}', '\u{0597}', '\u{0598}', '\u{0599}',
+    '\u{059C}', '\u{059D}', '\u{059E}', '\u{059F}', '\u{05A0}', '\u{05A1}', '\u{05A8}',
+    '\u{05A9}', '\u{05AB}', '\u{05AC}', '\u{05AF}', '\u{05C4}', '\u{0610}', '\u{0611}',
+    '\u{0612}', '\u{0613}', '\u{0614}', '\u{0615}', '\u{0616}', '\u{0617}', '\u{0657}',
+    '\u{0658}', '\u{0659}', '\u{065A}', '\u{065B}', '\u{065D}', '\u{065E}', '\u{06D6}',
+    '\u{06D7}', '\u{06D8}', '\u{06D9}', '\u{06DA}', '\u{06DB}', '\u{06DC}', '\u{06DF}',
+    '\u{06E0}', '\u{06E1}', '\u{06E2}', '\u{06E4}', '\u{06E7}', '\u{06E8}', '\u{06EB}',
+    '\u{06EC}', '\u{0730}', '\u{0732}', '\u{0733}', '\u{0735}', '\u{0736}', '\u{073A}',
+    '\u{073D}', '\u{073F}', '\u{0740}', '\u{0741}', '\u{0743}', '\u{0745}', '\u{0747}',
+    '\u{0749}', '\u{074A}', '\u{07EB}', '\u{07EC}', '\u{07ED}', '\u{07EE}', '\u{07EF}',
+    '\u{07F0}', '\u{07F1}', '\u{07F3}', '\u{0816}', '\u{0817}', '\u{0818}', '\u{0819}',
+    '\u{081B}', '\u{081C}', '\u{081D}', '\u{081E}', '\u{081F}', '\u{0820}', '\u{0821}',
+    '\u{0822}', '\u{0823}', '\u{0825}', '\u{0826}', '\u{0827}', '\u{0829}', '\u{082A}',
+    '\u{082B}', '\u{082C}', '\u{082D}', '\u{0951}', '\u{0953}', '\u{0954}', '\u{0F82}',
+    '\u{0F83}', '\u{0F86}', '\u{0F87}', '\u{135D}', '\u{135E}', '\u{135F}', '\u{17DD}',
+    '\u{193A}', '\u{1A17}', '\u{1A75}', '\u{1A76}', '\u{1A77}', '\u{1A78}', '\u{1A79}',
+    '\u{1A7A}', '\u{1A7B}', '\u{1A7C}', '\u{1B6B}', '\u{1B6D}', '\u{1B6E}', '\u{1B6F}',
+    '\u{1B70}', '\u{1B71}', '\u{1B72}', '\u{1B73}', '\u{1CD0}', '\u{1CD1}', '\u{1CD2}',
+    '\u{1CDA}', '\u{1CDB}', '\u{1CE0}', '\u{1DC0}', '\u{1DC1}', '\u{1DC3}', '\u{1DC4}',
+    '\u{1DC5}', '\u{1DC6}', '\u{1DC7}', '\u{1DC8}', '\u{1DC9}', '\u{1DCB}', '\u{1DCC}',
+    '\u{1DD1}', '\u{1DD2}', '\u{1DD3}', '\u{1DD4}', '\u{1DD5}', '\u{1DD6}', '\u{1DD7}',
+    '\u{1DD8}', '\u{1DD9}', '\u{1DDA}', '\u{1DDB}', '\u{1DDC}', '\u{1DDD}', '\u{1DDE}',
+    '\u{1DDF}', '\u{1DE0}', '\u{1DE1}', '\u{1DE2}', '\u{1DE3}', '\u{1DE4}', '\u{1DE5}',
+    '\u{1DE6}', '\u{1DFE}', '\u{20D0}', '\u{20D1}', '\u{20D4}', '\u{20D5}', '\u{20D6}',
+    '\u{20D7}', '\u{20DB}', '\u{20DC}', '\u{20E1}', '\u{20E7}', '\u{20E9}', '\u{20F0}',
+    '\u{2CEF}', '\u{2CF0}', '\u{2CF1}', '\u{2DE0}', '\u{2DE1}', '\u{2DE2}', '\u{2DE3}',
+    '\u{2DE4}', '\u{2DE5}', '\u{2DE6}', '\u{2DE7}', '\u{2DE8}', '\u{2DE9}', '\u{2DEA}',
+    '\u{2DEB}', '\u{2DEC}', '\u{2DED}', '\u{2DEE}', '\u{2DEF}', '\u{2DF0}', '\u{2DF1}',
+    '\u{2DF2}', '\u{2DF3}', '\u{2DF4}', '\u{2DF5}', '\u{2DF6}', '\u{2DF7}', '\u{2DF8}',
+    '\u{2DF9}', '\u{2DFA}', '\u{2DFB}', '\u{2DFC}', '\u{2DFD}', '\u{2DFE}', '\u{2DFF}',
+    '\u{A66F}', '\u{A67C}', '\u{A67D}', '\u{A6F0}', '\u{A6F1}', '\u{A8E0}', '\u{A8E1}',
+    '\u{A8E2}', '\u{A8E3}', '\u{A8E4}', '\u{A8E5}', '\u{A8E6}', '\u{A8E7}', '\u{A8E8}',
+    '\u{A8E9}', '\u{A8EA}', '\u{A8EB}', '\u{A8EC}', '\u{A8ED}', '\u{A8EE}', '\u{A8EF}',
+    '\u{A8F0}', '\u{A8F1}', '\u{AAB0}', '\u{AAB2}', '\u{AAB3}', '\u{AAB7}', '\u{AAB8}',
+    '\u{AABE}', '\u{AABF}', '\u{AAC1}', '\u{FE20}', '\u{FE21}', '\u{FE22}', '\u{FE23}',
+    '\u{FE24}', '\u{FE25}', '\u{FE26}', '\u{10A0F}', '\u{10A38}', '\u{1D185}', '\u{1D186}',
+    '\u{1D187}', '\u{1D188}', '\u{1D189}', '\u{1D1AA}', '\u{1D1AB}', '\u{1D1AC}', '\u{1D1AD}',
+    '\u{1D242}', '\u{1D243}', '\u{1D244}',
+];
+
+/// Placements cannot span more rows or columns than there are diacritics
+pub const MAX_PLACEMENT_CELLS: u32 = ROW_COLUMN_DIACRITICS.len() as u32;
+
+/// Largest graphics command, including the payload of all chunks of a transfer
+const MAX_APC_LEN: usize = 64 * 1024 * 1024;
+
+/// Decoded RGBA image
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Stored image, shared with the renderer through its handle
+pub struct GraphicImage {
+    pub width: u32,
+    pub height: u32,
+    pub handle: Handle,
+}
+
+impl GraphicImage {
+    fn memory(&self) -> usize {
+        self.width as usize * self.height as usize * 4
+    }
+}
+
+/// Where an image is shown, in cells
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placement {
+    pub columns: u32,
+    pub rows: u32,
+    /// Part of the image shown, in pixels
+    pub source: Rectangle<u32>,
+    /// Offset inside the first cell, in pixels
+    pub offset: (u32, u32),
+    /// Scale the source to fill all cells instead of showing it at its size
+    pub scale: bool,
+    /// Negative values are drawn under the text
+    pub z_index: i32,
+    /// Virtual placements are only shown by placeholders written by the application
+    pub is_virtual: bool,
+}
+
+impl Placement {
+    /// Returns the bounds of the whole image and its visible part, relative to the top left
+    /// of the placement
+    pub fn bounds(&self, image: &GraphicImage, cell_size: Size) -> (Rectangle, Rectangle) {
+        let source = self.source;
+        let (scale_x, scale_y) = if self.scale && source.width > 0 && source.height > 0 {
+            (
+                (self.columns as f32 * cell_size.width - self.offset.0 as f32)
+                    / source.width as f32,
+                (self.rows as f32 * cell_size.height - self.offset.1 as f32) / source.height as f32,
+            )
+        } else {
+            (1.0, 1.0)
+        };
+        let visible = Rectangle::new(
+            Point::new(self.offset.0 as f32, self.offset.1 as f32),
+            Size::new(
+                source.width as f32 * scale_x,
+                source.height as f32 * scale_y,
+            ),
+        );
+        let image_bounds = Rectangle::new(
+            Point::new(
+                visible.x - source.x as f32 * scale_x,
+                visible.y - source.y as f32 * scale_y,
+            ),
+            Size::new(image.width as f32 * scale_x, image.height as f32 * scale_y),
+        );
+        (image_bounds, visible)
+    }
+}
+
+/// Images and placements of a terminal, shared between the PTY reader and the renderer
+pub struct Graphics {
+    enabled: bool,
+    memory_limit: usize,
+    memory: usize,
+    /// Images in the order they were added, for eviction
+    images: IndexMap<u32, GraphicImage>,
+    image_numbers: HashMap<u32, u32>,
+    placements: HashMap<(u32, u32), Placement>,
+    next_image_id: u32,
+    next_placement_id: u32,
+}
+
+impl Graphics {
+    pub fn new(enabled: bool, memory_limit_mb: u32) -> Self {
+        Self {
+            enabled,
+            memory_limit: memory_limit_mb as usize * 1024 * 1024,
+            memory: 0,
+            images: IndexMap::new(),
+            image_numbers: HashMap::new(),
+            placements: HashMap::new(),
+            next_image_id: 0xFF_FFFF,
+            next_placement_id: 0xFF_FFFF,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Bytes that images may use, larger images are never decoded
+    pub fn memory_limit(&self) -> usize {
+        self.memory_limit
+    }
+
+    pub fn set_limits(&mut self, enabled: bool, memory_limit_mb: u32) {
+        self.enabled = enabled;
+        self.memory_limit = memory_limit_mb as usize * 1024 * 1024;
+        if !enabled {
+            self.clear();
+        }
+        self.evict(0);
+    }
+
+    pub fn clear(&mut self) {
+        self.images.clear();
+        self.image_numbers.clear();
+        self.placements.clear();
+        self.memory = 0;
+    }
+
+    /// Ids are handed out from the top of the 24 bit range, which fits in a color
+    pub fn allocate_image_id(&mut self) -> u32 {
+        while self.images.contains_key(&self.next_image_id) || self.next_image_id == 0 {
+            self.next_image_id = self.next_image_id.checked_sub(1).unwrap_or(0xFF_FFFF);
+        }
+        let id = self.next_image_id;
+        self.next_image_id = id.checked_sub(1).unwrap_or(0xFF_FFFF);
+        id
+    }
+
+    pub fn allocate_placement_id(&mut self, image_id: u32) -> u32 {
+        while self
+            .placements
+            .contains_key(&(image_id, self.next_placement_id))
+            || self.next_placement_id == 0
+        {
+            self.next_placement_id = self.next_placement_id.checked_sub(1).unwrap_or(0xFF_FFFF);
+        }
+        let id = self.next_placement_id;
+        self.next_placement_id = id.checked_sub(1).unwrap_or(0xFF_FFFF);
+        id
+    }
+
+    /// Remove the oldest images until `extra` more bytes fit in the memory limit
+    fn evict(&mut self, extra: usize) {
+        while self.memory + extra > self.memory_limit {
+            let Some((id, image)) = self.images.shift_remove_index(0) else {
+                break;
+            };
+            log::debug!("evicting image {} to stay within the memory limit", id);
+            self.memory -= image.memory();
+            self.placements.retain(|(image_id, _), _| *image_id != id);
+        }
+    }
+
+    /// Stores an image, replacing the image with the same id
+    pub fn insert_image(&mut self, id: u32, image: DecodedImage) -> Result<(), String> {
+        let image = GraphicImage {
+            width: image.width,
+            height: image.height,
+            handle: Handle::from_rgba(image.width, image.height, image.pixels),
+        };
+        let memory = image.memory();
+        if memory > self.memory_limit {
+            return Err(format!(
+                "EFBIG:image of {}x{} exceeds the memory limit",
+                image.width, image.height
+            ));
+        }
+        self.remove_image(id);
+        self.evict(memory);
+        self.memory += memory;
+        self.images.insert(id, image);
+        Ok(())
+    }
+
+    pub fn remove_image(&mut self, id: u32) {
+        if let Some(image) = self.images.shift_remove(&id) {
+            self.memory -= image.memory();
+        }
+        self.image_numbers.retain(|_, image_id| *image_id != id);
+    }
+
+    pub fn image(&self, id: u32) -> Option<&GraphicImage> {
+        self.images.get(&id)
+    }
+
+    pub fn image_ids(&self) -> Vec<u32> {
+        self.images.keys().copied().collect()
+    }
+
+    pub fn image_id_by_number(&self, number: u32) -> Option<u32> {
+        self.image_numbers.get(&number).copied()
+    }
+
+    pub fn set_image_number(&mut self, number: u32, id: u32) {
+        self.image_numbers.insert(number, id);
+    }
+
+    pub fn insert_placement(&mut self, image_id: u32, placement_id: u32, placement: Placement) {
+        self.placements.insert((image_id, placement_id), placement);
+    }
+
+    /// Remove placements of the image matching `filter`
+    pub fn remove_placements<F: Fn(u32, &Placement) -> bool>(&mut self, image_id: u32, filter: F) {
+        self.placements.retain(|(id, placement_id), placement| {
+            *id != image_id || !filter(*placement_id, placement)
+        });
+    }
+
+    /// Remove placements matching `filter` for all images
+    pub fn retain_placements<F: Fn(u32, u32, &Placement) -> bool>(&mut self, filter: F) {
+        self.placements
+            .retain(|(image_id, placement_id), placement| {
+                filter(*image_id, *placement_id, placement)
+            });
+    }
+
+    pub fn has_placements(&self, image_id: u32) -> bool {
+        self.placements.keys().any(|(id, _)| *id == image_id)
+    }
+
+    /// Find the image and placement shown by a placeholder cell
+    pub fn get(&self, image_id: u32, placement_id: u32) -> Option<(&GraphicImage, &Placement)> {
+        let image = self.images.get(&image_id)?;
+        let placement = match self.placements.get(&(image_id, placement_id)) {
+            Some(placement) => placement,
+            // Placeholders without a placement id may show any virtual placement
+            None if placement_id == 0 => self
+                .placements
+                .iter()
+                .find(|((id, _), placement)| *id == image_id && placement.is_virtual)
+                .map(|(_, placement)| placement)?,
+            None => return None,
+        };
+        Some((image, placement))
+    }
+}
+
+/// Part of an image shown by a placeholder cell
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ImageCell {
+    pub column: usize,
+    pub image_id: u32,
+    pub placement_id: u32,
+    pub row: u32,
+    pub col: u32,
+}
+
+fn color_id(color: Color) -> Option<u32> {
+    match color {
+        Color::Spec(rgb) => {
+            Some((u32::from(rgb.r) << 16) | (u32::from(rgb.g) << 8) | u32::from(rgb.b))
+        }
+        Color::Indexed(index) => Some(u32::from(index)),
+        Color::Named(_) => None,
+    }
+}
+
+fn diacritic_index(c: char) -> Option<u32> {
+    ROW_COLUMN_DIACRITICS
+        .binary_search(&c)
+        .ok()
+        .map(|index| index as u32)
+}
+
+impl ImageCell {
+    /// Decodes a placeholder cell, missing diacritics are inferred from the cell to the left
+    pub fn decode(
+        column: usize,
+        fg: Color,
+        underline_color: Option<Color>,
+        zerowidth: &[char],
+        prev_opt: Option<&ImageCell>,
+    ) -> Option<Self> {
+        let id = color_id(fg)?;
+        let placement_id = underline_color.and_then(color_id).unwrap_or(0);
+        let mut diacritics = zerowidth.iter().filter_map(|&c| diacritic_index(c));
+        let row_opt = diacritics.next();
+        let col_opt = diacritics.next();
+        let msb_opt = diacritics.next();
+
+        let prev_opt = prev_opt.filter(|prev| {
+            prev.column + 1 == column
+                && prev.image_id & 0xFF_FFFF == id
+                && prev.placement_id == placement_id
+        });
+        let row = row_opt.or(prev_opt.map(|prev| prev.row)).unwrap_or(0);
+        let col = col_opt
+            .or(prev_opt
+                .filter(|prev| prev.row == row)
+                .map(|prev| prev.col + 1))
+            .unwrap_or(0);
+        let msb = msb_opt
+            .or(prev_opt.map(|prev| prev.image_id >> 24))
+            .unwrap_or(0);
+        Some(Self {
+            column,
+            image_id: (msb << 24) | id,
+            placement_id,
+            row,
+            col,
+        })
+    }
+}
+
+/// Where the cursor is left after writing placeholders
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CursorMovement {
+    /// After the last column of the last row, like kitty
+    AfterImage,
+    /// On the row below the image, like sixel
+    BelowImage,
+    /// At the top left of the image
+    Unchanged,
+}
+
+/// Placeholder cells for a placement, written into the PTY stream in place of the image
+///
+/// Each row is written from the top left column of the image and the cursor is moved back to
+/// it afterwards, moving down with index so it scrolls like the image would. The cursor saved
+/// by the application is left alone, so the colors used for the ids are reset afterwards. A row
+/// ending in the last column leaves the cursor there, which moves it back one column too far
+/// unless the image starts in the first column.
+pub fn placeholder_text(
+    image_id: u32,
+    placement_id: u32,
+    columns: u32,
+    rows: u32,
+    cursor_movement: CursorMovement,
+) -> Vec<u8> {
+    let columns = columns.clamp(1, MAX_PLACEMENT_CELLS);
+    let rows = rows.clamp(1, MAX_PLACEMENT_CELLS);
+    let msb = (image_id >> 24) as usize;
+    let mut text = format!(
+        "\x1b[38;2;{};{};{}m\x1b[58;2;{};{};{}m",
+        (image_id >> 16) & 0xFF,
+        (image_id >> 8) & 0xFF,
+        image_id & 0xFF,
+        (placement_id >> 16) & 0xFF,
+        (placement_id >> 8) & 0xFF,
+        placement_id & 0xFF,
+    );
+    for row in 0..rows {
+        for col in 0..columns {
+            text.push(PLACEHOLDER);
+            text.push(ROW_COLUMN_DIACRITICS[row as usize]);
+            text.push(ROW_COLUMN_DIACRITICS[col as usize]);
+            if msb > 0 {
+                text.push(ROW_COLUMN_DIACRITICS[msb]);
+            }
+        }
+        text.push_str(&format!("\x1b[{columns}D"));
+        if row + 1 < rows {
+            text.push_str("\x1bD");
+        }
+    }
+    text.push_str("\x1b[39;59m");
+    match cursor_movement {
+        CursorMovement::AfterImage => text.push_str(&format!("\x1b[{columns}C")),
+        CursorMovement::BelowImage => text.push_str("\x1bD"),
+        CursorMovement::Unchanged => {
+            if rows > 1 {
+                text.push_str(&format!("\x1b[{}A", rows - 1));
+            }
+        }
+    }
+    text.into_bytes()
+}
+
+enum FilterState {
+    Ground,
+    Escape,
+    /// Parameters of a DCS sequence, which is a sixel image if the final byte is q
+    DcsParams(Vec<u8>),
+    Sixel(Box<SixelDecoder>),
+    SixelEscape(Box<SixelDecoder>),
+    ApcStart,
+    Kitty(Vec<u8>),
+    KittyEscape(Vec<u8>),
+}
+
+/// Removes sixel and kitty graphics sequences from the PTY stream, replacing placed images
+/// with placeholders
+pub struct GraphicsFilter {
+    graphics: Arc<Mutex<Graphics>>,
+    state: FilterState,
+    window_size: WindowSize,
+    kitty_transfer_opt: Option<kitty_graphics::Transfer>,
+    /// Set when the chunks of a transfer that was too large are being skipped
+    kitty_discarding: bool,
+    /// Responses to be written back to the PTY
+    pub replies: Vec<String>,
+    /// Set when stored images or placements changed
+    pub changed: bool,
+}
+
+impl GraphicsFilter {
+    pub fn new(graphics: Arc<Mutex<Graphics>>, window_size: WindowSize) -> Self {
+        Self {
+            graphics,
+            state: FilterState::Ground,
+            window_size,
+            kitty_transfer_opt: None,
+            kitty_discarding: false,
+            replies: Vec::new(),
+            changed: false,
+        }
+    }
+
+    pub fn set_window_size(&mut self, window_size: WindowSize) {
+        self.window_size = window_size;
+    }
+
+    fn cell_size(&self) -> (u32, u32) {
+        (
+            u32::from(self.window_size.cell_width.max(1)),
+            u32::from(self.window_size.cell_height.max(1)),
+        )
+    }
+
+    pub fn advance(&mut self, input: &[u8], output: &mut Vec<u8>) {
+        let mut i = 0;
+        while i < input.len() {
+            if let FilterState::Ground = self.state {
+                // Pass through everything up to the next escape
+                match input[i..].iter().position(|&byte| byte == 0x1B) {
+                    Some(offset) => {
+                        output.extend_from_slice(&input[i..i + offset]);
+                        i += offset + 1;
+                        self.state = FilterState::Escape;
+                    }
+                    None => {
+                        output.extend_from_slice(&input[i..]);
+                        i = input.len();
+                    }
+                }
+                continue;
+            }
+
+            let byte = input[i];
+            i += 1;
+            self.state = match mem::replace(&mut self.state, FilterState::Ground) {
+                FilterState::Ground => unreachable!(),
+                FilterState::Escape => match byte {
+                    b'P' => FilterState::DcsParams(Vec::new()),
+                    b'_' => FilterState::ApcStart,
+                    0x1B => {
+                        output.push(0x1B);
+                        FilterState::Escape
+                    }
+                    _ => {
+                        output.extend_from_slice(&[0x1B, byte]);
+                        FilterState::Ground
+                    }
+                },
+                FilterState::DcsParams(mut params) => match byte {
+                    b'0'..=b'9' | b';' if params.len() < 32 => {
+                        params.push(byte);
+                        FilterState::DcsParams(params)
+                    }
+                    b'q' => {
+                        let memory_limit = self.graphics.lock().unwrap().memory_limit();
+                        FilterState::Sixel(Box::new(SixelDecoder::new(memory_limit)))
+                    }
+                    _ => {
+                        // Any other DCS sequence is passed through
+                        output.extend_from_slice(b"\x1bP");
+                        output.extend_from_slice(&params);
+                        i -= 1;
+                        FilterState::Ground
+                    }
+                },
+                FilterState::Sixel(mut decoder) => match byte {
+                    0x1B => FilterState::SixelEscape(decoder),
+                    // Cancel the sequence
+                    0x18 | 0x1A => FilterState::Ground,
+                    _ => {
+                        decoder.advance(byte);
+                        FilterState::Sixel(decoder)
+                    }
+                },
+                FilterState::SixelEscape(decoder) => {
+                    self.sixel(*decoder, output);
+                    if byte == b'\\' {
+                        FilterState::Ground
+                    } else {
+                        // Another sequence ended the image
+                        i -= 1;
+                        FilterState::Escape
+                    }
+                }
+                FilterState::ApcStart => {
+                    if byte == b'G' {
+                        FilterState::Kitty(Vec::new())
+                    } else {
+                        output.extend_from_slice(b"\x1b_");
+                        i -= 1;
+                        FilterState::Ground
+                    }
+                }
+                FilterState::Kitty(mut data) => match byte {
+                    0x1B => FilterState::KittyEscape(data),
+                    0x18 | 0x1A => FilterState::Ground,
+                    _ => {
+                        if data.len() < MAX_APC_LEN {
+                            data.push(byte);
+                        }
+                        FilterState::Kitty(data)
+                    }
+                },
+                FilterState::KittyEscape(data) => {
+                    if byte == b'\\' {
+                        self.kitty(&data, output);
+                        FilterState::Ground
+                    } else {
+                        // Unterminated commands are dropped
+                        i -= 1;
+                        FilterState::Escape
+                    }
+                }
+            };
+        }
+    }
+
+    fn sixel(&mut self, decoder: SixelDecoder, output: &mut Vec<u8>) {
+        if !self.graphics.lock().unwrap().enabled() {
+            return;
+        }
+        // The renderer is not kept waiting while the image is built
+        let Some(image) = decoder.finish() else {
+            return;
+        };
+        let mut graphics = self.graphics.lock().unwrap();
+        if !graphics.enabled() {
+            return;
+        }
+        let (cell_width, cell_height) = self.cell_size();
+        let (width, height) = (image.width, image.height);
+        let image_id = graphics.allocate_image_id();
+        if let Err(err) = graphics.insert_image(image_id, image) {
+            log::warn!("failed to store sixel image: {}", err);
+            return;
+        }
+        let placement = Placement {
+            columns: width.div_ceil(cell_width).min(MAX_PLACEMENT_CELLS),
+            rows: height.div_ceil(cell_height).min(MAX_PLACEMENT_CELLS),
+            source: Rectangle::new(Point::new(0, 0), Size::new(width, height)),
+            offset: (0, 0),
+            scale: false,
+            z_index: 0,
+            is_virtual: false,
+        };
+        let placement_id = graphics.allocate_placement_id(image_id);
+        output.extend_from_slice(&placeholder_text(
+            image_id,
+            placement_id,
+            placement.columns,
+            placement.rows,
+            CursorMovement::BelowImage,
+        ));
+        graphics.insert_placement(image_id, placement_id, placement);
+        self.changed = true;
+    }
+
+    fn kitty(&mut self, data: &[u8], output: &mut Vec<u8>) {
+        let (control, payload) = match data.iter().position(|&byte| byte == b';') {
+            Some(i) => (&data[..i], &data[i + 1..]),
+            None => (data, &data[data.len()..]),
+        };
+
+        if !self.graphics.lock().unwrap().enabled() {
+            return;
+        }
+
+        if self.kitty_discarding {
+            self.kitty_discarding = kitty_graphics::Command::parse(control).more;
+            return;
+        }
+
+        // Chunks after the first one only carry m and q
+        let transfer = match self.kitty_transfer_opt.take() {
+            Some(mut transfer) => {
+                let chunk = kitty_graphics::Command::parse(control);
+                if transfer.payload.len() + payload.len() > MAX_APC_LEN {
+                    let image_id = transfer.command.image_id;
+                    self.replies.extend(kitty_graphics::reply(
+                        &transfer.command,
+                        image_id,
+                        Err("EFBIG:image data is too large".to_string()),
+                    ));
+                    self.kitty_discarding = chunk.more;
+                    return;
+                }
+                transfer.payload.extend_from_slice(payload);
+                if chunk.more {
+                    self.kitty_transfer_opt = Some(transfer);
+                    return;
+                }
+                transfer
+            }
+            None => {
+                let command = kitty_graphics::Command::parse(control);
+                let transfer = kitty_graphics::Transfer {
+                    command,
+                    payload: payload.to_vec(),
+                };
+                if transfer.command.more {
+                    self.kitty_transfer_opt = Some(transfer);
+                    return;
+                }
+                transfer
+            }
+        };
+
+        // Decoding may read files and decompress data, the renderer is not kept waiting for it
+        let decoded = kitty_graphics::decode(transfer);
+        let mut graphics = self.graphics.lock().unwrap();
+        if !graphics.enabled() {
+            return;
+        }
+        let result = kitty_graphics::execute(&mut graphics, decoded, self.cell_size());
+        output.extend_from_slice(&result.output);
+        self.replies.extend(result.reply_opt);
+        self.changed |= result.changed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CursorMovement, Graphics, GraphicsFilter, ImageCell, PLACEHOLDER, ROW_COLUMN_DIACRITICS,
+        placeholder_text,
+    };
+    use alacritty_terminal::{
+        event::WindowSize,
+        vte::ansi::{Color, NamedColor, Rgb},
+    };
+    use std::sync::{Arc, Mutex};
+
+    fn filter() -> (Arc<Mutex<Graphics>>, GraphicsFilter) {
+        let graphics = Arc::new(Mutex::new(Graphics::new(true, 16)));
+        let window_size = WindowSize {
+            num_lines: 24,
+            num_cols: 80,
+            cell_width: 4,
+            cell_height: 8,
+        };
+        (graphics.clone(), GraphicsFilter::new(graphics, window_size))
+    }
+
+    fn placeholders(text: &[u8]) -> usize {
+        String::from_utf8_lossy(text)
+            .chars()
+            .filter(|&c| c == PLACEHOLDER)
+            .count()
+    }
+
+    #[test]
+    fn diacritics_are_sorted() {
+        assert!(ROW_COLUMN_DIACRITICS.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn placeholder_cells_round_trip() {
+        let text = placeholder_text(0x01_0203, 7, 2, 2, CursorMovement::AfterImage);
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("\x1b[38;2;1;2;3m"));
+        assert!(text.contains("\x1b[58;2;0;0;7m"));
+        assert!(text.ends_with("\x1b[2D\x1b[39;59m\x1b[2C"));
+        assert!(!text.contains("\x1b7"));
+        assert_eq!(placeholders(text.as_bytes()), 4);
+
+        let fg = Color::Spec(Rgb { r: 1, g: 2, b: 3 });
+        let underline = Some(Color::Spec(Rgb { r: 0, g: 0, b: 7 }));
+        let cell = ImageCell::decode(
+            5,
+            fg,
+            underline,
+            &[ROW_COLUMN_DIACRITICS[1], ROW_COLUMN_DIACRITICS[0]],
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            cell,
+            ImageCell {
+                column: 5,
+                image_id: 0x01_0203,
+                placement_id: 7,
+                row: 1,
+                col: 0,
+            }
+        );
+
+        // Missing diacritics continue the row of the cell to the left
+        let next = ImageCell::decode(6, fg, underline, &[], Some(&cell)).unwrap();
+        assert_eq!((next.row, next.col), (1, 1));
+
+        // Cells with a default foreground are not images
+        assert!(
+            ImageCell::decode(5, Color::Named(NamedColor::Foreground), None, &[], None).is_none()
+        );
+    }
+
+    #[test]
+    fn sixel_is_replaced_with_placeholders() {
+        let (graphics, mut filter) = filter();
+        let mut output = Vec::new();
+        // A 5x12 pixel sixel covers 2x2 cells of 4x8 pixels, split over two reads
+        filter.advance(b"a\x1bPq#1;2;100;0;0!5~-", &mut output);
+        filter.advance(b"!5~\x1b\\b", &mut output);
+
+        assert!(output.starts_with(b"a\x1b[38;2;"));
+        assert!(output.ends_with(b"\x1bDb"));
+        assert_eq!(placeholders(&output), 4);
+        assert!(filter.changed);
+        assert_eq!(graphics.lock().unwrap().image_ids().len(), 1);
+    }
+
+    #[test]
+    fn other_sequences_pass_through() {
+        let (graphics, mut filter) = filter();
+        let input: &[u8] = b"\x1b[1mbold\x1bP$qm\x1b\\\x1b_other\x1b\\\x1b\x1b]0;title\x07";
+        let mut output = Vec::new();
+        for chunk in input.chunks(3) {
+            filter.advance(chunk, &mut output);
+        }
+        assert_eq!(output, input);
+        assert!(graphics.lock().unwrap().image_ids().is_empty());
+    }
+
+    #[test]
+    fn disabled_graphics_are_stripped() {
+        let (graphics, mut filter) = filter();
+        graphics.lock().unwrap().set_limits(false, 16);
+        let mut output = Vec::new();
+        filter.advance(
+            b"a\x1bPq~\x1b\\\x1b_Ga=T,f=24,s=1,v=1;AAAA\x1b\\b",
+            &mut output,
+        );
+        assert_eq!(output, b"ab");
+        assert!(filter.replies.is_empty());
+    }
+
+    #[test]
+    fn oversized_chunked_transfers_are_dropped() {
+        let (graphics, mut filter) = filter();
+        let chunk = vec![b'A'; 4 * 1024 * 1024];
+        let mut output = Vec::new();
+        filter.advance(b"\x1b_Ga=T,f=24,s=1,v=1,i=3,m=1;", &mut output);
+        filter.advance(&chunk, &mut output);
+        filter.advance(b"\x1b\\", &mut output);
+        for _ in 0..20 {
+            filter.advance(b"\x1b_Gm=1;", &mut output);
+            filter.advance(&chunk, &mut output);
+            filter.advance(b"\x1b\\", &mut output);
+        }
+        assert!(filter.kitty_transfer_opt.is_none());
+        assert_eq!(filter.replies.len(), 1);
+        assert!(filter.replies[0].starts_with("\x1b_Gi=3;EFBIG:"));
+
+        // The remaining chunks are skipped, the next command is executed again
+        filter.advance(b"\x1b_Gm=0;AAAA\x1b\\", &mut output);
+        filter.advance(b"\x1b_Gf=24,s=1,v=1,i=4;AAAA\x1b\\", &mut output);
+        assert_eq!(filter.replies.len(), 2);
+        assert_eq!(filter.replies[1], "\x1b_Gi=4;OK\x1b\\");
+        assert!(graphics.lock().unwrap().image(3).is_none());
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn memory_limit_evicts_oldest_images() {
+        let (graphics, mut filter) = filter();
+        // Each 1024x1024 image uses 4 MiB of the 16 MiB limit
+        let sixel = b"\x1bPq\"1;1;1024;1024#1~\x1b\\";
+        let mut output = Vec::new();
+        for _ in 0..5 {
+            filter.advance(sixel, &mut output);
+        }
+        let graphics = graphics.lock().unwrap();
+        assert_eq!(graphics.image_ids().len(), 4);
+    }
+}
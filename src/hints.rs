@@ -0,0 +1,320 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use alacritty_terminal::{
+    Term,
+    grid::Dimensions,
+    index::Line,
+    term::search::{Match, RegexSearch},
+};
+use std::{cmp::Reverse, env, path::Path};
+
+use crate::{
+    config::{HintAction, HintConfig},
+    terminal::{HintPostProcessor, visible_regex_match_iter},
+};
+
+/// Match of a hint pattern and the label typed to select it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hint {
+    pub label: String,
+    pub regex_match: Match,
+    pub text: String,
+}
+
+/// Result of typing a character while hints are shown
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HintInput {
+    /// The typed characters start the labels of several hints
+    Pending,
+    /// Text of the hint with the typed label
+    Selected(String),
+    /// No label starts with the typed characters
+    Ignored,
+}
+
+/// Labelled matches of the hint patterns on the screen
+pub struct Hints {
+    pub action: HintAction,
+    alphabet: Vec<char>,
+    regexes: Vec<RegexSearch>,
+    hints: Vec<Hint>,
+    typed: String,
+}
+
+impl Hints {
+    pub fn new(config: &HintConfig, action: HintAction) -> Self {
+        let mut alphabet = Vec::new();
+        for c in config.alphabet.chars() {
+            if !c.is_whitespace() && !alphabet.contains(&c) {
+                alphabet.push(c);
+            }
+        }
+        if alphabet.len() < 2 {
+            log::warn!(
+                "hint alphabet {:?} needs at least two characters",
+                config.alphabet
+            );
+            alphabet = HintConfig::default().alphabet.chars().collect();
+        }
+
+        let regexes = config
+            .patterns
+            .iter()
+            .filter(|pattern| pattern.actions.contains(&action))
+            .filter_map(|pattern| match RegexSearch::new(&pattern.regex) {
+                Ok(regex) => Some(regex),
+                Err(err) => {
+                    log::warn!("invalid hint pattern {:?}: {}", pattern.name, err);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            action,
+            alphabet,
+            regexes,
+            hints: Vec::new(),
+            typed: String::new(),
+        }
+    }
+
+    /// Find the matches on the screen and label them
+    pub fn update<T>(&mut self, term: &Term<T>) {
+        let mut matches = Vec::new();
+        for regex in &mut self.regexes {
+            let regex_matches: Vec<_> = visible_regex_match_iter(term, regex).collect();
+            for regex_match in regex_matches {
+                matches.extend(HintPostProcessor::new(term, regex, regex_match));
+            }
+        }
+
+        // Labels are drawn over the start of a match, so it has to be on the screen
+        let viewport_start = Line(-(term.grid().display_offset() as i32));
+        let viewport_end = viewport_start + term.bottommost_line();
+        matches.retain(|regex_match: &Match| {
+            (viewport_start..=viewport_end).contains(&regex_match.start().line)
+        });
+
+        // Patterns can match the same text, keep the match starting first and then the longest
+        matches.sort_by_key(|regex_match| (*regex_match.start(), Reverse(*regex_match.end())));
+        let mut end_opt = None;
+        matches.retain(|regex_match| {
+            if end_opt.is_some_and(|end| *regex_match.start() <= end) {
+                false
+            } else {
+                end_opt = Some(*regex_match.end());
+                true
+            }
+        });
+
+        // Matches near the bottom, where the latest output is, get the shortest labels
+        let labels = labels(&self.alphabet, matches.len());
+        self.hints = matches
+            .into_iter()
+            .rev()
+            .zip(labels)
+            .map(|(regex_match, label)| Hint {
+                label,
+                text: term.bounds_to_string(*regex_match.start(), *regex_match.end()),
+                regex_match,
+            })
+            .collect();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+
+    pub fn input(&mut self, c: char) -> HintInput {
+        let typed = format!("{}{}", self.typed, c);
+        // No label starts with another, so a complete label selects a single hint
+        if let Some(hint) = self.hints.iter().find(|hint| hint.label == typed) {
+            HintInput::Selected(hint.text.clone())
+        } else if self.hints.iter().any(|hint| hint.label.starts_with(&typed)) {
+            self.typed = typed;
+            HintInput::Pending
+        } else {
+            HintInput::Ignored
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.typed.pop();
+    }
+
+    /// Hints with labels starting with the typed characters, and the rest of their labels
+    pub fn visible(&self) -> impl Iterator<Item = (&Hint, &str)> {
+        self.hints.iter().filter_map(|hint| {
+            hint.label
+                .strip_prefix(self.typed.as_str())
+                .map(|rest| (hint, rest))
+        })
+    }
+}
+
+/// Labels for a number of hints, none of them the start of another
+fn labels(alphabet: &[char], count: usize) -> Vec<String> {
+    let mut labels: Vec<String> = alphabet.iter().map(|c| c.to_string()).collect();
+    // Replace the last of the shortest labels with longer ones starting with it until there are
+    // enough, so the first hints keep the shortest labels
+    while labels.len() < count {
+        let len = labels
+            .iter()
+            .map(|label| label.chars().count())
+            .min()
+            .unwrap_or_default();
+        let Some(index) = labels
+            .iter()
+            .rposition(|label| label.chars().count() == len)
+        else {
+            break;
+        };
+        let prefix = labels[index].clone();
+        labels.splice(
+            index..=index,
+            alphabet.iter().map(|c| format!("{prefix}{c}")),
+        );
+    }
+    labels.truncate(count);
+    labels
+}
+
+/// What to open for the text of a hint
+///
+/// URLs are opened as they are, paths lose their line and column and are made absolute.
+pub fn open_target(text: &str, working_directory: Option<&Path>) -> String {
+    let mut path = text;
+    for _ in 0..2 {
+        if let Some((rest, number)) = path.rsplit_once(':')
+            && !number.is_empty()
+            && number.bytes().all(|b| b.is_ascii_digit())
+        {
+            path = rest;
+        }
+    }
+    // Paths only contain a colon before the line number, everything else starts with a scheme
+    if path.contains(':') {
+        return text.to_string();
+    }
+
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => match env::var_os("HOME") {
+            Some(home) => Path::new(&home).join(rest),
+            None => Path::new(path).to_path_buf(),
+        },
+        None => match working_directory {
+            Some(working_directory) => working_directory.join(path),
+            None => Path::new(path).to_path_buf(),
+        },
+    };
+    path.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HintInput, Hints, labels, open_target};
+    use crate::{
+        config::{HintAction, HintConfig},
+        terminal::Size,
+    };
+    use alacritty_terminal::{Term, event::VoidListener, term::Config, vte::ansi::Processor};
+    use std::path::Path;
+
+    fn test_term(bytes: &[u8]) -> Term<VoidListener> {
+        let size = Size {
+            width: 60,
+            height: 4,
+            cell_width: 1.0,
+            cell_height: 1.0,
+        };
+        let mut term = Term::new(Config::default(), &size, VoidListener);
+        let mut parser: Processor = Processor::new();
+        parser.advance(&mut term, bytes);
+        term
+    }
+
+    #[test]
+    fn labels_are_prefix_free() {
+        let alphabet = ['a', 'b', 'c'];
+        assert_eq!(labels(&alphabet, 2), vec!["a", "b"]);
+        assert_eq!(labels(&alphabet, 5), vec!["a", "b", "ca", "cb", "cc"]);
+
+        let many = labels(&alphabet, 20);
+        assert_eq!(many.len(), 20);
+        for (i, label) in many.iter().enumerate() {
+            for (j, other) in many.iter().enumerate() {
+                assert!(i == j || !other.starts_with(label.as_str()));
+            }
+        }
+    }
+
+    #[test]
+    fn hints_match_default_patterns() {
+        let term = test_term(
+            b"see https://example.com/a_(b). at src/main.rs:12:4\r\n\
+              commit 1a2b3c4d from 192.168.1.20 id\r\n\
+              123e4567-e89b-12d3-a456-426614174000\r\n",
+        );
+        let mut hints = Hints::new(&HintConfig::default(), HintAction::Copy);
+        hints.update(&term);
+        let mut texts: Vec<_> = hints.visible().map(|(hint, _)| hint.text.clone()).collect();
+        texts.sort();
+        assert_eq!(
+            texts,
+            vec![
+                "123e4567-e89b-12d3-a456-426614174000",
+                "192.168.1.20",
+                "1a2b3c4d",
+                "https://example.com/a_(b)",
+                "src/main.rs:12:4",
+            ]
+        );
+
+        // Only URLs and paths can be opened
+        let mut hints = Hints::new(&HintConfig::default(), HintAction::Open);
+        hints.update(&term);
+        assert_eq!(hints.visible().count(), 2);
+    }
+
+    #[test]
+    fn typing_labels_selects_hints() {
+        let config = HintConfig {
+            alphabet: "ab".to_string(),
+            ..Default::default()
+        };
+        let term = test_term(b"/a/1 /a/2 /a/3\r\n");
+        let mut hints = Hints::new(&config, HintAction::Copy);
+        hints.update(&term);
+
+        // The last match gets the shortest label
+        let labels: Vec<_> = hints
+            .visible()
+            .map(|(hint, label)| (hint.text.as_str(), label))
+            .collect();
+        assert_eq!(labels, vec![("/a/3", "a"), ("/a/2", "ba"), ("/a/1", "bb")]);
+
+        assert_eq!(hints.input('c'), HintInput::Ignored);
+        assert_eq!(hints.input('b'), HintInput::Pending);
+        assert_eq!(hints.visible().count(), 2);
+        hints.backspace();
+        assert_eq!(hints.visible().count(), 3);
+        assert_eq!(hints.input('b'), HintInput::Pending);
+        assert_eq!(hints.input('b'), HintInput::Selected("/a/1".to_string()));
+    }
+
+    #[test]
+    fn open_targets() {
+        let dir = Path::new("/home/user/project");
+        assert_eq!(
+            open_target("https://example.com:8080/x", Some(dir)),
+            "https://example.com:8080/x"
+        );
+        assert_eq!(
+            open_target("src/main.rs:12:4", Some(dir)),
+            "/home/user/project/src/main.rs"
+        );
+        assert_eq!(open_target("/etc/hosts", Some(dir)), "/etc/hosts");
+        assert_eq!(open_target("main.rs:3", None), "main.rs");
+    }
+}
@@ -0,0 +1,276 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::widget::pane_grid;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+#[cfg(unix)]
+use std::{
+    os::unix::net::{UnixListener, UnixStream},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use crate::xdg;
+
+/// Time a client waits for the running instance to answer
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Waits after failing to accept a client, doubled for each failure in a row
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(100);
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(5);
+
+/// Where the running instance opens the terminal of a request
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Target {
+    Tab,
+    Split(SplitDirection),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum SplitDirection {
+    Right,
+    Down,
+}
+
+impl SplitDirection {
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        match arg {
+            "right" => Some(Self::Right),
+            "down" => Some(Self::Down),
+            _ => None,
+        }
+    }
+
+    pub fn axis(self) -> pane_grid::Axis {
+        match self {
+            Self::Right => pane_grid::Axis::Vertical,
+            Self::Down => pane_grid::Axis::Horizontal,
+        }
+    }
+}
+
+/// Terminal to open, sent by `cosmic-term --new-tab` or `--split` to the running instance
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Request {
+    pub target: Target,
+    /// Name of the profile, the default profile is used if not set
+    pub profile: Option<String>,
+    pub working_directory: Option<PathBuf>,
+    pub command: Option<Vec<String>>,
+}
+
+fn socket_path() -> Option<PathBuf> {
+    Some(xdg::runtime_dir()?.join("instance.sock"))
+}
+
+/// Send a request to the running instance, failing if there is none
+pub fn send(request: &Request) -> io::Result<()> {
+    let path = socket_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set"))?;
+    send_to(&path, request)
+}
+
+#[cfg(not(unix))]
+fn send_to(_path: &Path, _request: &Request) -> io::Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(not(unix))]
+fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "requests need UNIX sockets")
+}
+
+#[cfg(unix)]
+fn send_to(path: &Path, request: &Request) -> io::Result<()> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let line = ron::to_string(request).map_err(io::Error::other)?;
+    writeln!(stream, "{}", line)?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    match reply.trim_end() {
+        "ok" => Ok(()),
+        "" => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "no reply from the running instance",
+        )),
+        error => Err(io::Error::other(error.to_string())),
+    }
+}
+
+/// Socket of the running instance, which other cosmic-term processes send requests to
+pub struct Server {
+    #[cfg(unix)]
+    listener: UnixListener,
+}
+
+impl Server {
+    /// Listen for requests, unless another process does already
+    pub fn bind() -> io::Result<Option<Self>> {
+        let path = socket_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set"))?;
+        Self::bind_at(&path)
+    }
+
+    #[cfg(not(unix))]
+    fn bind_at(_path: &Path) -> io::Result<Option<Self>> {
+        Ok(None)
+    }
+
+    #[cfg(not(unix))]
+    pub fn run(&self, _handle: impl Fn(Request) -> bool + Clone + Send + 'static) {}
+
+    #[cfg(unix)]
+    fn bind_at(path: &Path) -> io::Result<Option<Self>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match UnixListener::bind(path) {
+            Ok(listener) => Ok(Some(Self { listener })),
+            Err(err) if err.kind() == io::ErrorKind::AddrInUse => {
+                if UnixStream::connect(path).is_ok() {
+                    return Ok(None);
+                }
+                // Left behind by an instance that did not exit cleanly
+                fs::remove_file(path)?;
+                UnixListener::bind(path).map(|listener| Some(Self { listener }))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Pass requests to `handle` until it returns false
+    ///
+    /// Each client is read in its own thread, so one that never sends its request does not hold
+    /// up the others.
+    #[cfg(unix)]
+    pub fn run(&self, handle: impl Fn(Request) -> bool + Clone + Send + 'static) {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let mut backoff = ACCEPT_BACKOFF_MIN;
+        while !stopped.load(Ordering::Relaxed) {
+            let stream = match self.listener.accept() {
+                Ok((stream, _addr)) => {
+                    backoff = ACCEPT_BACKOFF_MIN;
+                    stream
+                }
+                Err(err) => {
+                    // Failures like running out of file descriptors last a while
+                    log::warn!("failed to accept request: {}", err);
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
+                    continue;
+                }
+            };
+            let handle = handle.clone();
+            let stopped = stopped.clone();
+            thread::spawn(move || match serve(stream) {
+                Ok(Some(request)) => {
+                    if !handle(request) {
+                        stopped.store(true, Ordering::Relaxed);
+                    }
+                }
+                Ok(None) => {}
+                Err(err) => log::warn!("failed to receive request: {}", err),
+            });
+        }
+    }
+}
+
+/// Read the request of a client and acknowledge it
+#[cfg(unix)]
+fn serve(stream: UnixStream) -> io::Result<Option<Request>> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    // Processes starting up connect without a request to see if this one is running
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut stream = reader.into_inner();
+    match ron::from_str(&line) {
+        Ok(request) => {
+            writeln!(stream, "ok")?;
+            Ok(Some(request))
+        }
+        Err(err) => {
+            writeln!(stream, "invalid request: {}", err)?;
+            Err(io::Error::new(io::ErrorKind::InvalidData, err))
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{Request, Server, SplitDirection, Target, send_to, serve};
+    use std::{env, fs, os::unix::net::UnixStream, process, sync::mpsc, thread, time::Duration};
+
+    #[test]
+    fn request_round_trip() {
+        let dir = env::temp_dir().join(format!("cosmic-term-ipc-{}", process::id()));
+        let path = dir.join("instance.sock");
+        let server = Server::bind_at(&path).unwrap().unwrap();
+        // Only one process listens
+        assert!(Server::bind_at(&path).unwrap().is_none());
+
+        let request = Request {
+            target: Target::Split(SplitDirection::Right),
+            profile: Some("Server".to_string()),
+            working_directory: Some("/tmp".into()),
+            command: Some(vec!["cargo".to_string(), "run".to_string()]),
+        };
+        let client = {
+            let path = path.clone();
+            let request = request.clone();
+            thread::spawn(move || send_to(&path, &request))
+        };
+        // The check of the second bind connects without a request
+        let received = loop {
+            let (stream, _addr) = server.listener.accept().unwrap();
+            if let Some(request) = serve(stream).unwrap() {
+                break request;
+            }
+        };
+        assert_eq!(received, request);
+        client.join().unwrap().unwrap();
+
+        // A socket without a listener is replaced
+        drop(server);
+        assert!(Server::bind_at(&path).unwrap().is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn silent_clients_do_not_block_requests() {
+        let dir = env::temp_dir().join(format!("cosmic-term-ipc-silent-{}", process::id()));
+        let path = dir.join("instance.sock");
+        let server = Server::bind_at(&path).unwrap().unwrap();
+        let (request_tx, request_rx) = mpsc::channel();
+        thread::spawn(move || server.run(move |request| request_tx.send(request).is_ok()));
+
+        let _silent = UnixStream::connect(&path).unwrap();
+        let request = Request {
+            target: Target::Tab,
+            profile: None,
+            working_directory: None,
+            command: None,
+        };
+        send_to(&path, &request).unwrap();
+        // Answered before the silent client times out
+        assert_eq!(
+            request_rx.recv_timeout(Duration::from_secs(1)).unwrap(),
+            request
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,709 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//Implemented according to
+//https://sw.kovidgoyal.net/kitty/graphics-protocol/
+
+use base64::{Engine, engine::general_purpose::STANDARD_NO_PAD};
+use cosmic::iced::{Point, Rectangle, Size};
+use flate2::read::ZlibDecoder;
+use std::{
+    env, fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::graphics::{
+    CursorMovement, DecodedImage, Graphics, MAX_IMAGE_DIMENSION, MAX_PLACEMENT_CELLS, Placement,
+    placeholder_text,
+};
+
+/// Largest amount of image data read from a file or decompressed
+const MAX_DATA_LEN: u64 = 256 * 1024 * 1024;
+
+/// Control data of a graphics command
+#[derive(Clone, Debug, PartialEq)]
+pub struct Command {
+    pub action: u8,
+    pub quiet: u32,
+    pub format: u32,
+    pub medium: u8,
+    pub compressed: bool,
+    pub width: u32,
+    pub height: u32,
+    pub data_size: u32,
+    pub data_offset: u32,
+    pub image_id: u32,
+    pub image_number: u32,
+    pub placement_id: u32,
+    pub more: bool,
+    pub source_x: u32,
+    pub source_y: u32,
+    pub source_width: u32,
+    pub source_height: u32,
+    pub offset_x: u32,
+    pub offset_y: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub cursor_movement: u32,
+    pub virtual_placement: bool,
+    pub z_index: i32,
+    pub delete: u8,
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Self {
+            action: b't',
+            quiet: 0,
+            format: 32,
+            medium: b'd',
+            compressed: false,
+            width: 0,
+            height: 0,
+            data_size: 0,
+            data_offset: 0,
+            image_id: 0,
+            image_number: 0,
+            placement_id: 0,
+            more: false,
+            source_x: 0,
+            source_y: 0,
+            source_width: 0,
+            source_height: 0,
+            offset_x: 0,
+            offset_y: 0,
+            columns: 0,
+            rows: 0,
+            cursor_movement: 0,
+            virtual_placement: false,
+            z_index: 0,
+            delete: b'a',
+        }
+    }
+}
+
+impl Command {
+    /// Parses comma separated key=value pairs, unknown keys and invalid values are ignored
+    pub fn parse(control: &[u8]) -> Self {
+        let mut command = Self::default();
+        for pair in control.split(|&byte| byte == b',') {
+            let (key, value) = match pair {
+                [key, b'=', value @ ..] if !value.is_empty() => (*key, value),
+                _ => continue,
+            };
+            let number = || {
+                std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|value| value.parse::<u32>().ok())
+            };
+            let set = |field: &mut u32| {
+                if let Some(number) = number() {
+                    *field = number;
+                }
+            };
+            match key {
+                b'a' => command.action = value[0],
+                b'q' => set(&mut command.quiet),
+                b'f' => set(&mut command.format),
+                b't' => command.medium = value[0],
+                b'o' => command.compressed = value[0] == b'z',
+                b's' => set(&mut command.width),
+                b'v' => set(&mut command.height),
+                b'S' => set(&mut command.data_size),
+                b'O' => set(&mut command.data_offset),
+                b'i' => set(&mut command.image_id),
+                b'I' => set(&mut command.image_number),
+                b'p' => set(&mut command.placement_id),
+                b'm' => command.more = value[0] == b'1',
+                b'x' => set(&mut command.source_x),
+                b'y' => set(&mut command.source_y),
+                b'w' => set(&mut command.source_width),
+                b'h' => set(&mut command.source_height),
+                b'X' => set(&mut command.offset_x),
+                b'Y' => set(&mut command.offset_y),
+                b'c' => set(&mut command.columns),
+                b'r' => set(&mut command.rows),
+                b'C' => set(&mut command.cursor_movement),
+                b'U' => command.virtual_placement = value[0] == b'1',
+                b'z' => {
+                    if let Some(z_index) = std::str::from_utf8(value)
+                        .ok()
+                        .and_then(|value| value.parse::<i32>().ok())
+                    {
+                        command.z_index = z_index;
+                    }
+                }
+                b'd' => command.delete = value[0],
+                _ => {}
+            }
+        }
+        command
+    }
+}
+
+/// Command with its payload, collected from all chunks
+pub struct Transfer {
+    pub command: Command,
+    pub payload: Vec<u8>,
+}
+
+/// Result of executing a command
+#[derive(Debug, Default)]
+pub struct Outcome {
+    /// Placeholder text to insert in the PTY stream
+    pub output: Vec<u8>,
+    /// Response to write back to the PTY
+    pub reply_opt: Option<String>,
+    /// Set when stored images or placements changed
+    pub changed: bool,
+}
+
+/// Command with the image it transmits, if any
+pub struct Decoded {
+    pub command: Command,
+    pub image_opt: Option<Result<DecodedImage, String>>,
+}
+
+/// Decodes the image of a transmit command, which may read files and decompress data so it is
+/// done without holding the graphics lock
+pub fn decode(transfer: Transfer) -> Decoded {
+    let Transfer { command, payload } = transfer;
+    let image_opt = match command.action {
+        b't' | b'T' | b'q' => Some(if command.image_id > 0 && command.image_number > 0 {
+            Err("EINVAL:image id and number are mutually exclusive".to_string())
+        } else {
+            load_image(&command, &payload)
+        }),
+        _ => None,
+    };
+    Decoded { command, image_opt }
+}
+
+pub fn execute(graphics: &mut Graphics, decoded: Decoded, cell_size: (u32, u32)) -> Outcome {
+    let Decoded { command, image_opt } = decoded;
+    let mut outcome = Outcome::default();
+    let mut image_id = command.image_id;
+    let result = match command.action {
+        b't' | b'T' | b'q' => image_opt
+            .unwrap_or_else(|| Err("EINVAL:image was not decoded".to_string()))
+            .and_then(|image| {
+                if command.action == b'q' {
+                    return Ok(());
+                }
+                if image_id == 0 {
+                    image_id = graphics.allocate_image_id();
+                }
+                if command.image_number > 0 {
+                    graphics.set_image_number(command.image_number, image_id);
+                }
+                graphics.insert_image(image_id, image)?;
+                outcome.changed = true;
+                if command.action == b'T' {
+                    outcome.output = place(graphics, image_id, &command, cell_size)?;
+                }
+                Ok(())
+            }),
+        b'p' => {
+            if image_id == 0 && command.image_number > 0 {
+                image_id = graphics
+                    .image_id_by_number(command.image_number)
+                    .unwrap_or(0);
+            }
+            place(graphics, image_id, &command, cell_size).map(|output| {
+                outcome.output = output;
+                outcome.changed = true;
+            })
+        }
+        b'd' => {
+            delete(graphics, &command);
+            outcome.changed = true;
+            // Deletions are never answered
+            return outcome;
+        }
+        action => Err(format!("EINVAL:unsupported action {}", char::from(action))),
+    };
+
+    outcome.reply_opt = reply(&command, image_id, result);
+    outcome
+}
+
+/// Response to a command, only commands that identify their image are answered
+pub fn reply(command: &Command, image_id: u32, result: Result<(), String>) -> Option<String> {
+    if let Err(err) = &result {
+        log::warn!("kitty graphics command failed: {}", err);
+    }
+
+    let quiet = match result {
+        Ok(()) => command.quiet >= 1,
+        Err(_) => command.quiet >= 2,
+    };
+    if (command.image_id == 0 && command.image_number == 0) || quiet {
+        return None;
+    }
+    let mut reply = format!("\x1b_Gi={}", image_id);
+    if command.image_number > 0 {
+        reply.push_str(&format!(",I={}", command.image_number));
+    }
+    if command.placement_id > 0 {
+        reply.push_str(&format!(",p={}", command.placement_id));
+    }
+    match result {
+        Ok(()) => reply.push_str(";OK"),
+        Err(err) => {
+            reply.push(';');
+            reply.push_str(&err);
+        }
+    }
+    reply.push_str("\x1b\\");
+    Some(reply)
+}
+
+/// Error for everything that goes wrong reading an image from a file, so replies do not tell
+/// the application anything about files it could not read itself
+const FILE_ERROR: &str = "EBADF:failed to read image file";
+
+/// Directories that temporary files may be removed from once read
+fn temp_dirs() -> Vec<PathBuf> {
+    [env::temp_dir(), PathBuf::from("/dev/shm")]
+        .iter()
+        .filter_map(|dir| fs::canonicalize(dir).ok())
+        .collect()
+}
+
+fn read_file(command: &Command, payload: &[u8]) -> Result<Vec<u8>, String> {
+    let path = String::from_utf8(decode_base64(payload)?)
+        .map_err(|_| "EINVAL:file name is not valid UTF-8".to_string())?;
+    let path = PathBuf::from(path);
+    let canonical = fs::canonicalize(&path).map_err(|err| {
+        log::debug!("failed to resolve {:?}: {}", path, err);
+        FILE_ERROR.to_string()
+    })?;
+    if ["/proc", "/sys", "/dev"]
+        .iter()
+        .any(|prefix| canonical.starts_with(prefix))
+        && !canonical.starts_with("/dev/shm")
+    {
+        log::debug!(
+            "refusing to read {:?} from a special file system",
+            canonical
+        );
+        return Err(FILE_ERROR.to_string());
+    }
+    let result = read_file_data(command, &canonical).map_err(|err| {
+        log::debug!("failed to read {:?}: {}", canonical, err);
+        FILE_ERROR.to_string()
+    });
+    // Temporary files are removed once read, but only regular files in temporary directories
+    // that look like they were meant to be
+    if command.medium == b't'
+        && fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_file())
+        && canonical
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.contains("tty-graphics-protocol"))
+        && canonical
+            .parent()
+            .is_some_and(|parent| temp_dirs().iter().any(|dir| dir == parent))
+        && let Err(err) = fs::remove_file(&canonical)
+    {
+        log::warn!("failed to remove {:?}: {}", canonical, err);
+    }
+    result
+}
+
+fn read_file_data(command: &Command, path: &Path) -> io::Result<Vec<u8>> {
+    // Checked before opening too, opening a FIFO would block
+    if !fs::metadata(path)?.is_file() {
+        return Err(io::Error::other("not a regular file"));
+    }
+    let mut file = fs::File::open(path)?;
+    if !file.metadata()?.is_file() {
+        return Err(io::Error::other("not a regular file"));
+    }
+    file.seek(SeekFrom::Start(u64::from(command.data_offset)))?;
+    let limit = match command.data_size {
+        0 => MAX_DATA_LEN,
+        size => u64::from(size).min(MAX_DATA_LEN),
+    };
+    let mut data = Vec::new();
+    file.take(limit).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn decode_base64(payload: &[u8]) -> Result<Vec<u8>, String> {
+    let payload: Vec<u8> = payload
+        .iter()
+        .copied()
+        .filter(|&byte| byte != b'=')
+        .collect();
+    STANDARD_NO_PAD
+        .decode(payload)
+        .map_err(|err| format!("EINVAL:invalid base64 data: {}", err))
+}
+
+fn load_image(command: &Command, payload: &[u8]) -> Result<DecodedImage, String> {
+    match command.medium {
+        b'd' => decode_image(command, decode_base64(payload)?),
+        b'f' | b't' => decode_image(command, read_file(command, payload)?).map_err(|err| {
+            log::debug!("failed to decode image file: {}", err);
+            FILE_ERROR.to_string()
+        }),
+        medium => Err(format!(
+            "EINVAL:unsupported transmission medium {}",
+            char::from(medium)
+        )),
+    }
+}
+
+fn decode_image(command: &Command, mut data: Vec<u8>) -> Result<DecodedImage, String> {
+    if command.compressed {
+        let mut decompressed = Vec::new();
+        ZlibDecoder::new(data.as_slice())
+            .take(MAX_DATA_LEN)
+            .read_to_end(&mut decompressed)
+            .map_err(|err| format!("EINVAL:failed to decompress data: {}", err))?;
+        data = decompressed;
+    }
+
+    let image = match command.format {
+        24 | 32 => {
+            let (width, height) = (command.width, command.height);
+            if width == 0
+                || height == 0
+                || width > MAX_IMAGE_DIMENSION
+                || height > MAX_IMAGE_DIMENSION
+            {
+                return Err(format!("EINVAL:invalid image size {}x{}", width, height));
+            }
+            let bytes_per_pixel = if command.format == 24 { 3 } else { 4 };
+            let len = width as usize * height as usize * bytes_per_pixel;
+            if data.len() < len {
+                return Err(format!(
+                    "ENODATA:expected {} bytes of image data, got {}",
+                    len,
+                    data.len()
+                ));
+            }
+            data.truncate(len);
+            let pixels = if bytes_per_pixel == 3 {
+                data.chunks_exact(3)
+                    .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 0xFF])
+                    .collect()
+            } else {
+                data
+            };
+            DecodedImage {
+                width,
+                height,
+                pixels,
+            }
+        }
+        100 => {
+            let image = image::load_from_memory_with_format(&data, image::ImageFormat::Png)
+                .map_err(|err| format!("EBADPNG:{}", err))?
+                .into_rgba8();
+            if image.width() > MAX_IMAGE_DIMENSION || image.height() > MAX_IMAGE_DIMENSION {
+                return Err(format!(
+                    "EINVAL:invalid image size {}x{}",
+                    image.width(),
+                    image.height()
+                ));
+            }
+            DecodedImage {
+                width: image.width(),
+                height: image.height(),
+                pixels: image.into_raw(),
+            }
+        }
+        format => return Err(format!("EINVAL:unsupported format {}", format)),
+    };
+    if image.width == 0 || image.height == 0 {
+        return Err("EINVAL:empty image".to_string());
+    }
+    Ok(image)
+}
+
+fn cells(pixels: u64, cell_size: u32) -> u32 {
+    pixels
+        .div_ceil(u64::from(cell_size))
+        .min(u64::from(MAX_PLACEMENT_CELLS)) as u32
+}
+
+fn place(
+    graphics: &mut Graphics,
+    image_id: u32,
+    command: &Command,
+    cell_size: (u32, u32),
+) -> Result<Vec<u8>, String> {
+    let Some(image) = graphics.image(image_id) else {
+        return Err("ENOENT:image not found".to_string());
+    };
+    let (cell_width, cell_height) = cell_size;
+
+    let x = command.source_x.min(image.width - 1);
+    let y = command.source_y.min(image.height - 1);
+    let width = match command.source_width {
+        0 => image.width - x,
+        width => width.min(image.width - x),
+    };
+    let height = match command.source_height {
+        0 => image.height - y,
+        height => height.min(image.height - y),
+    };
+    // Offsets must stay inside the first cell
+    let offset = (
+        command.offset_x.min(cell_width - 1),
+        command.offset_y.min(cell_height - 1),
+    );
+
+    let (columns, rows) = match (command.columns, command.rows) {
+        (0, 0) => (
+            (width + offset.0).div_ceil(cell_width),
+            (height + offset.1).div_ceil(cell_height),
+        ),
+        // Keep the aspect ratio when only one side is given
+        (0, rows) => {
+            let pixels =
+                u64::from(rows) * u64::from(cell_height) * u64::from(width) / u64::from(height);
+            (cells(pixels, cell_width), rows)
+        }
+        (columns, 0) => {
+            let pixels =
+                u64::from(columns) * u64::from(cell_width) * u64::from(height) / u64::from(width);
+            (columns, cells(pixels, cell_height))
+        }
+        size => size,
+    };
+    let columns = columns.clamp(1, MAX_PLACEMENT_CELLS);
+    let rows = rows.clamp(1, MAX_PLACEMENT_CELLS);
+
+    let placement = Placement {
+        columns,
+        rows,
+        source: Rectangle::new(Point::new(x, y), Size::new(width, height)),
+        offset,
+        scale: command.columns > 0 || command.rows > 0,
+        z_index: command.z_index,
+        is_virtual: command.virtual_placement,
+    };
+
+    // Placement ids are stored in the underline color of placeholders
+    let mut placement_id = command.placement_id & 0xFF_FFFF;
+    if command.virtual_placement {
+        graphics.insert_placement(image_id, placement_id, placement);
+        return Ok(Vec::new());
+    }
+    if placement_id == 0 {
+        placement_id = graphics.allocate_placement_id(image_id);
+    }
+    graphics.insert_placement(image_id, placement_id, placement);
+    let cursor_movement = if command.cursor_movement == 1 {
+        CursorMovement::Unchanged
+    } else {
+        CursorMovement::AfterImage
+    };
+    Ok(placeholder_text(
+        image_id,
+        placement_id,
+        columns,
+        rows,
+        cursor_movement,
+    ))
+}
+
+fn delete(graphics: &mut Graphics, command: &Command) {
+    let free = command.delete.is_ascii_uppercase();
+    let image_id = match command.delete.to_ascii_lowercase() {
+        b'a' => {
+            graphics.retain_placements(|_, _, placement| placement.is_virtual);
+            if free {
+                for image_id in graphics.image_ids() {
+                    if !graphics.has_placements(image_id) {
+                        graphics.remove_image(image_id);
+                    }
+                }
+            }
+            return;
+        }
+        b'i' => command.image_id,
+        b'n' => match graphics.image_id_by_number(command.image_number) {
+            Some(image_id) => image_id,
+            None => return,
+        },
+        delete => {
+            log::debug!(
+                "unsupported kitty graphics delete target {}",
+                char::from(delete)
+            );
+            return;
+        }
+    };
+
+    let placement_id = command.placement_id & 0xFF_FFFF;
+    graphics.remove_placements(image_id, |id, _| placement_id == 0 || id == placement_id);
+    if free && !graphics.has_placements(image_id) {
+        graphics.remove_image(image_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, Transfer, decode, execute};
+    use crate::graphics::{Graphics, PLACEHOLDER};
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    use std::{env, fs, process};
+
+    fn run(graphics: &mut Graphics, control: &str, payload: &[u8]) -> super::Outcome {
+        let transfer = Transfer {
+            command: Command::parse(control.as_bytes()),
+            payload: STANDARD.encode(payload).into_bytes(),
+        };
+        execute(graphics, decode(transfer), (10, 20))
+    }
+
+    fn placeholders(text: &[u8]) -> usize {
+        String::from_utf8_lossy(text)
+            .chars()
+            .filter(|&c| c == PLACEHOLDER)
+            .count()
+    }
+
+    #[test]
+    fn parses_control_data() {
+        let command = Command::parse(b"a=T,f=100,i=31,p=7,c=4,z=-2,m=1,bogus,Q=9");
+        assert_eq!(command.action, b'T');
+        assert_eq!(command.format, 100);
+        assert_eq!(command.image_id, 31);
+        assert_eq!(command.placement_id, 7);
+        assert_eq!(command.columns, 4);
+        assert_eq!(command.z_index, -2);
+        assert!(command.more);
+        assert_eq!(command.medium, b'd');
+    }
+
+    #[test]
+    fn transmits_and_places() {
+        let mut graphics = Graphics::new(true, 16);
+        // 25x30 pixels cover 3x2 cells of 10x20 pixels
+        let outcome = run(
+            &mut graphics,
+            "a=T,f=24,s=25,v=30,i=5",
+            &[0x80; 25 * 30 * 3],
+        );
+        assert_eq!(outcome.reply_opt.as_deref(), Some("\x1b_Gi=5;OK\x1b\\"));
+        assert_eq!(placeholders(&outcome.output), 6);
+        assert!(outcome.output.ends_with(b"\x1b[3C"));
+        let image = graphics.image(5).unwrap();
+        assert_eq!((image.width, image.height), (25, 30));
+
+        // Placing again with a size in columns keeps the aspect ratio
+        let outcome = run(&mut graphics, "a=p,i=5,p=2,c=6,q=1", &[]);
+        assert!(outcome.reply_opt.is_none());
+        let (_, placement) = graphics.get(5, 2).unwrap();
+        assert_eq!((placement.columns, placement.rows), (6, 4));
+        assert!(placement.scale);
+    }
+
+    #[test]
+    fn reports_errors() {
+        let mut graphics = Graphics::new(true, 16);
+        let outcome = run(&mut graphics, "a=p,i=9", &[]);
+        assert!(outcome.reply_opt.unwrap().starts_with("\x1b_Gi=9;ENOENT:"));
+
+        let outcome = run(&mut graphics, "f=32,s=2,v=2,i=3", &[0; 4]);
+        assert!(outcome.reply_opt.unwrap().starts_with("\x1b_Gi=3;ENODATA:"));
+
+        // Without an id nothing is answered
+        let outcome = run(&mut graphics, "a=p", &[]);
+        assert!(outcome.reply_opt.is_none());
+    }
+
+    #[test]
+    fn image_numbers_get_new_ids() {
+        let mut graphics = Graphics::new(true, 16);
+        let outcome = run(&mut graphics, "f=32,s=1,v=1,I=13", &[0; 4]);
+        let image_id = graphics.image_id_by_number(13).unwrap();
+        assert_eq!(
+            outcome.reply_opt.unwrap(),
+            format!("\x1b_Gi={},I=13;OK\x1b\\", image_id)
+        );
+    }
+
+    #[test]
+    fn virtual_placements_and_deletion() {
+        let mut graphics = Graphics::new(true, 16);
+        run(&mut graphics, "f=32,s=1,v=1,i=1,q=2", &[0; 4]);
+        let outcome = run(&mut graphics, "a=p,U=1,i=1,c=2,r=2,q=2", &[]);
+        assert!(outcome.output.is_empty());
+        // Placeholders without a placement id find the virtual placement
+        assert!(graphics.get(1, 0).is_some());
+
+        run(&mut graphics, "a=p,i=1,p=4,q=2", &[]);
+        run(&mut graphics, "a=d,d=i,i=1,p=4", &[]);
+        assert!(graphics.get(1, 4).is_none());
+        assert!(graphics.image(1).is_some());
+
+        run(&mut graphics, "a=d,d=I,i=1", &[]);
+        assert!(graphics.image(1).is_none());
+    }
+
+    #[test]
+    fn decompresses_png_data() {
+        use flate2::{Compression, write::ZlibEncoder};
+        use std::io::Write;
+
+        let mut png = Vec::new();
+        image::RgbaImage::from_pixel(2, 3, image::Rgba([1, 2, 3, 4]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&png).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut graphics = Graphics::new(true, 16);
+        let outcome = run(&mut graphics, "f=100,o=z,i=2", &compressed);
+        assert_eq!(outcome.reply_opt.as_deref(), Some("\x1b_Gi=2;OK\x1b\\"));
+        let image = graphics.image(2).unwrap();
+        assert_eq!((image.width, image.height), (2, 3));
+    }
+
+    #[test]
+    fn reads_files_without_revealing_errors() {
+        let mut graphics = Graphics::new(true, 16);
+        let dir = env::temp_dir().join(format!("cosmic-term-kitty-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let image = dir.join("image.rgba");
+        fs::write(&image, [0u8; 4]).unwrap();
+        let file = |path: &std::path::Path| path.to_str().unwrap().as_bytes().to_vec();
+
+        let outcome = run(&mut graphics, "t=f,f=32,s=1,v=1,i=1", &file(&image));
+        assert_eq!(outcome.reply_opt.as_deref(), Some("\x1b_Gi=1;OK\x1b\\"));
+        assert!(image.exists());
+
+        // Missing files, directories, special files and bad data all give the same error
+        let error = "\x1b_Gi=2;EBADF:failed to read image file\x1b\\";
+        for (control, path) in [
+            ("t=f,f=32,s=1,v=1,i=2", dir.join("missing")),
+            ("t=f,f=32,s=1,v=1,i=2", dir.clone()),
+            ("t=f,f=32,s=1,v=1,i=2", "/proc/self/environ".into()),
+            ("t=f,f=32,s=9,v=9,i=2", image.clone()),
+        ] {
+            let outcome = run(&mut graphics, control, &file(&path));
+            assert_eq!(outcome.reply_opt.as_deref(), Some(error));
+        }
+
+        // Temporary files are only removed directly in the temporary directory
+        let nested = dir.join("tty-graphics-protocol-image");
+        fs::write(&nested, [0u8; 4]).unwrap();
+        run(&mut graphics, "t=t,f=32,s=1,v=1,i=3", &file(&nested));
+        assert!(nested.exists());
+
+        let temp = env::temp_dir().join(format!("tty-graphics-protocol-{}", process::id()));
+        fs::write(&temp, [0u8; 4]).unwrap();
+        let outcome = run(&mut graphics, "t=t,f=32,s=1,v=1,i=4", &file(&temp));
+        assert_eq!(outcome.reply_opt.as_deref(), Some("\x1b_Gi=4;OK\x1b\\"));
+        assert!(!temp.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,686 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//Implemented according to
+//https://sw.kovidgoyal.net/kitty/keyboard-protocol/
+//The enhancement flags pushed with CSI > flags u are tracked by alacritty_terminal in TermMode,
+//which also answers CSI ? u queries. Keys the flags leave to the legacy encoding are encoded
+//like xterm does:
+//https://sw.kovidgoyal.net/kitty/keyboard-protocol/#legacy-key-event-encoding
+
+use alacritty_terminal::term::TermMode;
+use cosmic::iced::keyboard::{Key, Location, Modifiers, key::Named};
+
+const MOD_SHIFT: u8 = 0b1;
+const MOD_ALT: u8 = 0b10;
+const MOD_CTRL: u8 = 0b100;
+const MOD_SUPER: u8 = 0b1000;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeyEventKind {
+    Press,
+    Release,
+}
+
+/// Key event as received from iced
+#[derive(Clone, Copy, Debug)]
+pub struct KeyInput<'a> {
+    /// Key without modifiers applied
+    pub key: &'a Key,
+    /// Key with modifiers applied, reported as the shifted alternate key
+    pub modified_key: &'a Key,
+    pub location: Location,
+    pub modifiers: Modifiers,
+    pub text: Option<&'a str>,
+    pub kind: KeyEventKind,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum KeyCode {
+    /// Keys producing text, sent as text unless disambiguation is required
+    Text(u32),
+    /// Enter, Tab, Backspace and Escape keep their legacy bytes when unmodified
+    Legacy(u32),
+    /// Modifier keys are only reported when all keys are reported as escape codes
+    Modifier(u32),
+    /// Keys that never produce text, sent as CSI number ~, CSI 1 letter or CSI number u
+    Functional(u32, char),
+}
+
+fn keypad_code(c: char) -> Option<u32> {
+    Some(match c {
+        '0'..='9' => 57399 + (c as u32 - '0' as u32),
+        '.' => 57409,
+        '/' => 57410,
+        '*' => 57411,
+        '-' => 57412,
+        '+' => 57413,
+        '=' => 57415,
+        ',' => 57416,
+        _ => return None,
+    })
+}
+
+fn key_code(key: &Key, location: Location) -> Option<KeyCode> {
+    match key {
+        Key::Character(string) => {
+            let mut chars = string.chars();
+            let c = chars.next()?;
+            if chars.next().is_some() {
+                // Keys producing more than one character cannot be represented
+                return None;
+            }
+            if location == Location::Numpad
+                && let Some(code) = keypad_code(c)
+            {
+                return Some(KeyCode::Text(code));
+            }
+            // The key code is always the lower case version of the key
+            Some(KeyCode::Text(c.to_lowercase().next().unwrap_or(c) as u32))
+        }
+        Key::Named(named) => named_key_code(*named, location),
+        _ => None,
+    }
+}
+
+fn named_key_code(named: Named, location: Location) -> Option<KeyCode> {
+    let modifier = |left, right| {
+        KeyCode::Modifier(if location == Location::Right {
+            right
+        } else {
+            left
+        })
+    };
+    Some(match named {
+        Named::Escape => KeyCode::Legacy(27),
+        Named::Enter if location == Location::Numpad => KeyCode::Legacy(57414),
+        Named::Enter => KeyCode::Legacy(13),
+        Named::Tab => KeyCode::Legacy(9),
+        Named::Backspace => KeyCode::Legacy(127),
+        Named::Space => KeyCode::Text(32),
+        Named::Insert => KeyCode::Functional(2, '~'),
+        Named::Delete => KeyCode::Functional(3, '~'),
+        Named::ArrowLeft => KeyCode::Functional(1, 'D'),
+        Named::ArrowRight => KeyCode::Functional(1, 'C'),
+        Named::ArrowUp => KeyCode::Functional(1, 'A'),
+        Named::ArrowDown => KeyCode::Functional(1, 'B'),
+        Named::PageUp => KeyCode::Functional(5, '~'),
+        Named::PageDown => KeyCode::Functional(6, '~'),
+        Named::Home => KeyCode::Functional(1, 'H'),
+        Named::End => KeyCode::Functional(1, 'F'),
+        Named::F1 => KeyCode::Functional(1, 'P'),
+        Named::F2 => KeyCode::Functional(1, 'Q'),
+        // CSI R is not used for F3 as it conflicts with cursor position reports
+        Named::F3 => KeyCode::Functional(13, '~'),
+        Named::F4 => KeyCode::Functional(1, 'S'),
+        Named::F5 => KeyCode::Functional(15, '~'),
+        Named::F6 => KeyCode::Functional(17, '~'),
+        Named::F7 => KeyCode::Functional(18, '~'),
+        Named::F8 => KeyCode::Functional(19, '~'),
+        Named::F9 => KeyCode::Functional(20, '~'),
+        Named::F10 => KeyCode::Functional(21, '~'),
+        Named::F11 => KeyCode::Functional(23, '~'),
+        Named::F12 => KeyCode::Functional(24, '~'),
+        Named::F13 => KeyCode::Functional(57376, 'u'),
+        Named::F14 => KeyCode::Functional(57377, 'u'),
+        Named::F15 => KeyCode::Functional(57378, 'u'),
+        Named::F16 => KeyCode::Functional(57379, 'u'),
+        Named::F17 => KeyCode::Functional(57380, 'u'),
+        Named::F18 => KeyCode::Functional(57381, 'u'),
+        Named::F19 => KeyCode::Functional(57382, 'u'),
+        Named::F20 => KeyCode::Functional(57383, 'u'),
+        Named::F21 => KeyCode::Functional(57384, 'u'),
+        Named::F22 => KeyCode::Functional(57385, 'u'),
+        Named::F23 => KeyCode::Functional(57386, 'u'),
+        Named::F24 => KeyCode::Functional(57387, 'u'),
+        Named::F25 => KeyCode::Functional(57388, 'u'),
+        Named::F26 => KeyCode::Functional(57389, 'u'),
+        Named::F27 => KeyCode::Functional(57390, 'u'),
+        Named::F28 => KeyCode::Functional(57391, 'u'),
+        Named::F29 => KeyCode::Functional(57392, 'u'),
+        Named::F30 => KeyCode::Functional(57393, 'u'),
+        Named::F31 => KeyCode::Functional(57394, 'u'),
+        Named::F32 => KeyCode::Functional(57395, 'u'),
+        Named::F33 => KeyCode::Functional(57396, 'u'),
+        Named::F34 => KeyCode::Functional(57397, 'u'),
+        Named::F35 => KeyCode::Functional(57398, 'u'),
+        Named::CapsLock => KeyCode::Functional(57358, 'u'),
+        Named::ScrollLock => KeyCode::Functional(57359, 'u'),
+        Named::NumLock => KeyCode::Functional(57360, 'u'),
+        Named::PrintScreen => KeyCode::Functional(57361, 'u'),
+        Named::Pause => KeyCode::Functional(57362, 'u'),
+        Named::ContextMenu => KeyCode::Functional(57363, 'u'),
+        Named::MediaPlay => KeyCode::Functional(57428, 'u'),
+        Named::MediaPause => KeyCode::Functional(57429, 'u'),
+        Named::MediaPlayPause => KeyCode::Functional(57430, 'u'),
+        Named::MediaStop => KeyCode::Functional(57432, 'u'),
+        Named::MediaFastForward => KeyCode::Functional(57433, 'u'),
+        Named::MediaRewind => KeyCode::Functional(57434, 'u'),
+        Named::MediaTrackNext => KeyCode::Functional(57435, 'u'),
+        Named::MediaTrackPrevious => KeyCode::Functional(57436, 'u'),
+        Named::MediaRecord => KeyCode::Functional(57437, 'u'),
+        Named::AudioVolumeDown => KeyCode::Functional(57438, 'u'),
+        Named::AudioVolumeUp => KeyCode::Functional(57439, 'u'),
+        Named::AudioVolumeMute => KeyCode::Functional(57440, 'u'),
+        Named::Shift => modifier(57441, 57447),
+        Named::Control => modifier(57442, 57448),
+        Named::Alt => modifier(57443, 57449),
+        Named::Super => modifier(57444, 57450),
+        Named::Hyper => modifier(57445, 57451),
+        Named::Meta => modifier(57446, 57452),
+        Named::AltGraph => KeyCode::Modifier(57453),
+        _ => return None,
+    })
+}
+
+fn modifier_bits(modifiers: Modifiers) -> u8 {
+    let mut bits = 0;
+    if modifiers.shift() {
+        bits |= MOD_SHIFT;
+    }
+    if modifiers.alt() {
+        bits |= MOD_ALT;
+    }
+    if modifiers.control() {
+        bits |= MOD_CTRL;
+    }
+    if modifiers.logo() {
+        bits |= MOD_SUPER;
+    }
+    bits
+}
+
+/// Encodes a key event for the enhancement flags in `mode`, keys they leave to the legacy
+/// encoding are encoded like xterm does
+///
+/// Returns `None` if the key is not sent to the application
+pub fn encode(mode: TermMode, input: &KeyInput) -> Option<Vec<u8>> {
+    encode_enhanced(mode, input).or_else(|| encode_legacy(mode, input))
+}
+
+/// Returns `None` if the key must use the legacy encoding (or, for releases, not be reported)
+fn encode_enhanced(mode: TermMode, input: &KeyInput) -> Option<Vec<u8>> {
+    if !mode.intersects(TermMode::KITTY_KEYBOARD_PROTOCOL) {
+        return None;
+    }
+    let disambiguate = mode.contains(TermMode::DISAMBIGUATE_ESC_CODES);
+    let report_event_types = mode.contains(TermMode::REPORT_EVENT_TYPES);
+    let report_alternate_keys = mode.contains(TermMode::REPORT_ALTERNATE_KEYS);
+    let report_all = mode.contains(TermMode::REPORT_ALL_KEYS_AS_ESC);
+    // Associated text is only defined when all keys are reported as escape codes
+    let report_text = report_all && mode.contains(TermMode::REPORT_ASSOCIATED_TEXT);
+
+    let is_release = input.kind == KeyEventKind::Release;
+    if is_release && !report_event_types {
+        return None;
+    }
+
+    let key_code = key_code(input.key, input.location)?;
+    let mods = modifier_bits(input.modifiers);
+    // Shift alone does not make text ambiguous
+    let ambiguous_mods = mods & !MOD_SHIFT != 0;
+
+    let use_escape = report_all
+        || match key_code {
+            KeyCode::Text(_) => {
+                // Text was sent for the press, so its release has to be an escape code
+                is_release
+                    || (disambiguate && (ambiguous_mods || input.location == Location::Numpad))
+            }
+            KeyCode::Legacy(code) => disambiguate && (code == 27 || ambiguous_mods),
+            KeyCode::Modifier(_) => false,
+            KeyCode::Functional(_, _) => true,
+        };
+    if !use_escape {
+        return None;
+    }
+
+    let (number, suffix) = match key_code {
+        KeyCode::Text(code) | KeyCode::Legacy(code) | KeyCode::Modifier(code) => (code, 'u'),
+        KeyCode::Functional(number, suffix) => (number, suffix),
+    };
+
+    let mut key_field = number.to_string();
+    if report_alternate_keys
+        && input.modifiers.shift()
+        && input.location != Location::Numpad
+        && let KeyCode::Text(code) = key_code
+        && let Key::Character(shifted) = input.modified_key
+        && let Some(shifted) = shifted.chars().next()
+        && shifted as u32 != code
+    {
+        key_field.push_str(&format!(":{}", shifted as u32));
+    }
+
+    let mut modifier_field = String::new();
+    if mods != 0 || is_release {
+        modifier_field.push_str(&(mods + 1).to_string());
+    }
+    if is_release {
+        modifier_field.push_str(":3");
+    }
+
+    let text_field = if report_text && !is_release {
+        input
+            .text
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| !c.is_control())
+            .map(|c| (c as u32).to_string())
+            .collect::<Vec<_>>()
+            .join(":")
+    } else {
+        String::new()
+    };
+
+    let mut escape_code = String::from("\x1b[");
+    if !text_field.is_empty() {
+        escape_code.push_str(&format!("{key_field};{modifier_field};{text_field}"));
+    } else if !modifier_field.is_empty() {
+        escape_code.push_str(&format!("{key_field};{modifier_field}"));
+    } else if matches!(suffix, 'u' | '~') {
+        // The key number 1 is omitted for CSI letter forms without parameters
+        escape_code.push_str(&key_field);
+    }
+    escape_code.push(suffix);
+    Some(escape_code.into_bytes())
+}
+
+/// Legacy encoding, where releases are never reported
+fn encode_legacy(mode: TermMode, input: &KeyInput) -> Option<Vec<u8>> {
+    if input.kind == KeyEventKind::Release {
+        return None;
+    }
+    let modifiers = input.modifiers;
+    let alt_prefix = if modifiers.alt() { "\x1b" } else { "" };
+
+    if let Key::Named(named) = input.key
+        && input.modified_key == input.key
+    {
+        let code = match named {
+            Named::Backspace if modifiers.control() => "\x08",
+            Named::Backspace => "\x7f",
+            Named::Enter => "\r",
+            Named::Escape => "\x1b",
+            Named::Tab if modifiers.shift() => "\x1b[Z",
+            Named::Tab => "\t",
+            // The enhancements use CSI 13 ~ instead
+            Named::F3 => return Some(legacy_functional(mode, 1, 'R', modifiers)),
+            _ => match named_key_code(*named, input.location)? {
+                KeyCode::Functional(number, suffix) if suffix != 'u' => {
+                    return Some(legacy_functional(mode, number, suffix, modifiers));
+                }
+                _ => return None,
+            },
+        };
+        return Some(format!("{alt_prefix}{code}").into_bytes());
+    }
+
+    // The text is used instead of the key to allow for dead keys
+    let c = input
+        .text
+        .and_then(|text| text.chars().next())
+        .unwrap_or_default();
+    if *input.key == Key::Character(" ".into()) {
+        // Ctrl+Space sends NUL
+        return Some(if modifiers.control() {
+            vec![0]
+        } else {
+            format!("{alt_prefix}{c}").into_bytes()
+        });
+    }
+    let text = match (
+        modifiers.logo(),
+        modifiers.control(),
+        modifiers.alt(),
+        modifiers.shift(),
+    ) {
+        // Super is left to the desktop
+        (true, _, _, _) => return None,
+        // Ctrl+Alt sends non-control characters and control characters 0-31 after ESC
+        (false, true, true, _) if !c.is_control() || (c as u32) < 32 => format!("\x1b{c}"),
+        // Ctrl sends control characters, like Ctrl+A to Ctrl+Z
+        (false, true, false, false) if c.is_control() => c.to_string(),
+        // Ctrl+Shift+_ sends C-_ like xterm and gnome-terminal, whichever key produces '_'
+        (false, true, false, true) if *input.modified_key == Key::Character("_".into()) => {
+            "\x1f".to_string()
+        }
+        (false, false, true, _) if !c.is_control() => format!("\x1b{c}"),
+        (false, false, false, _) if !c.is_control() => c.to_string(),
+        _ => return None,
+    };
+    Some(text.into_bytes())
+}
+
+/// Functional keys without modifiers are sent as CSI number ~, or SS3 letter for F1 to F4 and,
+/// in application cursor mode, for the cursor keys
+fn legacy_functional(mode: TermMode, number: u32, suffix: char, modifiers: Modifiers) -> Vec<u8> {
+    let mods = modifier_bits(modifiers);
+    let escape_code = if mods != 0 {
+        format!("\x1b[{number};{}{suffix}", mods + 1)
+    } else if suffix == '~' {
+        format!("\x1b[{number}~")
+    } else if mode.contains(TermMode::APP_CURSOR) || matches!(suffix, 'P' | 'Q' | 'R' | 'S') {
+        format!("\x1bO{suffix}")
+    } else {
+        format!("\x1b[{suffix}")
+    };
+    escape_code.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyEventKind, KeyInput, encode};
+    use alacritty_terminal::term::TermMode;
+    use cosmic::iced::keyboard::{Key, Location, Modifiers, key::Named};
+
+    fn press(mode: TermMode, key: Key, modified_key: Key, modifiers: Modifiers) -> Option<String> {
+        // Like the text of iced, with the control characters of Ctrl+letter
+        let text = match &modified_key {
+            Key::Character(c) if !modifiers.control() => Some(c.to_string()),
+            Key::Character(c) if c.len() == 1 && c.as_bytes()[0].is_ascii_alphabetic() => {
+                Some(char::from(c.as_bytes()[0] & 0x1f).to_string())
+            }
+            _ => None,
+        };
+        encode(
+            mode,
+            &KeyInput {
+                key: &key,
+                modified_key: &modified_key,
+                location: Location::Standard,
+                modifiers,
+                text: text.as_deref(),
+                kind: KeyEventKind::Press,
+            },
+        )
+        .map(|code| String::from_utf8(code).unwrap())
+    }
+
+    fn release(mode: TermMode, key: Key, modifiers: Modifiers) -> Option<String> {
+        encode(
+            mode,
+            &KeyInput {
+                key: &key,
+                modified_key: &key,
+                location: Location::Standard,
+                modifiers,
+                text: None,
+                kind: KeyEventKind::Release,
+            },
+        )
+        .map(|code| String::from_utf8(code).unwrap())
+    }
+
+    fn char_key(c: &str) -> Key {
+        Key::Character(c.into())
+    }
+
+    fn named_press(mode: TermMode, named: Named, modifiers: Modifiers) -> Option<String> {
+        press(mode, Key::Named(named), Key::Named(named), modifiers)
+    }
+
+    #[test]
+    fn no_enhancements_uses_legacy_encoding() {
+        let mode = TermMode::empty();
+        let press = |key, modified_key, modifiers| {
+            press(mode, char_key(key), char_key(modified_key), modifiers)
+        };
+        assert_eq!(press("a", "a", Modifiers::empty()).as_deref(), Some("a"));
+        assert_eq!(press("a", "A", Modifiers::SHIFT).as_deref(), Some("A"));
+        assert_eq!(press("a", "a", Modifiers::CTRL).as_deref(), Some("\x01"));
+        assert_eq!(press("a", "a", Modifiers::ALT).as_deref(), Some("\x1ba"));
+        assert_eq!(
+            press("a", "a", Modifiers::CTRL | Modifiers::ALT).as_deref(),
+            Some("\x1b\x01")
+        );
+        assert_eq!(
+            press("-", "_", Modifiers::CTRL | Modifiers::SHIFT).as_deref(),
+            Some("\x1f")
+        );
+        assert_eq!(press(" ", " ", Modifiers::CTRL).as_deref(), Some("\0"));
+        assert_eq!(press(" ", " ", Modifiers::ALT).as_deref(), Some("\x1b "));
+        assert_eq!(press("a", "a", Modifiers::LOGO), None);
+        assert_eq!(release(mode, char_key("a"), Modifiers::empty()), None);
+
+        assert_eq!(
+            named_press(mode, Named::Escape, Modifiers::empty()).as_deref(),
+            Some("\x1b")
+        );
+        assert_eq!(
+            named_press(mode, Named::Backspace, Modifiers::ALT).as_deref(),
+            Some("\x1b\x7f")
+        );
+        assert_eq!(
+            named_press(mode, Named::Backspace, Modifiers::CTRL).as_deref(),
+            Some("\x08")
+        );
+        assert_eq!(
+            named_press(mode, Named::Tab, Modifiers::SHIFT).as_deref(),
+            Some("\x1b[Z")
+        );
+        assert_eq!(named_press(mode, Named::Shift, Modifiers::SHIFT), None);
+    }
+
+    #[test]
+    fn legacy_functional_keys() {
+        let mode = TermMode::empty();
+        let app_cursor = TermMode::APP_CURSOR;
+        assert_eq!(
+            named_press(mode, Named::ArrowUp, Modifiers::empty()).as_deref(),
+            Some("\x1b[A")
+        );
+        assert_eq!(
+            named_press(app_cursor, Named::ArrowUp, Modifiers::empty()).as_deref(),
+            Some("\x1bOA")
+        );
+        // Modified keys are the same in application cursor mode
+        assert_eq!(
+            named_press(app_cursor, Named::Home, Modifiers::CTRL).as_deref(),
+            Some("\x1b[1;5H")
+        );
+        assert_eq!(
+            named_press(mode, Named::F1, Modifiers::empty()).as_deref(),
+            Some("\x1bOP")
+        );
+        assert_eq!(
+            named_press(mode, Named::F3, Modifiers::SHIFT).as_deref(),
+            Some("\x1b[1;2R")
+        );
+        assert_eq!(
+            named_press(mode, Named::F5, Modifiers::empty()).as_deref(),
+            Some("\x1b[15~")
+        );
+        assert_eq!(
+            named_press(mode, Named::Delete, Modifiers::ALT).as_deref(),
+            Some("\x1b[3;3~")
+        );
+        // Keys only the enhancements can report
+        assert_eq!(named_press(mode, Named::F13, Modifiers::empty()), None);
+    }
+
+    #[test]
+    fn disambiguate_escape_codes() {
+        let mode = TermMode::DISAMBIGUATE_ESC_CODES;
+        let escape = Key::Named(Named::Escape);
+        let enter = Key::Named(Named::Enter);
+        let up = Key::Named(Named::ArrowUp);
+
+        // Plain and shifted text is still sent as text
+        assert_eq!(
+            press(mode, char_key("a"), char_key("a"), Modifiers::empty()).as_deref(),
+            Some("a")
+        );
+        assert_eq!(
+            press(mode, char_key("a"), char_key("A"), Modifiers::SHIFT).as_deref(),
+            Some("A")
+        );
+        // Enter keeps its legacy encoding unless modified
+        assert_eq!(
+            press(mode, enter.clone(), enter.clone(), Modifiers::empty()).as_deref(),
+            Some("\r")
+        );
+        assert_eq!(
+            press(mode, enter.clone(), enter, Modifiers::CTRL).as_deref(),
+            Some("\x1b[13;5u")
+        );
+        assert_eq!(
+            press(mode, escape.clone(), escape, Modifiers::empty()).as_deref(),
+            Some("\x1b[27u")
+        );
+        assert_eq!(
+            press(mode, char_key("a"), char_key("a"), Modifiers::CTRL).as_deref(),
+            Some("\x1b[97;5u")
+        );
+        assert_eq!(
+            press(
+                mode,
+                char_key("a"),
+                char_key("A"),
+                Modifiers::ALT | Modifiers::SHIFT
+            )
+            .as_deref(),
+            Some("\x1b[97;4u")
+        );
+        // Functional keys use CSI forms even without application cursor mode
+        assert_eq!(
+            press(mode, up.clone(), up.clone(), Modifiers::empty()).as_deref(),
+            Some("\x1b[A")
+        );
+        assert_eq!(
+            press(mode, up.clone(), up, Modifiers::CTRL).as_deref(),
+            Some("\x1b[1;5A")
+        );
+        let f3 = Key::Named(Named::F3);
+        assert_eq!(
+            press(mode, f3.clone(), f3, Modifiers::empty()).as_deref(),
+            Some("\x1b[13~")
+        );
+        // Releases and modifier keys are not reported at this level
+        assert_eq!(release(mode, char_key("a"), Modifiers::empty()), None);
+        let shift = Key::Named(Named::Shift);
+        assert_eq!(press(mode, shift.clone(), shift, Modifiers::SHIFT), None);
+    }
+
+    #[test]
+    fn report_event_types() {
+        let mode = TermMode::DISAMBIGUATE_ESC_CODES | TermMode::REPORT_EVENT_TYPES;
+        let enter = Key::Named(Named::Enter);
+        let up = Key::Named(Named::ArrowUp);
+
+        // Presses do not carry the default event type
+        assert_eq!(
+            press(mode, char_key("a"), char_key("a"), Modifiers::CTRL).as_deref(),
+            Some("\x1b[97;5u")
+        );
+        assert_eq!(
+            release(mode, char_key("a"), Modifiers::empty()).as_deref(),
+            Some("\x1b[97;1:3u")
+        );
+        assert_eq!(
+            release(mode, char_key("a"), Modifiers::CTRL).as_deref(),
+            Some("\x1b[97;5:3u")
+        );
+        assert_eq!(
+            release(mode, up, Modifiers::empty()).as_deref(),
+            Some("\x1b[1;1:3A")
+        );
+        // Enter, Tab and Backspace releases are only sent when all keys are reported
+        assert_eq!(release(mode, enter, Modifiers::empty()), None);
+    }
+
+    #[test]
+    fn report_alternate_keys() {
+        let mode = TermMode::DISAMBIGUATE_ESC_CODES | TermMode::REPORT_ALTERNATE_KEYS;
+        assert_eq!(
+            press(
+                mode,
+                char_key("a"),
+                char_key("A"),
+                Modifiers::CTRL | Modifiers::SHIFT
+            )
+            .as_deref(),
+            Some("\x1b[97:65;6u")
+        );
+        assert_eq!(
+            press(
+                mode,
+                char_key("1"),
+                char_key("!"),
+                Modifiers::ALT | Modifiers::SHIFT
+            )
+            .as_deref(),
+            Some("\x1b[49:33;4u")
+        );
+        // No shifted key without shift
+        assert_eq!(
+            press(mode, char_key("a"), char_key("a"), Modifiers::CTRL).as_deref(),
+            Some("\x1b[97;5u")
+        );
+        // Shifted text is still sent as text
+        assert_eq!(
+            press(mode, char_key("a"), char_key("A"), Modifiers::SHIFT).as_deref(),
+            Some("A")
+        );
+    }
+
+    #[test]
+    fn report_all_keys_as_escape_codes() {
+        let mode = TermMode::DISAMBIGUATE_ESC_CODES | TermMode::REPORT_ALL_KEYS_AS_ESC;
+        let enter = Key::Named(Named::Enter);
+        let backspace = Key::Named(Named::Backspace);
+        let shift = Key::Named(Named::Shift);
+
+        assert_eq!(
+            press(mode, char_key("a"), char_key("a"), Modifiers::empty()).as_deref(),
+            Some("\x1b[97u")
+        );
+        assert_eq!(
+            press(mode, char_key("a"), char_key("A"), Modifiers::SHIFT).as_deref(),
+            Some("\x1b[97;2u")
+        );
+        assert_eq!(
+            press(mode, enter.clone(), enter, Modifiers::empty()).as_deref(),
+            Some("\x1b[13u")
+        );
+        assert_eq!(
+            press(mode, backspace.clone(), backspace, Modifiers::empty()).as_deref(),
+            Some("\x1b[127u")
+        );
+        assert_eq!(
+            press(mode, shift.clone(), shift, Modifiers::SHIFT).as_deref(),
+            Some("\x1b[57441;2u")
+        );
+    }
+
+    #[test]
+    fn report_associated_text() {
+        let mode = TermMode::DISAMBIGUATE_ESC_CODES
+            | TermMode::REPORT_ALL_KEYS_AS_ESC
+            | TermMode::REPORT_ASSOCIATED_TEXT;
+        let enter = Key::Named(Named::Enter);
+
+        assert_eq!(
+            press(mode, char_key("a"), char_key("a"), Modifiers::empty()).as_deref(),
+            Some("\x1b[97;;97u")
+        );
+        assert_eq!(
+            press(mode, char_key("a"), char_key("A"), Modifiers::SHIFT).as_deref(),
+            Some("\x1b[97;2;65u")
+        );
+        // Control characters are never sent as text
+        assert_eq!(
+            press(mode, char_key("a"), char_key("a"), Modifiers::CTRL).as_deref(),
+            Some("\x1b[97;5u")
+        );
+        assert_eq!(
+            press(mode, enter.clone(), enter, Modifiers::empty()).as_deref(),
+            Some("\x1b[13u")
+        );
+        // Text is not reported with associated text alone
+        let mode = TermMode::DISAMBIGUATE_ESC_CODES | TermMode::REPORT_ASSOCIATED_TEXT;
+        assert_eq!(
+            press(mode, char_key("a"), char_key("a"), Modifiers::empty()).as_deref(),
+            Some("a")
+        );
+    }
+}
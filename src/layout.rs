@@ -0,0 +1,229 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use serde::Deserialize;
+use std::{
+    env,
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::Config,
+    session::{Session, SessionAxis, SessionPane, SessionTab, SessionTabs},
+    xdg,
+};
+
+/// Splits and tabs written by hand, opened with `--layout` or from the profile menu
+///
+/// ```ron
+/// (
+///     root: Split(
+///         axis: Vertical,
+///         a: Tabs([(title: "editor", command: "nvim .", working_directory: "~/src/app")]),
+///         b: Tabs([(profile: "Server", command: "cargo run")]),
+///     ),
+/// )
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Layout {
+    pub root: LayoutPane,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub enum LayoutPane {
+    Split {
+        axis: SessionAxis,
+        /// Share of the first pane, between 0 and 1
+        #[serde(default = "default_ratio")]
+        ratio: f32,
+        a: Box<LayoutPane>,
+        b: Box<LayoutPane>,
+    },
+    Tabs(Vec<LayoutTab>),
+}
+
+fn default_ratio() -> f32 {
+    0.5
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct LayoutTab {
+    /// Name of the profile, the default profile is used if not set
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Command line, instead of the command of the profile
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub working_directory: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Focus this tab and its pane
+    #[serde(default)]
+    pub focused: bool,
+}
+
+impl Layout {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let ron = fs::read_to_string(path)?;
+        Ok(ron::from_str(&ron)?)
+    }
+
+    /// Session opening the layout, with profiles looked up by name
+    pub fn session(&self, config: &Config) -> Session {
+        Session {
+            root: session_pane(&self.root, config),
+        }
+    }
+}
+
+fn session_pane(pane: &LayoutPane, config: &Config) -> SessionPane {
+    match pane {
+        LayoutPane::Split { axis, ratio, a, b } => SessionPane::Split {
+            axis: *axis,
+            ratio: *ratio,
+            a: Box::new(session_pane(a, config)),
+            b: Box::new(session_pane(b, config)),
+        },
+        LayoutPane::Tabs(tabs) => SessionPane::Tabs(SessionTabs {
+            tabs: tabs.iter().map(|tab| session_tab(tab, config)).collect(),
+            active: tabs.iter().position(|tab| tab.focused).unwrap_or(0),
+            focused: tabs.iter().any(|tab| tab.focused),
+        }),
+    }
+}
+
+fn session_tab(tab: &LayoutTab, config: &Config) -> SessionTab {
+    let profile_id = match &tab.profile {
+        Some(name) => {
+            let profile_id_opt = config.profile_id(name);
+            if profile_id_opt.is_none() {
+                log::warn!("layout uses unknown profile {:?}", name);
+            }
+            profile_id_opt
+        }
+        None => config.default_profile,
+    };
+    let command = tab
+        .command
+        .as_ref()
+        .and_then(|command| match shlex::split(command) {
+            Some(args) if !args.is_empty() => Some(args),
+            _ => {
+                log::warn!("layout has invalid command {:?}", command);
+                None
+            }
+        });
+    let working_directory = tab.working_directory.as_ref().map(|dir| {
+        match (dir.strip_prefix("~/"), env::var_os("HOME")) {
+            (Some(rest), Some(home)) => Path::new(&home).join(rest),
+            _ => PathBuf::from(dir),
+        }
+    });
+    SessionTab {
+        profile_id,
+        working_directory,
+        title: tab.title.clone(),
+        command,
+        scrollback_snapshot: None,
+    }
+}
+
+/// Directory of the layouts listed in the profile menu
+pub fn layouts_dir() -> Option<PathBuf> {
+    Some(xdg::config_dir()?.join("layouts"))
+}
+
+/// Path of a layout from its name in the layouts directory, or the path itself if it has a
+/// directory or extension
+pub fn layout_path(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if name.contains('/') || path.extension().is_some() {
+        return Some(path.to_path_buf());
+    }
+    Some(layouts_dir()?.join(format!("{}.ron", name)))
+}
+
+/// Names and paths of the layouts in the layouts directory, sorted by name
+pub fn layouts() -> io::Result<Vec<(String, PathBuf)>> {
+    let Some(dir) = layouts_dir() else {
+        return Ok(Vec::new());
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut layouts = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "ron")
+            && let Some(name) = path.file_stem().and_then(|stem| stem.to_str())
+        {
+            layouts.push((name.to_string(), path));
+        }
+    }
+    layouts.sort();
+    Ok(layouts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Layout;
+    use crate::{
+        config::{Config, Profile, ProfileId},
+        session::SessionPane,
+    };
+
+    #[test]
+    fn layout_to_session() {
+        let layout: Layout = ron::from_str(
+            r#"(
+                root: Split(
+                    axis: Vertical,
+                    ratio: 0.6,
+                    a: Split(
+                        axis: Horizontal,
+                        a: Tabs([(title: "editor", command: "nvim '.'")]),
+                        b: Tabs([(profile: "Server", command: "cargo run", focused: true)]),
+                    ),
+                    b: Tabs([(working_directory: "/var/log"), (profile: "Missing")]),
+                ),
+            )"#,
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.profiles.insert(
+            ProfileId(7),
+            Profile {
+                name: "Server".to_string(),
+                ..Profile::default()
+            },
+        );
+        config.default_profile = Some(ProfileId(7));
+        let session = layout.session(&config);
+
+        let SessionPane::Split { ratio, a, .. } = &session.root else {
+            panic!("root is not a split");
+        };
+        assert_eq!(*ratio, 0.6);
+        let SessionPane::Split { ratio, .. } = a.as_ref() else {
+            panic!("first pane is not a split");
+        };
+        assert_eq!(*ratio, 0.5);
+
+        let panes = session.panes();
+        assert_eq!(panes.len(), 3);
+        assert_eq!(
+            panes[0].tabs[0].command,
+            Some(vec!["nvim".to_string(), ".".to_string()])
+        );
+        assert_eq!(panes[0].tabs[0].profile_id, Some(ProfileId(7)));
+        assert!(!panes[0].focused);
+        assert!(panes[1].focused);
+        assert_eq!(panes[2].tabs[0].working_directory, Some("/var/log".into()));
+        assert_eq!(panes[2].tabs[1].profile_id, None);
+    }
+}
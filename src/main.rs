@@ -1,7 +1,10 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
-use alacritty_terminal::{event::Event as TermEvent, term, term::color::Colors as TermColors, tty};
+use alacritty_terminal::{
+    event::Event as TermEvent, selection::SelectionType, term, term::color::Colors as TermColors,
+    tty,
+};
 use cosmic::iced::clipboard::dnd::DndAction;
 use cosmic::iced::core::keyboard::key::Named;
 use cosmic::iced::keyboard::key::Physical;
@@ -16,7 +19,7 @@ use cosmic::{
     cosmic_theme, executor,
     iced::{
         self, Alignment, Color, Event, Length, Limits, Padding, Subscription,
-        advanced::graphics::text::font_system,
+        advanced::{graphics::text::font_system, widget::text::Style as TextStyle},
         clipboard, event,
         futures::SinkExt,
         keyboard::{Event as KeyEvent, Key, Modifiers},
@@ -37,35 +40,76 @@ use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
     env,
     error::Error,
+    ffi::OsStr,
     fs,
     path::PathBuf,
     process,
     rc::Rc,
-    sync::{LazyLock, Mutex, atomic::Ordering},
+    sync::{Arc, LazyLock, Mutex, atomic::Ordering},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use tokio::sync::mpsc;
 
+use bell::BellSound;
+mod bell;
+
 use config::{
-    AppTheme, CONFIG_VERSION, ColorScheme, ColorSchemeId, ColorSchemeKind, Config, Profile,
-    ProfileId,
+    AppTheme, BellConfig, CONFIG_VERSION, ClipboardReadPolicy, ColorScheme, ColorSchemeId,
+    ColorSchemeKind, Config, CopyConfig, CursorShape, HintAction, NotificationConfig, Profile,
+    ProfileId, ScrollbackLines, SessionLogConfig,
 };
 mod config;
+
+use copy_mode::{CopyModeCommand, CopyModeKeys};
+mod copy_mode;
+
+mod graphics;
+mod hints;
+mod kitty_graphics;
+mod kitty_keyboard;
 mod mouse_reporter;
 
+use notify::DesktopNotifier;
+mod notify;
+
 use icon_cache::IconCache;
 mod icon_cache;
 
 use key_bind::key_binds;
 mod key_bind;
 
+use layout::Layout;
+mod layout;
+
+mod pty;
+
+use rich_text::{ClipboardText, ExportFormat};
+mod rich_text;
+
+use search::{SearchMatches, SearchOptions};
+mod search;
+
+mod scrollback_snapshot;
+
+use session::{Session, SessionTab};
+mod session;
+
+use session_log::SessionLog;
+mod session_log;
+
+use shell_integration::{ReportedDirectory, ShellEvent};
+mod shell_integration;
+
 mod shortcuts;
+mod sixel;
 
 mod localize;
 
 use menu::menu_bar;
 mod menu;
 
-use terminal::{Terminal, TerminalPaneGrid, TerminalScroll};
+use terminal::{RichTextLines, Terminal, TerminalPaneGrid, TerminalScroll};
 mod terminal;
 
 use terminal_box::terminal_box;
@@ -80,6 +124,13 @@ mod terminal_theme;
 
 mod dnd;
 
+mod ipc;
+
+use remote::{RemoteReply, RemoteRequest, RemoteResponse, TerminalInfo};
+mod remote;
+
+mod xdg;
+
 use clap_lex::RawArgs;
 
 static ICON_CACHE: LazyLock<Mutex<IconCache>> = LazyLock::new(|| Mutex::new(IconCache::new()));
@@ -99,6 +150,20 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut shell_args = Vec::new();
     let mut daemonize = true;
     let mut working_directory = None;
+    let mut layout_name_opt = None;
+    let mut session_name_opt = None;
+    let mut restore_last_session = true;
+    let mut profile_name_opt = None;
+    let mut ipc_target_opt = None;
+    // `cosmic-term cli` controls running windows instead of opening one
+    if raw_args.peek_os(&cursor).is_some_and(|arg| arg == "cli") {
+        raw_args.next_os(&mut cursor);
+        let args: Vec<String> = raw_args
+            .remaining(&mut cursor)
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        process::exit(remote::cli(&args));
+    }
     // Parse the arguments using clap_lex
     while let Some(arg) = raw_args.next_os(&mut cursor) {
         match arg.to_str() {
@@ -124,6 +189,51 @@ fn main() -> Result<(), Box<dyn Error>> {
             Some("--no-daemon") => {
                 daemonize = false;
             }
+            Some(arg_str @ "--layout") => {
+                if let Some(layout_arg) = raw_args.next_os(&mut cursor) {
+                    layout_name_opt = Some(layout_arg.to_string_lossy().to_string());
+                } else {
+                    eprintln!("Missing argument for {arg_str}");
+                    process::exit(1);
+                }
+            }
+            Some(arg_str @ "--session") => {
+                if let Some(name_arg) = raw_args.next_os(&mut cursor) {
+                    session_name_opt = Some(name_arg.to_string_lossy().to_string());
+                } else {
+                    eprintln!("Missing argument for {arg_str}");
+                    process::exit(1);
+                }
+            }
+            Some("--no-restore") => {
+                restore_last_session = false;
+            }
+            Some(arg_str @ "--profile") => {
+                if let Some(profile_arg) = raw_args.next_os(&mut cursor) {
+                    profile_name_opt = Some(profile_arg.to_string_lossy().to_string());
+                } else {
+                    eprintln!("Missing argument for {arg_str}");
+                    process::exit(1);
+                }
+            }
+            Some("--new-tab") => {
+                ipc_target_opt = Some(ipc::Target::Tab);
+            }
+            Some("--new-window") => {
+                ipc_target_opt = None;
+            }
+            Some(arg_str @ "--split") => {
+                let direction_opt = raw_args
+                    .next_os(&mut cursor)
+                    .and_then(|direction_arg| direction_arg.to_str())
+                    .and_then(ipc::SplitDirection::from_arg);
+                if let Some(direction) = direction_opt {
+                    ipc_target_opt = Some(ipc::Target::Split(direction));
+                } else {
+                    eprintln!("Missing or invalid argument for {arg_str}, expected right or down");
+                    process::exit(1);
+                }
+            }
             Some("-e") | Some("--command") | Some("--") => {
                 // Handle the '--command' or '-e' flag
                 break;
@@ -142,6 +252,29 @@ fn main() -> Result<(), Box<dyn Error>> {
             shell_program_opt = Some(arg.to_string_lossy().to_string());
         }
     }
+    let command = shell_program_opt.map(|shell_program| {
+        let mut command = vec![shell_program];
+        command.extend(shell_args);
+        command
+    });
+
+    // Ask the running instance to open the terminal, or start a new one if there is none
+    if let Some(target) = ipc_target_opt {
+        let working_directory = match &working_directory {
+            Some(dir) => env::current_dir().map(|current_dir| current_dir.join(dir)).ok(),
+            None => env::current_dir().ok(),
+        };
+        let request = ipc::Request {
+            target,
+            profile: profile_name_opt.clone(),
+            working_directory,
+            command: command.clone(),
+        };
+        match ipc::send(&request) {
+            Ok(()) => return Ok(()),
+            Err(err) => log::info!("no running instance, opening a new window: {}", err),
+        }
+    }
 
     // Platform-specific daemonization logic
 
@@ -178,16 +311,66 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let shortcuts_config = shortcuts::ShortcutsConfig::new(config.shortcuts_custom.clone());
 
-    let shell = shell_program_opt.map(|shell_program| tty::Shell::new(shell_program, shell_args));
-    let startup_options = Some(tty::Options {
-        shell,
+    // Open a layout or a saved session, or the last one if nothing else was asked for
+    let session_opt = if let Some(layout_name) = layout_name_opt {
+        let layout = layout::layout_path(&layout_name)
+            .ok_or_else(|| "no config directory".into())
+            .and_then(|path| Layout::load(&path));
+        match layout {
+            Ok(layout) => Some(layout.session(&config)),
+            Err(err) => {
+                eprintln!("failed to load layout {:?}: {}", layout_name, err);
+                process::exit(1);
+            }
+        }
+    } else if let Some(session_name) = session_name_opt {
+        let session = session::session_path(&session_name)
+            .ok_or_else(|| "no data directory".into())
+            .and_then(|path| Session::load(&path));
+        match session {
+            Ok(session) => Some(session),
+            Err(err) => {
+                eprintln!("failed to load session {:?}: {}", session_name, err);
+                process::exit(1);
+            }
+        }
+    } else if restore_last_session
+        && config.restore_last_session
+        && command.is_none()
+        && working_directory.is_none()
+        && profile_name_opt.is_none()
+        && let Some(path) = session::last_session_path()
+        && path.exists()
+    {
+        match Session::load(&path) {
+            Ok(session) => Some(session),
+            Err(err) => {
+                log::warn!("failed to load last session from {:?}: {}", path, err);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let profile_id_opt = profile_name_opt.and_then(|profile_name| {
+        let profile_id_opt = config.profile_id(&profile_name);
+        if profile_id_opt.is_none() {
+            eprintln!("unknown profile {:?}, using the default profile", profile_name);
+        }
+        profile_id_opt
+    });
+    let startup_tab_opt = Some(SessionTab {
+        profile_id: profile_id_opt,
         working_directory,
-        ..tty::Options::default()
+        command,
+        ..SessionTab::default()
     });
 
     // Terminal config setup
+    // The history size is set by the terminal from the profile
     let term_config = term::Config {
-        scrolling_history: 100_000,
+        kitty_keyboard: true,
         ..term::Config::default()
     };
     // Set up environmental variables for terminal
@@ -196,6 +379,14 @@ fn main() -> Result<(), Box<dyn Error>> {
     unsafe {
         env::set_var("TERM", "xterm-256color");
     }
+    // Lets `cosmic-term cli` in the terminals find this window
+    if config.remote_control
+        && let Some(path) = remote::socket_path()
+    {
+        unsafe {
+            env::set_var("COSMIC_TERM_SOCKET", path);
+        }
+    }
 
     // Set settings
     let mut settings = Settings::default();
@@ -207,7 +398,8 @@ fn main() -> Result<(), Box<dyn Error>> {
         config_handler,
         config,
         shortcuts_config,
-        startup_options,
+        session_opt,
+        startup_tab_opt,
         term_config,
     };
 
@@ -223,10 +415,19 @@ fn print_help() {
 Designed for the COSMIC™ desktop environment, cosmic-term is a libcosmic-based terminal emulator.
 
 Project home page: https://github.com/pop-os/cosmic-term
+Usage: cosmic-term [options] [-e <command>...]
+       cosmic-term cli <command>     Control running windows, see cosmic-term cli --help
 Options:
   --help                          Show this message
   --version                       Show the version of cosmic-term
-  -w, --working-directory <dir>   Set the working directory for the terminal"#
+  -w, --working-directory <dir>   Set the working directory for the terminal
+  --layout <file>                 Open the splits and tabs of a layout file
+  --session <name>                Open a saved session, by name or path
+  --no-restore                    Do not restore the last session
+  --profile <name>                Open the terminal with this profile
+  --new-tab                       Open a tab in the running instance
+  --split <right|down>            Split the focused pane of the running instance
+  --new-window                    Open a new window, the default"#
     );
 }
 
@@ -235,7 +436,8 @@ pub struct Flags {
     config_handler: Option<cosmic_config::Config>,
     config: Config,
     shortcuts_config: shortcuts::ShortcutsConfig,
-    startup_options: Option<tty::Options>,
+    session_opt: Option<Session>,
+    startup_tab_opt: Option<SessionTab>,
     term_config: term::Config,
 }
 
@@ -245,12 +447,17 @@ pub enum Action {
     ClearScrollback,
     ColorSchemes(ColorSchemeKind),
     Copy,
+    CopyAs(ExportFormat),
+    CopyLastCommandOutput,
+    CopyMode,
     CopyUrlByMenu,
     CopyOrSigint,
     CopyPrimary,
     Find,
+    Hints(HintAction),
     KeyboardShortcuts,
     LaunchUrlByMenu,
+    LayoutOpen(usize),
     PaneFocusDown,
     PaneFocusLeft,
     PaneFocusRight,
@@ -262,7 +469,13 @@ pub enum Action {
     PastePrimary,
     ProfileOpen(ProfileId),
     Profiles,
+    PromptNext,
+    PromptPrevious,
+    SaveScrollback(ExportFormat),
     SelectAll,
+    SelectLastCommandOutput,
+    SessionLog,
+    Sessions,
     Settings,
     #[cfg(feature = "password_manager")]
     PasswordManager,
@@ -298,12 +511,17 @@ impl Action {
                 Message::ToggleContextPage(ContextPage::ColorSchemes(*color_scheme_kind))
             }
             Self::Copy => Message::Copy(entity_opt),
+            Self::CopyAs(format) => Message::CopyAs(entity_opt, *format),
+            Self::CopyLastCommandOutput => Message::CopyLastCommandOutput(entity_opt),
+            Self::CopyMode => Message::CopyMode(entity_opt),
             Self::CopyUrlByMenu => Message::CopyUrlByMenu,
             Self::CopyOrSigint => Message::CopyOrSigint(entity_opt),
             Self::CopyPrimary => Message::CopyPrimary(entity_opt),
             Self::Find => Message::Find(true),
+            Self::Hints(hint_action) => Message::Hints(entity_opt, *hint_action),
             Self::KeyboardShortcuts => Message::ToggleContextPage(ContextPage::KeyboardShortcuts),
             Self::LaunchUrlByMenu => Message::LaunchUrlByMenu,
+            Self::LayoutOpen(index) => Message::LayoutOpen(*index),
             Self::PaneFocusDown => Message::PaneFocusAdjacent(pane_grid::Direction::Down),
             Self::PaneFocusLeft => Message::PaneFocusAdjacent(pane_grid::Direction::Left),
             Self::PaneFocusRight => Message::PaneFocusAdjacent(pane_grid::Direction::Right),
@@ -317,7 +535,13 @@ impl Action {
             Self::PastePrimary => Message::PastePrimary(entity_opt),
             Self::ProfileOpen(profile_id) => Message::ProfileOpen(*profile_id),
             Self::Profiles => Message::ToggleContextPage(ContextPage::Profiles),
+            Self::PromptNext => Message::ScrollToPrompt(entity_opt, true),
+            Self::PromptPrevious => Message::ScrollToPrompt(entity_opt, false),
+            Self::SaveScrollback(format) => Message::SaveScrollback(entity_opt, *format),
             Self::SelectAll => Message::SelectAll(entity_opt),
+            Self::SelectLastCommandOutput => Message::SelectLastCommandOutput(entity_opt),
+            Self::SessionLog => Message::SessionLog(entity_opt),
+            Self::Sessions => Message::ToggleContextPage(ContextPage::Sessions),
             Self::Settings => Message::ToggleContextPage(ContextPage::Settings),
             Self::ShowHeaderBar(show_headerbar) => Message::ShowHeaderBar(*show_headerbar),
             Self::TabActivate0 => Message::TabActivateJump(0),
@@ -352,11 +576,21 @@ impl MenuAction for Action {
     }
 }
 
+fn find_error_style(theme: &cosmic::Theme) -> TextStyle {
+    TextStyle {
+        color: Some(theme.cosmic().destructive.base.into()),
+        ..Default::default()
+    }
+}
+
 /// Messages that are used specifically by our [`App`].
 #[derive(Clone, Debug)]
 pub enum Message {
     AppTheme(AppTheme),
+    Bell(BellConfig),
     ClearScrollback(Option<segmented_button::Entity>),
+    ClipboardLoadAnswer(pane_grid::Pane, segmented_button::Entity, bool),
+    ClipboardReadDefault(usize),
     ColorSchemeCollapse,
     ColorSchemeDelete(ColorSchemeKind, ColorSchemeId),
     ColorSchemeExpand(ColorSchemeKind, Option<ColorSchemeId>),
@@ -369,9 +603,18 @@ pub enum Message {
     ColorSchemeTabActivate(widget::segmented_button::Entity),
     Config(Box<Config>),
     Copy(Option<segmented_button::Entity>),
+    CopyAs(Option<segmented_button::Entity>, ExportFormat),
+    CopyLastCommandOutput(Option<segmented_button::Entity>),
+    CopyMode(Option<segmented_button::Entity>),
+    CopyModeSearchSubmit,
+    CopyOptions(CopyConfig),
     CopyOrSigint(Option<segmented_button::Entity>),
     CopyPrimary(Option<segmented_button::Entity>),
+    CopySelection(pane_grid::Pane, segmented_button::Entity),
     CopyUrlByMenu,
+    CursorBlink,
+    CursorBlinkDefault(bool),
+    CursorShapeDefault(usize),
     DefaultBoldFontWeight(usize),
     DefaultDimFontWeight(usize),
     DefaultFont(usize),
@@ -382,15 +625,22 @@ pub enum Message {
     DialogMessage(Box<DialogMessage>), // DialogMessage is huge, so we use a box to make the size of this enum smaller on the stack
     Drop(Option<(pane_grid::Pane, segmented_button::Entity, DndDrop)>),
     Find(bool),
+    FindMatches(pane_grid::Pane, segmented_button::Entity, SearchMatches),
     FindNext,
+    FindOptions(SearchOptions),
     FindPrevious,
     FindSearchValueChanged(String),
+    HintSelected(segmented_button::Entity, HintAction, String),
+    Hints(Option<segmented_button::Entity>, HintAction),
     MiddleClick(pane_grid::Pane, Option<segmented_button::Entity>),
     FocusFollowMouse(bool),
     Key(Modifiers, Physical, Key),
     LaunchUrl(String),
     LaunchUrlByMenu,
+    LayoutOpen(usize),
     Modifiers(Modifiers),
+    NotificationMinDuration(usize),
+    Notifications(NotificationConfig),
     ShortcutCaptureCancel,
     ShortcutCaptureStart(shortcuts::KeyBindAction),
     ShortcutConflictCancel,
@@ -414,7 +664,10 @@ pub enum Message {
     PastePrimary(Option<segmented_button::Entity>),
     PasteValue(Option<segmented_button::Entity>, String),
     ProfileCollapse(ProfileId),
+    ProfileClipboardRead(ProfileId, usize),
     ProfileCommand(ProfileId, String),
+    ProfileCursorBlink(ProfileId, usize),
+    ProfileCursorShape(ProfileId, usize),
     ProfileDirectory(ProfileId, String),
     ProfileExpand(ProfileId),
     ProfileHold(ProfileId, bool),
@@ -422,17 +675,40 @@ pub enum Message {
     ProfileNew,
     ProfileOpen(ProfileId),
     ProfileRemove(ProfileId),
+    ProfileScrollbackLines(ProfileId, usize),
+    ProfilePersistScrollback(ProfileId, bool),
+    ProfileSessionLog(ProfileId, bool),
     ProfileSyntaxTheme(ProfileId, ColorSchemeKind, usize),
     ProfileTabTitle(ProfileId, String),
+    ProfileWordSeparators(ProfileId, String),
     ReorderTab(Pane, ReorderEvent),
+    RemoteControl(bool),
+    RestoreLastSession(bool),
+    SaveScrollback(Option<segmented_button::Entity>, ExportFormat),
+    SaveScrollbackResult(Arc<RichTextLines>, ExportFormat, DialogResult),
     Surface(surface::Action),
+    PersistScrollbackMaxSize(usize),
+    ScrollbackLinesDefault(usize),
+    ScrollbackSnapshot,
+    ScrollToPrompt(Option<segmented_button::Entity>, bool),
     SelectAll(Option<segmented_button::Entity>),
+    SelectLastCommandOutput(Option<segmented_button::Entity>),
+    SessionLog(Option<segmented_button::Entity>),
+    SessionLogDirectory(String),
+    SessionLogFileName(String),
+    SessionLogMaxSize(usize),
+    SessionLogStripEscapes(bool),
+    SessionDelete(String),
+    SessionName(String),
+    SessionOpen(String),
+    SessionSave,
     ShowAdvancedFontSettings(bool),
     ShowHeaderBar(bool),
     ShowPaneBorders(bool),
     SyntaxTheme(ColorSchemeKind, usize),
     SystemThemeChange,
     TabNewInheritWorkingDirectory(bool),
+    TabNewSshRemote(bool),
     TabActivate(segmented_button::Entity),
     TabActivateJump(usize),
     TabClose(Option<segmented_button::Entity>),
@@ -449,6 +725,7 @@ pub enum Message {
     UpdateDefaultProfile((bool, ProfileId)),
     UseBrightBold(bool),
     WindowClose,
+    WordSeparators(String),
     WindowNew,
     WindowFocused,
     WindowUnfocused,
@@ -456,6 +733,8 @@ pub enum Message {
     ZoomOut,
     ZoomReset,
     ContextMenuPopupClosed(window::Id),
+    Ipc(ipc::Request),
+    Remote(RemoteRequest, RemoteReply),
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -464,6 +743,7 @@ pub enum ContextPage {
     ColorSchemes(ColorSchemeKind),
     KeyboardShortcuts,
     Profiles,
+    Sessions,
     Settings,
     #[cfg(feature = "password_manager")]
     PasswordManager,
@@ -486,6 +766,13 @@ pub struct App {
     shortcuts_config: shortcuts::ShortcutsConfig,
     key_binds: HashMap<KeyBind, Action>,
     app_themes: Vec<String>,
+    clipboard_read_names: Vec<String>,
+    profile_clipboard_read_names: Vec<String>,
+    scrollback_lines_names: Vec<String>,
+    profile_scrollback_lines_names: Vec<String>,
+    cursor_shape_names: Vec<String>,
+    profile_cursor_blink_names: Vec<String>,
+    profile_cursor_shape_names: Vec<String>,
     font_names: Vec<String>,
     font_size_names: Vec<String>,
     font_sizes: Vec<u16>,
@@ -498,6 +785,12 @@ pub struct App {
     curr_font_stretches: Vec<Stretch>,
     zoom_step_names: Vec<String>,
     zoom_steps: Vec<u16>,
+    notification_duration_names: Vec<String>,
+    notification_durations: Vec<u64>,
+    session_log_max_size_names: Vec<String>,
+    session_log_max_sizes: Vec<u64>,
+    persist_scrollback_max_size_names: Vec<String>,
+    persist_scrollback_max_sizes: Vec<u32>,
     theme_names_dark: Vec<String>,
     theme_names_light: Vec<String>,
     themes: HashMap<(String, ColorSchemeKind), TermColors>,
@@ -507,9 +800,22 @@ pub struct App {
     find: bool,
     find_search_id: widget::Id,
     find_search_value: String,
+    find_options: SearchOptions,
+    /// Direction of the search started with `/` or `?` in copy mode
+    copy_mode_search_opt: Option<bool>,
+    copy_mode_keys: CopyModeKeys,
+    /// Error of the last search, shown in the find bar
+    find_error_opt: Option<String>,
     term_event_tx_opt:
         Option<mpsc::UnboundedSender<(pane_grid::Pane, segmented_button::Entity, TermEvent)>>,
-    startup_options: Option<tty::Options>,
+    /// Session to restore once terminals can be created
+    startup_session_opt: Option<Session>,
+    /// Options for the next terminal, from the command line or a session
+    startup_tab_opt: Option<SessionTab>,
+    session_name: String,
+    session_names: Vec<String>,
+    /// Layouts listed in the profile menu, by name
+    layouts: Vec<(String, PathBuf)>,
     term_config: term::Config,
     color_scheme_errors: Vec<String>,
     color_scheme_expanded: Option<(ColorSchemeKind, Option<ColorSchemeId>)>,
@@ -536,6 +842,9 @@ pub struct App {
     )>,
     #[cfg(feature = "password_manager")]
     password_mgr: password_manager::PasswordManager,
+    bell_sound: Box<dyn BellSound>,
+    desktop_notifier: Box<dyn DesktopNotifier>,
+    window_focused: bool,
 }
 
 impl App {
@@ -764,6 +1073,109 @@ impl App {
         Task::none()
     }
 
+    fn search(&mut self, forwards: bool) -> Task<Message> {
+        let pane = self.pane_model.focused();
+        self.find_error_opt = None;
+        if let Some(tab_model) = self.pane_model.active() {
+            let entity = tab_model.active();
+            if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+                let mut terminal = terminal.lock().unwrap();
+                if self.find_search_value.is_empty() {
+                    terminal.search_clear();
+                } else if let Err(err) =
+                    terminal.search(&self.find_search_value, self.find_options, forwards)
+                {
+                    // Highlights of the last valid search would not match the input
+                    terminal.search_clear();
+                    self.find_error_opt = Some(err);
+                } else if let Some(mut search_rx) = terminal.search_count() {
+                    // Find the matches in the whole scrollback for the find bar
+                    let search_matches = stream::channel(
+                        16,
+                        move |mut output: iced::futures::channel::mpsc::Sender<Message>| async move {
+                            while let Some(slice) = search_rx.recv().await {
+                                let message = Message::FindMatches(pane, entity, slice);
+                                if output.send(message).await.is_err() {
+                                    break;
+                                }
+                            }
+                        },
+                    );
+                    return Task::batch([
+                        Task::run(search_matches, action::app),
+                        self.update_focus(),
+                    ]);
+                }
+            }
+        }
+
+        // Focus correct input
+        self.update_focus()
+    }
+
+    // Remove search highlights from all tabs
+    fn search_clear(&mut self) {
+        self.find_error_opt = None;
+        for (_pane, tab_model) in self.pane_model.panes.iter() {
+            for entity in tab_model.iter() {
+                if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+                    terminal.lock().unwrap().search_clear();
+                }
+            }
+        }
+    }
+
+    /// Run a command of copy mode in the focused terminal
+    fn copy_mode_command(&mut self, command: CopyModeCommand) -> Task<Message> {
+        match command {
+            CopyModeCommand::Search { forwards } => {
+                self.copy_mode_search_opt = Some(forwards);
+                self.find = true;
+                self.find_search_value.clear();
+                return self.update_focus();
+            }
+            CopyModeCommand::SearchNext { same } => {
+                let forwards = self.copy_mode_search_opt.unwrap_or(true);
+                return self.search(forwards == same);
+            }
+            _ => {}
+        }
+
+        let Some(tab_model) = self.pane_model.active() else {
+            return Task::none();
+        };
+        let Some(terminal) = tab_model.data::<Mutex<Terminal>>(tab_model.active()) else {
+            return Task::none();
+        };
+        let mut terminal = terminal.lock().unwrap();
+        if command == CopyModeCommand::Yank {
+            let Some(task) = clipboard_write_selection(&terminal, self.config.copy.rich_text)
+            else {
+                return Task::none();
+            };
+            terminal.copy_mode_command(CopyModeCommand::Exit);
+            drop(terminal);
+            self.copy_mode_exited();
+            return Task::batch([task, self.update_focus()]);
+        }
+        terminal.copy_mode_command(command);
+        let exited = !terminal.copy_mode_active();
+        drop(terminal);
+        if exited {
+            self.copy_mode_exited();
+        }
+        Task::none()
+    }
+
+    /// Remove the search of copy mode after leaving it
+    fn copy_mode_exited(&mut self) {
+        self.copy_mode_search_opt = None;
+        if !self.find {
+            self.find_search_value.clear();
+            self.search_clear();
+        }
+    }
+
     fn update_focus(&self) -> Task<Message> {
         if self.find {
             widget::text_input::focus(self.find_search_id.clone())
@@ -1181,6 +1593,32 @@ impl App {
                         .theme_names_light
                         .iter()
                         .position(|theme_name| theme_name == &profile.syntax_theme_light);
+                    let cursor_shape_selected = match profile.cursor_shape {
+                        Some(cursor_shape) => CursorShape::ALL
+                            .iter()
+                            .position(|x| x == &cursor_shape)
+                            .map(|index| index + 1),
+                        None => Some(0),
+                    };
+                    let clipboard_read_selected = match profile.clipboard_read {
+                        Some(clipboard_read) => ClipboardReadPolicy::ALL
+                            .iter()
+                            .position(|x| x == &clipboard_read)
+                            .map(|index| index + 1),
+                        None => Some(0),
+                    };
+                    let scrollback_lines_selected = match profile.scrollback_lines {
+                        Some(scrollback_lines) => ScrollbackLines::PRESETS
+                            .iter()
+                            .position(|x| x == &scrollback_lines)
+                            .map(|index| index + 1),
+                        None => Some(0),
+                    };
+                    let cursor_blink_selected = match profile.cursor_blink {
+                        Some(true) => 1,
+                        Some(false) => 2,
+                        None => 0,
+                    };
 
                     let expanded_section = widget::settings::section()
                         .add(
@@ -1238,6 +1676,23 @@ impl App {
                                 ])
                                 .spacing(space_xxxs)
                                 .into(),
+                                widget::column::with_children(vec![
+                                    widget::text(fl!("word-separators")).into(),
+                                    widget::text_input(
+                                        self.config.word_separators.as_str(),
+                                        profile.word_separators.as_deref().unwrap_or_default(),
+                                    )
+                                    .on_input(move |text| {
+                                        Message::ProfileWordSeparators(profile_id, text)
+                                    })
+                                    .on_paste(move |text| {
+                                        Message::ProfileWordSeparators(profile_id, text)
+                                    })
+                                    .into(),
+                                    widget::text::caption(fl!("word-separators-inherit")).into(),
+                                ])
+                                .spacing(space_xxxs)
+                                .into(),
                             ])
                             .padding([0, space_s])
                             .spacing(space_xs),
@@ -1277,6 +1732,42 @@ impl App {
                                 ),
                             ),
                         )
+                        .add(
+                            widget::settings::item::builder(fl!("cursor-shape")).control(
+                                widget::dropdown(
+                                    &self.profile_cursor_shape_names,
+                                    cursor_shape_selected,
+                                    move |index| Message::ProfileCursorShape(profile_id, index),
+                                ),
+                            ),
+                        )
+                        .add(
+                            widget::settings::item::builder(fl!("cursor-blink")).control(
+                                widget::dropdown(
+                                    &self.profile_cursor_blink_names,
+                                    Some(cursor_blink_selected),
+                                    move |index| Message::ProfileCursorBlink(profile_id, index),
+                                ),
+                            ),
+                        )
+                        .add(
+                            widget::settings::item::builder(fl!("clipboard-read")).control(
+                                widget::dropdown(
+                                    &self.profile_clipboard_read_names,
+                                    clipboard_read_selected,
+                                    move |index| Message::ProfileClipboardRead(profile_id, index),
+                                ),
+                            ),
+                        )
+                        .add(
+                            widget::settings::item::builder(fl!("scrollback-lines")).control(
+                                widget::dropdown(
+                                    &self.profile_scrollback_lines_names,
+                                    scrollback_lines_selected,
+                                    move |index| Message::ProfileScrollbackLines(profile_id, index),
+                                ),
+                            ),
+                        )
                         .add(
                             widget::settings::item::builder(fl!("make-default")).control(
                                 widget::toggler(
@@ -1300,6 +1791,20 @@ impl App {
                             ])
                             .align_y(Alignment::Center)
                             .padding([0, space_s]),
+                        )
+                        .add(
+                            widget::settings::item::builder(fl!("profile-session-log"))
+                                .description(fl!("profile-session-log-description"))
+                                .toggler(profile.session_log, move |t| {
+                                    Message::ProfileSessionLog(profile_id, t)
+                                }),
+                        )
+                        .add(
+                            widget::settings::item::builder(fl!("profile-persist-scrollback"))
+                                .description(fl!("profile-persist-scrollback-description"))
+                                .toggler(profile.persist_scrollback, move |t| {
+                                    Message::ProfilePersistScrollback(profile_id, t)
+                                }),
                         );
 
                     let padding = Padding {
@@ -1326,6 +1831,66 @@ impl App {
         widget::settings::view_column(sections).into()
     }
 
+    fn sessions(&self) -> Element<'_, Message> {
+        let cosmic_theme::Spacing { space_xxs, .. } = self.core().system_theme().cosmic().spacing;
+
+        let mut sections = Vec::with_capacity(3);
+
+        let name = self.session_name.trim();
+        let save_session = widget::row::with_children(vec![
+            widget::text_input(fl!("session-name"), &self.session_name)
+                .on_input(Message::SessionName)
+                .on_paste(Message::SessionName)
+                .on_submit(|_| Message::SessionSave)
+                .into(),
+            widget::button::standard(fl!("save-session"))
+                .on_press_maybe(
+                    (!name.is_empty() && !name.contains('/')).then_some(Message::SessionSave),
+                )
+                .into(),
+        ])
+        .align_y(Alignment::Center)
+        .spacing(space_xxs);
+        sections.push(save_session.into());
+
+        if !self.session_names.is_empty() {
+            let mut sessions_section = widget::settings::section().title(fl!("saved-sessions"));
+            for session_name in &self.session_names {
+                sessions_section = sessions_section.add(
+                    widget::settings::item::builder(session_name.clone()).control(
+                        widget::row::with_children(vec![
+                            widget::button::standard(fl!("open-session"))
+                                .on_press(Message::SessionOpen(session_name.clone()))
+                                .into(),
+                            widget::button::custom(icon_cache_get("edit-delete-symbolic", 16))
+                                .on_press(Message::SessionDelete(session_name.clone()))
+                                .class(style::Button::Icon)
+                                .into(),
+                        ])
+                        .align_y(Alignment::Center)
+                        .spacing(space_xxs),
+                    ),
+                );
+            }
+            sections.push(sessions_section.into());
+        }
+
+        sections.push(
+            widget::settings::section()
+                .add(
+                    widget::settings::item::builder(fl!("restore-last-session"))
+                        .description(fl!("restore-last-session-description"))
+                        .toggler(
+                            self.config.restore_last_session,
+                            Message::RestoreLastSession,
+                        ),
+                )
+                .into(),
+        );
+
+        widget::settings::view_column(sections).into()
+    }
+
     fn settings(&self) -> Element<'_, Message> {
         let t = self.core().system_theme();
         let app_theme_selected = match self.config.app_theme {
@@ -1372,6 +1937,19 @@ impl App {
             .zoom_steps
             .iter()
             .position(|zoom_step| zoom_step == &self.config.font_size_zoom_step_mul_100);
+        let cursor_shape_selected = CursorShape::ALL
+            .iter()
+            .position(|cursor_shape| cursor_shape == &self.config.cursor_shape);
+        let clipboard_read_selected = ClipboardReadPolicy::ALL
+            .iter()
+            .position(|clipboard_read| clipboard_read == &self.config.clipboard_read);
+        let scrollback_lines_selected = ScrollbackLines::PRESETS
+            .iter()
+            .position(|scrollback_lines| scrollback_lines == &self.config.scrollback_lines);
+        let persist_scrollback_max_size_selected = self
+            .persist_scrollback_max_sizes
+            .iter()
+            .position(|size_mb| *size_mb == self.config.persist_scrollback_max_mb);
 
         let appearance_section = widget::settings::section()
             .title(fl!("appearance"))
@@ -1417,7 +1995,18 @@ impl App {
                     .control(widget::slider(0..=100, self.config.opacity, |opacity| {
                         Message::Opacity(opacity)
                     }))
-            }));
+            }))
+            .add(
+                widget::settings::item::builder(fl!("cursor-shape")).control(widget::dropdown(
+                    &self.cursor_shape_names,
+                    cursor_shape_selected,
+                    Message::CursorShapeDefault,
+                )),
+            )
+            .add(
+                widget::settings::item::builder(fl!("cursor-blink"))
+                    .toggler(self.config.cursor_blink, Message::CursorBlinkDefault),
+            );
 
         let mut font_section = widget::settings::section()
             .title(fl!("font"))
@@ -1514,6 +2103,166 @@ impl App {
                     .toggler(self.config.show_pane_borders, Message::ShowPaneBorders),
             );
 
+        let bell = self.config.bell;
+        let bell_section = widget::settings::section()
+            .title(fl!("bell"))
+            .add(
+                widget::settings::item::builder(fl!("bell-visual")).toggler(
+                    bell.visual,
+                    move |visual| Message::Bell(BellConfig { visual, ..bell }),
+                ),
+            )
+            .add(
+                widget::settings::item::builder(fl!("bell-urgent")).toggler(
+                    bell.urgent,
+                    move |urgent| Message::Bell(BellConfig { urgent, ..bell }),
+                ),
+            )
+            .add(
+                widget::settings::item::builder(fl!("bell-tab-badge")).toggler(
+                    bell.tab_badge,
+                    move |tab_badge| Message::Bell(BellConfig { tab_badge, ..bell }),
+                ),
+            )
+            .add(
+                widget::settings::item::builder(fl!("bell-sound")).toggler(
+                    bell.sound,
+                    move |sound| Message::Bell(BellConfig { sound, ..bell }),
+                ),
+            );
+
+        let copy = self.config.copy;
+        let copy_section = widget::settings::section()
+            .title(fl!("copy-options"))
+            .add(
+                widget::settings::item::builder(fl!("copy-on-select"))
+                    .description(fl!("copy-on-select-description"))
+                    .toggler(copy.on_select, move |on_select| {
+                        Message::CopyOptions(CopyConfig { on_select, ..copy })
+                    }),
+            )
+            .add(
+                widget::settings::item::builder(fl!("copy-trim-trailing-whitespace")).toggler(
+                    copy.trim_trailing_whitespace,
+                    move |trim_trailing_whitespace| {
+                        Message::CopyOptions(CopyConfig {
+                            trim_trailing_whitespace,
+                            ..copy
+                        })
+                    },
+                ),
+            )
+            .add(
+                widget::settings::item::builder(fl!("copy-join-wrapped-lines")).toggler(
+                    copy.join_wrapped_lines,
+                    move |join_wrapped_lines| {
+                        Message::CopyOptions(CopyConfig {
+                            join_wrapped_lines,
+                            ..copy
+                        })
+                    },
+                ),
+            )
+            .add(
+                widget::settings::item::builder(fl!("copy-rich-text"))
+                    .description(fl!("copy-rich-text-description"))
+                    .toggler(copy.rich_text, move |rich_text| {
+                        Message::CopyOptions(CopyConfig { rich_text, ..copy })
+                    }),
+            );
+
+        let notifications = self.config.notifications;
+        let notification_duration_selected = self
+            .notification_durations
+            .iter()
+            .position(|secs| *secs == notifications.min_duration_secs);
+        let notifications_section = widget::settings::section()
+            .title(fl!("notifications"))
+            .add(
+                widget::settings::item::builder(fl!("notify-command-finished"))
+                    .description(fl!("notify-command-finished-description"))
+                    .toggler(notifications.command_finished, move |command_finished| {
+                        Message::Notifications(NotificationConfig {
+                            command_finished,
+                            ..notifications
+                        })
+                    }),
+            )
+            .add(
+                widget::settings::item::builder(fl!("notify-min-duration")).control(
+                    widget::dropdown(
+                        &self.notification_duration_names,
+                        notification_duration_selected,
+                        Message::NotificationMinDuration,
+                    ),
+                ),
+            )
+            .add(
+                widget::settings::item::builder(fl!("notify-app-requests")).toggler(
+                    notifications.app_requests,
+                    move |app_requests| {
+                        Message::Notifications(NotificationConfig {
+                            app_requests,
+                            ..notifications
+                        })
+                    },
+                ),
+            )
+            .add(
+                widget::settings::item::builder(fl!("notify-tab-badge")).toggler(
+                    notifications.tab_badge,
+                    move |tab_badge| {
+                        Message::Notifications(NotificationConfig {
+                            tab_badge,
+                            ..notifications
+                        })
+                    },
+                ),
+            );
+
+        let session_log = &self.config.session_log;
+        let session_log_max_size_selected = self
+            .session_log_max_sizes
+            .iter()
+            .position(|size_mb| *size_mb == session_log.max_size_mb);
+        let session_log_directory_default = session_log::default_directory()
+            .map(|directory| directory.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let session_log_section = widget::settings::section()
+            .title(fl!("session-logs"))
+            .add(
+                widget::settings::item::builder(fl!("session-log-directory")).control(
+                    widget::text_input(session_log_directory_default, &session_log.directory)
+                        .on_input(Message::SessionLogDirectory)
+                        .on_paste(Message::SessionLogDirectory)
+                        .width(Length::Fixed(240.0)),
+                ),
+            )
+            .add(
+                widget::settings::item::builder(fl!("session-log-file-name"))
+                    .description(fl!("session-log-file-name-description"))
+                    .control(
+                        widget::text_input("", &session_log.file_name)
+                            .on_input(Message::SessionLogFileName)
+                            .on_paste(Message::SessionLogFileName)
+                            .width(Length::Fixed(240.0)),
+                    ),
+            )
+            .add(
+                widget::settings::item::builder(fl!("session-log-strip-escapes"))
+                    .description(fl!("session-log-strip-escapes-description"))
+                    .toggler(session_log.strip_escapes, Message::SessionLogStripEscapes),
+            )
+            .add(
+                widget::settings::item::builder(fl!("session-log-max-size")).control(
+                    widget::dropdown(
+                        &self.session_log_max_size_names,
+                        session_log_max_size_selected,
+                        Message::SessionLogMaxSize,
+                    ),
+                ),
+            );
+
         let advanced_section = widget::settings::section()
             .title(fl!("advanced"))
             .add(
@@ -1528,16 +2277,268 @@ impl App {
                         self.config.tab_new_inherit_working_directory,
                         Message::TabNewInheritWorkingDirectory,
                     ),
-            );
-
-        widget::settings::view_column(vec![
-            appearance_section.into(),
-            font_section.into(),
-            splits_section.into(),
-            advanced_section.into(),
-        ])
-        .into()
-    }
+            )
+            .add(
+                widget::settings::item::builder(fl!("tab-new-ssh-remote"))
+                    .description(fl!("tab-new-ssh-remote-description"))
+                    .toggler(self.config.tab_new_ssh_remote, Message::TabNewSshRemote),
+            )
+            .add(
+                widget::settings::item::builder(fl!("remote-control"))
+                    .description(fl!("remote-control-description"))
+                    .toggler(self.config.remote_control, Message::RemoteControl),
+            )
+            .add(
+                widget::settings::item::builder(fl!("clipboard-read"))
+                    .description(fl!("clipboard-read-description"))
+                    .control(widget::dropdown(
+                        &self.clipboard_read_names,
+                        clipboard_read_selected,
+                        Message::ClipboardReadDefault,
+                    )),
+            )
+            .add(
+                widget::settings::item::builder(fl!("scrollback-lines"))
+                    .description(fl!("scrollback-lines-description"))
+                    .control(widget::dropdown(
+                        &self.scrollback_lines_names,
+                        scrollback_lines_selected,
+                        Message::ScrollbackLinesDefault,
+                    )),
+            )
+            .add(
+                widget::settings::item::builder(fl!("persist-scrollback-max-size"))
+                    .description(fl!("persist-scrollback-max-size-description"))
+                    .control(widget::dropdown(
+                        &self.persist_scrollback_max_size_names,
+                        persist_scrollback_max_size_selected,
+                        Message::PersistScrollbackMaxSize,
+                    )),
+            )
+            .add(
+                widget::settings::item::builder(fl!("word-separators"))
+                    .description(fl!("word-separators-description"))
+                    .control(
+                        widget::text_input("", &self.config.word_separators)
+                            .on_input(Message::WordSeparators)
+                            .on_paste(Message::WordSeparators)
+                            .width(Length::Fixed(160.0)),
+                    ),
+            );
+
+        widget::settings::view_column(vec![
+            appearance_section.into(),
+            font_section.into(),
+            splits_section.into(),
+            bell_section.into(),
+            copy_section.into(),
+            notifications_section.into(),
+            session_log_section.into(),
+            advanced_section.into(),
+        ])
+        .into()
+    }
+
+    fn bell(&mut self, pane: pane_grid::Pane, entity: segmented_button::Entity) -> Task<Message> {
+        let focused_pane = self.pane_model.focused();
+        let Some(tab_model) = self.pane_model.panes.get_mut(pane) else {
+            return Task::none();
+        };
+        let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) else {
+            return Task::none();
+        };
+
+        let bell = {
+            let mut terminal = terminal.lock().unwrap();
+            let bell = self.config.bell(terminal.profile_id_opt);
+            let now = Instant::now();
+            if !terminal
+                .bell_limiter
+                .check(now, Duration::from_millis(bell.min_interval_ms))
+            {
+                return Task::none();
+            }
+            if bell.visual {
+                terminal.visual_bell_opt = Some(now);
+            }
+            bell
+        };
+
+        if bell.tab_badge && entity != tab_model.active() {
+            tab_badge_set(tab_model, entity, "emblem-important-symbolic");
+        }
+
+        if bell.sound {
+            self.bell_sound.play();
+        }
+
+        if bell.urgent
+            && (!self.window_focused || pane != focused_pane)
+            && let Some(window_id) = self.core.main_window_id()
+        {
+            return window::request_user_attention(
+                window_id,
+                Some(window::UserAttention::Informational),
+            );
+        }
+
+        Task::none()
+    }
+
+    fn shell_events(
+        &mut self,
+        pane: pane_grid::Pane,
+        entity: segmented_button::Entity,
+        events: &[ShellEvent],
+    ) {
+        let Some(tab_model) = self.pane_model.panes.get_mut(pane) else {
+            return;
+        };
+        // Events in the tab being looked at do not need attention
+        if self.window_focused && entity == tab_model.active() {
+            return;
+        }
+
+        let notifications = self.config.notifications;
+        let tab_title = tab_model.text(entity).unwrap_or_default().to_string();
+        let notification_opt = {
+            let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) else {
+                return;
+            };
+            let mut terminal = terminal.lock().unwrap();
+            notify::notify_events(
+                events,
+                &notifications,
+                &tab_title,
+                &mut terminal.notification_limiter,
+                Instant::now(),
+                self.desktop_notifier.as_ref(),
+            )
+        };
+        let Some(notification) = notification_opt else {
+            return;
+        };
+
+        if notifications.tab_badge && entity != tab_model.active() {
+            let icon_name = if notification.failed {
+                "dialog-error-symbolic"
+            } else {
+                "emblem-ok-symbolic"
+            };
+            tab_badge_set(tab_model, entity, icon_name);
+        }
+    }
+
+    /// Start or stop logging the output of a tab and update its recording indicator
+    fn session_log_set(
+        &mut self,
+        pane: pane_grid::Pane,
+        entity: segmented_button::Entity,
+        enabled: bool,
+    ) {
+        let Some(tab_model) = self.pane_model.panes.get_mut(pane) else {
+            return;
+        };
+        if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+            let terminal = terminal.lock().unwrap();
+            if !enabled {
+                terminal.session_log_stop();
+            } else if terminal.session_log_path().is_none()
+                && let Some(session_log) = session_log_create(
+                    &self.config,
+                    terminal.profile_id_opt,
+                    tab_model.text(entity).unwrap_or_default(),
+                )
+            {
+                terminal.session_log_start(session_log);
+            }
+        }
+        tab_icon_reset(tab_model, entity);
+    }
+
+    /// Show that writing the log of a tab failed, which stopped it
+    fn session_log_failed(
+        &mut self,
+        pane: pane_grid::Pane,
+        entity: segmented_button::Entity,
+        err: String,
+    ) {
+        let Some(tab_model) = self.pane_model.panes.get_mut(pane) else {
+            return;
+        };
+        tab_icon_reset(tab_model, entity);
+        let title = tab_model.text(entity).unwrap_or_default().to_string();
+        self.desktop_notifier.notify(notify::Notification {
+            summary: fl!("session-log-failed", title = title),
+            body: err,
+            failed: true,
+        });
+    }
+
+    /// Replace the panes with the layout of a session and open its tabs
+    fn session_restore(&mut self, session: &Session) -> Task<Message> {
+        let (pane_model, panes) = TerminalPaneGrid::from_session(session);
+        let focus = pane_model.focused();
+        self.pane_model = pane_model;
+        self.terminal_ids = panes
+            .iter()
+            .map(|pane| (*pane, widget::Id::unique()))
+            .collect();
+
+        let mut tasks = Vec::new();
+        for (pane, session_tabs) in panes.into_iter().zip(session.panes()) {
+            for tab in &session_tabs.tabs {
+                self.startup_tab_opt = Some(tab.clone());
+                tasks.push(self.create_and_focus_new_terminal(pane, tab.profile_id, false));
+            }
+            if session_tabs.tabs.is_empty() {
+                let profile_id_opt = self.get_default_profile();
+                tasks.push(self.create_and_focus_new_terminal(pane, profile_id_opt, false));
+            }
+            if let Some(tab_model) = self.pane_model.panes.get_mut(pane) {
+                tab_model.activate_position(session_tabs.active as u16);
+            }
+        }
+        self.pane_model.set_focus(focus);
+        tasks.push(self.update_title(Some(focus)));
+        Task::batch(tasks)
+    }
+
+    /// Save the scrollback of terminals with new output whose profile keeps it across restarts
+    fn scrollback_snapshot_save(&self) {
+        let max_size = (self.config.persist_scrollback_max_mb as usize).saturating_mul(1024 * 1024);
+        for tab_model in self.pane_model.panes.panes.values() {
+            for entity in tab_model.iter() {
+                if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+                    let mut terminal = terminal.lock().unwrap();
+                    if terminal.scrollback_snapshot_pending {
+                        terminal.scrollback_snapshot_save(max_size);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Save the layout to restore it on the next start, if enabled
+    fn session_save_last(&self) {
+        if !self.config.restore_last_session {
+            return;
+        }
+        let Some(path) = session::last_session_path() else {
+            return;
+        };
+        if let Err(err) = self.pane_model.session().save(&path) {
+            log::warn!("failed to save last session to {:?}: {}", path, err);
+        }
+    }
+
+    fn session_names_refresh(&mut self) {
+        match session::session_names() {
+            Ok(names) => self.session_names = names,
+            Err(err) => log::warn!("failed to list sessions: {}", err),
+        }
+    }
+
     fn get_default_profile(&self) -> Option<ProfileId> {
         self.config.default_profile
     }
@@ -1550,15 +2551,214 @@ impl App {
         terminal.working_directory()
     }
 
+    fn active_terminal_remote_directory(&self) -> Option<ReportedDirectory> {
+        let tab_model = self.pane_model.active()?;
+        let entity = tab_model.active();
+        let terminal = tab_model.data::<Mutex<Terminal>>(entity)?;
+        let terminal = terminal.lock().unwrap();
+        terminal.remote_directory().cloned()
+    }
+
+    /// Open the terminal of a request from another process, in a new tab or a split of the
+    /// focused pane, returning its id if it was created
+    fn terminal_open(&mut self, request: ipc::Request) -> (Option<u64>, Task<Message>) {
+        let profile_id_opt = match &request.profile {
+            Some(profile_name) => {
+                let profile_id_opt = self.config.profile_id(profile_name);
+                if profile_id_opt.is_none() {
+                    log::warn!("requested unknown profile {:?}", profile_name);
+                }
+                profile_id_opt.or_else(|| self.get_default_profile())
+            }
+            None => self.get_default_profile(),
+        };
+        let mut pane = self.pane_model.focused();
+        if let ipc::Target::Split(direction) = request.target
+            && let Some((split_pane, _)) = self.pane_model.panes.split(
+                direction.axis(),
+                pane,
+                segmented_button::ModelBuilder::default().build(),
+            )
+        {
+            self.terminal_ids.insert(split_pane, widget::Id::unique());
+            self.pane_model.panes_created += 1;
+            pane = split_pane;
+        }
+        self.startup_tab_opt = Some(SessionTab {
+            profile_id: profile_id_opt,
+            working_directory: request.working_directory,
+            command: request.command,
+            ..SessionTab::default()
+        });
+        self.terminal_create(pane, profile_id_opt, false)
+    }
+
+    /// Pane and tab of a terminal from its id
+    fn remote_target(
+        &self,
+        id: u64,
+    ) -> Result<(pane_grid::Pane, segmented_button::Entity), String> {
+        self.pane_model
+            .panes
+            .iter()
+            .find_map(|(pane, tab_model)| {
+                tab_model
+                    .iter()
+                    .find(|entity| {
+                        tab_model
+                            .data::<Mutex<Terminal>>(*entity)
+                            .is_some_and(|terminal| terminal.lock().unwrap().id == id)
+                    })
+                    .map(|entity| (*pane, entity))
+            })
+            .ok_or_else(|| format!("no terminal with id {}", id))
+    }
+
+    /// Terminals of the window, by pane in the order they are laid out
+    fn remote_terminals(&self) -> Vec<TerminalInfo> {
+        let focus = self.pane_model.focused();
+        let mut terminals = Vec::new();
+        for (index, pane) in self.pane_model.layout_panes().into_iter().enumerate() {
+            let Some(tab_model) = self.pane_model.panes.get(pane) else {
+                continue;
+            };
+            for entity in tab_model.iter() {
+                let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) else {
+                    continue;
+                };
+                let terminal = terminal.lock().unwrap();
+                let active = entity == tab_model.active();
+                terminals.push(TerminalInfo {
+                    window: process::id(),
+                    pane: index,
+                    id: terminal.id,
+                    title: tab_model.text(entity).unwrap_or_default().to_string(),
+                    working_directory: terminal.working_directory(),
+                    active,
+                    focused: active && pane == focus,
+                });
+            }
+        }
+        terminals
+    }
+
+    /// Answer a request of `cosmic-term cli`
+    fn remote(
+        &mut self,
+        request: RemoteRequest,
+    ) -> Result<(RemoteResponse, Task<Message>), String> {
+        match request {
+            RemoteRequest::List => Ok((
+                RemoteResponse::Terminals(self.remote_terminals()),
+                Task::none(),
+            )),
+            RemoteRequest::Spawn { target, request } => {
+                if let Some(id) = target {
+                    let (pane, _entity) = self.remote_target(id)?;
+                    self.pane_model.set_focus(pane);
+                }
+                let (id_opt, task) = self.terminal_open(request);
+                let response = match id_opt {
+                    Some(id) => RemoteResponse::Spawned(id),
+                    None => RemoteResponse::Error("failed to open terminal".to_string()),
+                };
+                Ok((response, task))
+            }
+            RemoteRequest::SendText { target, text } => {
+                let (pane, entity) = self.remote_target(target)?;
+                if let Some(tab_model) = self.pane_model.panes.get(pane)
+                    && let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity)
+                {
+                    terminal.lock().unwrap().input_no_scroll(text.into_bytes());
+                }
+                Ok((RemoteResponse::Done, Task::none()))
+            }
+            RemoteRequest::SendKeys { target, keys } => {
+                let (pane, entity) = self.remote_target(target)?;
+                if let Some(tab_model) = self.pane_model.panes.get(pane)
+                    && let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity)
+                {
+                    let terminal = terminal.lock().unwrap();
+                    let app_cursor = terminal
+                        .term
+                        .lock()
+                        .mode()
+                        .contains(term::TermMode::APP_CURSOR);
+                    let mut bytes = Vec::new();
+                    for key in &keys {
+                        bytes.extend(
+                            remote::key_bytes(key, app_cursor)
+                                .ok_or_else(|| format!("unknown key {:?}", key))?,
+                        );
+                    }
+                    terminal.input_no_scroll(bytes);
+                }
+                Ok((RemoteResponse::Done, Task::none()))
+            }
+            RemoteRequest::GetText { target, scrollback } => {
+                let (pane, entity) = self.remote_target(target)?;
+                let mut text = String::new();
+                if let Some(tab_model) = self.pane_model.panes.get(pane)
+                    && let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity)
+                {
+                    let terminal = terminal.lock().unwrap();
+                    let rich_text = if scrollback {
+                        terminal.scrollback_rich_text()
+                    } else {
+                        terminal.visible_rich_text()
+                    };
+                    text = rich_text.text();
+                }
+                Ok((RemoteResponse::Text(text), Task::none()))
+            }
+            RemoteRequest::SetTitle { target, title } => {
+                let (pane, entity) = self.remote_target(target)?;
+                if let Some(tab_model) = self.pane_model.panes.get_mut(pane) {
+                    if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+                        terminal.lock().unwrap().tab_title_override = title.clone();
+                    }
+                    tab_model.text_set(entity, title.unwrap_or_else(|| fl!("new-terminal")));
+                }
+                Ok((RemoteResponse::Done, self.update_title(Some(pane))))
+            }
+            RemoteRequest::Close { target } => {
+                let (pane, entity) = self.remote_target(target)?;
+                // Tabs are closed in the focused pane
+                let focus = self.pane_model.focused();
+                self.pane_model.set_focus(pane);
+                let task = self.update(Message::TabClose(Some(entity)));
+                if focus != pane && self.pane_model.panes.get(focus).is_some() {
+                    self.pane_model.set_focus(focus);
+                }
+                Ok((RemoteResponse::Done, task))
+            }
+        }
+    }
+
     fn create_and_focus_new_terminal(
         &mut self,
         pane: pane_grid::Pane,
         profile_id_opt: Option<ProfileId>,
         inherit_working_directory: bool,
     ) -> Task<Message> {
+        self.terminal_create(pane, profile_id_opt, inherit_working_directory)
+            .1
+    }
+
+    /// Create and focus a new terminal, returning its id if it was created
+    fn terminal_create(
+        &mut self,
+        pane: pane_grid::Pane,
+        profile_id_opt: Option<ProfileId>,
+        inherit_working_directory: bool,
+    ) -> (Option<u64>, Task<Message>) {
+        let mut id_opt = None;
         let inherited_working_directory = inherit_working_directory
             .then(|| self.active_terminal_working_directory())
             .flatten();
+        let remote_directory_opt = (inherit_working_directory && self.config.tab_new_ssh_remote)
+            .then(|| self.active_terminal_remote_directory())
+            .flatten();
         self.pane_model.set_focus(pane);
         match &self.term_event_tx_opt {
             Some(term_event_tx) => {
@@ -1579,58 +2779,59 @@ impl App {
                     Some(colors) => {
                         let current_pane = self.pane_model.focused();
                         if let Some(tab_model) = self.pane_model.active_mut() {
-                            let (options, tab_title_override) = if let Some(profile) =
-                                profile_id_opt
-                                    .and_then(|profile_id| self.config.profiles.get(&profile_id))
-                            {
-                                // Merge profile and startup options, preferring startup options
-                                let startup_options =
-                                    self.startup_options.take().unwrap_or_default();
-                                let options = tty::Options {
-                                    shell: startup_options.shell.or_else(|| {
-                                        if let Some(mut args) = shlex::split(&profile.command)
-                                            && !args.is_empty()
-                                        {
-                                            let command = args.remove(0);
-                                            return Some(tty::Shell::new(command, args));
-                                        }
-                                        None
-                                    }),
-                                    working_directory: startup_options
-                                        .working_directory
-                                        .or_else(|| inherited_working_directory.clone())
-                                        .or_else(|| {
-                                            (!profile.working_directory.is_empty())
-                                                .then(|| profile.working_directory.clone().into())
-                                        }),
-                                    drain_on_exit: startup_options.drain_on_exit
-                                        || profile.drain_on_exit,
-                                    ..startup_options
-                                };
-                                let tab_title_override = if profile.tab_title.is_empty() {
-                                    None
-                                } else {
-                                    Some(profile.tab_title.clone())
-                                };
-                                (options, tab_title_override)
-                            } else {
-                                let mut options = self.startup_options.take().unwrap_or_default();
-                                if options.working_directory.is_none() {
-                                    options.working_directory = inherited_working_directory.clone();
-                                }
-                                (options, None)
+                            // Merge profile and startup options, preferring startup options
+                            let startup_tab = self.startup_tab_opt.take().unwrap_or_default();
+                            let profile_opt = profile_id_opt
+                                .and_then(|profile_id| self.config.profiles.get(&profile_id));
+                            let mut command_opt = startup_tab.command.clone();
+                            let mut options = tty::Options {
+                                shell: startup_tab.shell(),
+                                working_directory: startup_tab
+                                    .working_directory
+                                    .clone()
+                                    .or_else(|| inherited_working_directory.clone()),
+                                ..tty::Options::default()
                             };
+                            let mut tab_title_override = startup_tab.title.clone();
+                            if let Some(profile) = profile_opt {
+                                if options.shell.is_none()
+                                    && let Some(mut args) = shlex::split(&profile.command)
+                                    && !args.is_empty()
+                                {
+                                    let command = args.remove(0);
+                                    options.shell = Some(tty::Shell::new(command, args));
+                                }
+                                if options.working_directory.is_none()
+                                    && !profile.working_directory.is_empty()
+                                {
+                                    options.working_directory =
+                                        Some(profile.working_directory.clone().into());
+                                }
+                                options.drain_on_exit = profile.drain_on_exit;
+                                if tab_title_override.is_none() && !profile.tab_title.is_empty() {
+                                    tab_title_override = Some(profile.tab_title.clone());
+                                }
+                            }
+                            // Commands from the profile or command line take precedence
+                            if options.shell.is_none()
+                                && let Some(args) = remote_directory_opt
+                                    .as_ref()
+                                    .and_then(|directory| directory.ssh_args())
+                            {
+                                let mut command = vec!["ssh".to_string()];
+                                command.extend(args.iter().cloned());
+                                command_opt = Some(command);
+                                options.shell = Some(tty::Shell::new("ssh".to_string(), args));
+                            }
 
-                            let entity = tab_model
-                                .insert()
-                                .text(
-                                    tab_title_override
-                                        .clone()
-                                        .unwrap_or_else(|| fl!("new-terminal")),
-                                )
-                                .closable()
-                                .activate()
-                                .id();
+                            let title = tab_title_override
+                                .clone()
+                                .unwrap_or_else(|| fl!("new-terminal"));
+                            let session_log_opt = profile_opt
+                                .is_some_and(|profile| profile.session_log)
+                                .then(|| session_log_create(&self.config, profile_id_opt, &title))
+                                .flatten();
+                            let entity = tab_model.insert().text(title).closable().activate().id();
                             match Terminal::new(
                                 current_pane,
                                 entity,
@@ -1641,8 +2842,12 @@ impl App {
                                 *colors,
                                 profile_id_opt,
                                 tab_title_override,
+                                session_log_opt,
+                                startup_tab.scrollback_snapshot.clone(),
                             ) {
                                 Ok(mut terminal) => {
+                                    id_opt = Some(terminal.id);
+                                    terminal.command_opt = command_opt;
                                     terminal.set_config(
                                         &self.config,
                                         color_scheme_kind,
@@ -1650,6 +2855,8 @@ impl App {
                                     );
                                     tab_model
                                         .data_set::<Mutex<Terminal>>(entity, Mutex::new(terminal));
+                                    // Show the recording indicator of a session log
+                                    tab_icon_reset(tab_model, entity);
                                 }
                                 Err(err) if profile_id_opt.is_some() => {
                                     // Create a tab without a profile if the selected
@@ -1671,12 +2878,16 @@ impl App {
                                     // closes the only open tab. This would close cosmic term
                                     // if launched with an invalid profile (issue #274)
                                     tab_model.remove(entity);
-                                    return self.update(Message::TabNewNoProfile);
+                                    return self.terminal_create(
+                                        self.pane_model.focused(),
+                                        None,
+                                        self.config.tab_new_inherit_working_directory,
+                                    );
                                 }
                                 Err(err) => {
                                     log::error!("failed to open terminal: {}", err);
                                     // Clean up partially created tab
-                                    return self.update(Message::TabClose(Some(entity)));
+                                    return (None, self.update(Message::TabClose(Some(entity))));
                                 }
                             }
                         } else {
@@ -1696,7 +2907,7 @@ impl App {
                 log::warn!("tried to create new tab before having event channel");
             }
         }
-        self.update_title(Some(pane))
+        (id_opt, self.update_title(Some(pane)))
     }
 }
 
@@ -1738,6 +2949,43 @@ impl Application for App {
 
         let app_themes = vec![fl!("match-desktop"), fl!("dark"), fl!("light")];
 
+        let cursor_shape_names: Vec<String> = CursorShape::ALL
+            .iter()
+            .map(|cursor_shape| match cursor_shape {
+                CursorShape::Block => fl!("cursor-block"),
+                CursorShape::Underline => fl!("cursor-underline"),
+                CursorShape::Beam => fl!("cursor-beam"),
+            })
+            .collect();
+        let clipboard_read_names: Vec<String> = ClipboardReadPolicy::ALL
+            .iter()
+            .map(|clipboard_read| match clipboard_read {
+                ClipboardReadPolicy::Allow => fl!("allow"),
+                ClipboardReadPolicy::Ask => fl!("ask"),
+                ClipboardReadPolicy::Deny => fl!("deny"),
+            })
+            .collect();
+        let mut profile_clipboard_read_names = vec![fl!("profile-default")];
+        profile_clipboard_read_names.extend(clipboard_read_names.iter().cloned());
+        let scrollback_lines_names: Vec<String> = ScrollbackLines::PRESETS
+            .iter()
+            .map(|scrollback_lines| match scrollback_lines {
+                ScrollbackLines::Limited(0) => fl!("scrollback-none"),
+                ScrollbackLines::Limited(lines) => fl!("scrollback-limited", lines = *lines),
+                ScrollbackLines::Unlimited => fl!("scrollback-unlimited"),
+            })
+            .collect();
+        let mut profile_scrollback_lines_names = vec![fl!("profile-default")];
+        profile_scrollback_lines_names.extend(scrollback_lines_names.iter().cloned());
+
+        let mut profile_cursor_shape_names = vec![fl!("profile-default")];
+        profile_cursor_shape_names.extend(cursor_shape_names.iter().cloned());
+        let profile_cursor_blink_names = vec![
+            fl!("profile-default"),
+            fl!("cursor-blinking"),
+            fl!("cursor-steady"),
+        ];
+
         let font_name_faces_map = {
             let mut font_name_faces_map = BTreeMap::<_, Vec<_>>::new();
             let mut font_system = font_system().write().unwrap();
@@ -1833,6 +3081,36 @@ impl Application for App {
             zoom_steps.push(zoom_step);
         }
 
+        let mut notification_duration_names = Vec::new();
+        let mut notification_durations = Vec::new();
+        for secs in [0, 5, 10, 30, 60, 300] {
+            notification_duration_names.push(notify::format_duration(Duration::from_secs(secs)));
+            notification_durations.push(secs);
+        }
+
+        let layouts = layout::layouts().unwrap_or_else(|err| {
+            log::warn!("failed to list layouts: {}", err);
+            Vec::new()
+        });
+
+        let mut session_log_max_size_names = Vec::new();
+        let mut session_log_max_sizes = Vec::new();
+        for size_mb in [0, 10, 100, 1000] {
+            session_log_max_size_names.push(if size_mb == 0 {
+                fl!("session-log-no-rotation")
+            } else {
+                fl!("session-log-size", size = size_mb)
+            });
+            session_log_max_sizes.push(size_mb);
+        }
+
+        let mut persist_scrollback_max_size_names = Vec::new();
+        let mut persist_scrollback_max_sizes = Vec::new();
+        for size_mb in [1, 10, 50, 200] {
+            persist_scrollback_max_size_names.push(fl!("persist-scrollback-size", size = size_mb));
+            persist_scrollback_max_sizes.push(size_mb);
+        }
+
         let pane_model = TerminalPaneGrid::new(segmented_button::ModelBuilder::default().build());
         let mut terminal_ids = HashMap::new();
         terminal_ids.insert(pane_model.focused(), widget::Id::unique());
@@ -1864,6 +3142,13 @@ impl Application for App {
             shortcuts_config: flags.shortcuts_config,
             key_binds,
             app_themes,
+            clipboard_read_names,
+            profile_clipboard_read_names,
+            scrollback_lines_names,
+            profile_scrollback_lines_names,
+            cursor_shape_names,
+            profile_cursor_blink_names,
+            profile_cursor_shape_names,
             font_names,
             font_size_names,
             font_sizes,
@@ -1876,6 +3161,12 @@ impl Application for App {
             curr_font_stretches: Vec::new(),
             zoom_step_names,
             zoom_steps,
+            notification_duration_names,
+            notification_durations,
+            session_log_max_size_names,
+            session_log_max_sizes,
+            persist_scrollback_max_size_names,
+            persist_scrollback_max_sizes,
             theme_names_dark: Vec::new(),
             theme_names_light: Vec::new(),
             themes: HashMap::new(),
@@ -1885,7 +3176,15 @@ impl Application for App {
             find: false,
             find_search_id: widget::Id::unique(),
             find_search_value: String::new(),
-            startup_options: flags.startup_options,
+            find_options: SearchOptions::default(),
+            copy_mode_search_opt: None,
+            copy_mode_keys: CopyModeKeys::default(),
+            find_error_opt: None,
+            startup_session_opt: flags.session_opt,
+            startup_tab_opt: flags.startup_tab_opt,
+            session_name: String::new(),
+            session_names: Vec::new(),
+            layouts,
             term_config: flags.term_config,
             term_event_tx_opt: None,
             color_scheme_errors: Vec::new(),
@@ -1906,6 +3205,9 @@ impl Application for App {
             context_menu_popup: None,
             #[cfg(feature = "password_manager")]
             password_mgr: Default::default(),
+            bell_sound: Box::new(bell::CanberraBellSound),
+            desktop_notifier: Box::new(notify::FreedesktopNotifier),
+            window_focused: true,
         };
 
         app.set_curr_font_weights_and_stretches();
@@ -1935,6 +3237,14 @@ impl Application for App {
             // Close find if open
             self.find = false;
             self.find_search_value.clear();
+            self.search_clear();
+        } else if self.pane_model.active().is_some_and(|tab_model| {
+            tab_model
+                .data::<Mutex<Terminal>>(tab_model.active())
+                .is_some_and(|terminal| terminal.lock().unwrap().copy_mode_active())
+        }) {
+            // Leave copy mode
+            return self.copy_mode_command(CopyModeCommand::Exit);
         }
 
         // Focus correct widget
@@ -1981,6 +3291,33 @@ impl Application for App {
                 config_set!(app_theme, app_theme);
                 return self.update_config();
             }
+            Message::Bell(bell) => {
+                config_set!(bell, bell);
+            }
+            Message::ClipboardLoadAnswer(pane, entity, allow) => {
+                if let Some(tab_model) = self.pane_model.panes.get(pane)
+                    && let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity)
+                {
+                    let load_opt = terminal.lock().unwrap().clipboard_load_opt.take();
+                    if let Some((kind, callback)) = load_opt
+                        && allow
+                    {
+                        return Task::batch([
+                            clipboard_load(pane, entity, kind, callback),
+                            self.update_focus(),
+                        ]);
+                    }
+                }
+                return self.update_focus();
+            }
+            Message::ClipboardReadDefault(index) => match ClipboardReadPolicy::ALL.get(index) {
+                Some(clipboard_read) => {
+                    config_set!(clipboard_read, *clipboard_read);
+                }
+                None => {
+                    log::warn!("failed to find clipboard read policy with index {}", index);
+                }
+            },
             Message::ClearScrollback(entity_opt) => {
                 if let Some(tab_model) = self.pane_model.active() {
                     let entity = entity_opt.unwrap_or_else(|| tab_model.active());
@@ -2206,8 +3543,44 @@ impl Application for App {
                     let entity = entity_opt.unwrap_or_else(|| tab_model.active());
                     if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
                         let terminal = terminal.lock().unwrap();
-                        let term = terminal.term.lock();
-                        if let Some(text) = term.selection_to_string() {
+                        if let Some(task) =
+                            clipboard_write_selection(&terminal, self.config.copy.rich_text)
+                        {
+                            return Task::batch([task, self.update_focus()]);
+                        }
+                    }
+                } else {
+                    log::warn!("Failed to get focused pane");
+                }
+                return self.update_focus();
+            }
+            Message::CopyAs(entity_opt, format) => {
+                if let Some(tab_model) = self.pane_model.active() {
+                    let entity = entity_opt.unwrap_or_else(|| tab_model.active());
+                    if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+                        let terminal = terminal.lock().unwrap();
+                        let task_opt = match format {
+                            ExportFormat::Html => clipboard_write_selection(&terminal, true),
+                            ExportFormat::Text => terminal.selection_text().map(clipboard::write),
+                            ExportFormat::Ansi => terminal
+                                .selection_rich_text()
+                                .map(|rich_text| clipboard::write(rich_text.ansi())),
+                        };
+                        if let Some(task) = task_opt {
+                            return Task::batch([task, self.update_focus()]);
+                        }
+                    }
+                } else {
+                    log::warn!("Failed to get focused pane");
+                }
+                return self.update_focus();
+            }
+            Message::CopyLastCommandOutput(entity_opt) => {
+                if let Some(tab_model) = self.pane_model.active() {
+                    let entity = entity_opt.unwrap_or_else(|| tab_model.active());
+                    if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+                        let terminal = terminal.lock().unwrap();
+                        if let Some(text) = terminal.last_command_output() {
                             return Task::batch([clipboard::write(text), self.update_focus()]);
                         }
                     }
@@ -2216,23 +3589,47 @@ impl Application for App {
                 }
                 return self.update_focus();
             }
+            Message::CopyMode(entity_opt) => {
+                if let Some(tab_model) = self.pane_model.active() {
+                    let entity = entity_opt.unwrap_or_else(|| tab_model.active());
+                    if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+                        let mut terminal = terminal.lock().unwrap();
+                        terminal.copy_mode_toggle();
+                        if !terminal.copy_mode_active() {
+                            drop(terminal);
+                            self.copy_mode_exited();
+                        }
+                    }
+                } else {
+                    log::warn!("Failed to get focused pane");
+                }
+                self.copy_mode_keys = CopyModeKeys::default();
+                return self.update_focus();
+            }
+            Message::CopyModeSearchSubmit => {
+                // Search as you type already moved the cursor to the match
+                self.find = false;
+                return self.update_focus();
+            }
+            Message::CopyOptions(copy) => {
+                config_set!(copy, copy);
+                return self.update_config();
+            }
             Message::CopyOrSigint(entity_opt) => {
                 if let Some(tab_model) = self.pane_model.active() {
                     let entity = entity_opt.unwrap_or_else(|| tab_model.active());
                     if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
                         let mut terminal = terminal.lock().unwrap();
-                        let mut term = terminal.term.lock();
-                        if let Some(text) = term.selection_to_string() {
+                        if let Some(task) =
+                            clipboard_write_selection(&terminal, self.config.copy.rich_text)
+                        {
                             // Clear selection (to allow next Ctrl+C to signal)
-                            term.selection = None;
-                            drop(term);
+                            terminal.term.lock().selection = None;
                             // Mark as dirty
                             terminal.needs_update = true;
                             drop(terminal);
-                            return Task::batch([clipboard::write(text), self.update_focus()]);
+                            return Task::batch([task, self.update_focus()]);
                         } else {
-                            // Drop the lock for term so that input_scroll doesn't block forever
-                            drop(term);
                             // 0x03 is ^C
                             terminal.input_scroll(b"\x03".as_slice());
                         }
@@ -2252,8 +3649,7 @@ impl Application for App {
                     let entity = entity_opt.unwrap_or_else(|| tab_model.active());
                     if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
                         let terminal = terminal.lock().unwrap();
-                        let term = terminal.term.lock();
-                        if let Some(text) = term.selection_to_string() {
+                        if let Some(text) = terminal.selection_text() {
                             return Task::batch([
                                 clipboard::write_primary(text),
                                 self.update_focus(),
@@ -2264,6 +3660,18 @@ impl Application for App {
                     log::warn!("Failed to get focused pane");
                 }
             }
+            Message::CopySelection(pane, entity) => {
+                if let Some(tab_model) = self.pane_model.panes.get(pane)
+                    && let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity)
+                {
+                    let terminal = terminal.lock().unwrap();
+                    if let Some(task) =
+                        clipboard_write_selection(&terminal, self.config.copy.rich_text)
+                    {
+                        return task;
+                    }
+                }
+            }
             Message::DefaultFont(index) => {
                 match self.font_names.get(index) {
                     Some(font_name) => {
@@ -2375,6 +3783,7 @@ impl Application for App {
             Message::Drop(None) => {}
             Message::Find(find) => {
                 self.find = find;
+                self.copy_mode_search_opt = None;
                 if find {
                     if let Some(tab_model) = self.pane_model.active() {
                         let entity = tab_model.active();
@@ -2390,42 +3799,65 @@ impl Application for App {
                     }
                 } else {
                     self.find_search_value.clear();
+                    self.search_clear();
                 }
 
                 // Focus correct input
                 return self.update_focus();
             }
-            Message::FindNext => {
-                if !self.find_search_value.is_empty()
-                    && let Some(tab_model) = self.pane_model.active()
+            Message::FindMatches(pane, entity, search_matches) => {
+                if let Some(tab_model) = self.pane_model.panes.get(pane)
+                    && let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity)
                 {
-                    let entity = tab_model.active();
-                    if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
-                        let mut terminal = terminal.lock().unwrap();
-                        terminal.search(&self.find_search_value, true);
-                    }
+                    terminal.lock().unwrap().add_search_matches(search_matches);
                 }
-
-                // Focus correct input
-                return self.update_focus();
+            }
+            Message::FindNext => {
+                return self.search(true);
+            }
+            Message::FindOptions(options) => {
+                self.find_options = options;
+                return self.search(false);
             }
             Message::FindPrevious => {
-                if !self.find_search_value.is_empty()
-                    && let Some(tab_model) = self.pane_model.active()
-                {
-                    let entity = tab_model.active();
+                return self.search(false);
+            }
+            Message::FindSearchValueChanged(value) => {
+                // Search as you type, starting from the bottom like the find bar does, or in the
+                // direction picked in copy mode
+                self.find_search_value = value;
+                return self.search(self.copy_mode_search_opt.unwrap_or(false));
+            }
+            Message::HintSelected(entity, hint_action, text) => match hint_action {
+                HintAction::Copy => {
+                    return Task::batch([clipboard::write(text), self.update_focus()]);
+                }
+                HintAction::Open => {
+                    let working_directory_opt = self.pane_model.active().and_then(|tab_model| {
+                        let terminal = tab_model.data::<Mutex<Terminal>>(entity)?;
+                        terminal.lock().unwrap().working_directory()
+                    });
+                    let target = hints::open_target(&text, working_directory_opt.as_deref());
+                    return self.update(Message::LaunchUrl(target));
+                }
+                HintAction::Paste => {
+                    return self.update(Message::PasteValue(Some(entity), text));
+                }
+            },
+            Message::Hints(entity_opt, hint_action) => {
+                if let Some(tab_model) = self.pane_model.active() {
+                    let entity = entity_opt.unwrap_or_else(|| tab_model.active());
                     if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
                         let mut terminal = terminal.lock().unwrap();
-                        terminal.search(&self.find_search_value, false);
+                        if !terminal.hints_start(&self.config.hints, hint_action) {
+                            log::info!("no hints to select");
+                        }
                     }
+                } else {
+                    log::warn!("Failed to get focused pane");
                 }
-
-                // Focus correct input
                 return self.update_focus();
             }
-            Message::FindSearchValueChanged(value) => {
-                self.find_search_value = value;
-            }
             Message::MiddleClick(pane, entity_opt) => {
                 self.pane_model.set_focus(pane);
                 return Task::batch([
@@ -2477,6 +3909,19 @@ impl Application for App {
                     return Task::none();
                 }
 
+                // Handle keys of copy mode, which the terminal keeps from the shell
+                if !self.find
+                    && !self.core.window.show_context
+                    && self.pane_model.active().is_some_and(|tab_model| {
+                        tab_model
+                            .data::<Mutex<Terminal>>(tab_model.active())
+                            .is_some_and(|terminal| terminal.lock().unwrap().copy_mode_active())
+                    })
+                    && let Some(command) = self.copy_mode_keys.command(modifiers, &key)
+                {
+                    return self.copy_mode_command(command);
+                }
+
                 // Handle configurable keys
                 for (key_bind, action) in &self.key_binds {
                     if key_bind.matches(modifiers, &key, Some(&physical)) {
@@ -2484,11 +3929,34 @@ impl Application for App {
                     }
                 }
             }
+            Message::Ipc(request) => {
+                let (_id_opt, task) = self.terminal_open(request);
+                let mut tasks = vec![task];
+                if let Some(window_id) = self.core.main_window_id() {
+                    tasks.push(window::gain_focus(window_id));
+                }
+                return Task::batch(tasks);
+            }
+            Message::Remote(request, reply) => match self.remote(request) {
+                Ok((response, task)) => {
+                    reply.send(response);
+                    return task;
+                }
+                Err(err) => reply.send(RemoteResponse::Error(err)),
+            },
             Message::LaunchUrl(url) => {
                 if let Err(err) = open::that_detached(&url) {
                     log::warn!("failed to open {:?}: {}", url, err);
                 }
             }
+            Message::LayoutOpen(index) => match self.layouts.get(index) {
+                Some((_name, path)) => {
+                    window_spawn([OsStr::new("--layout"), path.as_os_str()]);
+                }
+                None => {
+                    log::warn!("failed to find layout with index {}", index);
+                }
+            },
             Message::CopyUrlByMenu => {
                 if let Some(tab_model) = self.pane_model.active() {
                     let entity = tab_model.active();
@@ -2506,7 +3974,31 @@ impl Application for App {
                         }
                     }
                 }
-            }
+            }
+            Message::CursorBlink => {
+                if let Some(tab_model) = self.pane_model.active() {
+                    let entity = tab_model.active();
+                    if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+                        let mut terminal = terminal.lock().unwrap();
+                        terminal.blink_cursor();
+                    }
+                }
+            }
+            Message::CursorBlinkDefault(cursor_blink) => {
+                if cursor_blink != self.config.cursor_blink {
+                    config_set!(cursor_blink, cursor_blink);
+                    return self.update_config();
+                }
+            }
+            Message::CursorShapeDefault(index) => match CursorShape::ALL.get(index) {
+                Some(cursor_shape) => {
+                    config_set!(cursor_shape, *cursor_shape);
+                    return self.update_config();
+                }
+                None => {
+                    log::warn!("failed to find cursor shape with index {}", index);
+                }
+            },
             Message::LaunchUrlByMenu => {
                 if let Some(tab_model) = self.pane_model.active() {
                     let entity = tab_model.active();
@@ -2527,6 +4019,23 @@ impl Application for App {
             Message::Modifiers(modifiers) => {
                 self.modifiers = modifiers;
             }
+            Message::NotificationMinDuration(index) => {
+                match self.notification_durations.get(index) {
+                    Some(min_duration_secs) => {
+                        let notifications = NotificationConfig {
+                            min_duration_secs: *min_duration_secs,
+                            ..self.config.notifications
+                        };
+                        config_set!(notifications, notifications);
+                    }
+                    None => {
+                        log::warn!("failed to find notification duration with index {}", index);
+                    }
+                }
+            }
+            Message::Notifications(notifications) => {
+                config_set!(notifications, notifications);
+            }
             Message::MouseEnter(pane) => {
                 self.pane_model.set_focus(pane);
                 return self.update_focus();
@@ -2600,8 +4109,11 @@ impl Application for App {
                 );
                 if let Some((pane, _)) = result {
                     self.terminal_ids.insert(pane, widget::Id::unique());
-                    let command =
-                        self.create_and_focus_new_terminal(pane, self.get_default_profile(), false);
+                    let command = self.create_and_focus_new_terminal(
+                        pane,
+                        self.get_default_profile(),
+                        self.config.tab_new_inherit_working_directory,
+                    );
                     self.pane_model.panes_created += 1;
                     return command;
                 }
@@ -2673,12 +4185,40 @@ impl Application for App {
             Message::ProfileCollapse(_profile_id) => {
                 self.profile_expanded = None;
             }
+            Message::ProfileClipboardRead(profile_id, index) => {
+                if let Some(profile) = self.config.profiles.get_mut(&profile_id) {
+                    // The first entry uses the default policy
+                    profile.clipboard_read = index
+                        .checked_sub(1)
+                        .and_then(|index| ClipboardReadPolicy::ALL.get(index).copied());
+                    return self.save_profiles();
+                }
+            }
             Message::ProfileCommand(profile_id, text) => {
                 if let Some(profile) = self.config.profiles.get_mut(&profile_id) {
                     profile.command = text;
                     return self.save_profiles();
                 }
             }
+            Message::ProfileCursorBlink(profile_id, index) => {
+                if let Some(profile) = self.config.profiles.get_mut(&profile_id) {
+                    profile.cursor_blink = match index {
+                        1 => Some(true),
+                        2 => Some(false),
+                        _ => None,
+                    };
+                    return Task::batch([self.save_profiles(), self.update_config()]);
+                }
+            }
+            Message::ProfileCursorShape(profile_id, index) => {
+                if let Some(profile) = self.config.profiles.get_mut(&profile_id) {
+                    // The first entry uses the default cursor shape
+                    profile.cursor_shape = index
+                        .checked_sub(1)
+                        .and_then(|index| CursorShape::ALL.get(index).copied());
+                    return Task::batch([self.save_profiles(), self.update_config()]);
+                }
+            }
             Message::ProfileDirectory(profile_id, text) => {
                 if let Some(profile) = self.config.profiles.get_mut(&profile_id) {
                     profile.working_directory = text;
@@ -2737,6 +4277,27 @@ impl Application for App {
                 self.config.profiles.remove(&profile_id);
                 return self.save_profiles();
             }
+            Message::ProfilePersistScrollback(profile_id, persist_scrollback) => {
+                if let Some(profile) = self.config.profiles.get_mut(&profile_id) {
+                    profile.persist_scrollback = persist_scrollback;
+                    return self.save_profiles();
+                }
+            }
+            Message::ProfileSessionLog(profile_id, session_log) => {
+                if let Some(profile) = self.config.profiles.get_mut(&profile_id) {
+                    profile.session_log = session_log;
+                    return self.save_profiles();
+                }
+            }
+            Message::ProfileScrollbackLines(profile_id, index) => {
+                if let Some(profile) = self.config.profiles.get_mut(&profile_id) {
+                    // The first entry uses the default scrollback size
+                    profile.scrollback_lines = index
+                        .checked_sub(1)
+                        .and_then(|index| ScrollbackLines::PRESETS.get(index).copied());
+                    return Task::batch([self.save_profiles(), self.update_config()]);
+                }
+            }
             Message::ProfileSyntaxTheme(profile_id, color_scheme_kind, theme_i) => {
                 match self
                     .theme_names(color_scheme_kind)
@@ -2767,6 +4328,153 @@ impl Application for App {
                     return self.save_profiles();
                 }
             }
+            Message::ProfileWordSeparators(profile_id, text) => {
+                if let Some(profile) = self.config.profiles.get_mut(&profile_id) {
+                    // Empty input uses the default word separators
+                    profile.word_separators = (!text.is_empty()).then_some(text);
+                    return Task::batch([self.save_profiles(), self.update_config()]);
+                }
+            }
+            Message::ScrollbackLinesDefault(index) => match ScrollbackLines::PRESETS.get(index) {
+                Some(scrollback_lines) => {
+                    config_set!(scrollback_lines, *scrollback_lines);
+                }
+                None => {
+                    log::warn!("failed to find scrollback size with index {}", index);
+                }
+            },
+            Message::ScrollToPrompt(entity_opt, forwards) => {
+                if let Some(tab_model) = self.pane_model.active() {
+                    let entity = entity_opt.unwrap_or_else(|| tab_model.active());
+                    if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+                        let mut terminal = terminal.lock().unwrap();
+                        terminal.scroll_to_prompt(forwards);
+                    }
+                }
+                return self.update_focus();
+            }
+            Message::SaveScrollback(entity_opt, format) => {
+                if self.dialog_opt.is_none()
+                    && let Some(tab_model) = self.pane_model.active()
+                {
+                    let entity = entity_opt.unwrap_or_else(|| tab_model.active());
+                    if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+                        // Save the lines there were when asked for
+                        let lines = Arc::new(terminal.lock().unwrap().scrollback_lines());
+                        let (dialog, command) = Dialog::new(
+                            DialogSettings::new().kind(DialogKind::SaveFile {
+                                filename: format!("scrollback.{}", format.extension()),
+                            }),
+                            |msg| Message::DialogMessage(Box::new(msg)),
+                            move |result| {
+                                Message::SaveScrollbackResult(lines.clone(), format, result)
+                            },
+                        );
+                        self.dialog_opt = Some(dialog);
+                        return command;
+                    }
+                }
+            }
+            Message::SaveScrollbackResult(lines, format, result) => {
+                self.dialog_opt = None;
+                if let DialogResult::Open(mut paths) = result {
+                    let path = paths.swap_remove(0);
+                    // Formatting a long scrollback and writing it can take a while
+                    thread::spawn(move || {
+                        if let Err(err) = fs::write(&path, lines.rich_text().export(format)) {
+                            log::error!("failed to save scrollback to {:?}: {}", path, err);
+                        }
+                    });
+                }
+            }
+            Message::SessionLog(entity_opt) => {
+                let pane = self.pane_model.focused();
+                if let Some(tab_model) = self.pane_model.active() {
+                    let entity = entity_opt.unwrap_or_else(|| tab_model.active());
+                    let logging = tab_model
+                        .data::<Mutex<Terminal>>(entity)
+                        .and_then(|terminal| terminal.lock().unwrap().session_log_path())
+                        .is_some();
+                    self.session_log_set(pane, entity, !logging);
+                } else {
+                    log::warn!("Failed to get focused pane");
+                }
+                return self.update_focus();
+            }
+            Message::SessionLogDirectory(directory) => {
+                let session_log = SessionLogConfig {
+                    directory,
+                    ..self.config.session_log.clone()
+                };
+                config_set!(session_log, session_log);
+            }
+            Message::SessionLogFileName(file_name) => {
+                let session_log = SessionLogConfig {
+                    file_name,
+                    ..self.config.session_log.clone()
+                };
+                config_set!(session_log, session_log);
+            }
+            Message::PersistScrollbackMaxSize(index) => {
+                match self.persist_scrollback_max_sizes.get(index) {
+                    Some(max_mb) => {
+                        config_set!(persist_scrollback_max_mb, *max_mb);
+                    }
+                    None => {
+                        log::warn!("failed to find scrollback size with index {}", index);
+                    }
+                }
+            }
+            Message::ScrollbackSnapshot => {
+                self.scrollback_snapshot_save();
+            }
+            Message::SessionLogMaxSize(index) => match self.session_log_max_sizes.get(index) {
+                Some(max_size_mb) => {
+                    let session_log = SessionLogConfig {
+                        max_size_mb: *max_size_mb,
+                        ..self.config.session_log.clone()
+                    };
+                    config_set!(session_log, session_log);
+                }
+                None => {
+                    log::warn!("failed to find session log size with index {}", index);
+                }
+            },
+            Message::SessionLogStripEscapes(strip_escapes) => {
+                let session_log = SessionLogConfig {
+                    strip_escapes,
+                    ..self.config.session_log.clone()
+                };
+                config_set!(session_log, session_log);
+            }
+            Message::SessionDelete(name) => {
+                if let Some(path) = session::session_path(&name)
+                    && let Err(err) = fs::remove_file(&path)
+                {
+                    log::warn!("failed to delete session {:?}: {}", path, err);
+                }
+                self.session_names_refresh();
+            }
+            Message::SessionName(name) => {
+                self.session_name = name;
+            }
+            Message::SessionOpen(name) => {
+                window_spawn([OsStr::new("--session"), OsStr::new(&name)]);
+            }
+            Message::SessionSave => {
+                let name = self.session_name.trim();
+                if name.is_empty() || name.contains('/') {
+                    return Task::none();
+                }
+                match session::session_path(name) {
+                    Some(path) => match self.pane_model.session().save(&path) {
+                        Ok(()) => self.session_name.clear(),
+                        Err(err) => log::warn!("failed to save session to {:?}: {}", path, err),
+                    },
+                    None => log::warn!("failed to find directory for sessions"),
+                }
+                self.session_names_refresh();
+            }
             Message::SelectAll(entity_opt) => {
                 if let Some(tab_model) = self.pane_model.active() {
                     let entity = entity_opt.unwrap_or_else(|| tab_model.active());
@@ -2777,6 +4485,18 @@ impl Application for App {
                 }
                 return self.update_focus();
             }
+            Message::SelectLastCommandOutput(entity_opt) => {
+                if let Some(tab_model) = self.pane_model.active() {
+                    let entity = entity_opt.unwrap_or_else(|| tab_model.active());
+                    if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+                        let mut terminal = terminal.lock().unwrap();
+                        if !terminal.select_last_command_output() {
+                            log::info!("no command output found, is shell integration enabled?");
+                        }
+                    }
+                }
+                return self.update_focus();
+            }
             Message::ShowHeaderBar(show_headerbar) => {
                 if show_headerbar != self.config.show_headerbar {
                     config_set!(show_headerbar, show_headerbar);
@@ -2789,6 +4509,15 @@ impl Application for App {
                     tab_new_inherit_working_directory
                 );
             }
+            Message::TabNewSshRemote(tab_new_ssh_remote) => {
+                config_set!(tab_new_ssh_remote, tab_new_ssh_remote);
+            }
+            Message::RemoteControl(remote_control) => {
+                config_set!(remote_control, remote_control);
+            }
+            Message::RestoreLastSession(restore_last_session) => {
+                config_set!(restore_last_session, restore_last_session);
+            }
             Message::ShowPaneBorders(show_pane_borders) => {
                 if show_pane_borders != self.config.show_pane_borders {
                     config_set!(show_pane_borders, show_pane_borders);
@@ -2800,6 +4529,12 @@ impl Application for App {
                     return self.update_config();
                 }
             }
+            Message::WordSeparators(word_separators) => {
+                if word_separators != self.config.word_separators {
+                    config_set!(word_separators, word_separators);
+                    return self.update_config();
+                }
+            }
             Message::ShowAdvancedFontSettings(show) => {
                 self.show_advanced_font_settings = show;
             }
@@ -2827,6 +4562,8 @@ impl Application for App {
             Message::TabActivate(entity) => {
                 if let Some(tab_model) = self.pane_model.active_mut() {
                     tab_model.activate(entity);
+                    // Clear bell badge
+                    tab_icon_reset(tab_model, entity);
                 }
                 return self.update_title(None);
             }
@@ -2860,6 +4597,14 @@ impl Application for App {
                         } else {
                             tab_model.activate_position(position + 1);
                         }
+                        // Clear bell badge
+                        let active = tab_model.active();
+                        tab_icon_reset(tab_model, active);
+                    }
+
+                    // Closed tabs are not restored
+                    if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+                        terminal.lock().unwrap().scrollback_snapshot_remove();
                     }
 
                     // Remove item
@@ -3050,21 +4795,24 @@ impl Application for App {
             Message::TermEvent(pane, entity, event) => {
                 match event {
                     TermEvent::Bell => {
-                        //TODO: audible or visible bell options?
+                        return self.bell(pane, entity);
                     }
                     TermEvent::ClipboardLoad(kind, callback) => {
-                        match kind {
-                            term::ClipboardType::Clipboard => {
-                                log::info!("clipboard load");
-                                return clipboard::read().map(move |data_opt| {
-                                    //TODO: what to do when data_opt is None?
-                                    callback(&data_opt.unwrap_or_default());
-                                    // We don't need to do anything else
-                                    action::none()
-                                });
-                            }
-                            term::ClipboardType::Selection => {
-                                log::info!("TODO: load selection");
+                        if let Some(tab_model) = self.pane_model.panes.get(pane)
+                            && let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity)
+                        {
+                            let mut terminal = terminal.lock().unwrap();
+                            match self.config.clipboard_read(terminal.profile_id_opt) {
+                                ClipboardReadPolicy::Allow => {
+                                    return clipboard_load(pane, entity, kind, callback);
+                                }
+                                ClipboardReadPolicy::Ask => {
+                                    // Shown as a banner in the pane until answered
+                                    terminal.clipboard_load_opt = Some((kind, callback));
+                                }
+                                ClipboardReadPolicy::Deny => {
+                                    log::info!("denied {:?} load", kind);
+                                }
                             }
                         }
                     }
@@ -3074,7 +4822,8 @@ impl Application for App {
                             return clipboard::write(data);
                         }
                         term::ClipboardType::Selection => {
-                            log::info!("TODO: store selection");
+                            log::info!("selection store");
+                            return clipboard::write_primary(data);
                         }
                     },
                     TermEvent::ColorRequest(index, f) => {
@@ -3088,7 +4837,13 @@ impl Application for App {
                         }
                     }
                     TermEvent::CursorBlinkingChange => {
-                        //TODO: should we blink the cursor?
+                        if let Some(tab_model) = self.pane_model.panes.get(pane)
+                            && let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity)
+                        {
+                            let mut terminal = terminal.lock().unwrap();
+                            terminal.reset_cursor_blink();
+                            terminal.needs_update = true;
+                        }
                     }
                     TermEvent::Exit => {
                         return self.update(Message::TabClose(Some(entity)));
@@ -3130,7 +4885,10 @@ impl Application for App {
                         if let Some(tab_model) = self.pane_model.panes.get_mut(pane) {
                             let has_override =
                                 if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
-                                    let terminal = terminal.lock().unwrap();
+                                    let mut terminal = terminal.lock().unwrap();
+                                    // Shells without integration often set the title after
+                                    // changing directories
+                                    terminal.directory_label_update();
                                     terminal.tab_title_override.is_some()
                                 } else {
                                     false
@@ -3147,6 +4905,21 @@ impl Application for App {
                         {
                             let mut terminal = terminal.lock().unwrap();
                             terminal.needs_update = true;
+                            terminal.scrollback_snapshot_pending = true;
+                            let shell_events = terminal.take_shell_events();
+                            for event in &shell_events {
+                                if let ShellEvent::WorkingDirectory(directory) = event {
+                                    terminal.set_reported_directory(directory.clone());
+                                }
+                            }
+                            let session_log_error_opt = terminal.take_session_log_error();
+                            drop(terminal);
+                            if !shell_events.is_empty() {
+                                self.shell_events(pane, entity, &shell_events);
+                            }
+                            if let Some(err) = session_log_error_opt {
+                                self.session_log_failed(pane, entity, err);
+                            }
                         }
                     }
                     TermEvent::ChildExit(_error_code) => {
@@ -3180,10 +4953,26 @@ impl Application for App {
                 // Set new terminal event channel
                 self.term_event_tx_opt = Some(term_event_tx);
 
-                // Spawn first tab
-                return self.update(Message::TabNew);
+                if let Some(session) = self.startup_session_opt.take() {
+                    return self.session_restore(&session);
+                }
+
+                // Spawn first tab, with the profile from the command line if there is one
+                let profile_id_opt = self
+                    .startup_tab_opt
+                    .as_ref()
+                    .and_then(|startup_tab| startup_tab.profile_id)
+                    .or_else(|| self.get_default_profile());
+                return self.create_and_focus_new_terminal(
+                    self.pane_model.focused(),
+                    profile_id_opt,
+                    false,
+                );
             }
             Message::ToggleContextPage(context_page) => {
+                if context_page == ContextPage::Sessions {
+                    self.session_names_refresh();
+                }
                 if self.context_page == context_page {
                     self.core.window.show_context = !self.core.window.show_context;
                     self.pane_model.update_terminal_focus();
@@ -3248,6 +5037,10 @@ impl Application for App {
                 config_set!(default_profile, default.then_some(profile_id));
             }
             Message::WindowClose => {
+                self.scrollback_snapshot_save();
+                scrollback_snapshot::flush();
+                self.session_save_last();
+                remote::remove_socket();
                 if let Some(window_id) = self.core.main_window_id() {
                     return window::close(window_id);
                 }
@@ -3255,6 +5048,7 @@ impl Application for App {
             Message::WindowNew => match env::current_exe() {
                 Ok(exe) => {
                     let mut command = process::Command::new(&exe);
+                    command.arg("--no-restore");
                     if self.config.tab_new_inherit_working_directory
                         && let Some(dir) = self.active_terminal_working_directory()
                     {
@@ -3273,12 +5067,14 @@ impl Application for App {
                 }
             },
             Message::WindowFocused => {
+                self.window_focused = true;
                 if !self.core.window.show_context {
                     self.pane_model.update_terminal_focus();
                 }
                 return self.update_focus();
             }
             Message::WindowUnfocused => {
+                self.window_focused = false;
                 self.pane_model.unfocus_all_terminals();
             }
             Message::ZoomIn => {
@@ -3357,6 +5153,11 @@ impl Application for App {
                 Message::ToggleContextPage(ContextPage::Profiles),
             )
             .title(fl!("profiles")),
+            ContextPage::Sessions => context_drawer::context_drawer(
+                self.sessions(),
+                Message::ToggleContextPage(ContextPage::Sessions),
+            )
+            .title(fl!("sessions")),
             ContextPage::Settings => context_drawer::context_drawer(
                 self.settings(),
                 Message::ToggleContextPage(ContextPage::Settings),
@@ -3400,7 +5201,12 @@ impl Application for App {
     }
 
     fn header_start(&self) -> Vec<Element<'_, Self::Message>> {
-        vec![menu_bar(&self.core, &self.config, &self.key_binds)]
+        vec![menu_bar(
+            &self.core,
+            &self.config,
+            &self.layouts,
+            &self.key_binds,
+        )]
     }
 
     fn header_end(&self) -> Vec<Element<'_, Self::Message>> {
@@ -3419,6 +5225,12 @@ impl Application for App {
         {
             return Some(Message::ContextMenuPopupClosed(id));
         }
+        if self.core.main_window_id() == Some(id) {
+            self.scrollback_snapshot_save();
+            scrollback_snapshot::flush();
+            self.session_save_last();
+            remote::remove_socket();
+        }
         None
     }
 
@@ -3462,31 +5274,73 @@ impl Application for App {
             let mut tab_column = widget::column::with_capacity(1);
 
             if tab_model.iter().count() > 1 {
-                tab_column = tab_column.push(
-                    widget::container(
-                        widget::tab_bar::horizontal(tab_model)
-                            .enable_tab_drag(String::from("x-cosmic-term/tab"))
-                            .on_reorder(move |event| Message::ReorderTab(pane, event))
-                            .tab_drag_threshold(25.)
-                            .button_height(32)
-                            .button_spacing(space_xxs)
-                            .on_activate(Message::TabActivate)
-                            .on_close(|entity| Message::TabClose(Some(entity))),
-                    )
-                    .class(style::Container::Custom(Box::new(|theme| {
-                        let cosmic = theme.cosmic();
-                        cosmic::iced::widget::container::Style {
-                            icon_color: Some(Color::from(cosmic.background(theme.transparent).on)),
-                            text_color: Some(Color::from(cosmic.background(theme.transparent).on)),
-                            background: Some(iced::Background::Color(
-                                cosmic.background(theme.transparent).base.into(),
-                            )),
-                            border: iced::Border::default(),
-                            shadow: iced::Shadow::default(),
-                            snap: true,
+                let tab_bar = widget::tab_bar::horizontal(tab_model)
+                    .enable_tab_drag(String::from("x-cosmic-term/tab"))
+                    .on_reorder(move |event| Message::ReorderTab(pane, event))
+                    .tab_drag_threshold(25.)
+                    .button_height(32)
+                    .button_spacing(space_xxs)
+                    .on_activate(Message::TabActivate)
+                    .on_close(|entity| Message::TabClose(Some(entity)));
+                // The tab bar has no tooltips, so the working directory of each tab is shown by
+                // an area laid over its button. Tabs share the width of the bar equally.
+                let mut tooltips = widget::row::with_capacity(tab_model.iter().count())
+                    .spacing(space_xxs)
+                    .height(Length::Fill);
+                let mut has_tooltips = false;
+                for entity in tab_model.iter() {
+                    let area = widget::space::horizontal().height(Length::Fill);
+                    let mut label_opt = None;
+                    if let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) {
+                        label_opt = terminal
+                            .lock()
+                            .unwrap()
+                            .directory_label()
+                            .map(str::to_string);
+                    }
+                    tooltips = tooltips.push(match label_opt {
+                        Some(label) => {
+                            has_tooltips = true;
+                            widget::tooltip(
+                                area,
+                                widget::text::body(label),
+                                widget::tooltip::Position::Bottom,
+                            )
+                            .into()
                         }
-                    })))
-                    .width(Length::Fill),
+                        None => Element::from(area),
+                    });
+                }
+                // The areas take no input, so clicks and drags reach the tab bar below
+                let tab_bar: Element<_> = if has_tooltips {
+                    iced::widget::Stack::new()
+                        .width(Length::Fill)
+                        .push(tab_bar)
+                        .push(tooltips)
+                        .into()
+                } else {
+                    tab_bar.into()
+                };
+                tab_column = tab_column.push(
+                    widget::container(tab_bar)
+                        .class(style::Container::Custom(Box::new(|theme| {
+                            let cosmic = theme.cosmic();
+                            cosmic::iced::widget::container::Style {
+                                icon_color: Some(Color::from(
+                                    cosmic.background(theme.transparent).on,
+                                )),
+                                text_color: Some(Color::from(
+                                    cosmic.background(theme.transparent).on,
+                                )),
+                                background: Some(iced::Background::Color(
+                                    cosmic.background(theme.transparent).base.into(),
+                                )),
+                                border: iced::Border::default(),
+                                shadow: iced::Shadow::default(),
+                                snap: true,
+                            }
+                        })))
+                        .width(Length::Fill),
                 );
             }
 
@@ -3504,6 +5358,9 @@ impl Application for App {
                     .on_context_menu(move |menu_state| Message::TabContextMenu(pane, menu_state))
                     .on_middle_click(move || Message::MiddleClick(pane, Some(entity_middle_click)))
                     .on_open_hyperlink(Some(Box::new(Message::LaunchUrl)))
+                    .on_hint(move |hint_action, text| {
+                        Message::HintSelected(entity, hint_action, text)
+                    })
                     .on_window_focused(|| Message::WindowFocused)
                     .on_window_unfocused(|| Message::WindowUnfocused)
                     .opacity(if t.transparent {
@@ -3521,6 +5378,11 @@ impl Application for App {
                     terminal_box = terminal_box.on_mouse_enter(move || Message::MouseEnter(pane));
                 }
 
+                if self.config.copy.on_select {
+                    terminal_box =
+                        terminal_box.on_selection(move || Message::CopySelection(pane, entity));
+                }
+
                 // If a context menu popup is active for this pane, inform the
                 // terminal_box so it will emit on_context_menu(None) on click
                 // to dismiss the popup.
@@ -3564,7 +5426,65 @@ impl Application for App {
                 } else {
                     terminal_box.into()
                 };
+
+                // Ask before letting the program read the clipboard
+                let clipboard_load_kind = terminal
+                    .lock()
+                    .unwrap()
+                    .clipboard_load_opt
+                    .as_ref()
+                    .map(|(kind, _)| *kind);
+                if let Some(kind) = clipboard_load_kind {
+                    let banner = widget::row::with_children(vec![
+                        widget::text::body(match kind {
+                            term::ClipboardType::Clipboard => fl!("clipboard-read-request"),
+                            term::ClipboardType::Selection => fl!("selection-read-request"),
+                        })
+                        .into(),
+                        widget::space::horizontal().into(),
+                        widget::button::standard(fl!("deny"))
+                            .on_press(Message::ClipboardLoadAnswer(pane, entity, false))
+                            .into(),
+                        widget::button::suggested(fl!("allow"))
+                            .on_press(Message::ClipboardLoadAnswer(pane, entity, true))
+                            .into(),
+                    ])
+                    .align_y(Alignment::Center)
+                    .padding(space_xxs)
+                    .spacing(space_xxs);
+
+                    tab_column = tab_column
+                        .push(widget::layer_container(banner).layer(cosmic_theme::Layer::Primary));
+                }
+
                 tab_column = tab_column.push(tab_element);
+
+                // Show the mode and the kind of selection while in copy mode
+                let copy_mode_opt = {
+                    let terminal = terminal.lock().unwrap();
+                    terminal
+                        .copy_mode_active()
+                        .then(|| terminal.copy_mode_selection())
+                };
+                if let Some(selection_opt) = copy_mode_opt {
+                    let mode = match selection_opt {
+                        Some(SelectionType::Lines) => fl!("copy-mode-visual-line"),
+                        Some(SelectionType::Block) => fl!("copy-mode-visual-block"),
+                        Some(_) => fl!("copy-mode-visual"),
+                        None => fl!("copy-mode"),
+                    };
+                    let status = widget::row::with_children(vec![
+                        widget::text::heading(mode).into(),
+                        widget::space::horizontal().into(),
+                        widget::text::caption(fl!("copy-mode-help")).into(),
+                    ])
+                    .align_y(Alignment::Center)
+                    .padding(space_xxs)
+                    .spacing(space_xxs);
+
+                    tab_column = tab_column
+                        .push(widget::layer_container(status).layer(cosmic_theme::Layer::Primary));
+                }
             }
 
             //Only draw find in the currently focused pane
@@ -3578,7 +5498,9 @@ impl Application for App {
                 // This is inverted for ease of use, usually in terminals you want to search
                 // upwards, which is FindPrevious
                 .on_submit(|_| {
-                    if self.modifiers.contains(Modifiers::SHIFT) {
+                    if self.copy_mode_search_opt.is_some() {
+                        Message::CopyModeSearchSubmit
+                    } else if self.modifiers.contains(Modifiers::SHIFT) {
                         Message::FindNext
                     } else {
                         Message::FindPrevious
@@ -3591,8 +5513,84 @@ impl Application for App {
                         .class(style::Button::Icon)
                         .into(),
                 );
+                let find_status: Element<_> = match &self.find_error_opt {
+                    Some(err) => widget::tooltip(
+                        widget::text::body(fl!("find-invalid-regex"))
+                            .class(style::Text::Custom(find_error_style)),
+                        widget::text::body(err.clone()),
+                        widget::tooltip::Position::Top,
+                    )
+                    .into(),
+                    None => {
+                        let search_position_opt = tab_model
+                            .data::<Mutex<Terminal>>(tab_model.active())
+                            .and_then(|terminal| terminal.lock().unwrap().search_position());
+                        widget::text::body(match search_position_opt {
+                            Some((_, count, progress)) if progress < 1.0 => fl!(
+                                "find-searching",
+                                count = count,
+                                percent = (progress * 100.0) as u32
+                            ),
+                            Some((_, 0, _)) => fl!("find-no-matches"),
+                            Some((Some(position), count, _)) => {
+                                fl!("find-position", position = position, count = count)
+                            }
+                            Some((None, count, _)) => fl!("find-count", count = count),
+                            None => String::new(),
+                        })
+                        .into()
+                    }
+                };
+                let find_toggle = |label: &'static str,
+                                   enabled: bool,
+                                   tooltip: String,
+                                   options: SearchOptions|
+                 -> Element<_> {
+                    widget::tooltip(
+                        button::custom(widget::text::body(label))
+                            .on_press(Message::FindOptions(options))
+                            .padding(space_xxs)
+                            .class(if enabled {
+                                style::Button::Standard
+                            } else {
+                                style::Button::Icon
+                            }),
+                        widget::text::body(tooltip),
+                        widget::tooltip::Position::Top,
+                    )
+                    .into()
+                };
+                let options = self.find_options;
                 let find_widget = widget::row::with_children(vec![
                     find_input.into(),
+                    find_toggle(
+                        "Aa",
+                        options.case_sensitive,
+                        fl!("find-case-sensitive"),
+                        SearchOptions {
+                            case_sensitive: !options.case_sensitive,
+                            ..options
+                        },
+                    ),
+                    find_toggle(
+                        "ab",
+                        options.whole_word,
+                        fl!("find-whole-word"),
+                        SearchOptions {
+                            whole_word: !options.whole_word,
+                            ..options
+                        },
+                    ),
+                    find_toggle(
+                        ".*",
+                        options.regex,
+                        fl!("find-regex"),
+                        SearchOptions {
+                            regex: !options.regex,
+                            ..options
+                        },
+                    ),
+                    find_status,
                     widget::tooltip(
                         button::custom(icon_cache_get("go-up-symbolic", 16))
                             .on_press(Message::FindPrevious)
@@ -3669,8 +5667,24 @@ impl Application for App {
 
     fn subscription(&self) -> Subscription<Self::Message> {
         struct ConfigSubscription;
+        struct IpcSubscription;
+        struct RemoteSubscription;
         struct TerminalEventSubscription;
 
+        // Only the cursor of the focused terminal blinks
+        let cursor_blinking = self.window_focused
+            && self.pane_model.active().is_some_and(|tab_model| {
+                tab_model
+                    .data::<Mutex<Terminal>>(tab_model.active())
+                    .is_some_and(|terminal| terminal.lock().unwrap().cursor_blinking())
+            });
+
+        let persist_scrollback = self
+            .config
+            .profiles
+            .values()
+            .any(|profile| profile.persist_scrollback);
+
         Subscription::batch([
             event::listen_with(|event, _status, _window_id| match event {
                 Event::Keyboard(KeyEvent::KeyPressed {
@@ -3687,6 +5701,61 @@ impl Application for App {
                 }
                 _ => None,
             }),
+            Subscription::run_with(TypeId::of::<IpcSubscription>(), |_| {
+                stream::channel(
+                    16,
+                    |mut output: iced::futures::channel::mpsc::Sender<Message>| async move {
+                        let server = match ipc::Server::bind() {
+                            Ok(Some(server)) => server,
+                            // Another window receives the requests
+                            Ok(None) => return,
+                            Err(err) => {
+                                log::warn!("failed to listen for requests: {}", err);
+                                return;
+                            }
+                        };
+                        let (request_tx, mut request_rx) = mpsc::unbounded_channel();
+                        thread::spawn(move || {
+                            server.run(move |request| request_tx.send(request).is_ok());
+                        });
+                        while let Some(request) = request_rx.recv().await {
+                            output.send(Message::Ipc(request)).await.unwrap();
+                        }
+                    },
+                )
+            }),
+            // Other programs can do anything in the terminals, so only listen when enabled
+            if self.config.remote_control {
+                Subscription::run_with(TypeId::of::<RemoteSubscription>(), |_| {
+                    stream::channel(
+                        16,
+                        |mut output: iced::futures::channel::mpsc::Sender<Message>| async move {
+                            let server = match remote::RemoteServer::bind() {
+                                Ok(server) => server,
+                                Err(err) => {
+                                    log::warn!("failed to listen for remote control: {}", err);
+                                    return;
+                                }
+                            };
+                            let (request_tx, mut request_rx) = mpsc::unbounded_channel();
+                            // Stops when the subscription ends because remote control was
+                            // turned off, dropping the receiver
+                            thread::spawn(move || {
+                                let closed_tx = request_tx.clone();
+                                server.run(
+                                    move || closed_tx.is_closed(),
+                                    move |request, reply| request_tx.send((request, reply)).is_ok(),
+                                );
+                            });
+                            while let Some((request, reply)) = request_rx.recv().await {
+                                output.send(Message::Remote(request, reply)).await.unwrap();
+                            }
+                        },
+                    )
+                })
+            } else {
+                Subscription::none()
+            },
             Subscription::run_with(TypeId::of::<TerminalEventSubscription>(), |_| {
                 stream::channel(
                     100,
@@ -3724,10 +5793,124 @@ impl Application for App {
                 Some(dialog) => dialog.subscription(),
                 None => Subscription::none(),
             },
+            if cursor_blinking {
+                iced::time::every(terminal::CURSOR_BLINK_INTERVAL).map(|_| Message::CursorBlink)
+            } else {
+                Subscription::none()
+            },
+            if persist_scrollback {
+                iced::time::every(scrollback_snapshot::SAVE_INTERVAL)
+                    .map(|_| Message::ScrollbackSnapshot)
+            } else {
+                Subscription::none()
+            },
         ])
     }
 }
 
+/// Show the recording indicator on tabs logging their output, in place of bell and notification
+/// badges
+fn tab_icon_reset(
+    tab_model: &mut segmented_button::SingleSelectModel,
+    entity: segmented_button::Entity,
+) {
+    if tab_logging(tab_model, entity) {
+        tab_model.icon_set(entity, icon_cache_get("media-record-symbolic", 16));
+    } else {
+        tab_model.icon_remove(entity);
+    }
+}
+
+/// Show a bell or notification badge on a tab, unless it shows that its output is logged
+fn tab_badge_set(
+    tab_model: &mut segmented_button::SingleSelectModel,
+    entity: segmented_button::Entity,
+    icon_name: &'static str,
+) {
+    if !tab_logging(tab_model, entity) {
+        tab_model.icon_set(entity, icon_cache_get(icon_name, 16));
+    }
+}
+
+fn tab_logging(
+    tab_model: &segmented_button::SingleSelectModel,
+    entity: segmented_button::Entity,
+) -> bool {
+    tab_model
+        .data::<Mutex<Terminal>>(entity)
+        .is_some_and(|terminal| terminal.lock().unwrap().session_log_path().is_some())
+}
+
+/// Open a new window by running cosmic-term again with these arguments
+fn window_spawn<'a>(args: impl IntoIterator<Item = &'a OsStr>) {
+    match env::current_exe() {
+        Ok(exe) => {
+            if let Err(err) = process::Command::new(&exe).args(args).spawn() {
+                log::error!("failed to execute {:?}: {}", exe, err);
+            }
+        }
+        Err(err) => {
+            log::error!("failed to get current executable path: {}", err);
+        }
+    }
+}
+
+/// Write the selection to the clipboard, with HTML and RTF versions if `rich_text` is set
+fn clipboard_write_selection(terminal: &Terminal, rich_text: bool) -> Option<Task<Message>> {
+    let text = terminal.selection_text()?;
+    if rich_text && let Some(rich_text) = terminal.selection_rich_text() {
+        return Some(clipboard::write_data(ClipboardText::new(text, &rich_text)));
+    }
+    Some(clipboard::write(text))
+}
+
+/// Create a log for the output of a tab, named from the settings, its profile and its title
+fn session_log_create(
+    config: &Config,
+    profile_id_opt: Option<ProfileId>,
+    title: &str,
+) -> Option<SessionLog> {
+    let profile = profile_id_opt
+        .and_then(|profile_id| config.profiles.get(&profile_id))
+        .map_or("default", |profile| profile.name.as_str());
+    let unix_secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let Some(path) = session_log::log_path(&config.session_log, unix_secs, profile, title) else {
+        log::warn!("failed to find directory for session logs");
+        return None;
+    };
+    match SessionLog::create(&path, &config.session_log) {
+        Ok(session_log) => {
+            log::info!("logging session to {:?}", session_log.path());
+            Some(session_log)
+        }
+        Err(err) => {
+            log::warn!("failed to create session log {:?}: {}", path, err);
+            None
+        }
+    }
+}
+
+/// Reply to an OSC 52 clipboard read with the clipboard contents
+fn clipboard_load(
+    pane: pane_grid::Pane,
+    entity: segmented_button::Entity,
+    kind: term::ClipboardType,
+    callback: terminal::ClipboardLoadCallback,
+) -> Task<Message> {
+    log::info!("{:?} load", kind);
+    let reply = move |data_opt: Option<String>| {
+        //TODO: what to do when data_opt is None?
+        let text = callback(&data_opt.unwrap_or_default());
+        action::app(Message::TermEvent(pane, entity, TermEvent::PtyWrite(text)))
+    };
+    match kind {
+        term::ClipboardType::Clipboard => clipboard::read().map(reply),
+        term::ClipboardType::Selection => clipboard::read_primary().map(reply),
+    }
+}
+
 #[cfg(feature = "wayland")]
 fn is_wayland() -> bool {
     matches!(
@@ -16,9 +16,11 @@ use cosmic::{
         responsive_menu_bar, segmented_button,
     },
 };
-use std::{collections::HashMap, sync::LazyLock};
+use std::{collections::HashMap, path::PathBuf, sync::LazyLock};
 
-use crate::{Action, ColorSchemeId, ColorSchemeKind, Config, Message, fl};
+use crate::{
+    Action, ColorSchemeId, ColorSchemeKind, Config, ExportFormat, HintAction, Message, fl,
+};
 
 static MENU_ID: LazyLock<cosmic::widget::Id> =
     LazyLock::new(|| cosmic::widget::Id::new("responsive-menu"));
@@ -81,6 +83,14 @@ pub fn context_menu<'a>(
         Element::from(menu_item(fl!("copy"), Action::Copy)),
         Element::from(menu_item(fl!("paste"), Action::Paste)),
         Element::from(menu_item(fl!("select-all"), Action::SelectAll)),
+        Element::from(menu_item(
+            fl!("select-last-command-output"),
+            Action::SelectLastCommandOutput,
+        )),
+        Element::from(menu_item(
+            fl!("copy-last-command-output"),
+            Action::CopyLastCommandOutput,
+        )),
         Element::from(divider::horizontal::light()),
         Element::from(menu_item(fl!("clear-scrollback"), Action::ClearScrollback)),
         Element::from(divider::horizontal::light()),
@@ -198,12 +208,23 @@ pub fn color_scheme_menu<'a>(
 pub fn menu_bar<'a>(
     core: &Core,
     config: &Config,
+    layouts: &[(String, PathBuf)],
     key_binds: &HashMap<KeyBind, Action>,
 ) -> Element<'a, Message> {
     let mut profile_items = Vec::with_capacity(config.profiles.len());
     for (name, id) in config.profile_names() {
         profile_items.push(MenuItem::Button(name, None, Action::ProfileOpen(id)));
     }
+    if !layouts.is_empty() {
+        profile_items.push(MenuItem::Divider);
+        for (index, (name, _path)) in layouts.iter().enumerate() {
+            profile_items.push(MenuItem::Button(
+                name.clone(),
+                None,
+                Action::LayoutOpen(index),
+            ));
+        }
+    }
 
     //TODO: what to do if there are no profiles?
 
@@ -227,6 +248,29 @@ pub fn menu_bar<'a>(
                         MenuItem::Divider,
                         MenuItem::Folder(fl!("profile"), profile_items),
                         MenuItem::Button(fl!("menu-profiles"), None, Action::Profiles),
+                        MenuItem::Button(fl!("menu-sessions"), None, Action::Sessions),
+                        MenuItem::Divider,
+                        MenuItem::Folder(
+                            fl!("save-scrollback"),
+                            vec![
+                                MenuItem::Button(
+                                    fl!("format-html"),
+                                    None,
+                                    Action::SaveScrollback(ExportFormat::Html),
+                                ),
+                                MenuItem::Button(
+                                    fl!("format-text"),
+                                    None,
+                                    Action::SaveScrollback(ExportFormat::Text),
+                                ),
+                                MenuItem::Button(
+                                    fl!("format-ansi"),
+                                    None,
+                                    Action::SaveScrollback(ExportFormat::Ansi),
+                                ),
+                            ],
+                        ),
+                        MenuItem::Button(fl!("session-log-toggle"), None, Action::SessionLog),
                         MenuItem::Divider,
                         MenuItem::Button(fl!("close-tab"), None, Action::TabClose),
                         MenuItem::Divider,
@@ -237,12 +281,53 @@ pub fn menu_bar<'a>(
                     fl!("edit"),
                     vec![
                         MenuItem::Button(fl!("copy"), None, Action::Copy),
+                        MenuItem::Folder(
+                            fl!("copy-as"),
+                            vec![
+                                MenuItem::Button(
+                                    fl!("format-html"),
+                                    None,
+                                    Action::CopyAs(ExportFormat::Html),
+                                ),
+                                MenuItem::Button(
+                                    fl!("format-text"),
+                                    None,
+                                    Action::CopyAs(ExportFormat::Text),
+                                ),
+                                MenuItem::Button(
+                                    fl!("format-ansi"),
+                                    None,
+                                    Action::CopyAs(ExportFormat::Ansi),
+                                ),
+                            ],
+                        ),
                         MenuItem::Button(fl!("paste"), None, Action::Paste),
                         MenuItem::Button(fl!("select-all"), None, Action::SelectAll),
                         MenuItem::Divider,
                         MenuItem::Button(fl!("clear-scrollback"), None, Action::ClearScrollback),
                         MenuItem::Divider,
+                        MenuItem::Button(fl!("previous-prompt"), None, Action::PromptPrevious),
+                        MenuItem::Button(fl!("next-prompt"), None, Action::PromptNext),
+                        MenuItem::Button(
+                            fl!("select-last-command-output"),
+                            None,
+                            Action::SelectLastCommandOutput,
+                        ),
+                        MenuItem::Button(
+                            fl!("copy-last-command-output"),
+                            None,
+                            Action::CopyLastCommandOutput,
+                        ),
+                        MenuItem::Divider,
                         MenuItem::Button(fl!("find"), None, Action::Find),
+                        MenuItem::Button(fl!("hints-copy"), None, Action::Hints(HintAction::Copy)),
+                        MenuItem::Button(fl!("hints-open"), None, Action::Hints(HintAction::Open)),
+                        MenuItem::Button(
+                            fl!("hints-paste"),
+                            None,
+                            Action::Hints(HintAction::Paste),
+                        ),
+                        MenuItem::Button(fl!("copy-mode-toggle"), None, Action::CopyMode),
                     ],
                 ),
                 (
@@ -0,0 +1,318 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{
+    collections::VecDeque,
+    mem, process, thread,
+    time::{Duration, Instant},
+};
+
+use crate::{config::NotificationConfig, fl, shell_integration::ShellEvent};
+
+/// Desktop notification about a tab that is not visible
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Notification {
+    pub summary: String,
+    pub body: String,
+    /// The command failed, shown with a different icon
+    pub failed: bool,
+}
+
+/// Notifications a tab may show per [`NOTIFICATION_INTERVAL`]
+const MAX_NOTIFICATIONS: usize = 3;
+
+const NOTIFICATION_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Backend used to show desktop notifications
+pub trait DesktopNotifier {
+    fn notify(&self, notification: Notification);
+}
+
+/// Posts notifications to the freedesktop notification server using notify-send
+pub struct FreedesktopNotifier;
+
+impl DesktopNotifier for FreedesktopNotifier {
+    fn notify(&self, notification: Notification) {
+        // Wait for notify-send in a thread so it does not block the UI or linger as a zombie
+        thread::spawn(move || {
+            match process::Command::new("notify-send")
+                .arg("--app-name")
+                .arg(fl!("cosmic-terminal"))
+                .arg("--icon")
+                .arg(if notification.failed {
+                    "dialog-error-symbolic"
+                } else {
+                    "utilities-terminal-symbolic"
+                })
+                .arg("--")
+                .arg(&notification.summary)
+                .arg(&notification.body)
+                .stdin(process::Stdio::null())
+                .stdout(process::Stdio::null())
+                .stderr(process::Stdio::null())
+                .status()
+            {
+                Ok(status) => {
+                    if !status.success() {
+                        log::warn!("failed to show notification: {}", status);
+                    }
+                }
+                Err(err) => {
+                    log::warn!("failed to show notification: {}", err);
+                }
+            }
+        });
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let minutes = secs / 60;
+    let seconds = secs % 60;
+    if minutes == 0 {
+        fl!("duration-seconds", seconds = seconds)
+    } else if seconds == 0 {
+        fl!("duration-minutes", minutes = minutes)
+    } else {
+        format!(
+            "{} {}",
+            fl!("duration-minutes", minutes = minutes),
+            fl!("duration-seconds", seconds = seconds)
+        )
+    }
+}
+
+/// Returns the notification to show for an event in a tab that is not visible
+pub fn notification(
+    event: &ShellEvent,
+    config: &NotificationConfig,
+    tab_title: &str,
+) -> Option<Notification> {
+    match event {
+        ShellEvent::CommandFinished {
+            exit_status,
+            duration,
+        } => {
+            if !config.command_finished || duration.as_secs() < config.min_duration_secs {
+                return None;
+            }
+            let failed = exit_status.is_some_and(|exit_status| exit_status != 0);
+            let summary = match exit_status {
+                Some(exit_status) if failed => {
+                    fl!("command-failed", tab = tab_title, status = *exit_status)
+                }
+                _ => fl!("command-finished", tab = tab_title),
+            };
+            Some(Notification {
+                summary,
+                body: fl!("command-duration", duration = format_duration(*duration)),
+                failed,
+            })
+        }
+        ShellEvent::Notify { title, body } => {
+            if !config.app_requests {
+                return None;
+            }
+            Some(Notification {
+                summary: title.clone().unwrap_or_else(|| tab_title.to_string()),
+                body: body.clone(),
+                failed: false,
+            })
+        }
+        ShellEvent::WorkingDirectory(_) => None,
+    }
+}
+
+/// Limits the notifications of a tab, so a program requesting them in a loop cannot flood the
+/// desktop. Notifications over the limit are counted and mentioned by the next one shown.
+#[derive(Debug, Default)]
+pub struct NotificationLimiter {
+    /// When the notifications of the last interval were shown, oldest first
+    shown: VecDeque<Instant>,
+    suppressed: u32,
+}
+
+impl NotificationLimiter {
+    /// Returns the notification to show at `now`, or `None` if the tab reached the limit
+    pub fn check(&mut self, now: Instant, mut notification: Notification) -> Option<Notification> {
+        while self
+            .shown
+            .front()
+            .is_some_and(|shown| now.saturating_duration_since(*shown) >= NOTIFICATION_INTERVAL)
+        {
+            self.shown.pop_front();
+        }
+        if self.shown.len() >= MAX_NOTIFICATIONS {
+            self.suppressed += 1;
+            return None;
+        }
+        self.shown.push_back(now);
+        let suppressed = mem::take(&mut self.suppressed);
+        if suppressed > 0 {
+            notification.body = format!(
+                "{}\n{}",
+                notification.body,
+                fl!("notifications-suppressed", count = suppressed)
+            );
+        }
+        Some(notification)
+    }
+}
+
+/// Shows notifications for the events of a tab that is not visible, within the limit of the
+/// tab, returning the last one even if it was not shown
+pub fn notify_events(
+    events: &[ShellEvent],
+    config: &NotificationConfig,
+    tab_title: &str,
+    limiter: &mut NotificationLimiter,
+    now: Instant,
+    notifier: &dyn DesktopNotifier,
+) -> Option<Notification> {
+    let mut last_opt = None;
+    for event in events {
+        if let Some(notification) = notification(event, config, tab_title) {
+            if let Some(notification) = limiter.check(now, notification.clone()) {
+                notifier.notify(notification);
+            }
+            last_opt = Some(notification);
+        }
+    }
+    last_opt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        DesktopNotifier, MAX_NOTIFICATIONS, NOTIFICATION_INTERVAL, Notification,
+        NotificationLimiter, notify_events,
+    };
+    use crate::{config::NotificationConfig, shell_integration::ShellEvent};
+    use std::{
+        cell::RefCell,
+        time::{Duration, Instant},
+    };
+
+    /// Stand-in for the notification server that records what was shown
+    #[derive(Default)]
+    struct RecordingNotifier(RefCell<Vec<Notification>>);
+
+    impl DesktopNotifier for RecordingNotifier {
+        fn notify(&self, notification: Notification) {
+            self.0.borrow_mut().push(notification);
+        }
+    }
+
+    fn finished(exit_status: i32, secs: u64) -> ShellEvent {
+        ShellEvent::CommandFinished {
+            exit_status: Some(exit_status),
+            duration: Duration::from_secs(secs),
+        }
+    }
+
+    #[test]
+    fn only_long_commands_notify() {
+        let notifier = RecordingNotifier::default();
+        let config = NotificationConfig::default();
+        let last = notify_events(
+            &[finished(0, 3), finished(0, 12), finished(101, 60)],
+            &config,
+            "cargo",
+            &mut NotificationLimiter::default(),
+            Instant::now(),
+            &notifier,
+        );
+        let shown = notifier.0.into_inner();
+        assert_eq!(shown.len(), 2);
+        assert!(!shown[0].failed);
+        assert!(shown[1].failed);
+        assert_eq!(last.as_ref(), shown.last());
+    }
+
+    #[test]
+    fn application_requests_use_the_tab_title() {
+        let notifier = RecordingNotifier::default();
+        let events = [
+            ShellEvent::Notify {
+                title: None,
+                body: "done".to_string(),
+            },
+            ShellEvent::Notify {
+                title: Some("Build".to_string()),
+                body: "ok".to_string(),
+            },
+        ];
+        notify_events(
+            &events,
+            &NotificationConfig::default(),
+            "tab",
+            &mut NotificationLimiter::default(),
+            Instant::now(),
+            &notifier,
+        );
+        let shown = notifier.0.into_inner();
+        assert_eq!(
+            shown
+                .iter()
+                .map(|notification| (notification.summary.as_str(), notification.body.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("tab", "done"), ("Build", "ok")]
+        );
+    }
+
+    #[test]
+    fn disabled_notifications_are_not_shown() {
+        let notifier = RecordingNotifier::default();
+        let config = NotificationConfig {
+            command_finished: false,
+            app_requests: false,
+            ..Default::default()
+        };
+        let events = [
+            finished(0, 600),
+            ShellEvent::Notify {
+                title: None,
+                body: "done".to_string(),
+            },
+        ];
+        let last = notify_events(
+            &events,
+            &config,
+            "tab",
+            &mut NotificationLimiter::default(),
+            Instant::now(),
+            &notifier,
+        );
+        assert_eq!(last, None);
+        assert!(notifier.0.into_inner().is_empty());
+    }
+
+    #[test]
+    fn notification_floods_are_limited() {
+        let notifier = RecordingNotifier::default();
+        let mut limiter = NotificationLimiter::default();
+        let config = NotificationConfig::default();
+        let request = ShellEvent::Notify {
+            title: None,
+            body: "x".to_string(),
+        };
+        let start = Instant::now();
+        let events = vec![request; 100];
+        let last = notify_events(&events, &config, "tab", &mut limiter, start, &notifier);
+        // The tab badge still shows the last one
+        assert!(last.is_some());
+        assert_eq!(notifier.0.borrow().len(), MAX_NOTIFICATIONS);
+
+        // Still limited until the interval has passed
+        let later = start + NOTIFICATION_INTERVAL / 2;
+        notify_events(&events[..1], &config, "tab", &mut limiter, later, &notifier);
+        assert_eq!(notifier.0.borrow().len(), MAX_NOTIFICATIONS);
+
+        // The next one mentions the ones that were dropped
+        let after = start + NOTIFICATION_INTERVAL;
+        notify_events(&events[..1], &config, "tab", &mut limiter, after, &notifier);
+        let shown = notifier.0.into_inner();
+        assert_eq!(shown.len(), MAX_NOTIFICATIONS + 1);
+        assert!(shown[MAX_NOTIFICATIONS].body.starts_with("x\n"));
+        assert!(shown[MAX_NOTIFICATIONS].body.contains("98"));
+    }
+}
@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use alacritty_terminal::{
+    event::{Event, EventListener, OnResize, WindowSize},
+    tty::{ChildEvent, EventedPty, EventedReadWrite},
+};
+use polling::{Event as PollEvent, PollMode, Poller};
+use std::{
+    io, mem,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    graphics::{Graphics, GraphicsFilter},
+    session_log::SharedSessionLog,
+    shell_integration::{ShellEvent, ShellIntegrationFilter},
+    terminal::EventProxy,
+};
+
+/// PTY that rewrites what it reads before it reaches the terminal parser
+///
+/// Graphics sequences are replaced with placeholders and shell integration marks are attached
+/// to the cells they apply to. What is read is written unchanged to the session log, if there is
+/// one. The terminal is locked while the event loop reads, so filters
+/// can only talk to the terminal through events.
+pub struct FilterPty<T> {
+    pty: T,
+    graphics: GraphicsFilter,
+    shell_integration: ShellIntegrationFilter,
+    shell_events: Arc<Mutex<Vec<ShellEvent>>>,
+    session_log: SharedSessionLog,
+    session_log_error: Arc<Mutex<Option<String>>>,
+    event_proxy: EventProxy,
+    read_buf: Box<[u8]>,
+    graphics_output: Vec<u8>,
+    output: Vec<u8>,
+    output_pos: usize,
+}
+
+impl<T> FilterPty<T> {
+    pub fn new(
+        pty: T,
+        graphics: Arc<Mutex<Graphics>>,
+        shell_events: Arc<Mutex<Vec<ShellEvent>>>,
+        session_log: SharedSessionLog,
+        session_log_error: Arc<Mutex<Option<String>>>,
+        window_size: WindowSize,
+        event_proxy: EventProxy,
+    ) -> Self {
+        Self {
+            pty,
+            graphics: GraphicsFilter::new(graphics, window_size),
+            shell_integration: ShellIntegrationFilter::default(),
+            shell_events,
+            session_log,
+            session_log_error,
+            event_proxy,
+            read_buf: vec![0; 0x10000].into_boxed_slice(),
+            graphics_output: Vec::new(),
+            output: Vec::new(),
+            output_pos: 0,
+        }
+    }
+}
+
+impl<T: EventedReadWrite> io::Read for FilterPty<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if self.output_pos < self.output.len() {
+                let len = (self.output.len() - self.output_pos).min(buf.len());
+                buf[..len].copy_from_slice(&self.output[self.output_pos..self.output_pos + len]);
+                self.output_pos += len;
+                if self.output_pos == self.output.len() {
+                    self.output.clear();
+                    self.output_pos = 0;
+                }
+                return Ok(len);
+            }
+
+            let len = self.pty.reader().read(&mut self.read_buf)?;
+            if len == 0 {
+                return Ok(0);
+            }
+            let mut session_log_failed = false;
+            {
+                let mut session_log_opt = self.session_log.lock().unwrap();
+                if let Some(session_log) = session_log_opt.as_mut()
+                    && let Err(err) = session_log.write(&self.read_buf[..len])
+                {
+                    log::warn!(
+                        "failed to write session log {:?}: {}",
+                        session_log.path(),
+                        err
+                    );
+                    // The tab stops showing that it is logging and tells why
+                    *self.session_log_error.lock().unwrap() =
+                        Some(format!("{}: {}", session_log.path().display(), err));
+                    *session_log_opt = None;
+                    session_log_failed = true;
+                }
+            }
+            self.graphics_output.clear();
+            self.graphics
+                .advance(&self.read_buf[..len], &mut self.graphics_output);
+            self.shell_integration
+                .advance(&self.graphics_output, &mut self.output);
+            for reply in self.graphics.replies.drain(..) {
+                self.event_proxy.send_event(Event::PtyWrite(reply));
+            }
+            let has_shell_events = !self.shell_integration.events.is_empty();
+            if has_shell_events {
+                self.shell_events
+                    .lock()
+                    .unwrap()
+                    .append(&mut self.shell_integration.events);
+            }
+            if mem::take(&mut self.graphics.changed) || has_shell_events || session_log_failed {
+                self.event_proxy.send_event(Event::Wakeup);
+            }
+            // Everything read may have been part of an image, so keep reading until it blocks
+        }
+    }
+}
+
+impl<T: EventedReadWrite> EventedReadWrite for FilterPty<T> {
+    type Reader = Self;
+    type Writer = T::Writer;
+
+    unsafe fn register(
+        &mut self,
+        poll: &Arc<Poller>,
+        interest: PollEvent,
+        mode: PollMode,
+    ) -> io::Result<()> {
+        unsafe { self.pty.register(poll, interest, mode) }
+    }
+
+    fn reregister(
+        &mut self,
+        poll: &Arc<Poller>,
+        interest: PollEvent,
+        mode: PollMode,
+    ) -> io::Result<()> {
+        self.pty.reregister(poll, interest, mode)
+    }
+
+    fn deregister(&mut self, poll: &Arc<Poller>) -> io::Result<()> {
+        self.pty.deregister(poll)
+    }
+
+    fn reader(&mut self) -> &mut Self::Reader {
+        self
+    }
+
+    fn writer(&mut self) -> &mut Self::Writer {
+        self.pty.writer()
+    }
+}
+
+impl<T: EventedPty> EventedPty for FilterPty<T> {
+    fn next_child_event(&mut self) -> Option<ChildEvent> {
+        self.pty.next_child_event()
+    }
+}
+
+impl<T: OnResize> OnResize for FilterPty<T> {
+    fn on_resize(&mut self, window_size: WindowSize) {
+        self.graphics.set_window_size(window_size);
+        self.pty.on_resize(window_size);
+    }
+}
@@ -0,0 +1,817 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Remote control of a window over a UNIX socket, used by `cosmic-term cli`
+//!
+//! Each window listens on its own socket and sets `COSMIC_TERM_SOCKET` for its shells, and
+//! `COSMIC_TERM_ID` to the id of their terminal. Requests and responses are single lines of RON.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::mpsc,
+    time::Duration,
+};
+#[cfg(unix)]
+use std::{
+    os::unix::{
+        fs::MetadataExt,
+        net::{UnixListener, UnixStream},
+    },
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use crate::{
+    ipc::{self, SplitDirection},
+    xdg,
+};
+
+/// Time to wait for the other side of the socket
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Waits after failing to accept a client, doubled for each failure in a row
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(100);
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(5);
+
+/// Time between checks for new clients and whether to stop
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum RemoteRequest {
+    /// Terminals of the window, with their panes
+    List,
+    /// Open a tab or split next to a terminal, or in the focused pane
+    Spawn {
+        target: Option<u64>,
+        request: ipc::Request,
+    },
+    /// Write text to a terminal like it was typed
+    SendText { target: u64, text: String },
+    /// Write keys to a terminal, named like `enter`, `ctrl+c` or `up`
+    SendKeys { target: u64, keys: Vec<String> },
+    /// Text on the screen of a terminal, or all of its scrollback
+    GetText { target: u64, scrollback: bool },
+    /// Keep a title for the tab, or let programs set it again
+    SetTitle { target: u64, title: Option<String> },
+    /// Close the tab of a terminal, and its pane if it was the last tab
+    Close { target: u64 },
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum RemoteResponse {
+    Done,
+    Terminals(Vec<TerminalInfo>),
+    Spawned(u64),
+    Text(String),
+    Error(String),
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TerminalInfo {
+    /// Process id of the window
+    pub window: u32,
+    /// Position of the pane in the layout, from the top left
+    pub pane: usize,
+    pub id: u64,
+    pub title: String,
+    pub working_directory: Option<PathBuf>,
+    /// Shown in its pane
+    pub active: bool,
+    /// Active in the focused pane
+    pub focused: bool,
+}
+
+/// Sends the response to a request back to the client waiting for it
+#[derive(Clone, Debug)]
+pub struct RemoteReply(mpsc::Sender<RemoteResponse>);
+
+impl RemoteReply {
+    pub fn send(&self, response: RemoteResponse) {
+        // The client may have given up waiting
+        let _ = self.0.send(response);
+    }
+}
+
+fn socket_path_for(window: u32) -> Option<PathBuf> {
+    Some(xdg::runtime_dir()?.join(format!("remote-{}.sock", window)))
+}
+
+/// Socket of this window
+pub fn socket_path() -> Option<PathBuf> {
+    socket_path_for(process::id())
+}
+
+/// Sockets of windows, newest first, which may be left by windows that crashed
+fn socket_paths() -> io::Result<Vec<PathBuf>> {
+    let Some(dir) = xdg::runtime_dir() else {
+        return Ok(Vec::new());
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("remote-") && name.ends_with(".sock"))
+        {
+            paths.push((entry.metadata()?.modified()?, path));
+        }
+    }
+    paths.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(paths.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Send a request and wait for its response
+#[cfg(unix)]
+fn request(path: &Path, request: &RemoteRequest) -> io::Result<RemoteResponse> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let line = ron::to_string(request).map_err(io::Error::other)?;
+    writeln!(stream, "{}", line)?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    ron::from_str(&response).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(not(unix))]
+fn request(_path: &Path, _request: &RemoteRequest) -> io::Result<RemoteResponse> {
+    Err(unsupported())
+}
+
+#[cfg(unix)]
+fn listening(path: &Path) -> bool {
+    UnixStream::connect(path).is_ok()
+}
+
+#[cfg(not(unix))]
+fn listening(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "remote control needs UNIX sockets",
+    )
+}
+
+/// Socket of a window, receiving requests from `cosmic-term cli`
+pub struct RemoteServer {
+    #[cfg(unix)]
+    listener: UnixListener,
+    #[cfg(unix)]
+    path: PathBuf,
+    /// Device and inode of the socket, to tell it apart from one bound later at the same path
+    #[cfg(unix)]
+    inode: (u64, u64),
+}
+
+impl RemoteServer {
+    /// Listen on the socket of this window, removing sockets left by windows that crashed
+    pub fn bind() -> io::Result<Self> {
+        for path in socket_paths()? {
+            if !listening(&path) {
+                let _ = fs::remove_file(&path);
+            }
+        }
+        let path = socket_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set"))?;
+        Self::bind_at(&path)
+    }
+
+    #[cfg(unix)]
+    fn bind_at(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Only a process with the same id can have used the path
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        // Accepting is polled so the server notices when it should stop
+        listener.set_nonblocking(true)?;
+        let metadata = fs::metadata(path)?;
+        Ok(Self {
+            listener,
+            path: path.to_path_buf(),
+            inode: (metadata.dev(), metadata.ino()),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn bind_at(_path: &Path) -> io::Result<Self> {
+        Err(unsupported())
+    }
+
+    /// Answer requests with `handle` until it returns false or `is_stopped` returns true
+    ///
+    /// Each client is served in its own thread, so one that never sends its request or never
+    /// reads its response does not hold up the others.
+    #[cfg(unix)]
+    pub fn run(
+        &self,
+        is_stopped: impl Fn() -> bool,
+        handle: impl Fn(RemoteRequest, RemoteReply) -> bool + Clone + Send + 'static,
+    ) {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let mut backoff = ACCEPT_BACKOFF_MIN;
+        while !stopped.load(Ordering::Relaxed) && !is_stopped() {
+            let stream = match self.listener.accept() {
+                Ok((stream, _addr)) => {
+                    backoff = ACCEPT_BACKOFF_MIN;
+                    stream
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_POLL_INTERVAL);
+                    continue;
+                }
+                Err(err) => {
+                    // Failures like running out of file descriptors last a while
+                    log::warn!("failed to accept remote request: {}", err);
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
+                    continue;
+                }
+            };
+            let handle = handle.clone();
+            let stopped = stopped.clone();
+            thread::spawn(move || match serve(stream, &handle) {
+                Ok(true) => {}
+                Ok(false) => stopped.store(true, Ordering::Relaxed),
+                Err(err) => log::warn!("failed to answer remote request: {}", err),
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn run(
+        &self,
+        _is_stopped: impl Fn() -> bool,
+        _handle: impl Fn(RemoteRequest, RemoteReply) -> bool + Clone + Send + 'static,
+    ) {
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        // Unless a server bound since then replaced the socket
+        if fs::metadata(&self.path)
+            .is_ok_and(|metadata| (metadata.dev(), metadata.ino()) == self.inode)
+            && let Err(err) = fs::remove_file(&self.path)
+        {
+            log::warn!("failed to remove {:?}: {}", self.path, err);
+        }
+    }
+}
+
+/// Remove the socket of this window when it exits, servers still running are not dropped
+pub fn remove_socket() {
+    if let Some(path) = socket_path()
+        && path.exists()
+        && let Err(err) = fs::remove_file(&path)
+    {
+        log::warn!("failed to remove {:?}: {}", path, err);
+    }
+}
+
+/// Answer the request of a client, returning false if the handler has stopped
+#[cfg(unix)]
+fn serve(
+    stream: UnixStream,
+    handle: &impl Fn(RemoteRequest, RemoteReply) -> bool,
+) -> io::Result<bool> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    // Windows starting up connect without a request to see if this one is running
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(true);
+    }
+    let mut stream = reader.into_inner();
+
+    let response = match ron::from_str(&line) {
+        Ok(request) => {
+            let (reply_tx, reply_rx) = mpsc::channel();
+            if !handle(request, RemoteReply(reply_tx)) {
+                return Ok(false);
+            }
+            reply_rx
+                .recv_timeout(TIMEOUT)
+                .unwrap_or_else(|err| RemoteResponse::Error(err.to_string()))
+        }
+        Err(err) => RemoteResponse::Error(format!("invalid request: {}", err)),
+    };
+    let line = ron::to_string(&response).map_err(io::Error::other)?;
+    writeln!(stream, "{}", line)?;
+    Ok(true)
+}
+
+/// Bytes a key sends, from names like `enter`, `ctrl+c`, `alt+b`, `up` or `f5`, or a character
+pub fn key_bytes(name: &str, app_cursor: bool) -> Option<Vec<u8>> {
+    if let Some(key) = name.strip_prefix("ctrl+") {
+        let mut chars = key.chars();
+        let byte = match (chars.next()?.to_ascii_lowercase(), chars.next()) {
+            (c @ 'a'..='z', None) => c as u8 - b'a' + 1,
+            ('@' | ' ', None) => 0,
+            ('[', None) => 0x1b,
+            ('\\', None) => 0x1c,
+            (']', None) => 0x1d,
+            ('^', None) => 0x1e,
+            ('_', None) => 0x1f,
+            _ => return None,
+        };
+        return Some(vec![byte]);
+    }
+    if let Some(key) = name.strip_prefix("alt+") {
+        let mut bytes = vec![0x1b];
+        bytes.extend(key_bytes(key, app_cursor)?);
+        return Some(bytes);
+    }
+    let cursor = |c: char| {
+        if app_cursor {
+            format!("\x1bO{}", c)
+        } else {
+            format!("\x1b[{}", c)
+        }
+    };
+    let sequence = match name {
+        "enter" => "\r".to_string(),
+        "tab" => "\t".to_string(),
+        "space" => " ".to_string(),
+        "escape" | "esc" => "\x1b".to_string(),
+        "backspace" => "\x7f".to_string(),
+        "up" => cursor('A'),
+        "down" => cursor('B'),
+        "right" => cursor('C'),
+        "left" => cursor('D'),
+        "home" => cursor('H'),
+        "end" => cursor('F'),
+        "insert" => "\x1b[2~".to_string(),
+        "delete" => "\x1b[3~".to_string(),
+        "pageup" => "\x1b[5~".to_string(),
+        "pagedown" => "\x1b[6~".to_string(),
+        "f1" => "\x1bOP".to_string(),
+        "f2" => "\x1bOQ".to_string(),
+        "f3" => "\x1bOR".to_string(),
+        "f4" => "\x1bOS".to_string(),
+        _ => {
+            if let Some(number) = name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                let code = match number {
+                    5 => 15,
+                    6..=10 => number + 11,
+                    11 | 12 => number + 12,
+                    _ => return None,
+                };
+                format!("\x1b[{}~", code)
+            } else {
+                let mut chars = name.chars();
+                let c = chars.next()?;
+                if chars.next().is_some() {
+                    return None;
+                }
+                c.to_string()
+            }
+        }
+    };
+    Some(sequence.into_bytes())
+}
+
+const CLI_HELP: &str = r#"Control cosmic-term windows from scripts
+
+Usage: cosmic-term cli [--window <pid>] <command> [options]
+
+Commands:
+  list                                     List terminals with their window, pane and id
+  spawn [--target <id>] [--split <right|down>] [--profile <name>] [--cwd <dir>] [-- <command>...]
+                                           Open a tab or split, printing the id of its terminal
+  send-text --target <id> <text>...        Write text to a terminal
+  send-keys --target <id> <key>...         Write keys like enter, ctrl+c or up to a terminal
+  get-text --target <id> [--scrollback]    Print the screen or scrollback of a terminal
+  set-title --target <id> [<title>]        Set the title of a tab, or reset it
+  close --target <id>                      Close the tab of a terminal
+
+The window is the one of COSMIC_TERM_SOCKET, set in its shells, or else the newest one."#;
+
+/// Window and request from the arguments of `cosmic-term cli`
+fn cli_request(args: &[String]) -> Result<(Option<u32>, RemoteRequest), String> {
+    let mut window_opt = None;
+    let mut target_opt = None;
+    let mut split_opt = None;
+    let mut profile_opt = None;
+    let mut working_directory_opt = None;
+    let mut scrollback = false;
+    let mut verb_opt = None;
+    let mut values = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("missing argument for {}", name))
+        };
+        match arg.as_str() {
+            "--window" => {
+                let window = value(arg)?;
+                window_opt = Some(
+                    window
+                        .parse()
+                        .map_err(|_| format!("invalid window {window}"))?,
+                );
+            }
+            "--target" => {
+                let target = value(arg)?;
+                target_opt = Some(target.parse().map_err(|_| format!("invalid id {target}"))?);
+            }
+            "--split" => {
+                let split = value(arg)?;
+                split_opt = Some(
+                    SplitDirection::from_arg(&split)
+                        .ok_or_else(|| format!("invalid split {split}, expected right or down"))?,
+                );
+            }
+            "--profile" => profile_opt = Some(value(arg)?),
+            "--cwd" => working_directory_opt = Some(value(arg)?),
+            "--scrollback" => scrollback = true,
+            "--" => {
+                values.extend(args.by_ref().cloned());
+            }
+            _ if verb_opt.is_none() => verb_opt = Some(arg.clone()),
+            _ => values.push(arg.clone()),
+        }
+    }
+
+    let verb = verb_opt.ok_or_else(|| "missing command".to_string())?;
+    let target = || target_opt.ok_or_else(|| format!("{verb} needs --target <id>"));
+    let request = match verb.as_str() {
+        "list" => RemoteRequest::List,
+        "spawn" => {
+            let working_directory = match working_directory_opt {
+                Some(dir) => Some(env::current_dir().map_err(|err| err.to_string())?.join(dir)),
+                None => None,
+            };
+            RemoteRequest::Spawn {
+                target: target_opt,
+                request: ipc::Request {
+                    target: split_opt.map_or(ipc::Target::Tab, ipc::Target::Split),
+                    profile: profile_opt,
+                    working_directory,
+                    command: (!values.is_empty()).then_some(values),
+                },
+            }
+        }
+        "send-text" => RemoteRequest::SendText {
+            target: target()?,
+            text: values.join(" "),
+        },
+        "send-keys" => RemoteRequest::SendKeys {
+            target: target()?,
+            keys: values,
+        },
+        "get-text" => RemoteRequest::GetText {
+            target: target()?,
+            scrollback,
+        },
+        "set-title" => RemoteRequest::SetTitle {
+            target: target()?,
+            title: (!values.is_empty()).then(|| values.join(" ")),
+        },
+        "close" => RemoteRequest::Close { target: target()? },
+        _ => return Err(format!("unknown command {verb}")),
+    };
+    Ok((window_opt, request))
+}
+
+/// Output of `cosmic-term cli` for a response
+fn cli_output(response: RemoteResponse) -> Result<String, String> {
+    match response {
+        RemoteResponse::Done => Ok(String::new()),
+        RemoteResponse::Terminals(terminals) => {
+            let mut output = "WINDOW\tPANE\tID\tSTATE\tTITLE\tDIRECTORY\n".to_string();
+            for terminal in terminals {
+                let state = if terminal.focused {
+                    "focused"
+                } else if terminal.active {
+                    "active"
+                } else {
+                    "-"
+                };
+                let directory = terminal
+                    .working_directory
+                    .map_or_else(|| "-".to_string(), |dir| dir.display().to_string());
+                output.push_str(&format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\n",
+                    terminal.window, terminal.pane, terminal.id, state, terminal.title, directory
+                ));
+            }
+            Ok(output)
+        }
+        RemoteResponse::Spawned(id) => Ok(format!("{}\n", id)),
+        RemoteResponse::Text(text) => Ok(if text.ends_with('\n') {
+            text
+        } else {
+            text + "\n"
+        }),
+        RemoteResponse::Error(err) => Err(err),
+    }
+}
+
+/// Send the request of `cosmic-term cli` to the socket and format the response
+fn cli_run(path: &Path, request: &RemoteRequest) -> Result<String, String> {
+    let response = self::request(path, request)
+        .map_err(|err| format!("failed to send request to {}: {}", path.display(), err))?;
+    cli_output(response)
+}
+
+/// Run `cosmic-term cli` with the arguments after `cli`, returning the exit code
+pub fn cli(args: &[String]) -> i32 {
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", CLI_HELP);
+        return 0;
+    }
+    let result = cli_request(args).and_then(|(window_opt, request)| {
+        let path_opt = match window_opt {
+            Some(window) => socket_path_for(window),
+            None => env::var_os("COSMIC_TERM_SOCKET")
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| {
+                    socket_paths()
+                        .ok()?
+                        .into_iter()
+                        .find(|path| listening(path))
+                }),
+        };
+        let path = path_opt.ok_or_else(|| {
+            "no cosmic-term window with remote control enabled is running".to_string()
+        })?;
+        cli_run(&path, &request)
+    });
+    match result {
+        Ok(output) => {
+            print!("{}", output);
+            0
+        }
+        Err(err) => {
+            eprintln!("cosmic-term cli: {}", err);
+            1
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{
+        RemoteRequest, RemoteResponse, RemoteServer, TerminalInfo, cli_request, cli_run, key_bytes,
+    };
+    use crate::ipc::{self, SplitDirection};
+    use std::{
+        env, fs,
+        os::unix::net::UnixStream,
+        process,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+            mpsc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn every_verb_over_socket() {
+        let dir = env::temp_dir().join(format!("cosmic-term-remote-{}", process::id()));
+        let path = dir.join("remote-test.sock");
+        let server = RemoteServer::bind_at(&path).unwrap();
+
+        // A mocked window answering with canned responses, reporting the requests it receives
+        let (request_tx, request_rx) = mpsc::channel();
+        thread::spawn(move || {
+            server.run(
+                || false,
+                move |request, reply| {
+                    let response = match &request {
+                        RemoteRequest::List => RemoteResponse::Terminals(vec![TerminalInfo {
+                            window: 42,
+                            pane: 0,
+                            id: 1,
+                            title: "shell".to_string(),
+                            working_directory: Some("/tmp".into()),
+                            active: true,
+                            focused: true,
+                        }]),
+                        RemoteRequest::Spawn { .. } => RemoteResponse::Spawned(2),
+                        RemoteRequest::GetText { .. } => RemoteResponse::Text("$ ls".to_string()),
+                        RemoteRequest::Close { target: 9 } => {
+                            RemoteResponse::Error("no terminal with id 9".to_string())
+                        }
+                        _ => RemoteResponse::Done,
+                    };
+                    reply.send(response);
+                    request_tx.send(request).is_ok()
+                },
+            )
+        });
+
+        let cwd = env::current_dir().unwrap();
+        let cases = [
+            (
+                args(&["list"]),
+                RemoteRequest::List,
+                Ok("WINDOW\tPANE\tID\tSTATE\tTITLE\tDIRECTORY\n42\t0\t1\tfocused\tshell\t/tmp\n"),
+            ),
+            (
+                args(&["spawn", "--profile", "Server", "--", "cargo", "run"]),
+                RemoteRequest::Spawn {
+                    target: None,
+                    request: ipc::Request {
+                        target: ipc::Target::Tab,
+                        profile: Some("Server".to_string()),
+                        working_directory: None,
+                        command: Some(args(&["cargo", "run"])),
+                    },
+                },
+                Ok("2\n"),
+            ),
+            (
+                args(&["spawn", "--target", "1", "--split", "down", "--cwd", "logs"]),
+                RemoteRequest::Spawn {
+                    target: Some(1),
+                    request: ipc::Request {
+                        target: ipc::Target::Split(SplitDirection::Down),
+                        profile: None,
+                        working_directory: Some(cwd.join("logs")),
+                        command: None,
+                    },
+                },
+                Ok("2\n"),
+            ),
+            (
+                args(&["send-text", "--target", "1", "echo", "hi"]),
+                RemoteRequest::SendText {
+                    target: 1,
+                    text: "echo hi".to_string(),
+                },
+                Ok(""),
+            ),
+            (
+                args(&["send-keys", "--target", "1", "ctrl+c", "enter"]),
+                RemoteRequest::SendKeys {
+                    target: 1,
+                    keys: args(&["ctrl+c", "enter"]),
+                },
+                Ok(""),
+            ),
+            (
+                args(&["get-text", "--target", "1", "--scrollback"]),
+                RemoteRequest::GetText {
+                    target: 1,
+                    scrollback: true,
+                },
+                Ok("$ ls\n"),
+            ),
+            (
+                args(&["set-title", "--target", "1", "build", "logs"]),
+                RemoteRequest::SetTitle {
+                    target: 1,
+                    title: Some("build logs".to_string()),
+                },
+                Ok(""),
+            ),
+            (
+                args(&["set-title", "--target", "1"]),
+                RemoteRequest::SetTitle {
+                    target: 1,
+                    title: None,
+                },
+                Ok(""),
+            ),
+            (
+                args(&["close", "--target", "1"]),
+                RemoteRequest::Close { target: 1 },
+                Ok(""),
+            ),
+            (
+                args(&["close", "--target", "9"]),
+                RemoteRequest::Close { target: 9 },
+                Err("no terminal with id 9"),
+            ),
+        ];
+        for (args, expected_request, expected_output) in cases {
+            let (window_opt, request) = cli_request(&args).unwrap();
+            assert_eq!(window_opt, None);
+            assert_eq!(request, expected_request);
+            let output = cli_run(&path, &request);
+            assert_eq!(
+                output.as_deref().map_err(String::as_str),
+                expected_output,
+                "{:?}",
+                args
+            );
+            assert_eq!(request_rx.recv().unwrap(), expected_request);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn silent_clients_do_not_block_requests() {
+        let dir = env::temp_dir().join(format!("cosmic-term-remote-silent-{}", process::id()));
+        let path = dir.join("remote-test.sock");
+        let server = RemoteServer::bind_at(&path).unwrap();
+        let (request_tx, request_rx) = mpsc::channel();
+        thread::spawn(move || {
+            server.run(
+                || false,
+                move |request, reply| {
+                    reply.send(RemoteResponse::Done);
+                    request_tx.send(request).is_ok()
+                },
+            )
+        });
+
+        let _silent = UnixStream::connect(&path).unwrap();
+        let client = {
+            let path = path.clone();
+            thread::spawn(move || cli_run(&path, &RemoteRequest::List))
+        };
+        // Answered before the silent client times out
+        assert_eq!(
+            request_rx.recv_timeout(Duration::from_secs(1)).unwrap(),
+            RemoteRequest::List
+        );
+        assert_eq!(client.join().unwrap().as_deref(), Ok(""));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stopped_servers_remove_their_socket() {
+        let dir = env::temp_dir().join(format!("cosmic-term-remote-stop-{}", process::id()));
+        let path = dir.join("remote-test.sock");
+        let server = RemoteServer::bind_at(&path).unwrap();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stopped = stopped.clone();
+            thread::spawn(move || server.run(|| stopped.load(Ordering::Relaxed), |_, _| true))
+        };
+        stopped.store(true, Ordering::Relaxed);
+        thread.join().unwrap();
+        assert!(!path.exists());
+
+        // A socket bound again at the same path is kept when the old server goes away
+        let old = RemoteServer::bind_at(&path).unwrap();
+        let new = RemoteServer::bind_at(&path).unwrap();
+        drop(old);
+        assert!(path.exists());
+        drop(new);
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_cli_arguments() {
+        assert!(cli_request(&args(&[])).is_err());
+        assert!(cli_request(&args(&["close"])).is_err());
+        assert!(cli_request(&args(&["spawn", "--split", "up"])).is_err());
+        assert!(cli_request(&args(&["frobnicate"])).is_err());
+        assert_eq!(
+            cli_request(&args(&["--window", "7", "list"])).unwrap(),
+            (Some(7), RemoteRequest::List)
+        );
+    }
+
+    #[test]
+    fn key_names() {
+        assert_eq!(key_bytes("ctrl+c", false), Some(vec![3]));
+        assert_eq!(key_bytes("alt+b", false), Some(b"\x1bb".to_vec()));
+        assert_eq!(key_bytes("up", false), Some(b"\x1b[A".to_vec()));
+        assert_eq!(key_bytes("up", true), Some(b"\x1bOA".to_vec()));
+        assert_eq!(key_bytes("f5", false), Some(b"\x1b[15~".to_vec()));
+        assert_eq!(key_bytes("f12", false), Some(b"\x1b[24~".to_vec()));
+        assert_eq!(key_bytes("é", false), Some("é".as_bytes().to_vec()));
+        assert_eq!(key_bytes("ctrl+cc", false), None);
+        assert_eq!(key_bytes("hyper", false), None);
+    }
+}
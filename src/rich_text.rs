@@ -0,0 +1,397 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::iced::clipboard::mime::AsMimeTypes;
+use cosmic_text::Color;
+use std::{borrow::Cow, fmt::Write};
+
+/// Format to copy or save terminal text in
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExportFormat {
+    /// HTML with colors and styles
+    Html,
+    /// Plain text
+    Text,
+    /// Text with escape sequences that reproduce the colors and styles in a terminal
+    Ansi,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::Text => "txt",
+            Self::Ansi => "ansi",
+        }
+    }
+}
+
+/// Text with the same colors and style
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextRun {
+    pub text: String,
+    pub fg: Color,
+    pub bg: Color,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl TextRun {
+    fn same_style(&self, other: &Self) -> bool {
+        self.fg == other.fg
+            && self.bg == other.bg
+            && self.bold == other.bold
+            && self.italic == other.italic
+            && self.underline == other.underline
+    }
+}
+
+/// Lines of terminal text with their colors
+#[derive(Clone, Debug, PartialEq)]
+pub struct RichText {
+    pub lines: Vec<Vec<TextRun>>,
+    /// Colors of the terminal around the text
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl RichText {
+    pub fn new(fg: Color, bg: Color) -> Self {
+        Self {
+            lines: vec![Vec::new()],
+            fg,
+            bg,
+        }
+    }
+
+    /// Add text to the last line, merging it with the previous run of the same style
+    pub fn push(&mut self, run: TextRun) {
+        let line = self.lines.last_mut().unwrap();
+        match line.last_mut() {
+            Some(last) if last.same_style(&run) => last.text.push_str(&run.text),
+            _ => line.push(run),
+        }
+    }
+
+    pub fn trim_line_end(&mut self) {
+        let line = self.lines.last_mut().unwrap();
+        while let Some(last) = line.last_mut() {
+            let len = last.text.trim_end_matches([' ', '\t']).len();
+            last.text.truncate(len);
+            if !last.text.is_empty() {
+                break;
+            }
+            line.pop();
+        }
+    }
+
+    pub fn new_line(&mut self) {
+        self.lines.push(Vec::new());
+    }
+
+    /// Remove empty lines at the end, like the unused lines below the prompt
+    pub fn trim_end_lines(&mut self) {
+        while self.lines.len() > 1 && self.lines.last().is_some_and(Vec::is_empty) {
+            self.lines.pop();
+        }
+    }
+
+    pub fn export(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Html => self.html_document(),
+            ExportFormat::Text => self.text(),
+            ExportFormat::Ansi => self.ansi(),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                text.push('\n');
+            }
+            for run in line {
+                text.push_str(&run.text);
+            }
+        }
+        text
+    }
+
+    /// Text with SGR sequences, the colors of the terminal are left to the one showing it
+    pub fn ansi(&self) -> String {
+        let mut ansi = String::new();
+        for line in &self.lines {
+            for run in line {
+                ansi.push_str("\x1b[0");
+                if run.bold {
+                    ansi.push_str(";1");
+                }
+                if run.italic {
+                    ansi.push_str(";3");
+                }
+                if run.underline {
+                    ansi.push_str(";4");
+                }
+                if run.fg != self.fg {
+                    let _ = write!(ansi, ";38;2;{};{};{}", run.fg.r(), run.fg.g(), run.fg.b());
+                }
+                if run.bg != self.bg {
+                    let _ = write!(ansi, ";48;2;{};{};{}", run.bg.r(), run.bg.g(), run.bg.b());
+                }
+                ansi.push('m');
+                ansi.push_str(&run.text);
+            }
+            if !line.is_empty() {
+                ansi.push_str("\x1b[0m");
+            }
+            ansi.push('\n');
+        }
+        ansi
+    }
+
+    /// Page with the HTML of the text, for saving to a file
+    pub fn html_document(&self) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n</head>\n\
+             <body style=\"background-color: {};\">\n{}\n</body>\n</html>\n",
+            css_color(self.bg),
+            self.html()
+        )
+    }
+
+    pub fn html(&self) -> String {
+        let mut html = format!(
+            "<pre style=\"font-family: monospace; color: {}; background-color: {};\">",
+            css_color(self.fg),
+            css_color(self.bg)
+        );
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                html.push('\n');
+            }
+            for run in line {
+                let _ = write!(
+                    html,
+                    "<span style=\"color: {}; background-color: {};",
+                    css_color(run.fg),
+                    css_color(run.bg)
+                );
+                if run.bold {
+                    html.push_str(" font-weight: bold;");
+                }
+                if run.italic {
+                    html.push_str(" font-style: italic;");
+                }
+                if run.underline {
+                    html.push_str(" text-decoration: underline;");
+                }
+                html.push_str("\">");
+                for c in run.text.chars() {
+                    match c {
+                        '&' => html.push_str("&amp;"),
+                        '<' => html.push_str("&lt;"),
+                        '>' => html.push_str("&gt;"),
+                        '"' => html.push_str("&quot;"),
+                        c => html.push(c),
+                    }
+                }
+                html.push_str("</span>");
+            }
+        }
+        html.push_str("</pre>");
+        html
+    }
+
+    pub fn rtf(&self) -> String {
+        // The color table starts at index 1, index 0 is the default color
+        let mut colors = vec![self.fg, self.bg];
+        let mut color_index = |color: Color| match colors.iter().position(|c| *c == color) {
+            Some(i) => i + 1,
+            None => {
+                colors.push(color);
+                colors.len()
+            }
+        };
+
+        let mut body = format!(
+            "\\cf{}\\chcbpat{} ",
+            color_index(self.fg),
+            color_index(self.bg)
+        );
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                body.push_str("\\line\n");
+            }
+            for run in line {
+                let _ = write!(
+                    body,
+                    "{{\\cf{}\\chcbpat{}\\cb{}",
+                    color_index(run.fg),
+                    color_index(run.bg),
+                    color_index(run.bg)
+                );
+                if run.bold {
+                    body.push_str("\\b");
+                }
+                if run.italic {
+                    body.push_str("\\i");
+                }
+                if run.underline {
+                    body.push_str("\\ul");
+                }
+                body.push(' ');
+                for c in run.text.chars() {
+                    match c {
+                        '\\' | '{' | '}' => {
+                            body.push('\\');
+                            body.push(c);
+                        }
+                        '\t' => body.push_str("\\tab "),
+                        c if c.is_ascii() => body.push(c),
+                        c => {
+                            // RTF uses signed 16-bit code units, with ? shown by old readers
+                            let mut units = [0; 2];
+                            for unit in c.encode_utf16(&mut units) {
+                                let _ = write!(body, "\\u{}?", *unit as i16);
+                            }
+                        }
+                    }
+                }
+                body.push('}');
+            }
+        }
+
+        let mut rtf = String::from("{\\rtf1\\ansi\\deff0{\\fonttbl{\\f0\\fmodern monospace;}}");
+        rtf.push_str("{\\colortbl;");
+        for color in colors {
+            let _ = write!(
+                rtf,
+                "\\red{}\\green{}\\blue{};",
+                color.r(),
+                color.g(),
+                color.b()
+            );
+        }
+        rtf.push_str("}\\f0 ");
+        rtf.push_str(&body);
+        rtf.push('}');
+        rtf
+    }
+}
+
+fn css_color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+/// Plain text with HTML and RTF versions for the clipboard
+#[derive(Clone, Debug)]
+pub struct ClipboardText {
+    plain: String,
+    html: String,
+    rtf: String,
+}
+
+impl ClipboardText {
+    pub fn new(plain: String, rich_text: &RichText) -> Self {
+        Self {
+            plain,
+            html: rich_text.html(),
+            rtf: rich_text.rtf(),
+        }
+    }
+}
+
+impl AsMimeTypes for ClipboardText {
+    fn available(&self) -> Cow<'static, [String]> {
+        Cow::from(vec![
+            "text/plain;charset=utf-8".to_string(),
+            "text/plain".to_string(),
+            "UTF8_STRING".to_string(),
+            "text/html".to_string(),
+            "text/rtf".to_string(),
+        ])
+    }
+
+    fn as_bytes(&self, mime_type: &str) -> Option<Cow<'static, [u8]>> {
+        let data = match mime_type {
+            "text/plain;charset=utf-8" | "text/plain" | "UTF8_STRING" => &self.plain,
+            "text/html" => &self.html,
+            "text/rtf" => &self.rtf,
+            _ => return None,
+        };
+        Some(Cow::Owned(data.as_bytes().to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExportFormat, RichText, TextRun};
+    use cosmic_text::Color;
+
+    fn run(text: &str, fg: Color, bold: bool) -> TextRun {
+        TextRun {
+            text: text.to_string(),
+            fg,
+            bg: Color::rgb(0, 0, 0),
+            bold,
+            italic: false,
+            underline: false,
+        }
+    }
+
+    fn rich_text() -> RichText {
+        let white = Color::rgb(0xff, 0xff, 0xff);
+        let red = Color::rgb(0xff, 0, 0);
+        let mut rich_text = RichText::new(white, Color::rgb(0, 0, 0));
+        rich_text.push(run("a<", red, true));
+        rich_text.push(run("b ", red, true));
+        rich_text.push(run("  ", white, false));
+        rich_text.trim_line_end();
+        rich_text.new_line();
+        rich_text.push(run("{é}", white, false));
+        rich_text
+    }
+
+    #[test]
+    fn runs_merge_and_trim() {
+        let rich_text = rich_text();
+        assert_eq!(rich_text.lines.len(), 2);
+        assert_eq!(rich_text.lines[0].len(), 1);
+        assert_eq!(rich_text.lines[0][0].text, "a<b");
+    }
+
+    #[test]
+    fn html_escapes_text() {
+        assert_eq!(
+            rich_text().html(),
+            "<pre style=\"font-family: monospace; color: #ffffff; background-color: #000000;\">\
+             <span style=\"color: #ff0000; background-color: #000000; font-weight: bold;\">\
+             a&lt;b</span>\n\
+             <span style=\"color: #ffffff; background-color: #000000;\">{é}</span></pre>"
+        );
+    }
+
+    #[test]
+    fn rtf_escapes_text() {
+        let rtf = rich_text().rtf();
+        assert!(rtf.starts_with("{\\rtf1\\ansi"));
+        assert!(rtf.contains(
+            "{\\colortbl;\\red255\\green255\\blue255;\\red0\\green0\\blue0;\\red255\\green0\\blue0;}"
+        ));
+        assert!(rtf.contains("{\\cf3\\chcbpat2\\cb2\\b a<b}\\line\n"));
+        assert!(rtf.contains("{\\cf1\\chcbpat2\\cb2 \\{\\u233?\\}}"));
+        assert!(rtf.ends_with('}'));
+    }
+
+    #[test]
+    fn ansi_skips_default_colors() {
+        let mut rich_text = rich_text();
+        rich_text.new_line();
+        rich_text.trim_end_lines();
+        assert_eq!(rich_text.export(ExportFormat::Text), "a<b\n{é}");
+        assert_eq!(
+            rich_text.export(ExportFormat::Ansi),
+            "\x1b[0;1;38;2;255;0;0ma<b\x1b[0m\n\x1b[0m{é}\x1b[0m\n"
+        );
+    }
+}
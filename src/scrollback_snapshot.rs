@@ -0,0 +1,289 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{
+    fs::{self, DirBuilder, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        LazyLock,
+        atomic::{AtomicU32, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+
+use crate::{config::ProfileId, fl, xdg};
+
+/// How often changed scrollback is saved, so it survives crashes and logging out
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Snapshots left for each profile, older ones are removed when terminals start
+const MAX_SNAPSHOTS: usize = 16;
+
+fn snapshots_dir() -> Option<PathBuf> {
+    Some(xdg::state_dir()?.join("scrollback"))
+}
+
+/// Whether the process that wrote a snapshot is gone, so its history can be restored
+fn orphaned(path: &Path) -> bool {
+    let Some(pid) = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.split('-').nth(1))
+        .and_then(|pid| pid.parse::<u32>().ok())
+    else {
+        return false;
+    };
+    if pid == process::id() {
+        return false;
+    }
+    #[cfg(target_os = "linux")]
+    {
+        !Path::new("/proc").join(pid.to_string()).exists()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        true
+    }
+}
+
+/// Snapshots of a profile, newest first
+fn snapshots(dir: &Path, profile_id: ProfileId) -> io::Result<Vec<PathBuf>> {
+    let prefix = format!("{}-", profile_id.0);
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "ansi")
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&prefix))
+        {
+            snapshots.push((entry.metadata()?.modified()?, path));
+        }
+    }
+    snapshots.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(snapshots.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Path for the snapshot of a new terminal, with the history of the newest snapshot the profile
+/// left in a previous run, if any
+///
+/// `preferred_opt` is claimed first if it is one of those snapshots, for tabs restored from a
+/// session. The snapshot is renamed to the new path, so two terminals never restore the same
+/// history.
+pub fn claim(
+    profile_id: ProfileId,
+    preferred_opt: Option<&Path>,
+) -> Option<(PathBuf, Option<Vec<u8>>)> {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    let dir = snapshots_dir()?;
+    let unix_nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos());
+    let path = dir.join(format!(
+        "{}-{}-{}-{}.ansi",
+        profile_id.0,
+        process::id(),
+        unix_nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let orphans: Vec<_> = match snapshots(&dir, profile_id) {
+        Ok(snapshots) => snapshots
+            .into_iter()
+            .filter(|path| orphaned(path))
+            .collect(),
+        Err(err) => {
+            log::warn!("failed to list scrollback snapshots in {:?}: {}", dir, err);
+            Vec::new()
+        }
+    };
+    for old in orphans.iter().skip(MAX_SNAPSHOTS) {
+        if let Err(err) = fs::remove_file(old) {
+            log::warn!("failed to remove scrollback snapshot {:?}: {}", old, err);
+        }
+    }
+    let mut candidates = orphans;
+    candidates.truncate(MAX_SNAPSHOTS);
+    if let Some(preferred) = preferred_opt
+        && let Some(index) = candidates.iter().position(|path| path == preferred)
+    {
+        let preferred = candidates.remove(index);
+        candidates.insert(0, preferred);
+    }
+    let mut history_opt = None;
+    for old in &candidates {
+        // Fails if a terminal in another window claimed it first
+        if fs::rename(old, &path).is_ok() {
+            match fs::read(&path) {
+                Ok(history) => history_opt = Some(history),
+                Err(err) => log::warn!("failed to read scrollback snapshot {:?}: {}", path, err),
+            }
+            break;
+        }
+    }
+    Some((path, history_opt))
+}
+
+/// Write the history, keeping whole lines from the end of it up to `max_size` bytes
+pub fn save(path: &Path, history: &str, max_size: usize) -> io::Result<()> {
+    let bytes = history.as_bytes();
+    let mut start = bytes.len().saturating_sub(max_size);
+    if start > 0 {
+        start = bytes[start - 1..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(bytes.len(), |index| start + index);
+    }
+    if let Some(parent) = path.parent() {
+        let mut builder = DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        builder.mode(0o700);
+        builder.create(parent)?;
+    }
+    // Write a temporary file first so a crash never leaves a partial snapshot. The history can
+    // contain secrets shown in the terminal, so only the user can read it.
+    let tmp_path = path.with_extension("ansi.tmp");
+    let mut options = File::options();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options.open(&tmp_path)?.write_all(&bytes[start..])?;
+    fs::rename(&tmp_path, path)
+}
+
+enum Job {
+    Save {
+        path: PathBuf,
+        history: Box<dyn FnOnce() -> String + Send>,
+        max_size: usize,
+    },
+    Remove(PathBuf),
+    Flush(mpsc::Sender<()>),
+}
+
+/// Thread writing snapshots in the order they are queued, so the UI never waits for the disk
+/// and a removed snapshot is not written again by an older save
+static WRITER: LazyLock<mpsc::Sender<Job>> = LazyLock::new(|| {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for job in rx {
+            match job {
+                Job::Save {
+                    path,
+                    history,
+                    max_size,
+                } => {
+                    if let Err(err) = save(&path, &history(), max_size) {
+                        log::warn!("failed to save scrollback to {:?}: {}", path, err);
+                    }
+                }
+                Job::Remove(path) => {
+                    if let Err(err) = fs::remove_file(&path)
+                        && err.kind() != io::ErrorKind::NotFound
+                    {
+                        log::warn!("failed to remove scrollback snapshot {:?}: {}", path, err);
+                    }
+                }
+                Job::Flush(done_tx) => {
+                    let _ = done_tx.send(());
+                }
+            }
+        }
+    });
+    tx
+});
+
+/// Save the history in the background, see [`save`]
+///
+/// The history is formatted on the writer thread, as it can take a while for long scrollbacks.
+pub fn save_later(
+    path: PathBuf,
+    history: impl FnOnce() -> String + Send + 'static,
+    max_size: usize,
+) {
+    let _ = WRITER.send(Job::Save {
+        path,
+        history: Box::new(history),
+        max_size,
+    });
+}
+
+/// Remove a snapshot in the background, after the saves queued before
+pub fn remove_later(path: PathBuf) {
+    let _ = WRITER.send(Job::Remove(path));
+}
+
+/// Wait for the queued snapshots to be written, before the application exits
+pub fn flush() {
+    let (done_tx, done_rx) = mpsc::channel();
+    if WRITER.send(Job::Flush(done_tx)).is_ok() {
+        let _ = done_rx.recv();
+    }
+}
+
+/// Output replaying restored history, with carriage returns and a separator line after it
+pub fn replay(history: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(history.len() + history.len() / 32 + 64);
+    for &byte in history {
+        if byte == b'\n' {
+            output.push(b'\r');
+        }
+        output.push(byte);
+    }
+    if !history.is_empty() && !history.ends_with(b"\n") {
+        output.extend_from_slice(b"\r\n");
+    }
+    output.extend_from_slice(
+        format!("\x1b[0;2m──── {} ────\x1b[0m\r\n", fl!("restored-history")).as_bytes(),
+    );
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{orphaned, save};
+    use std::{env, fs, path::Path, process};
+
+    #[test]
+    fn save_keeps_whole_lines() {
+        let dir = env::temp_dir().join(format!("cosmic-term-scrollback-{}", process::id()));
+        let path = dir.join("1-2-3-4.ansi");
+        save(&path, "first\nsecond\nthird\n", 13).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\nthird\n");
+        save(&path, "first\nsecond\nthird\n", 12).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third\n");
+        save(&path, "first\nsecond\n", 13).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn own_snapshots_are_not_orphaned() {
+        let own = format!("1-{}-3-4.ansi", process::id());
+        assert!(!orphaned(Path::new(&own)));
+        assert!(!orphaned(Path::new("1-invalid.ansi")));
+    }
+}
@@ -0,0 +1,369 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use alacritty_terminal::{
+    Term,
+    grid::Dimensions,
+    index::{Column, Direction, Line, Point},
+    sync::FairMutex,
+    term::search::{Match, RegexIter, RegexSearch},
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Lines searched or copied each time background work locks the terminal
+pub const SLICE_LINES: usize = 4096;
+
+/// Scrollbar tick marks are grouped into this many rows
+const TICK_ROWS: usize = 1024;
+
+/// Toggles of the find bar
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    /// Search for a regular expression instead of the text as typed
+    pub regex: bool,
+}
+
+impl SearchOptions {
+    /// Regular expression used to search for a value of the find bar
+    pub fn pattern(&self, value: &str) -> String {
+        let mut pattern = if self.regex {
+            value.to_string()
+        } else {
+            regex::escape(value)
+        };
+        if self.whole_word {
+            // The search DFA cannot look at Unicode word boundaries, so use ASCII ones
+            pattern = format!("(?-u:\\b)(?:{pattern})(?-u:\\b)");
+        }
+        // Without a flag, the terminal ignores case unless the value has uppercase letters
+        if self.case_sensitive {
+            format!("(?-i){pattern}")
+        } else {
+            format!("(?i){pattern}")
+        }
+    }
+
+    pub fn regex_search(&self, value: &str) -> Result<RegexSearch, String> {
+        RegexSearch::new(&self.pattern(value)).map_err(|err| err.to_string())
+    }
+}
+
+/// Matches of the find bar query in the grid, including the scrollback
+///
+/// The background search sends the matches of each slice of lines it searched, which are added
+/// to the ones found before.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchMatches {
+    /// Regular expression the matches were found for
+    pub pattern: String,
+    /// Start of each match in grid order, as line from the top of the scrollback and column
+    starts: Vec<(usize, usize)>,
+    /// Scrollbar tick marks of the matches, as ratios of the grid height
+    ticks: Vec<f32>,
+    /// Ratio of the grid that was searched
+    pub progress: f32,
+}
+
+impl SearchMatches {
+    /// Search the lines starting `next_line` lines from the top of the scrollback, moving it past
+    /// the searched lines
+    fn search_slice<T>(
+        term: &Term<T>,
+        pattern: &str,
+        regex: &mut RegexSearch,
+        next_line: &mut usize,
+    ) -> Self {
+        let grid = term.grid();
+        let history_size = grid.history_size() as i32;
+        let total_lines = grid.total_lines().max(1);
+        let mut search_matches = Self {
+            pattern: pattern.to_string(),
+            ..Default::default()
+        };
+
+        // Lines may have been dropped from the top of a full scrollback since the last slice
+        let start_line = Line(*next_line as i32 - history_size).max(grid.topmost_line());
+        if start_line <= grid.bottommost_line() {
+            // Slices end with a complete line, so no match is split between them
+            let end_line = (start_line + (SLICE_LINES - 1)).min(grid.bottommost_line());
+            let start = Point::new(start_line, Column(0));
+            let end = term.line_search_right(Point::new(end_line, Column(0)));
+
+            let mut last_row_opt = None;
+            for regex_match in RegexIter::new(start, end, Direction::Right, term, regex) {
+                let point = regex_match.start();
+                let line = (point.line.0 + history_size) as usize;
+                search_matches.starts.push((line, point.column.0));
+
+                // Many matches end up on the same pixel of the scrollbar, only keep one tick per row
+                let row = line * TICK_ROWS / total_lines;
+                if last_row_opt != Some(row) {
+                    search_matches
+                        .ticks
+                        .push((row as f32 + 0.5) / TICK_ROWS as f32);
+                    last_row_opt = Some(row);
+                }
+            }
+
+            *next_line = (end.line.0 + history_size + 1) as usize;
+        }
+
+        search_matches.progress = (*next_line as f32 / total_lines as f32).min(1.0);
+        search_matches
+    }
+
+    /// Add the matches of the next slice
+    pub fn extend(&mut self, slice: Self) {
+        self.starts.extend(slice.starts);
+        for tick in slice.ticks {
+            if self.ticks.last() != Some(&tick) {
+                self.ticks.push(tick);
+            }
+        }
+        self.progress = slice.progress;
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.progress >= 1.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Start of the first match at or after a point, or at or before it when searching backwards,
+    /// wrapping around the grid
+    pub fn next_start(&self, point: Point, history_size: usize, forwards: bool) -> Option<Point> {
+        let line = usize::try_from(point.line.0 + history_size as i32).ok()?;
+        let key = (line, point.column.0);
+        let (line, column) = if forwards {
+            let index = self.starts.partition_point(|start| *start < key);
+            self.starts.get(index).or(self.starts.first())
+        } else {
+            let index = self.starts.partition_point(|start| *start <= key);
+            index
+                .checked_sub(1)
+                .and_then(|index| self.starts.get(index))
+                .or(self.starts.last())
+        }?;
+        Some(Point::new(
+            Line(*line as i32 - history_size as i32),
+            Column(*column),
+        ))
+    }
+
+    /// Position of a match among all matches, starting at 1
+    ///
+    /// Lines keep their distance from the top of the scrollback while it grows, so this stays
+    /// correct for new output until the scrollback is full.
+    pub fn position(&self, regex_match: &Match, history_size: usize) -> Option<usize> {
+        let point = regex_match.start();
+        let line = usize::try_from(point.line.0 + history_size as i32).ok()?;
+        self.starts
+            .binary_search(&(line, point.column.0))
+            .ok()
+            .map(|index| index + 1)
+    }
+
+    pub fn ticks(&self) -> &[f32] {
+        &self.ticks
+    }
+}
+
+/// Search the whole grid for matches until done or cancelled, passing the matches of each slice
+/// of lines to `f`
+///
+/// The terminal is only locked while searching a slice, so output is not blocked for long by
+/// a huge scrollback.
+pub fn search_matches<T>(
+    term: &FairMutex<Term<T>>,
+    pattern: &str,
+    regex: &mut RegexSearch,
+    cancel: &AtomicBool,
+    mut f: impl FnMut(SearchMatches),
+) {
+    let mut next_line = 0;
+    while !cancel.load(Ordering::Relaxed) {
+        let slice = {
+            let term = term.lock();
+            SearchMatches::search_slice(&*term, pattern, regex, &mut next_line)
+        };
+        let done = slice.is_done();
+        f(slice);
+        if done {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SLICE_LINES, SearchMatches, SearchOptions, search_matches};
+    use crate::terminal::Size;
+    use alacritty_terminal::{
+        Term,
+        event::VoidListener,
+        grid::Dimensions,
+        index::{Column, Direction, Line, Point, Side},
+        sync::FairMutex,
+        term::{Config, search::RegexSearch},
+        vte::ansi::Processor,
+    };
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn test_term(bytes: &[u8]) -> Term<VoidListener> {
+        let size = Size {
+            width: 20,
+            height: 4,
+            cell_width: 1.0,
+            cell_height: 1.0,
+        };
+        let mut term = Term::new(Config::default(), &size, VoidListener);
+        let mut parser: Processor = Processor::new();
+        parser.advance(&mut term, bytes);
+        term
+    }
+
+    fn search_all(term: &Term<VoidListener>, pattern: &str) -> SearchMatches {
+        let mut regex = RegexSearch::new(pattern).unwrap();
+        let mut matches = SearchMatches {
+            pattern: pattern.to_string(),
+            ..Default::default()
+        };
+        let mut next_line = 0;
+        while !matches.is_done() {
+            matches.extend(SearchMatches::search_slice(
+                term,
+                pattern,
+                &mut regex,
+                &mut next_line,
+            ));
+        }
+        matches
+    }
+
+    #[test]
+    fn counts_matches_in_scrollback() {
+        let mut output = String::new();
+        for i in 0..10 {
+            output.push_str(&format!("line {i} error\r\n"));
+        }
+        let term = test_term(output.as_bytes());
+        let history_size = term.grid().history_size();
+        assert!(history_size > 0);
+
+        let matches = search_all(&term, "error");
+        assert_eq!(matches.pattern, "error");
+        assert_eq!(matches.len(), 10);
+        assert_eq!(matches.ticks().len(), 10);
+        assert!(
+            matches
+                .ticks()
+                .windows(2)
+                .all(|ticks| ticks[0] < ticks[1] && ticks[1] < 1.0)
+        );
+
+        // The first line of the scrollback holds the first match
+        let mut regex = RegexSearch::new("error").unwrap();
+        let first = term
+            .search_next(
+                &mut regex,
+                Point::new(Line(-(history_size as i32)), Column(0)),
+                Direction::Right,
+                Side::Left,
+                None,
+            )
+            .unwrap();
+        assert_eq!(matches.position(&first, history_size), Some(1));
+
+        // Positions stay the same after more output scrolls the match up
+        let moved = Point::new(first.start().line - 2, first.start().column)
+            ..=Point::new(first.end().line - 2, first.end().column);
+        assert_eq!(matches.position(&moved, history_size + 2), Some(1));
+    }
+
+    #[test]
+    fn no_matches() {
+        let term = test_term(b"nothing to see\r\n");
+        let matches = search_all(&term, "error");
+        assert_eq!(matches.len(), 0);
+        assert!(matches.ticks().is_empty());
+    }
+
+    #[test]
+    fn search_options() {
+        let term = test_term(b"foo(bar) Foo\r\nfoobar [ERROR]\r\n");
+        let count =
+            |value: &str, options: SearchOptions| search_all(&term, &options.pattern(value)).len();
+
+        let literal = SearchOptions::default();
+        assert_eq!(count("foo(bar)", literal), 1);
+        assert_eq!(count("[ERROR]", literal), 1);
+        assert_eq!(count("foo", literal), 3);
+
+        let case_sensitive = SearchOptions {
+            case_sensitive: true,
+            ..literal
+        };
+        assert_eq!(count("foo", case_sensitive), 2);
+        assert_eq!(count("Foo", case_sensitive), 1);
+
+        let whole_word = SearchOptions {
+            whole_word: true,
+            ..literal
+        };
+        assert_eq!(count("foo", whole_word), 2);
+        assert_eq!(count("error", whole_word), 1);
+
+        let regex = SearchOptions {
+            regex: true,
+            ..literal
+        };
+        assert_eq!(count("fo+(bar)?", regex), 3);
+        assert!(regex.regex_search("foo(bar").is_err());
+        assert!(literal.regex_search("foo(bar").is_ok());
+    }
+
+    #[test]
+    fn searches_in_slices() {
+        let lines = SLICE_LINES + SLICE_LINES / 2;
+        let term = test_term("error\r\n".repeat(lines).as_bytes());
+        let term = FairMutex::new(term);
+        let mut regex = RegexSearch::new("error").unwrap();
+
+        let mut slices = Vec::new();
+        let cancel = AtomicBool::new(false);
+        search_matches(&term, "error", &mut regex, &cancel, |slice| {
+            slices.push(slice)
+        });
+        assert_eq!(slices.len(), 2);
+        assert!(slices[0].progress < 1.0);
+        assert!(slices[1].is_done());
+        let mut matches = SearchMatches::default();
+        for slice in slices {
+            matches.extend(slice);
+        }
+        assert_eq!(matches.len(), lines);
+
+        // Moving to the next match wraps around the grid
+        let history_size = term.lock().grid().history_size();
+        let top = Point::new(Line(-(history_size as i32)), Column(0));
+        let bottom = Point::new(Line(3), Column(19));
+        assert_eq!(matches.next_start(top, history_size, true), Some(top));
+        assert_eq!(matches.next_start(top, history_size, false), Some(top));
+        assert_eq!(
+            matches.next_start(Point::new(top.line, Column(1)), history_size, true),
+            Some(Point::new(top.line + 1, Column(0)))
+        );
+        assert_eq!(matches.next_start(bottom, history_size, true), Some(top));
+
+        // Cancelled searches stop before the next slice
+        let mut count = 0;
+        search_matches(&term, "error", &mut regex, &cancel, |_| {
+            count += 1;
+            cancel.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(count, 1);
+    }
+}
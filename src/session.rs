@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use alacritty_terminal::tty;
+use cosmic::widget::pane_grid;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{config::ProfileId, xdg};
+
+/// Layout of the splits and tabs of a window, saved to restore it later
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Session {
+    pub root: SessionPane,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum SessionPane {
+    Split {
+        axis: SessionAxis,
+        /// Share of the first pane, between 0 and 1
+        ratio: f32,
+        a: Box<SessionPane>,
+        b: Box<SessionPane>,
+    },
+    Tabs(SessionTabs),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum SessionAxis {
+    Horizontal,
+    Vertical,
+}
+
+impl From<pane_grid::Axis> for SessionAxis {
+    fn from(axis: pane_grid::Axis) -> Self {
+        match axis {
+            pane_grid::Axis::Horizontal => Self::Horizontal,
+            pane_grid::Axis::Vertical => Self::Vertical,
+        }
+    }
+}
+
+impl From<SessionAxis> for pane_grid::Axis {
+    fn from(axis: SessionAxis) -> Self {
+        match axis {
+            SessionAxis::Horizontal => Self::Horizontal,
+            SessionAxis::Vertical => Self::Vertical,
+        }
+    }
+}
+
+/// Tabs of a pane
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SessionTabs {
+    pub tabs: Vec<SessionTab>,
+    #[serde(default)]
+    pub active: usize,
+    #[serde(default)]
+    pub focused: bool,
+}
+
+/// How to open a tab, from a session or the command line
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SessionTab {
+    #[serde(default)]
+    pub profile_id: Option<ProfileId>,
+    #[serde(default)]
+    pub working_directory: Option<PathBuf>,
+    /// Title that is kept instead of the one set by programs
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Program and arguments, instead of the command of the profile or the shell
+    #[serde(default)]
+    pub command: Option<Vec<String>>,
+    /// Scrollback snapshot of the tab, restored instead of the newest one of its profile
+    #[serde(default)]
+    pub scrollback_snapshot: Option<PathBuf>,
+}
+
+impl SessionTab {
+    pub fn shell(&self) -> Option<tty::Shell> {
+        let (program, args) = self.command.as_ref()?.split_first()?;
+        Some(tty::Shell::new(program.clone(), args.to_vec()))
+    }
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let ron = fs::read_to_string(path)?;
+        Ok(ron::from_str(&ron)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write a temporary file first so a crash never leaves a partial session
+        let tmp_path = path.with_extension("ron.tmp");
+        fs::write(&tmp_path, ron)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Tabs of each pane, in the order panes are laid out
+    pub fn panes(&self) -> Vec<&SessionTabs> {
+        fn collect<'a>(pane: &'a SessionPane, panes: &mut Vec<&'a SessionTabs>) {
+            match pane {
+                SessionPane::Split { a, b, .. } => {
+                    collect(a, panes);
+                    collect(b, panes);
+                }
+                SessionPane::Tabs(tabs) => panes.push(tabs),
+            }
+        }
+        let mut panes = Vec::new();
+        collect(&self.root, &mut panes);
+        panes
+    }
+}
+
+/// Directory of the sessions saved by name
+pub fn sessions_dir() -> Option<PathBuf> {
+    Some(xdg::data_dir()?.join("sessions"))
+}
+
+/// Path of a session from its name, or the path itself if it has a directory
+pub fn session_path(name: &str) -> Option<PathBuf> {
+    if name.contains('/') {
+        return Some(PathBuf::from(name));
+    }
+    Some(sessions_dir()?.join(format!("{}.ron", name)))
+}
+
+/// Session saved when the window closes, restored on startup if enabled
+pub fn last_session_path() -> Option<PathBuf> {
+    Some(xdg::state_dir()?.join("last-session.ron"))
+}
+
+/// Names of the saved sessions, sorted
+pub fn session_names() -> io::Result<Vec<String>> {
+    let Some(dir) = sessions_dir() else {
+        return Ok(Vec::new());
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "ron")
+            && let Some(name) = path.file_stem().and_then(|stem| stem.to_str())
+        {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Session, SessionAxis, SessionPane, SessionTab, SessionTabs};
+    use crate::config::ProfileId;
+
+    #[test]
+    fn session_round_trip() {
+        let session = Session {
+            root: SessionPane::Split {
+                axis: SessionAxis::Vertical,
+                ratio: 0.25,
+                a: Box::new(SessionPane::Tabs(SessionTabs {
+                    tabs: vec![SessionTab {
+                        profile_id: Some(ProfileId(3)),
+                        working_directory: Some("/tmp".into()),
+                        title: Some("logs".to_string()),
+                        command: Some(vec!["tail".to_string(), "-f".to_string()]),
+                        scrollback_snapshot: Some("/state/scrollback/3-1-2-3.ansi".into()),
+                    }],
+                    active: 0,
+                    focused: false,
+                })),
+                b: Box::new(SessionPane::Tabs(SessionTabs {
+                    tabs: vec![SessionTab::default(), SessionTab::default()],
+                    active: 1,
+                    focused: true,
+                })),
+            },
+        };
+        let ron = ron::ser::to_string_pretty(&session, ron::ser::PrettyConfig::new()).unwrap();
+        assert_eq!(ron::from_str::<Session>(&ron).unwrap(), session);
+
+        let panes = session.panes();
+        assert_eq!(panes.len(), 2);
+        assert_eq!(panes[0].tabs[0].title.as_deref(), Some("logs"));
+        assert_eq!(panes[1].active, 1);
+    }
+
+    #[test]
+    fn session_defaults() {
+        let session: Session = ron::from_str("(root: Tabs((tabs: [()])))").unwrap();
+        assert_eq!(session.panes()[0].tabs, vec![SessionTab::default()]);
+    }
+}
@@ -0,0 +1,333 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{
+    env,
+    ffi::OsString,
+    fs::{self, DirBuilder, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+
+use crate::{config::SessionLogConfig, xdg};
+
+/// Log of a terminal, written by the PTY reader and started or stopped from its tab
+pub type SharedSessionLog = Arc<Mutex<Option<SessionLog>>>;
+
+/// Directory for logs when none is configured, in the XDG state directory
+pub fn default_directory() -> Option<PathBuf> {
+    Some(xdg::state_dir()?.join("logs"))
+}
+
+/// Path of a new log, from the file name template and the values of its placeholders
+///
+/// `{date}` and `{time}` are replaced by the UTC time of `unix_secs`, `{profile}` and `{title}`
+/// by the names with path separators replaced.
+pub fn log_path(
+    config: &SessionLogConfig,
+    unix_secs: u64,
+    profile: &str,
+    title: &str,
+) -> Option<PathBuf> {
+    let directory = if config.directory.is_empty() {
+        default_directory()?
+    } else {
+        match config.directory.strip_prefix("~/") {
+            Some(rest) => Path::new(&env::var_os("HOME")?).join(rest),
+            None => PathBuf::from(&config.directory),
+        }
+    };
+
+    let (year, month, day) = civil_from_days((unix_secs / 86400) as i64);
+    let secs = unix_secs % 86400;
+    let file_name = config
+        .file_name
+        .replace("{date}", &format!("{:04}-{:02}-{:02}", year, month, day))
+        .replace(
+            "{time}",
+            &format!("{:02}-{:02}-{:02}", secs / 3600, secs / 60 % 60, secs % 60),
+        )
+        .replace("{profile}", &file_name_part(profile))
+        .replace("{title}", &file_name_part(title));
+    Some(directory.join(file_name))
+}
+
+/// Longest placeholder value, file names of most file systems are limited to 255 bytes
+const MAX_FILE_NAME_PART_LEN: usize = 100;
+
+fn file_name_part(name: &str) -> String {
+    let mut part = String::new();
+    for c in name.trim().chars() {
+        if part.len() + c.len_utf8() > MAX_FILE_NAME_PART_LEN {
+            break;
+        }
+        part.push(if c == '/' || c.is_control() { '_' } else { c });
+    }
+    part
+}
+
+/// Year, month and day of a number of days since 1970-01-01, from Howard Hinnant's algorithms
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Path with a suffix after its file name, like `session.log.1`
+fn suffixed_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
+
+/// Output of a terminal written to a file, rotated when it grows too large
+pub struct SessionLog {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    keep_files: u32,
+    stripper_opt: Option<EscapeStripper>,
+    stripped: Vec<u8>,
+}
+
+impl SessionLog {
+    /// Create a log at the path, adding a number to the file name if it is taken so that
+    /// terminals never write to the same file
+    pub fn create(path: &Path, config: &SessionLogConfig) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            create_dir(parent)?;
+        }
+        let mut unique_path = path.to_path_buf();
+        let mut number = 1;
+        let file = loop {
+            match append_options().create_new(true).open(&unique_path) {
+                Ok(file) => break file,
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists && number < 1000 => {
+                    unique_path = unique_name(path, number);
+                    number += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        Ok(Self {
+            path: unique_path,
+            file,
+            size: 0,
+            max_size: config.max_size_mb.saturating_mul(1024 * 1024),
+            keep_files: config.keep_files,
+            stripper_opt: config.strip_escapes.then(EscapeStripper::default),
+            stripped: Vec::new(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let bytes = match &mut self.stripper_opt {
+            Some(stripper) => {
+                self.stripped.clear();
+                stripper.strip(bytes, &mut self.stripped);
+                &self.stripped[..]
+            }
+            None => bytes,
+        };
+        self.file.write_all(bytes)?;
+        self.size += bytes.len() as u64;
+        if self.max_size > 0 && self.size >= self.max_size {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    /// Move the log to `<path>.1`, shifting older logs up and dropping the oldest, and start a
+    /// new file. Renaming is atomic, so readers always see complete files.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep_files == 0 {
+            self.file.set_len(0)?;
+        } else {
+            for number in (1..self.keep_files).rev() {
+                let from = suffixed_path(&self.path, &format!(".{}", number));
+                if from.exists() {
+                    fs::rename(
+                        &from,
+                        suffixed_path(&self.path, &format!(".{}", number + 1)),
+                    )?;
+                }
+            }
+            fs::rename(&self.path, suffixed_path(&self.path, ".1"))?;
+            self.file = append_options().create(true).open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// Logs can contain passwords and other secrets typed or shown in the terminal, so only the
+/// user can read them
+fn append_options() -> OpenOptions {
+    let mut options = File::options();
+    options.append(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options
+}
+
+fn create_dir(path: &Path) -> io::Result<()> {
+    let mut builder = DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    builder.mode(0o700);
+    builder.create(path)
+}
+
+/// Path with a number before the extension, like `session-1.log`
+fn unique_name(path: &Path, number: u32) -> PathBuf {
+    let mut file_name = path.file_stem().map(OsString::from).unwrap_or_default();
+    file_name.push(format!("-{}", number));
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    path.with_file_name(file_name)
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum StripState {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+    /// OSC, DCS, SOS, PM and APC, ended by BEL or ST
+    String,
+}
+
+/// Removes escape sequences and control characters other than newlines and tabs, keeping the
+/// state between reads so sequences split across them are removed too
+#[derive(Debug, Default)]
+pub struct EscapeStripper {
+    state: StripState,
+}
+
+impl EscapeStripper {
+    pub fn strip(&mut self, input: &[u8], output: &mut Vec<u8>) {
+        for &byte in input {
+            self.state = match (self.state, byte) {
+                (_, 0x1b) => StripState::Escape,
+                (StripState::Ground, b'\n' | b'\t') => {
+                    output.push(byte);
+                    StripState::Ground
+                }
+                (StripState::Ground, 0x00..=0x1f | 0x7f) => StripState::Ground,
+                (StripState::Ground, _) => {
+                    output.push(byte);
+                    StripState::Ground
+                }
+                (StripState::Escape, b'[') => StripState::Csi,
+                (StripState::Escape, b']' | b'P' | b'X' | b'^' | b'_') => StripState::String,
+                // Intermediate bytes, like in ESC ( B
+                (StripState::Escape, 0x20..=0x2f) => StripState::Escape,
+                (StripState::Escape, _) => StripState::Ground,
+                (StripState::Csi, 0x40..=0x7e) => StripState::Ground,
+                (StripState::Csi, _) => StripState::Csi,
+                (StripState::String, 0x07) => StripState::Ground,
+                (StripState::String, _) => StripState::String,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EscapeStripper, SessionLog, civil_from_days, log_path};
+    use crate::config::SessionLogConfig;
+    use std::{env, fs, process};
+
+    #[test]
+    fn strip_escapes_across_reads() {
+        let mut stripper = EscapeStripper::default();
+        let mut output = Vec::new();
+        stripper.strip(b"\x1b[1;31mred\x1b[0m\r\n\x1b]0;ti", &mut output);
+        stripper.strip(
+            b"tle\x07ok\x1b]8;;http://x\x1b\\link\x1b(B\tend",
+            &mut output,
+        );
+        assert_eq!(output, b"red\noklink\tend");
+    }
+
+    #[test]
+    fn file_name_template() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+
+        let config = SessionLogConfig {
+            directory: "/logs".to_string(),
+            ..Default::default()
+        };
+        // 2024-02-29 13:05:09 UTC
+        let path = log_path(&config, 1709211909, "Default", "vim a/b").unwrap();
+        assert_eq!(
+            path.to_str(),
+            Some("/logs/2024-02-29_13-05-09_Default_vim a_b.log")
+        );
+
+        // Long titles are cut so the file name stays within the limits of file systems
+        let path = log_path(&config, 1709211909, "Default", &"é".repeat(300)).unwrap();
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        assert!(file_name.len() < 255);
+        assert!(file_name.ends_with(&format!("_{}.log", "é".repeat(50))));
+    }
+
+    #[test]
+    fn rotate_keeps_old_logs() {
+        let dir = env::temp_dir().join(format!("cosmic-term-session-log-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let config = SessionLogConfig {
+            max_size_mb: 1,
+            keep_files: 2,
+            ..Default::default()
+        };
+        let path = dir.join("test.log");
+        let mut log = SessionLog::create(&path, &config).unwrap();
+        // Paths in use get a number
+        let other = SessionLog::create(&path, &config).unwrap();
+        assert_eq!(other.path(), dir.join("test-1.log"));
+
+        let chunk = vec![b'a'; 1024 * 1024];
+        for _ in 0..3 {
+            log.write(&chunk).unwrap();
+        }
+        log.write(b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(
+            fs::metadata(dir.join("test.log.1")).unwrap().len(),
+            1024 * 1024
+        );
+        assert!(dir.join("test.log.2").exists());
+        assert!(!dir.join("test.log.3").exists());
+
+        // Only the user can read logs, including rotated ones
+        #[cfg(unix)]
+        {
+            use std::{os::unix::fs::PermissionsExt, path::Path};
+            let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&path), 0o600);
+            assert_eq!(mode(&dir.join("test.log.1")), 0o600);
+            assert_eq!(mode(&dir), 0o700);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,792 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//Implemented according to
+//https://gitlab.freedesktop.org/Per_Bothner/specifications/blob/master/proposals/semantic-prompts.md
+
+use alacritty_terminal::{
+    Term,
+    grid::Dimensions,
+    index::{Column, Line, Point},
+    term::cell::Hyperlink,
+};
+use std::{
+    mem,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// Marks are stored as a hyperlink with this prefix on the first cell written after them, so
+/// they stay attached to their line in the scrollback
+const MARK_URI_PREFIX: &str = "cosmic-term-mark:";
+
+/// Longer OSC sequences are passed through without looking at them
+const MAX_OSC_LEN: usize = 4096;
+
+/// OSC 133 marks that apply to a cell
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PromptMark {
+    /// `A`, start of the prompt
+    pub prompt_start: bool,
+    /// `B`, start of the command typed by the user
+    pub command_start: bool,
+    /// `C`, start of the command output
+    pub output_start: bool,
+    /// `D`, end of the command output
+    pub command_end: bool,
+    /// Exit status reported with `D`
+    pub exit_status: Option<i32>,
+}
+
+impl PromptMark {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn merge(&mut self, other: Self) {
+        self.prompt_start |= other.prompt_start;
+        self.command_start |= other.command_start;
+        self.output_start |= other.output_start;
+        if other.command_end {
+            self.command_end = true;
+            self.exit_status = other.exit_status;
+        }
+    }
+
+    fn to_uri(self) -> String {
+        let mut uri = MARK_URI_PREFIX.to_string();
+        for (set, kind) in [
+            (self.prompt_start, 'A'),
+            (self.command_start, 'B'),
+            (self.output_start, 'C'),
+            (self.command_end, 'D'),
+        ] {
+            if set {
+                uri.push(kind);
+            }
+        }
+        if let Some(exit_status) = self.exit_status {
+            uri.push_str(&exit_status.to_string());
+        }
+        uri
+    }
+
+    /// Returns the marks stored in the hyperlink of a cell
+    pub fn from_hyperlink(link: &Hyperlink) -> Option<Self> {
+        let kinds = link.uri().strip_prefix(MARK_URI_PREFIX)?;
+        let mut mark = Self::default();
+        for (i, kind) in kinds.char_indices() {
+            match kind {
+                'A' => mark.prompt_start = true,
+                'B' => mark.command_start = true,
+                'C' => mark.output_start = true,
+                'D' => {
+                    mark.command_end = true;
+                    mark.exit_status = kinds[i + 1..].parse().ok();
+                    break;
+                }
+                _ => {}
+            }
+        }
+        Some(mark)
+    }
+}
+
+/// Returns true if the hyperlink only stores marks and should not be opened
+pub fn is_mark(link: &Hyperlink) -> bool {
+    link.uri().starts_with(MARK_URI_PREFIX)
+}
+
+/// Marks of a line, from left to right
+fn line_marks<T>(term: &Term<T>, line: Line) -> impl Iterator<Item = (Point, PromptMark)> {
+    let row = &term.grid()[line];
+    (0..term.columns()).filter_map(move |column| {
+        let link = row[Column(column)].hyperlink()?;
+        let mark = PromptMark::from_hyperlink(&link)?;
+        Some((Point::new(line, Column(column)), mark))
+    })
+}
+
+/// Marks from the end of `line` up to the top of the scrollback, from bottom to top
+///
+/// Lines are only read as far as the marks are needed, so finding recent marks does not look
+/// at the whole scrollback.
+pub fn marks_rev<T>(term: &Term<T>, line: Line) -> impl Iterator<Item = (Point, PromptMark)> {
+    (term.topmost_line().0..=line.0)
+        .rev()
+        .flat_map(move |line| {
+            let marks: Vec<_> = line_marks(term, Line(line)).collect();
+            marks.into_iter().rev()
+        })
+}
+
+/// Line of the closest prompt before or after `line`
+pub fn prompt_line<T>(term: &Term<T>, line: Line, forwards: bool) -> Option<Line> {
+    let has_prompt = |line: &i32| line_marks(term, Line(*line)).any(|(_, mark)| mark.prompt_start);
+    if forwards {
+        (line.0 + 1..=term.bottommost_line().0)
+            .find(has_prompt)
+            .map(Line)
+    } else {
+        (term.topmost_line().0..line.0)
+            .rev()
+            .find(has_prompt)
+            .map(Line)
+    }
+}
+
+/// Start of the output of the last finished command and the cell after its end,
+/// or `None` if it did not print anything
+pub fn last_command_output<T>(term: &Term<T>) -> Option<(Point, Point)> {
+    let mut marks = marks_rev(term, term.bottommost_line());
+    let (end, end_mark) = marks.find(|(_, mark)| mark.command_end)?;
+    if end_mark.output_start {
+        return None;
+    }
+    // The output must start after the previous command ended
+    let (start, _) = marks
+        .take_while(|(_, mark)| !mark.command_end)
+        .find(|(_, mark)| mark.output_start)?;
+    Some((start, end))
+}
+
+/// Working directory reported by the shell with OSC 7
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReportedDirectory {
+    /// Host name from the URI, `None` if it was empty
+    pub host: Option<String>,
+    pub path: PathBuf,
+}
+
+impl ReportedDirectory {
+    /// Parses `file://host/path` and the `kitty-shell-cwd://host/path` variant that is not
+    /// percent-encoded
+    ///
+    /// Any program can report a directory, so hosts that are not plain names or addresses are
+    /// rejected before they can reach the arguments of ssh.
+    pub fn parse(uri: &str) -> Option<Self> {
+        let (rest, encoded) = if let Some(rest) = uri.strip_prefix("file://") {
+            (rest, true)
+        } else if let Some(rest) = uri.strip_prefix("kitty-shell-cwd://") {
+            (rest, false)
+        } else {
+            return None;
+        };
+        let path_i = rest.find('/')?;
+        let (host, path) = rest.split_at(path_i);
+        if !host.is_empty() && !valid_host(host) {
+            return None;
+        }
+        let path = if encoded {
+            percent_decode(path)?
+        } else {
+            path.as_bytes().to_vec()
+        };
+        Some(Self {
+            host: (!host.is_empty()).then(|| host.to_string()),
+            path: PathBuf::from(String::from_utf8_lossy(&path).into_owned()),
+        })
+    }
+
+    /// Returns true if the directory is on another host, like when the shell runs in ssh
+    pub fn is_remote(&self, local_host: &str) -> bool {
+        self.host.as_deref().is_some_and(|host| {
+            // Some shells report the fully qualified name
+            let host = host.split('.').next().unwrap_or(host);
+            let local_host = local_host.split('.').next().unwrap_or(local_host);
+            host != "localhost" && !host.eq_ignore_ascii_case(local_host)
+        })
+    }
+
+    /// Arguments for ssh to open a login shell in this directory on its host
+    pub fn ssh_args(&self) -> Option<Vec<String>> {
+        let host = self.host.as_deref()?;
+        // ssh takes IPv6 addresses without brackets
+        let host = host
+            .strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .unwrap_or(host);
+        let path = self.path.to_str()?;
+        let command = format!("cd {} && exec \"$SHELL\" -l", shlex::try_quote(path).ok()?);
+        Some(vec![
+            "-t".to_string(),
+            "--".to_string(),
+            host.to_string(),
+            command,
+        ])
+    }
+}
+
+/// Whether a host is a name or IPv4 address, or an IPv6 address in brackets
+fn valid_host(host: &str) -> bool {
+    if let Some(address) = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+    {
+        return !address.is_empty()
+            && address
+                .chars()
+                .all(|c| c.is_ascii_hexdigit() || c == ':' || c == '.');
+    }
+    !host.starts_with('-')
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+}
+
+fn percent_decode(value: &str) -> Option<Vec<u8>> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Some(decoded)
+}
+
+/// Events reported by the shell or applications that the terminal reacts to
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ShellEvent {
+    /// A command started with `C` finished with `D`
+    CommandFinished {
+        exit_status: Option<i32>,
+        duration: Duration,
+    },
+    /// Desktop notification requested with OSC 9 or OSC 777
+    Notify { title: Option<String>, body: String },
+    /// The working directory changed, reported with OSC 7
+    WorkingDirectory(ReportedDirectory),
+}
+
+#[derive(Default)]
+enum FilterState {
+    #[default]
+    Ground,
+    /// Remaining continuation bytes of a marked character
+    Utf8(usize),
+    Escape,
+    EscapeIntermediate,
+    Csi,
+    Osc(Vec<u8>),
+    OscEscape(Vec<u8>),
+    OscPassthrough,
+    /// DCS, SOS, PM and APC strings
+    String,
+}
+
+/// Removes OSC 133 sequences from the PTY stream, storing the marks on the next printed cell
+///
+/// Notification requests with OSC 9 and OSC 777 and working directory reports with OSC 7
+/// are removed as well.
+#[derive(Default)]
+pub struct ShellIntegrationFilter {
+    state: FilterState,
+    pending: PromptMark,
+    /// Hyperlink opened by the application, which is restored after a marked cell
+    hyperlink_opt: Option<Vec<u8>>,
+    /// When the running command printed `C`
+    command_start_opt: Option<Instant>,
+    /// Events found since they were last taken
+    pub events: Vec<ShellEvent>,
+}
+
+impl ShellIntegrationFilter {
+    pub fn advance(&mut self, input: &[u8], output: &mut Vec<u8>) {
+        let mut i = 0;
+        while i < input.len() {
+            let byte = input[i];
+            i += 1;
+            self.state = match mem::take(&mut self.state) {
+                FilterState::Ground => match byte {
+                    // Output of escape sequences is delayed until it is known they are passed on
+                    0x1B => FilterState::Escape,
+                    0x00..=0x1F | 0x7F..=0xBF => {
+                        output.push(byte);
+                        FilterState::Ground
+                    }
+                    _ => {
+                        let continuation = match byte {
+                            0xC0..=0xDF => 1,
+                            0xE0..=0xEF => 2,
+                            0xF0..=0xFF => 3,
+                            _ => 0,
+                        };
+                        if self.pending.is_empty() {
+                            output.push(byte);
+                            FilterState::Ground
+                        } else {
+                            output.extend_from_slice(b"\x1b]8;;");
+                            output.extend_from_slice(self.pending.to_uri().as_bytes());
+                            output.extend_from_slice(b"\x1b\\");
+                            output.push(byte);
+                            if continuation > 0 {
+                                FilterState::Utf8(continuation)
+                            } else {
+                                self.end_mark(output);
+                                FilterState::Ground
+                            }
+                        }
+                    }
+                },
+                FilterState::Utf8(remaining) => {
+                    if (0x80..=0xBF).contains(&byte) {
+                        output.push(byte);
+                        if remaining > 1 {
+                            FilterState::Utf8(remaining - 1)
+                        } else {
+                            self.end_mark(output);
+                            FilterState::Ground
+                        }
+                    } else {
+                        // Invalid UTF-8, process the byte normally
+                        self.end_mark(output);
+                        i -= 1;
+                        FilterState::Ground
+                    }
+                }
+                FilterState::Escape => match byte {
+                    b']' => FilterState::Osc(Vec::new()),
+                    0x1B => {
+                        output.push(0x1B);
+                        FilterState::Escape
+                    }
+                    _ => {
+                        output.extend_from_slice(&[0x1B, byte]);
+                        match byte {
+                            b'[' => FilterState::Csi,
+                            b'P' | b'X' | b'^' | b'_' => FilterState::String,
+                            0x20..=0x2F => FilterState::EscapeIntermediate,
+                            _ => FilterState::Ground,
+                        }
+                    }
+                },
+                FilterState::EscapeIntermediate => match byte {
+                    0x1B => FilterState::Escape,
+                    0x20..=0x2F => {
+                        output.push(byte);
+                        FilterState::EscapeIntermediate
+                    }
+                    _ => {
+                        output.push(byte);
+                        FilterState::Ground
+                    }
+                },
+                FilterState::Csi => match byte {
+                    0x1B => FilterState::Escape,
+                    0x18 | 0x1A | 0x40..=0x7E => {
+                        output.push(byte);
+                        FilterState::Ground
+                    }
+                    _ => {
+                        output.push(byte);
+                        FilterState::Csi
+                    }
+                },
+                FilterState::Osc(mut data) => match byte {
+                    0x07 => {
+                        self.osc(&data, b"\x07", output);
+                        FilterState::Ground
+                    }
+                    0x1B => FilterState::OscEscape(data),
+                    _ if data.len() >= MAX_OSC_LEN => {
+                        output.extend_from_slice(b"\x1b]");
+                        output.extend_from_slice(&data);
+                        output.push(byte);
+                        FilterState::OscPassthrough
+                    }
+                    _ => {
+                        data.push(byte);
+                        FilterState::Osc(data)
+                    }
+                },
+                FilterState::OscEscape(data) => {
+                    if byte == b'\\' {
+                        self.osc(&data, b"\x1b\\", output);
+                        FilterState::Ground
+                    } else {
+                        // Unterminated sequences are passed on as they were
+                        output.extend_from_slice(b"\x1b]");
+                        output.extend_from_slice(&data);
+                        i -= 1;
+                        FilterState::Escape
+                    }
+                }
+                FilterState::OscPassthrough => match byte {
+                    0x1B => FilterState::Escape,
+                    0x07 => {
+                        output.push(byte);
+                        FilterState::Ground
+                    }
+                    _ => {
+                        output.push(byte);
+                        FilterState::OscPassthrough
+                    }
+                },
+                FilterState::String => match byte {
+                    0x1B => FilterState::Escape,
+                    0x18 | 0x1A => {
+                        output.push(byte);
+                        FilterState::Ground
+                    }
+                    _ => {
+                        output.push(byte);
+                        FilterState::String
+                    }
+                },
+            };
+        }
+    }
+
+    fn end_mark(&mut self, output: &mut Vec<u8>) {
+        output.extend_from_slice(b"\x1b]8;;\x1b\\");
+        if let Some(hyperlink) = &self.hyperlink_opt {
+            output.extend_from_slice(hyperlink);
+        }
+        self.pending = PromptMark::default();
+    }
+
+    fn osc(&mut self, data: &[u8], terminator: &[u8], output: &mut Vec<u8>) {
+        if let Some(params) = data.strip_prefix(b"133;") {
+            let mut params = params.split(|&byte| byte == b';');
+            let mut mark = PromptMark::default();
+            match params.next() {
+                Some(b"A") => mark.prompt_start = true,
+                Some(b"B") => mark.command_start = true,
+                Some(b"C") => {
+                    mark.output_start = true;
+                    self.command_start_opt = Some(Instant::now());
+                }
+                Some(b"D") => {
+                    mark.command_end = true;
+                    mark.exit_status = params
+                        .next()
+                        .and_then(|param| std::str::from_utf8(param).ok())
+                        .and_then(|param| param.parse().ok());
+                    // Shells may send D for empty command lines, those did not run anything
+                    if let Some(command_start) = self.command_start_opt.take() {
+                        self.events.push(ShellEvent::CommandFinished {
+                            exit_status: mark.exit_status,
+                            duration: command_start.elapsed(),
+                        });
+                    }
+                }
+                _ => {
+                    log::debug!("unsupported OSC 133 sequence {:?}", data);
+                }
+            }
+            self.pending.merge(mark);
+            return;
+        }
+
+        if let Some(event) = notify_event(data) {
+            self.events.push(event);
+            return;
+        }
+
+        if let Some(uri) = data.strip_prefix(b"7;") {
+            match std::str::from_utf8(uri)
+                .ok()
+                .and_then(ReportedDirectory::parse)
+            {
+                Some(directory) => self.events.push(ShellEvent::WorkingDirectory(directory)),
+                None => log::debug!("unsupported OSC 7 sequence {:?}", data),
+            }
+            return;
+        }
+
+        let mut sequence = b"\x1b]".to_vec();
+        sequence.extend_from_slice(data);
+        sequence.extend_from_slice(terminator);
+        if let Some(params) = data.strip_prefix(b"8;") {
+            // Remember the open hyperlink, an empty URI closes it
+            let has_uri = params
+                .splitn(2, |&byte| byte == b';')
+                .nth(1)
+                .is_some_and(|uri| !uri.is_empty());
+            self.hyperlink_opt = has_uri.then(|| sequence.clone());
+        }
+        output.extend_from_slice(&sequence);
+    }
+}
+
+/// Parses `9;body` and `777;notify;title;body` notification requests
+fn notify_event(data: &[u8]) -> Option<ShellEvent> {
+    let data = std::str::from_utf8(data).ok()?;
+    if let Some(body) = data.strip_prefix("9;") {
+        // ConEmu uses OSC 9 with a numeric command for other features, like progress
+        if let Some((command, _)) = body.split_once(';')
+            && !command.is_empty()
+            && command.bytes().all(|byte| byte.is_ascii_digit())
+        {
+            return None;
+        }
+        return Some(ShellEvent::Notify {
+            title: None,
+            body: body.to_string(),
+        });
+    }
+    let params = data.strip_prefix("777;notify;")?;
+    let (title, body) = params.split_once(';').unwrap_or((params, ""));
+    Some(ShellEvent::Notify {
+        title: Some(title.to_string()),
+        body: body.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        PromptMark, ReportedDirectory, ShellEvent, ShellIntegrationFilter, last_command_output,
+        marks_rev, prompt_line,
+    };
+    use alacritty_terminal::{
+        Term,
+        event::VoidListener,
+        grid::Dimensions,
+        index::{Column, Line, Point},
+        term::Config,
+        vte::ansi::Processor,
+    };
+
+    use crate::terminal::Size;
+    use std::path::PathBuf;
+
+    fn filter(input: &[u8]) -> Vec<u8> {
+        let mut filter = ShellIntegrationFilter::default();
+        let mut output = Vec::new();
+        // Split the input to check that sequences spanning reads are handled
+        for chunk in input.chunks(3) {
+            filter.advance(chunk, &mut output);
+        }
+        output
+    }
+
+    fn term(input: &[u8]) -> Term<VoidListener> {
+        let size = Size {
+            width: 20,
+            height: 6,
+            cell_width: 1.0,
+            cell_height: 1.0,
+        };
+        let mut term = Term::new(Config::default(), &size, VoidListener);
+        let mut parser: Processor = Processor::new();
+        parser.advance(&mut term, &filter(input));
+        term
+    }
+
+    #[test]
+    fn marks_are_attached_to_the_next_printed_cell() {
+        let output = filter(b"\x1b]133;A\x07\x1b[1m$\x1b[0m ");
+        assert_eq!(
+            output,
+            b"\x1b[1m\x1b]8;;cosmic-term-mark:A\x1b\\$\x1b]8;;\x1b\\\x1b[0m "
+        );
+
+        // Multi-byte characters are not split
+        let output = filter("\x1b]133;D;1\x1b\\\x1b]133;A\x1b\\❯".as_bytes());
+        assert_eq!(
+            output,
+            "\x1b]8;;cosmic-term-mark:AD1\x1b\\❯\x1b]8;;\x1b\\".as_bytes()
+        );
+    }
+
+    #[test]
+    fn other_sequences_pass_through() {
+        let input: &[u8] = b"\x1b]0;title\x07\x1b(Bab\x1bP+q544e\x1b\\\x1b]2;x\x1b\\\xc3\xa9";
+        assert_eq!(filter(input), input);
+    }
+
+    #[test]
+    fn reports_finished_commands_and_notifications() {
+        let mut filter = ShellIntegrationFilter::default();
+        let mut output = Vec::new();
+        // D without a running command is ignored
+        filter.advance(b"\x1b]133;D;0\x07\x1b]133;C\x07make\r\n", &mut output);
+        assert!(filter.events.is_empty());
+        filter.advance(
+            b"\x1b]133;D;2\x07\x1b]9;done\x07\x1b]9;4;1;50\x07\x1b]777;notify;Build;ok\x1b\\",
+            &mut output,
+        );
+        let events: Vec<_> = filter
+            .events
+            .drain(..)
+            .map(|event| match event {
+                ShellEvent::CommandFinished { exit_status, .. } => (exit_status, None, None),
+                ShellEvent::Notify { title, body } => (None, title, Some(body)),
+            })
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (Some(2), None, None),
+                (None, None, Some("done".to_string())),
+                (None, Some("Build".to_string()), Some("ok".to_string())),
+            ]
+        );
+        // ConEmu progress is passed on
+        assert!(output.ends_with(b"\x1b]9;4;1;50\x07"));
+    }
+
+    #[test]
+    fn parses_reported_directories() {
+        let mut filter = ShellIntegrationFilter::default();
+        let mut output = Vec::new();
+        filter.advance(b"\x1b]7;file://laptop/home/me/my%20dir\x07$ ", &mut output);
+        assert_eq!(output, b"$ ");
+        let directory = ReportedDirectory {
+            host: Some("laptop".to_string()),
+            path: "/home/me/my dir".into(),
+        };
+        assert_eq!(
+            filter.events,
+            vec![ShellEvent::WorkingDirectory(directory.clone())]
+        );
+        assert!(!directory.is_remote("laptop"));
+        assert!(!directory.is_remote("LAPTOP.local"));
+        assert!(directory.is_remote("desktop"));
+
+        let directory = ReportedDirectory::parse("kitty-shell-cwd://server/srv/100%").unwrap();
+        assert_eq!(directory.path, PathBuf::from("/srv/100%"));
+        assert!(directory.is_remote("laptop"));
+        assert!(
+            !ReportedDirectory::parse("file:///tmp")
+                .unwrap()
+                .is_remote("laptop")
+        );
+        assert_eq!(ReportedDirectory::parse("file:///bad%2"), None);
+
+        let directory = ReportedDirectory::parse("file://server/srv/it's").unwrap();
+        let args = directory.ssh_args().unwrap();
+        assert_eq!(args[..3], ["-t", "--", "server"]);
+        // The remote shell must see the path as a single word
+        assert_eq!(
+            shlex::split(&args[3]).unwrap(),
+            vec!["cd", "/srv/it's", "&&", "exec", "$SHELL", "-l"]
+        );
+
+        let directory = ReportedDirectory::parse("file://[fe80::1]/tmp").unwrap();
+        assert_eq!(directory.ssh_args().unwrap()[..3], ["-t", "--", "fe80::1"]);
+    }
+
+    #[test]
+    fn hosts_that_could_be_ssh_options_are_rejected() {
+        assert_eq!(
+            ReportedDirectory::parse("file://-oProxyCommand=sh -c 'touch x'/tmp"),
+            None
+        );
+        assert_eq!(
+            ReportedDirectory::parse("file://-oProxyCommand=touch%20x/tmp"),
+            None
+        );
+        assert_eq!(ReportedDirectory::parse("file://-server/tmp"), None);
+        assert_eq!(ReportedDirectory::parse("file://ser ver/tmp"), None);
+        assert_eq!(ReportedDirectory::parse("file://[-oX]/tmp"), None);
+        assert!(ReportedDirectory::parse("file://my-server.lan/tmp").is_some());
+    }
+
+    #[test]
+    fn application_hyperlinks_are_restored() {
+        let output = filter(b"\x1b]8;;https://a\x07\x1b]133;C\x07ab");
+        assert_eq!(
+            output,
+            b"\x1b]8;;https://a\x07\x1b]8;;cosmic-term-mark:C\x1b\\a\x1b]8;;\x1b\\\x1b]8;;https://a\x07b"
+        );
+    }
+
+    #[test]
+    fn finds_prompts_and_command_output() {
+        let ls: &[u8] = b"\x1b]133;A\x07$ \x1b]133;B\x07ls\r\n\x1b]133;C\x07a\r\nb\r\n\
+              \x1b]133;D;0\x07\x1b]133;A\x07$ ";
+        let term = self::term(
+            &[
+                ls,
+                b"\x1b]133;B\x07true\r\n\x1b]133;C\x07\x1b]133;D;2\x07\x1b]133;A\x07$ ",
+            ]
+            .concat(),
+        );
+        let mut marks: Vec<_> = marks_rev(&term, term.bottommost_line()).collect();
+        marks.reverse();
+        let kinds: Vec<_> = marks
+            .iter()
+            .map(|(point, mark)| (point.line.0, point.column.0, *mark))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    0,
+                    0,
+                    PromptMark {
+                        prompt_start: true,
+                        ..Default::default()
+                    }
+                ),
+                (
+                    0,
+                    2,
+                    PromptMark {
+                        command_start: true,
+                        ..Default::default()
+                    }
+                ),
+                (
+                    1,
+                    0,
+                    PromptMark {
+                        output_start: true,
+                        ..Default::default()
+                    }
+                ),
+                (
+                    3,
+                    0,
+                    PromptMark {
+                        prompt_start: true,
+                        command_end: true,
+                        exit_status: Some(0),
+                        ..Default::default()
+                    }
+                ),
+                (
+                    3,
+                    2,
+                    PromptMark {
+                        command_start: true,
+                        ..Default::default()
+                    }
+                ),
+                (
+                    4,
+                    0,
+                    PromptMark {
+                        prompt_start: true,
+                        output_start: true,
+                        command_end: true,
+                        exit_status: Some(2),
+                        ..Default::default()
+                    }
+                ),
+            ]
+        );
+
+        assert_eq!(prompt_line(&term, Line(4), false), Some(Line(3)));
+        assert_eq!(prompt_line(&term, Line(0), true), Some(Line(3)));
+        assert_eq!(prompt_line(&term, Line(4), true), None);
+
+        // The last command did not print anything, so the output of ls is not returned either
+        assert_eq!(last_command_output(&term), None);
+        assert_eq!(
+            last_command_output(&self::term(ls)),
+            Some((
+                Point::new(Line(1), Column(0)),
+                Point::new(Line(3), Column(0))
+            ))
+        );
+    }
+}
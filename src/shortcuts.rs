@@ -8,7 +8,7 @@ use cosmic::{
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 
-use crate::{Action, fl};
+use crate::{Action, config::HintAction, fl, rich_text::ExportFormat};
 
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum ModifierName {
@@ -59,8 +59,16 @@ pub enum KeyBindAction {
     Disable,
     ClearScrollback,
     Copy,
+    CopyAsAnsi,
+    CopyAsHtml,
+    CopyAsText,
+    CopyLastCommandOutput,
+    CopyMode,
     CopyOrSigint,
     Find,
+    HintsCopy,
+    HintsOpen,
+    HintsPaste,
     PaneFocusDown,
     PaneFocusLeft,
     PaneFocusRight,
@@ -72,7 +80,14 @@ pub enum KeyBindAction {
     PastePrimary,
     #[cfg_attr(not(feature = "password_manager"), allow(dead_code))]
     PasswordManager,
+    PromptNext,
+    PromptPrevious,
+    SaveScrollbackAnsi,
+    SaveScrollbackHtml,
+    SaveScrollbackText,
     SelectAll,
+    SelectLastCommandOutput,
+    SessionLog,
     Settings,
     TabActivate0,
     TabActivate1,
@@ -101,8 +116,16 @@ impl KeyBindAction {
             Self::Disable => None,
             Self::ClearScrollback => Some(Action::ClearScrollback),
             Self::Copy => Some(Action::Copy),
+            Self::CopyAsAnsi => Some(Action::CopyAs(ExportFormat::Ansi)),
+            Self::CopyAsHtml => Some(Action::CopyAs(ExportFormat::Html)),
+            Self::CopyAsText => Some(Action::CopyAs(ExportFormat::Text)),
+            Self::CopyLastCommandOutput => Some(Action::CopyLastCommandOutput),
+            Self::CopyMode => Some(Action::CopyMode),
             Self::CopyOrSigint => Some(Action::CopyOrSigint),
             Self::Find => Some(Action::Find),
+            Self::HintsCopy => Some(Action::Hints(HintAction::Copy)),
+            Self::HintsOpen => Some(Action::Hints(HintAction::Open)),
+            Self::HintsPaste => Some(Action::Hints(HintAction::Paste)),
             Self::PaneFocusDown => Some(Action::PaneFocusDown),
             Self::PaneFocusLeft => Some(Action::PaneFocusLeft),
             Self::PaneFocusRight => Some(Action::PaneFocusRight),
@@ -112,7 +135,14 @@ impl KeyBindAction {
             Self::PaneToggleMaximized => Some(Action::PaneToggleMaximized),
             Self::Paste => Some(Action::Paste),
             Self::PastePrimary => Some(Action::PastePrimary),
+            Self::PromptNext => Some(Action::PromptNext),
+            Self::PromptPrevious => Some(Action::PromptPrevious),
+            Self::SaveScrollbackAnsi => Some(Action::SaveScrollback(ExportFormat::Ansi)),
+            Self::SaveScrollbackHtml => Some(Action::SaveScrollback(ExportFormat::Html)),
+            Self::SaveScrollbackText => Some(Action::SaveScrollback(ExportFormat::Text)),
             Self::SelectAll => Some(Action::SelectAll),
+            Self::SelectLastCommandOutput => Some(Action::SelectLastCommandOutput),
+            Self::SessionLog => Some(Action::SessionLog),
             Self::Settings => Some(Action::Settings),
             Self::TabActivate0 => Some(Action::TabActivate0),
             Self::TabActivate1 => Some(Action::TabActivate1),
@@ -261,8 +291,16 @@ pub fn action_label(action: KeyBindAction) -> String {
         KeyBindAction::Disable => fl!("disable"),
         KeyBindAction::ClearScrollback => fl!("clear-scrollback"),
         KeyBindAction::Copy => fl!("copy"),
+        KeyBindAction::CopyAsAnsi => fl!("copy-as-ansi"),
+        KeyBindAction::CopyAsHtml => fl!("copy-as-html"),
+        KeyBindAction::CopyAsText => fl!("copy-as-text"),
+        KeyBindAction::CopyLastCommandOutput => fl!("copy-last-command-output"),
+        KeyBindAction::CopyMode => fl!("copy-mode-toggle"),
         KeyBindAction::CopyOrSigint => fl!("copy-or-sigint"),
         KeyBindAction::Find => fl!("find"),
+        KeyBindAction::HintsCopy => fl!("hints-copy"),
+        KeyBindAction::HintsOpen => fl!("hints-open"),
+        KeyBindAction::HintsPaste => fl!("hints-paste"),
         KeyBindAction::PaneFocusDown => fl!("focus-pane-down"),
         KeyBindAction::PaneFocusLeft => fl!("focus-pane-left"),
         KeyBindAction::PaneFocusRight => fl!("focus-pane-right"),
@@ -273,7 +311,14 @@ pub fn action_label(action: KeyBindAction) -> String {
         KeyBindAction::Paste => fl!("paste"),
         KeyBindAction::PastePrimary => fl!("paste-primary"),
         KeyBindAction::PasswordManager => fl!("password-manager"),
+        KeyBindAction::PromptNext => fl!("next-prompt"),
+        KeyBindAction::PromptPrevious => fl!("previous-prompt"),
+        KeyBindAction::SaveScrollbackAnsi => fl!("save-scrollback-ansi"),
+        KeyBindAction::SaveScrollbackHtml => fl!("save-scrollback-html"),
+        KeyBindAction::SaveScrollbackText => fl!("save-scrollback-text"),
         KeyBindAction::SelectAll => fl!("select-all"),
+        KeyBindAction::SelectLastCommandOutput => fl!("select-last-command-output"),
+        KeyBindAction::SessionLog => fl!("session-log-toggle"),
         KeyBindAction::Settings => fl!("settings"),
         KeyBindAction::TabActivate0 => fl!("tab-activate", number = 1),
         KeyBindAction::TabActivate1 => fl!("tab-activate", number = 2),
@@ -309,10 +354,17 @@ pub fn shortcut_groups() -> Vec<ShortcutGroup> {
         actions: vec![
             KeyBindAction::SelectAll,
             KeyBindAction::Copy,
+            KeyBindAction::CopyAsHtml,
+            KeyBindAction::CopyAsText,
+            KeyBindAction::CopyAsAnsi,
             KeyBindAction::CopyOrSigint,
             KeyBindAction::Paste,
             KeyBindAction::PastePrimary,
             KeyBindAction::Find,
+            KeyBindAction::HintsCopy,
+            KeyBindAction::HintsOpen,
+            KeyBindAction::HintsPaste,
+            KeyBindAction::CopyMode,
         ],
     });
     groups.push(ShortcutGroup {
@@ -362,7 +414,22 @@ pub fn shortcut_groups() -> Vec<ShortcutGroup> {
             KeyBindAction::ZoomReset,
         ],
     });
-    let mut other_actions = vec![KeyBindAction::ClearScrollback];
+    groups.push(ShortcutGroup {
+        title: fl!("shortcut-group-shell-integration"),
+        actions: vec![
+            KeyBindAction::PromptPrevious,
+            KeyBindAction::PromptNext,
+            KeyBindAction::SelectLastCommandOutput,
+            KeyBindAction::CopyLastCommandOutput,
+        ],
+    });
+    let mut other_actions = vec![
+        KeyBindAction::ClearScrollback,
+        KeyBindAction::SaveScrollbackHtml,
+        KeyBindAction::SaveScrollbackText,
+        KeyBindAction::SaveScrollbackAnsi,
+        KeyBindAction::SessionLog,
+    ];
     #[cfg(feature = "password_manager")]
     other_actions.push(KeyBindAction::PasswordManager);
     groups.push(ShortcutGroup {
@@ -457,6 +524,15 @@ fn fallback_shortcuts() -> Shortcuts {
     bind!([Ctrl], ",", Settings);
     bind!([], "F11", ToggleFullscreen);
 
+    // Ctrl+Shift+Y, Ctrl+Shift+E and Ctrl+Shift+P label URLs, paths and hashes to copy,
+    // open or paste them
+    bind!([Ctrl, Shift], "Y", HintsCopy);
+    bind!([Ctrl, Shift], "E", HintsOpen);
+    bind!([Ctrl, Shift], "P", HintsPaste);
+
+    // Ctrl+Shift+Space selects text with the keyboard in copy mode
+    bind!([Ctrl, Shift], "Space", CopyMode);
+
     // Ctrl+Alt+D splits horizontally, Ctrl+Alt+R splits vertically, Ctrl+Shift+X maximizes split
     //TODO: Adjust bindings as desired by UX
     bind!([Ctrl, Alt], "d", PaneSplitHorizontal);
@@ -500,6 +576,10 @@ fn fallback_shortcuts() -> Shortcuts {
     // CTRL+Alt+L clears the scrollback.
     bind!([Ctrl, Alt], "L", ClearScrollback);
 
+    // Ctrl+Shift+PageUp and Ctrl+Shift+PageDown jump between shell prompts
+    bind!([Ctrl, Shift], "PageUp", PromptPrevious);
+    bind!([Ctrl, Shift], "PageDown", PromptNext);
+
     Shortcuts(shortcuts)
 }
 
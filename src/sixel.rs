@@ -0,0 +1,359 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//Implemented according to
+//https://vt100.net/docs/vt3xx-gp/chapter14.html
+
+use crate::graphics::{DecodedImage, MAX_IMAGE_DIMENSION};
+
+/// Default VT340 palette, in RGB percentages
+const VT340_PALETTE: [[u32; 3]; 16] = [
+    [0, 0, 0],
+    [20, 20, 80],
+    [80, 13, 13],
+    [20, 80, 20],
+    [80, 20, 80],
+    [20, 80, 80],
+    [80, 80, 20],
+    [53, 53, 53],
+    [26, 26, 26],
+    [33, 33, 60],
+    [60, 26, 26],
+    [33, 60, 33],
+    [60, 33, 60],
+    [33, 60, 60],
+    [60, 60, 33],
+    [80, 80, 80],
+];
+
+fn percent_to_u8(value: u32) -> u8 {
+    ((value.min(100) * 255 + 50) / 100) as u8
+}
+
+fn rgb_percent(r: u32, g: u32, b: u32) -> [u8; 4] {
+    [percent_to_u8(r), percent_to_u8(g), percent_to_u8(b), 0xFF]
+}
+
+/// Sixel HLS colors have blue at a hue of 0 degrees
+fn hls_percent(h: u32, l: u32, s: u32) -> [u8; 4] {
+    let h = ((h + 240) % 360) as f32;
+    let l = l.min(100) as f32 / 100.0;
+    let s = s.min(100) as f32 / 100.0;
+
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+    let m = l - c / 2.0;
+    let (r, g, b) = match h as u32 / 60 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let to_u8 = |value: f32| ((value + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    [to_u8(r), to_u8(g), to_u8(b), 0xFF]
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    Data,
+    Repeat,
+    Color,
+    Raster,
+}
+
+/// Decodes sixel data incrementally as it is read from the PTY
+///
+/// Pixels that are never drawn are left transparent. Images that would need more memory than
+/// the limit are dropped before their pixels are allocated.
+pub struct SixelDecoder {
+    state: State,
+    params: Vec<u32>,
+    palette: [[u8; 4]; 256],
+    color: [u8; 4],
+    repeat: u32,
+    x: u32,
+    y: u32,
+    /// Size of the drawn area
+    width: u32,
+    height: u32,
+    /// Size of the allocated pixels, grown as needed
+    buffer_width: u32,
+    buffer_height: u32,
+    pixels: Vec<u8>,
+    memory_limit: usize,
+    /// Set when the image grew past the memory limit
+    aborted: bool,
+}
+
+impl SixelDecoder {
+    pub fn new(memory_limit: usize) -> Self {
+        let mut palette = [[0, 0, 0, 0xFF]; 256];
+        for (color, [r, g, b]) in palette.iter_mut().zip(VT340_PALETTE) {
+            *color = rgb_percent(r, g, b);
+        }
+        Self {
+            state: State::Data,
+            params: Vec::new(),
+            palette,
+            color: palette[0],
+            repeat: 1,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            buffer_width: 0,
+            buffer_height: 0,
+            pixels: Vec::new(),
+            memory_limit,
+            aborted: false,
+        }
+    }
+
+    pub fn advance(&mut self, byte: u8) {
+        if self.state != State::Data {
+            match byte {
+                b'0'..=b'9' => {
+                    if self.params.is_empty() {
+                        self.params.push(0);
+                    }
+                    let param = self.params.last_mut().unwrap();
+                    *param = param
+                        .saturating_mul(10)
+                        .saturating_add(u32::from(byte - b'0'));
+                    return;
+                }
+                b';' => {
+                    if self.params.is_empty() {
+                        self.params.push(0);
+                    }
+                    self.params.push(0);
+                    return;
+                }
+                _ => self.finish_command(),
+            }
+        }
+
+        match byte {
+            b'?'..=b'~' => {
+                let repeat = self.repeat;
+                self.repeat = 1;
+                self.draw(byte - b'?', repeat);
+            }
+            b'!' => self.state = State::Repeat,
+            b'#' => self.state = State::Color,
+            b'"' => self.state = State::Raster,
+            b'$' => self.x = 0,
+            b'-' => {
+                self.x = 0;
+                self.y = self.y.saturating_add(6);
+            }
+            _ => {}
+        }
+    }
+
+    fn finish_command(&mut self) {
+        let param = |i: usize| self.params.get(i).copied().unwrap_or(0);
+        match self.state {
+            State::Data => {}
+            State::Repeat => self.repeat = param(0).max(1),
+            State::Color => {
+                let index = (param(0) & 0xFF) as usize;
+                if self.params.len() >= 5 {
+                    self.palette[index] = match param(1) {
+                        1 => hls_percent(param(2), param(3), param(4)),
+                        _ => rgb_percent(param(2), param(3), param(4)),
+                    };
+                }
+                self.color = self.palette[index];
+            }
+            State::Raster => {
+                // Pan;Pad;Ph;Pv, the aspect ratio is assumed to be 1:1
+                let width = param(2).min(MAX_IMAGE_DIMENSION);
+                let height = param(3).min(MAX_IMAGE_DIMENSION);
+                if width > 0 && height > 0 && self.reserve(width, height) {
+                    self.width = self.width.max(width);
+                    self.height = self.height.max(height);
+                }
+            }
+        }
+        self.state = State::Data;
+        self.params.clear();
+    }
+
+    /// Grow the pixels to fit the size, returns false if the image is over the memory limit
+    fn reserve(&mut self, width: u32, height: u32) -> bool {
+        if self.aborted {
+            return false;
+        }
+        if width <= self.buffer_width && height <= self.buffer_height {
+            return true;
+        }
+        let width = width.max(self.buffer_width);
+        let height = height.max(self.buffer_height);
+        let memory = |width: u32, height: u32| width as usize * height as usize * 4;
+        if memory(width, height) > self.memory_limit {
+            log::warn!(
+                "dropping sixel image of at least {}x{} that exceeds the memory limit",
+                width,
+                height
+            );
+            self.aborted = true;
+            self.pixels = Vec::new();
+            return false;
+        }
+        // Grow geometrically to avoid copying for every new column, within the memory limit
+        let mut buffer_width = width
+            .max(self.buffer_width.saturating_mul(2))
+            .min(MAX_IMAGE_DIMENSION);
+        let mut buffer_height = height
+            .max(self.buffer_height.saturating_mul(2))
+            .min(MAX_IMAGE_DIMENSION);
+        if memory(buffer_width, buffer_height) > self.memory_limit {
+            buffer_width = width;
+            buffer_height = height;
+        }
+        let mut pixels = vec![0; buffer_width as usize * buffer_height as usize * 4];
+        let old_stride = self.buffer_width as usize * 4;
+        let new_stride = buffer_width as usize * 4;
+        for row in 0..self.buffer_height as usize {
+            pixels[row * new_stride..row * new_stride + old_stride]
+                .copy_from_slice(&self.pixels[row * old_stride..(row + 1) * old_stride]);
+        }
+        self.pixels = pixels;
+        self.buffer_width = buffer_width;
+        self.buffer_height = buffer_height;
+        true
+    }
+
+    fn draw(&mut self, sixel: u8, repeat: u32) {
+        let x = self.x;
+        let end_x = x.saturating_add(repeat).min(MAX_IMAGE_DIMENSION);
+        self.x = x.saturating_add(repeat);
+        if sixel == 0 || x >= end_x || self.y >= MAX_IMAGE_DIMENSION {
+            return;
+        }
+        let end_y = (self.y + 6).min(MAX_IMAGE_DIMENSION);
+        if !self.reserve(end_x, end_y) {
+            return;
+        }
+        self.width = self.width.max(end_x);
+        self.height = self.height.max(end_y);
+
+        let stride = self.buffer_width as usize * 4;
+        for y in self.y..end_y {
+            if sixel & (1 << (y - self.y)) == 0 {
+                continue;
+            }
+            let row = y as usize * stride;
+            for x in x..end_x {
+                let i = row + x as usize * 4;
+                self.pixels[i..i + 4].copy_from_slice(&self.color);
+            }
+        }
+    }
+
+    /// Returns the decoded image, or `None` if nothing was drawn
+    pub fn finish(mut self) -> Option<DecodedImage> {
+        self.finish_command();
+        if self.aborted || self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let stride = self.buffer_width as usize * 4;
+        let row_len = self.width as usize * 4;
+        let mut pixels = Vec::with_capacity(row_len * self.height as usize);
+        for row in 0..self.height as usize {
+            pixels.extend_from_slice(&self.pixels[row * stride..row * stride + row_len]);
+        }
+        Some(DecodedImage {
+            width: self.width,
+            height: self.height,
+            pixels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SixelDecoder;
+
+    fn decode(data: &[u8]) -> Option<(u32, u32, Vec<u8>)> {
+        let mut decoder = SixelDecoder::new(usize::MAX);
+        for &byte in data {
+            decoder.advance(byte);
+        }
+        decoder
+            .finish()
+            .map(|image| (image.width, image.height, image.pixels))
+    }
+
+    fn pixel(pixels: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let i = (y * width + x) as usize * 4;
+        pixels[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn decodes_colors_repeats_and_bands() {
+        // Define red and green, draw a full red sixel twice, then a green band below
+        let (width, height, pixels) = decode(b"#1;2;100;0;0#2;2;0;100;0#1!2~-#2~").unwrap();
+        assert_eq!((width, height), (2, 12));
+        assert_eq!(pixel(&pixels, width, 0, 0), [0xFF, 0, 0, 0xFF]);
+        assert_eq!(pixel(&pixels, width, 1, 5), [0xFF, 0, 0, 0xFF]);
+        assert_eq!(pixel(&pixels, width, 0, 6), [0, 0xFF, 0, 0xFF]);
+        // Pixels that are never drawn stay transparent
+        assert_eq!(pixel(&pixels, width, 1, 6), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn carriage_return_overdraws_the_same_band() {
+        // The first sixel sets the top pixel, the second one after $ the bottom pixel
+        let (width, height, pixels) = decode(b"#1;2;0;0;100@$#2;2;100;100;100_").unwrap();
+        assert_eq!((width, height), (1, 6));
+        assert_eq!(pixel(&pixels, width, 0, 0), [0, 0, 0xFF, 0xFF]);
+        assert_eq!(pixel(&pixels, width, 0, 5), [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(pixel(&pixels, width, 0, 2), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn raster_attributes_set_the_size() {
+        let (width, height, _) = decode(b"\"1;1;4;3#0~").unwrap();
+        assert_eq!((width, height), (4, 6));
+        assert!(decode(b"#1;2;100;0;0").is_none());
+    }
+
+    #[test]
+    fn images_over_the_memory_limit_are_dropped() {
+        // 100x100 pixels need 40000 bytes
+        let mut decoder = SixelDecoder::new(40_000);
+        for &byte in b"\"1;1;100;100#1~" {
+            decoder.advance(byte);
+        }
+        assert!(!decoder.aborted);
+        assert!(decoder.pixels.len() <= 40_000);
+
+        let mut decoder = SixelDecoder::new(40_000);
+        for &byte in b"\"1;1;10000;10000#1~-~" {
+            decoder.advance(byte);
+        }
+        assert!(decoder.aborted);
+        assert!(decoder.pixels.is_empty());
+        assert!(decoder.finish().is_none());
+
+        // Drawing past the limit without raster attributes
+        let mut decoder = SixelDecoder::new(40_000);
+        for &byte in b"#1!200~" {
+            decoder.advance(byte);
+        }
+        for _ in 0..20 {
+            decoder.advance(b'-');
+            decoder.advance(b'~');
+        }
+        assert!(decoder.finish().is_none());
+    }
+
+    #[test]
+    fn hls_hue_starts_at_blue() {
+        let (width, _, pixels) = decode(b"#1;1;0;50;100~").unwrap();
+        assert_eq!(pixel(&pixels, width, 0, 0), [0, 0, 0xFF, 0xFF]);
+    }
+}
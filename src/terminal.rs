@@ -2,19 +2,20 @@ use alacritty_terminal::{
     Term,
     event::{Event, EventListener, Notify, OnResize, WindowSize},
     event_loop::{EventLoop, Msg, Notifier},
-    grid::Dimensions,
+    grid::{Dimensions, Row},
     index::{Boundary, Column, Direction, Line, Point, Side},
-    selection::{Selection, SelectionType},
+    selection::{Selection, SelectionRange, SelectionType},
     sync::FairMutex,
     term::{
-        Config, TermDamage, TermMode,
-        cell::Flags,
+        ClipboardType, Config, TermDamage, TermMode,
+        cell::{Cell, Flags, LineLength},
         color::{self, Colors},
-        search::RegexSearch,
+        search::{Match, RegexIter, RegexSearch},
         viewport_to_point,
     },
     tty::{self, Options},
-    vte::ansi::{Color, CursorShape, NamedColor, Rgb},
+    vi_mode::ViMotion,
+    vte::ansi::{Color, CursorShape, CursorStyle, NamedColor, Processor, Rgb},
 };
 use cosmic::{
     iced::{advanced::graphics::text::font_system, mouse::ScrollDelta},
@@ -26,23 +27,40 @@ use cosmic_text::{
 use indexmap::IndexSet;
 use std::{
     borrow::Cow,
+    cmp::Reverse,
     collections::HashMap,
-    fs, io, mem,
+    fmt, fs, io, mem,
     path::PathBuf,
     sync::{
-        Arc, Mutex, Weak,
-        atomic::{AtomicU32, Ordering},
+        Arc, LazyLock, Mutex, Weak,
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
     },
-    time::Instant,
+    thread,
+    time::{Duration, Instant},
 };
 use tokio::sync::mpsc;
 
 pub use alacritty_terminal::grid::Scroll as TerminalScroll;
 
 use crate::{
-    config::{ColorSchemeKind, Config as AppConfig, ProfileId},
+    bell::BellLimiter,
+    config::{
+        self, ColorSchemeKind, Config as AppConfig, CopyConfig, HintAction, HintConfig, ProfileId,
+        ScrollbackLines, SmartSelectionRule,
+    },
+    copy_mode::CopyModeCommand,
+    graphics::{Graphics, ImageCell, PLACEHOLDER},
+    hints::{HintInput, Hints},
     menu::MenuState,
     mouse_reporter::MouseReporter,
+    notify::NotificationLimiter,
+    pty::FilterPty,
+    rich_text::{RichText, TextRun},
+    scrollback_snapshot,
+    search::{self, SearchMatches, SearchOptions},
+    session::{Session, SessionPane, SessionTab, SessionTabs},
+    session_log::{SessionLog, SharedSessionLog},
+    shell_integration::{self, PromptMark, ReportedDirectory, ShellEvent},
 };
 
 /// Minimum contrast between a fixed cursor color and the cell's background.
@@ -54,11 +72,16 @@ pub const MIN_CURSOR_CONTRAST: f64 = 1.5;
 /// A regex expression can start or end outside the visible screen. Therefore, without this constant, some regular expressions would not match at the top and bottom.
 pub const MAX_SEARCH_LINES: usize = 100;
 
+/// Maximum number of lines searched for the next match of the find bar while holding the
+/// terminal lock. Matches further away are found by the background search.
+const SEARCH_NEARBY_LINES: usize = 1000;
+
 /// https://github.com/alacritty/alacritty/blob/4a7728bf7fac06a35f27f6c4f31e0d9214e5152b/alacritty/src/config/ui_config.rs#L36-L39
+pub const URL_REGEX: &str = "(ipfs:|ipns:|magnet:|mailto:|gemini://|gopher://|https://|http://|news:|file:|git://|ssh:|ftp://)\
+                             [^\u{0000}-\u{001F}\u{007F}-\u{009F}<>\"\\s{-}\\^⟨⟩`]+";
+
 fn url_regex_search() -> RegexSearch {
-    let url_regex = "(ipfs:|ipns:|magnet:|mailto:|gemini://|gopher://|https://|http://|news:|file:|git://|ssh:|ftp://)\
-                         [^\u{0000}-\u{001F}\u{007F}-\u{009F}<>\"\\s{-}\\^⟨⟩`]+";
-    RegexSearch::new(url_regex).unwrap()
+    RegexSearch::new(URL_REGEX).unwrap()
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -122,8 +145,77 @@ fn as_dim(mut color: Color) -> Color {
     color
 }
 
+static LOCAL_HOST: LazyLock<String> =
+    LazyLock::new(|| gethostname::gethostname().to_string_lossy().into_owned());
+
+/// Host name used to tell if reported working directories are remote
+pub fn local_host() -> &'static str {
+    &LOCAL_HOST
+}
+
+/// Time between cursor blink phases
+pub const CURSOR_BLINK_INTERVAL: Duration = Duration::from_millis(530);
+
+fn cursor_style(app_config: &AppConfig, profile_id_opt: Option<ProfileId>) -> CursorStyle {
+    let (shape, blinking) = app_config.cursor_style(profile_id_opt);
+    CursorStyle {
+        shape: match shape {
+            config::CursorShape::Block => CursorShape::Block,
+            config::CursorShape::Underline => CursorShape::Underline,
+            config::CursorShape::Beam => CursorShape::Beam,
+        },
+        blinking,
+    }
+}
+
+/// Number of history lines to keep, unlimited scrollback keeps as many lines of the current
+/// width as fit in the memory limit
+fn scrolling_history(
+    scrollback_lines: ScrollbackLines,
+    memory_limit_mb: u32,
+    columns: usize,
+) -> usize {
+    match scrollback_lines {
+        ScrollbackLines::Limited(lines) => lines as usize,
+        ScrollbackLines::Unlimited => {
+            let memory_limit = memory_limit_mb as usize * 1024 * 1024;
+            memory_limit / (columns.max(1) * mem::size_of::<Cell>())
+        }
+    }
+}
+
+/// New history size, if it changed. History lines are as wide as the terminal, so the size is
+/// computed for the widest the terminal has been, which stays within the memory limit once it
+/// is that wide again without dropping lines when it gets narrower and wider again
+fn scrolling_history_update(
+    current: usize,
+    scrollback_lines: ScrollbackLines,
+    memory_limit_mb: u32,
+    widest_columns: usize,
+) -> Option<usize> {
+    let scrolling_history = scrolling_history(scrollback_lines, memory_limit_mb, widest_columns);
+    if scrolling_history == current {
+        None
+    } else {
+        Some(scrolling_history)
+    }
+}
+
 pub static WINDOW_BG_COLOR: AtomicU32 = AtomicU32::new(0xFF000000);
 
+fn smart_selection_regexes(rules: &[SmartSelectionRule]) -> Vec<RegexSearch> {
+    rules
+        .iter()
+        .filter_map(|rule| match RegexSearch::new(&rule.regex) {
+            Ok(regex) => Some(regex),
+            Err(err) => {
+                log::warn!("invalid smart selection rule {:?}: {}", rule.name, err);
+                None
+            }
+        })
+        .collect()
+}
+
 fn convert_color(colors: &Colors, color: Color) -> cosmic_text::Color {
     let rgb = match color {
         Color::Named(named_color) => match colors[named_color] {
@@ -153,6 +245,16 @@ fn convert_color(colors: &Colors, color: Color) -> cosmic_text::Color {
 
 type TabModel = segmented_button::Model<segmented_button::SingleSelect>;
 
+fn collect_panes(node: &pane_grid::Node, panes: &mut Vec<pane_grid::Pane>) {
+    match node {
+        pane_grid::Node::Split { a, b, .. } => {
+            collect_panes(a, panes);
+            collect_panes(b, panes);
+        }
+        pane_grid::Node::Pane(pane) => panes.push(*pane),
+    }
+}
+
 pub struct TerminalPaneGrid {
     pub panes: pane_grid::State<TabModel>,
     pub panes_created: usize,
@@ -185,6 +287,91 @@ impl TerminalPaneGrid {
         self.focus
     }
 
+    /// Panes laid out like a session, without tabs, and the panes in the order of
+    /// [`Session::panes`]
+    pub fn from_session(session: &Session) -> (Self, Vec<pane_grid::Pane>) {
+        fn configuration(pane: &SessionPane) -> pane_grid::Configuration<TabModel> {
+            match pane {
+                SessionPane::Split { axis, ratio, a, b } => pane_grid::Configuration::Split {
+                    axis: (*axis).into(),
+                    ratio: ratio.clamp(0.05, 0.95),
+                    a: Box::new(configuration(a)),
+                    b: Box::new(configuration(b)),
+                },
+                SessionPane::Tabs(_) => pane_grid::Configuration::Pane(TabModel::default()),
+            }
+        }
+        let state = pane_grid::State::with_configuration(configuration(&session.root));
+        let mut panes = Vec::new();
+        collect_panes(state.layout(), &mut panes);
+        let focus = session
+            .panes()
+            .iter()
+            .zip(&panes)
+            .find(|(session_tabs, _)| session_tabs.focused)
+            .map_or(panes[0], |(_, pane)| *pane);
+        (
+            Self {
+                panes: state,
+                panes_created: panes.len(),
+                focus,
+            },
+            panes,
+        )
+    }
+
+    /// Panes in the order they are laid out, from the top left
+    pub fn layout_panes(&self) -> Vec<pane_grid::Pane> {
+        let mut panes = Vec::new();
+        collect_panes(self.panes.layout(), &mut panes);
+        panes
+    }
+
+    /// Layout of the panes with the tabs in each, to save as a session
+    pub fn session(&self) -> Session {
+        Session {
+            root: self.session_pane(self.panes.layout()),
+        }
+    }
+
+    fn session_pane(&self, node: &pane_grid::Node) -> SessionPane {
+        match node {
+            pane_grid::Node::Split {
+                axis, ratio, a, b, ..
+            } => SessionPane::Split {
+                axis: (*axis).into(),
+                ratio: *ratio,
+                a: Box::new(self.session_pane(a)),
+                b: Box::new(self.session_pane(b)),
+            },
+            pane_grid::Node::Pane(pane) => {
+                let mut session_tabs = SessionTabs {
+                    focused: *pane == self.focus,
+                    ..SessionTabs::default()
+                };
+                if let Some(tab_model) = self.panes.get(*pane) {
+                    for entity in tab_model.iter() {
+                        let Some(terminal) = tab_model.data::<Mutex<Terminal>>(entity) else {
+                            continue;
+                        };
+                        if entity == tab_model.active() {
+                            session_tabs.active = session_tabs.tabs.len();
+                        }
+                        let terminal = terminal.lock().unwrap();
+                        session_tabs.tabs.push(SessionTab {
+                            profile_id: terminal.profile_id_opt,
+                            working_directory: terminal.working_directory(),
+                            title: terminal.tab_title_override.clone(),
+                            command: terminal.command_opt.clone(),
+                            scrollback_snapshot: terminal.scrollback_snapshot_path.clone(),
+                        });
+                    }
+                }
+                SessionPane::Tabs(session_tabs)
+            }
+        }
+    }
+
     pub fn update_terminal_focus(&self) {
         for (pane, tab_model) in self.panes.panes.iter() {
             let entity = tab_model.active();
@@ -236,29 +423,690 @@ impl Metadata {
     }
 }
 
+/// LEFT‑TO‑RIGHT ISOLATE character.
+/// This will be added to the beginning of lines to force the shaper to treat detected RTL
+/// lines as LTR. RTL text would still be rendered correctly. But this fixes the wrong
+/// behavior of it being aligned to the right.
+const LRI: char = '\u{2066}';
+
+/// Partial updates keep the metadata of untouched lines, so the set can grow over time.
+/// Once it is this large, the next update rebuilds all lines to start over.
+const MAX_METADATA_ENTRIES: usize = 4096;
+
+/// Viewport lines that have to be rebuilt from the grid
+#[derive(Debug, PartialEq, Eq)]
+enum LineDamage {
+    Full,
+    /// Damaged viewport lines, in ascending order
+    Partial(Vec<usize>),
+}
+
+impl LineDamage {
+    /// Take the damage accumulated by the terminal since the last call
+    fn take<T>(term: &mut Term<T>) -> Self {
+        let damage = match term.damage() {
+            TermDamage::Full => Self::Full,
+            TermDamage::Partial(damage_lines) => {
+                Self::Partial(damage_lines.map(|bounds| bounds.line).collect())
+            }
+        };
+        term.reset_damage();
+        damage
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Self::Full => false,
+            Self::Partial(lines) => lines.is_empty(),
+        }
+    }
+}
+
+/// State outside of the grid that changes how cells are rendered
+#[derive(Clone, Debug, PartialEq)]
+struct RenderState {
+    selection: Option<SelectionRange>,
+    cursor_shape: CursorShape,
+    active_regex_match: Option<Match>,
+    active_hyperlink_id: Option<String>,
+    /// Visible matches of the find bar query
+    search_matches: Vec<Match>,
+    /// Match of the find bar query that was last moved to
+    search_match_opt: Option<Match>,
+    /// Cursor of copy mode, drawn instead of the terminal cursor
+    vi_cursor_opt: Option<Point>,
+    is_focused: bool,
+}
+
+impl RenderState {
+    /// Whether the states only differ in the cursor shape, which blinking toggles
+    fn eq_except_cursor_shape(&self, other: &Self) -> bool {
+        let Self {
+            selection,
+            cursor_shape: _,
+            active_regex_match,
+            active_hyperlink_id,
+            search_matches,
+            search_match_opt,
+            vi_cursor_opt,
+            is_focused,
+        } = self;
+        *selection == other.selection
+            && *active_regex_match == other.active_regex_match
+            && *active_hyperlink_id == other.active_hyperlink_id
+            && *search_matches == other.search_matches
+            && *search_match_opt == other.search_match_opt
+            && *vi_cursor_opt == other.vi_cursor_opt
+            && *is_focused == other.is_focused
+    }
+}
+
+/// Converts grid lines into buffer lines
+struct LineBuilder<'a> {
+    colors: &'a Colors,
+    default_attrs: &'a Attrs<'static>,
+    metadata_set: &'a mut IndexSet<Metadata>,
+    /// Image placeholder cells of each viewport line
+    image_lines: &'a mut Vec<Vec<ImageCell>>,
+    /// Shell integration marks of each viewport line
+    prompt_marks: &'a mut Vec<PromptMark>,
+    render_state: &'a RenderState,
+    /// Cursor of the terminal and the colors set by its programs
+    cursor_point: Point,
+    term_colors: &'a Colors,
+    bold_font_weight: Weight,
+    dim_font_weight: Weight,
+    use_bright_bold: bool,
+}
+
+impl LineBuilder<'_> {
+    /// Rebuild the damaged lines of `buffer`, leaving all other lines untouched
+    fn update_buffer<T>(&mut self, term: &Term<T>, buffer: &mut Buffer, damage: &LineDamage) {
+        let screen_lines = term.screen_lines();
+        let lines: Vec<usize> = match damage {
+            LineDamage::Full => {
+                if buffer.lines.len() != screen_lines {
+                    buffer.lines.resize_with(screen_lines, || {
+                        BufferLine::new(
+                            "",
+                            LineEnding::default(),
+                            AttrsList::new(self.default_attrs),
+                            Shaping::Advanced,
+                        )
+                    });
+                    buffer.set_redraw(true);
+                }
+                self.image_lines.resize_with(screen_lines, Vec::new);
+                self.prompt_marks
+                    .resize(screen_lines, PromptMark::default());
+                (0..screen_lines).collect()
+            }
+            LineDamage::Partial(lines) => lines.clone(),
+        };
+
+        let display_offset = term.grid().display_offset() as i32;
+        let mut text = String::new();
+        let mut attrs_list = AttrsList::new(self.default_attrs);
+        let mut image_cells = Vec::new();
+        let mut cell_starts = Vec::new();
+        for line_i in lines {
+            if line_i >= buffer.lines.len() {
+                continue;
+            }
+
+            text.clear();
+            attrs_list.clear_spans();
+            image_cells.clear();
+            cell_starts.clear();
+            let line = Line(line_i as i32 - display_offset);
+            let prompt_mark = self.build_line(
+                &term.grid()[line],
+                line,
+                &mut text,
+                &mut attrs_list,
+                &mut image_cells,
+                &mut cell_starts,
+            );
+            if let Some(image_line) = self.image_lines.get_mut(line_i) {
+                image_line.clone_from(&image_cells);
+            }
+            if let Some(line_mark) = self.prompt_marks.get_mut(line_i) {
+                *line_mark = prompt_mark;
+            }
+
+            if buffer.lines[line_i].set_text(
+                text.clone(),
+                LineEnding::default(),
+                attrs_list.clone(),
+            ) {
+                buffer.set_redraw(true);
+            }
+        }
+    }
+
+    /// Build the text and attributes of a grid line, recording the column and text offset of
+    /// each cell
+    fn build_line(
+        &mut self,
+        row: &Row<Cell>,
+        line: Line,
+        text: &mut String,
+        attrs_list: &mut AttrsList,
+        image_cells: &mut Vec<ImageCell>,
+        cell_starts: &mut Vec<(usize, usize)>,
+    ) -> PromptMark {
+        let render_state = self.render_state;
+        let mut prompt_mark = PromptMark::default();
+
+        text.push(LRI);
+        for column in 0..row.len() {
+            let point = Point::new(line, Column(column));
+            let cell = &row[point.column];
+
+            //TODO: skip leading spacer?
+            if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+                // Skip wide spacers (cells after wide characters)
+                continue;
+            }
+
+            if let Some(link) = cell.hyperlink()
+                && let Some(mark) = PromptMark::from_hyperlink(&link)
+            {
+                prompt_mark.merge(mark);
+            }
+
+            let start = text.len();
+            cell_starts.push((column, start));
+            if cell.c == PLACEHOLDER {
+                // Images are drawn over placeholders, their diacritics are not shown
+                text.push(' ');
+                if let Some(image_cell) = ImageCell::decode(
+                    column,
+                    cell.fg,
+                    cell.underline_color(),
+                    cell.zerowidth().unwrap_or_default(),
+                    image_cells.last(),
+                ) {
+                    image_cells.push(image_cell);
+                }
+            } else {
+                // Tab skip/stop is handled by alacritty_terminal
+                text.push(match cell.c {
+                    '\t' => ' ',
+                    c => c,
+                });
+                if let Some(zerowidth) = cell.zerowidth() {
+                    for &c in zerowidth {
+                        text.push(c);
+                    }
+                }
+            }
+            let end = text.len();
+
+            let mut attrs = self.default_attrs.clone();
+
+            let cell_fg = if cell.flags.contains(Flags::DIM) {
+                as_dim(cell.fg)
+            } else if self.use_bright_bold && cell.flags.contains(Flags::BOLD) {
+                as_bright(cell.fg)
+            } else {
+                cell.fg
+            };
+
+            let (mut fg, mut bg) = if cell.flags.contains(Flags::INVERSE) {
+                (
+                    convert_color(self.colors, cell.bg),
+                    convert_color(self.colors, cell_fg),
+                )
+            } else {
+                (
+                    convert_color(self.colors, cell_fg),
+                    convert_color(self.colors, cell.bg),
+                )
+            };
+
+            if cell.flags.contains(Flags::HIDDEN) {
+                fg = bg;
+            }
+
+            // Change color if cursor
+            if point == render_state.vi_cursor_opt.unwrap_or(self.cursor_point)
+                && render_state.cursor_shape == CursorShape::Block
+                && render_state.is_focused
+            {
+                //Use specific cursor color if requested
+                if self.term_colors[NamedColor::Cursor].is_some() {
+                    fg = bg;
+                    bg = convert_color(self.term_colors, Color::Named(NamedColor::Cursor));
+                } else if self.colors[NamedColor::Cursor].is_some() {
+                    //Use specific theme cursor color if exists
+                    fg = bg;
+                    bg = convert_color(self.colors, Color::Named(NamedColor::Cursor));
+                } else {
+                    mem::swap(&mut fg, &mut bg);
+                }
+                let fg_rgb = Rgb {
+                    r: fg.r(),
+                    g: fg.g(),
+                    b: fg.b(),
+                };
+                let bg_rgb = Rgb {
+                    r: bg.r(),
+                    g: bg.g(),
+                    b: bg.b(),
+                };
+                let contrast = fg_rgb.contrast(bg_rgb);
+                if contrast < MIN_CURSOR_CONTRAST {
+                    fg = convert_color(self.colors, Color::Named(NamedColor::Background));
+                    bg = convert_color(self.colors, Color::Named(NamedColor::Foreground));
+                }
+            }
+
+            // Change color if selected
+            if let Some(range) = &render_state.selection
+                && range.contains(point)
+            {
+                //TODO: better handling of selection
+                mem::swap(&mut fg, &mut bg);
+            }
+
+            // Highlight search matches, the current match stands out from the others
+            if render_state
+                .search_match_opt
+                .as_ref()
+                .is_some_and(|search_match| search_match.contains(&point))
+            {
+                fg = convert_color(self.colors, Color::Named(NamedColor::Black));
+                bg = convert_color(self.colors, Color::Named(NamedColor::Red));
+            } else if matches_contain(&render_state.search_matches, point) {
+                fg = convert_color(self.colors, Color::Named(NamedColor::Black));
+                bg = convert_color(self.colors, Color::Named(NamedColor::Yellow));
+            }
+
+            // Convert foreground to linear
+            attrs = attrs.color(fg);
+
+            let underline_color = cell
+                .underline_color()
+                .map(|c| convert_color(self.colors, c))
+                .unwrap_or(fg);
+
+            let mut flags = cell.flags;
+
+            if let Some(active_match) = &render_state.active_regex_match
+                && active_match.contains(&point)
+            {
+                flags |= Flags::UNDERLINE;
+            }
+            if let Some(active_id) = &render_state.active_hyperlink_id {
+                let mut matches_active = cell
+                    .hyperlink()
+                    .is_some_and(|link| link.id() == active_id);
+                if !matches_active
+                    && cell
+                        .flags
+                        .intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER)
+                    && point.column.0 > 0
+                {
+                    matches_active = row[Column(point.column.0 - 1)]
+                        .hyperlink()
+                        .is_some_and(|link| link.id() == active_id);
+                }
+                if matches_active {
+                    flags |= Flags::UNDERLINE;
+                }
+            }
+
+            let metadata = Metadata::new(bg, fg)
+                .with_flags(flags)
+                .with_underline_color(underline_color);
+            let (meta_idx, _) = self.metadata_set.insert_full(metadata);
+            attrs = attrs.metadata(meta_idx);
+
+            //TODO: more flags
+            if cell.flags.contains(Flags::BOLD) {
+                attrs = attrs.weight(self.bold_font_weight);
+            } else if cell.flags.contains(Flags::DIM) {
+                // if DIM and !BOLD
+                attrs = attrs.weight(self.dim_font_weight);
+            }
+            if cell.flags.contains(Flags::ITALIC) {
+                //TODO: automatically use fake italic
+                attrs = attrs.cache_key_flags(CacheKeyFlags::FAKE_ITALIC);
+            }
+            if attrs != attrs_list.defaults() {
+                attrs_list.add_span(start..end, &attrs);
+            }
+        }
+        prompt_mark
+    }
+}
+
+/// Lines of a selection range with their first and last selected column, and whether their text
+/// continues on the next line because the terminal wrapped it
+fn selection_lines<T>(term: &Term<T>, range: &SelectionRange) -> Vec<(Line, Column, Column, bool)> {
+    let last_column = term.last_column();
+    (range.start.line.0..=range.end.line.0)
+        .map(|line| {
+            let line = Line(line);
+            if range.is_block {
+                return (line, range.start.column, range.end.column, false);
+            }
+            let start = if line == range.start.line {
+                range.start.column
+            } else {
+                Column(0)
+            };
+            let end = if line == range.end.line {
+                range.end.column
+            } else {
+                last_column
+            };
+            let wrapped = line != range.end.line
+                && term.grid()[line][last_column]
+                    .flags
+                    .contains(Flags::WRAPLINE);
+            (line, start, end, wrapped)
+        })
+        .collect()
+}
+
+/// Text of the selection, trimmed and joined as configured, the lines of a block selection are
+/// always trimmed
+pub fn selection_text<T>(term: &Term<T>, copy: CopyConfig) -> Option<String> {
+    let range = term.selection.as_ref()?.to_range(term)?;
+    // Columns cut out of tables are padded with the spaces between them
+    let trim = copy.trim_trailing_whitespace || range.is_block;
+    let lines = selection_lines(term, &range);
+    let mut text = String::new();
+    for (i, (line, start, end, wrapped)) in lines.iter().enumerate() {
+        text.push_str(&term.bounds_to_string(Point::new(*line, *start), Point::new(*line, *end)));
+        if *wrapped && copy.join_wrapped_lines {
+            continue;
+        }
+        if trim {
+            let len = text.trim_end_matches([' ', '\t']).len();
+            text.truncate(len);
+        }
+        if i + 1 < lines.len() {
+            text.push('\n');
+        }
+    }
+    Some(text)
+}
+
+/// Lines of a terminal to build rich text from, on another thread for long scrollbacks
+///
+/// The cells are copied a slice at a time, so the terminal is only locked briefly while the
+/// lines are formatted.
+pub struct RichTextLines<T = EventProxy> {
+    term: Arc<FairMutex<Term<T>>>,
+    /// First and last line, counted from the top of the scrollback when they were chosen
+    start_line: usize,
+    end_line: usize,
+    start_column: Column,
+    end_column: Column,
+    is_block: bool,
+    /// Leave out the empty lines at the end
+    trim_end_lines: bool,
+    colors: Colors,
+    default_attrs: Attrs<'static>,
+    /// Only the default entry, the set used for drawing is left alone
+    metadata_set: IndexSet<Metadata>,
+    bold_font_weight: Weight,
+    dim_font_weight: Weight,
+    use_bright_bold: bool,
+    copy_config: CopyConfig,
+}
+
+impl<T> RichTextLines<T> {
+    pub fn rich_text(&self) -> RichText {
+        // Columns cut out of tables are padded with the spaces between them
+        let trim = self.copy_config.trim_trailing_whitespace || self.is_block;
+        let mut metadata_set = self.metadata_set.clone();
+
+        // Build the lines like they are drawn, without the selection, cursor and highlights
+        let render_state = RenderState {
+            selection: None,
+            cursor_shape: CursorShape::Hidden,
+            active_regex_match: None,
+            active_hyperlink_id: None,
+            search_matches: Vec::new(),
+            search_match_opt: None,
+            vi_cursor_opt: None,
+            is_focused: false,
+        };
+        let default_fg = convert_color(&self.colors, Color::Named(NamedColor::Foreground));
+        let default_bg = convert_color(&self.colors, Color::Named(NamedColor::Background));
+        let mut rich_text = RichText::new(default_fg, default_bg);
+        let mut text = String::new();
+        let mut attrs_list = AttrsList::new(&self.default_attrs);
+        let mut image_cells = Vec::new();
+        let mut cell_starts = Vec::new();
+        let mut rows = Vec::new();
+        let mut next_line = self.start_line;
+        while next_line <= self.end_line {
+            let (cursor_point, term_colors) = {
+                let term = self.term.lock();
+                let grid = term.grid();
+                let history_size = grid.history_size() as i32;
+                // Lines may have been dropped from the top of a full scrollback since the last slice
+                let start = Line(next_line as i32 - history_size).max(grid.topmost_line());
+                let end = Line(self.end_line as i32 - history_size)
+                    .min(start + (search::SLICE_LINES - 1))
+                    .min(grid.bottommost_line());
+                if start > end {
+                    break;
+                }
+                rows.clear();
+                rows.extend((start.0..=end.0).map(|line| {
+                    let line = Line(line);
+                    ((line.0 + history_size) as usize, line, grid[line].clone())
+                }));
+                next_line = (end.0 + history_size + 1) as usize;
+                (grid.cursor.point, *term.colors())
+            };
+
+            let mut line_builder = LineBuilder {
+                colors: &self.colors,
+                default_attrs: &self.default_attrs,
+                metadata_set: &mut metadata_set,
+                image_lines: &mut Vec::new(),
+                prompt_marks: &mut Vec::new(),
+                render_state: &render_state,
+                cursor_point,
+                term_colors: &term_colors,
+                bold_font_weight: self.bold_font_weight,
+                dim_font_weight: self.dim_font_weight,
+                use_bright_bold: self.use_bright_bold,
+            };
+            for (index, line, row) in &rows {
+                let last_column = Column(row.len() - 1);
+                let (start, end, wrapped) = if self.is_block {
+                    (self.start_column, self.end_column, false)
+                } else {
+                    (
+                        if *index == self.start_line {
+                            self.start_column
+                        } else {
+                            Column(0)
+                        },
+                        if *index == self.end_line {
+                            self.end_column
+                        } else {
+                            last_column
+                        },
+                        *index != self.end_line && row[last_column].flags.contains(Flags::WRAPLINE),
+                    )
+                };
+
+                text.clear();
+                attrs_list.clear_spans();
+                image_cells.clear();
+                cell_starts.clear();
+                line_builder.build_line(
+                    row,
+                    *line,
+                    &mut text,
+                    &mut attrs_list,
+                    &mut image_cells,
+                    &mut cell_starts,
+                );
+
+                // Like the plain text, cells after the end of the text are not copied
+                let line_length = row.line_length();
+                for (cell_i, (column, text_start)) in cell_starts.iter().enumerate() {
+                    if *column < start.0 || *column > end.0 || *column >= line_length.0 {
+                        continue;
+                    }
+                    let text_end = cell_starts
+                        .get(cell_i + 1)
+                        .map_or(text.len(), |(_, text_start)| *text_start);
+                    let attrs = attrs_list.get_span(*text_start);
+                    let Some(metadata) = line_builder.metadata_set.get_index(attrs.metadata) else {
+                        continue;
+                    };
+                    rich_text.push(TextRun {
+                        text: text[*text_start..text_end].to_string(),
+                        fg: attrs.color_opt.unwrap_or(default_fg),
+                        bg: metadata.bg,
+                        bold: metadata.flags.contains(Flags::BOLD),
+                        italic: metadata.flags.contains(Flags::ITALIC),
+                        underline: metadata.flags.intersects(Flags::ALL_UNDERLINES),
+                    });
+                }
+
+                if wrapped && self.copy_config.join_wrapped_lines {
+                    continue;
+                }
+                if trim {
+                    rich_text.trim_line_end();
+                }
+                if *index < self.end_line {
+                    rich_text.new_line();
+                }
+            }
+        }
+        if self.trim_end_lines {
+            rich_text.trim_end_lines();
+        }
+        rich_text
+    }
+}
+
+impl<T> fmt::Debug for RichTextLines<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RichTextLines")
+            .field("start_line", &self.start_line)
+            .field("end_line", &self.end_line)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Longest match of the regexes on the wrapped line around a point containing the point
+fn smart_selection_match<T>(
+    term: &Term<T>,
+    regexes: &mut [RegexSearch],
+    point: Point,
+) -> Option<Match> {
+    let start = term.line_search_left(point);
+    let end = term.line_search_right(point);
+    let mut matches = Vec::new();
+    for regex in regexes {
+        let regex_matches: Vec<_> =
+            RegexIter::new(start, end, Direction::Right, term, regex).collect();
+        for regex_match in regex_matches {
+            matches.extend(
+                HintPostProcessor::new(term, regex, regex_match)
+                    .filter(|regex_match| regex_match.contains(&point)),
+            );
+        }
+    }
+    matches
+        .into_iter()
+        .max_by_key(|regex_match| (Reverse(*regex_match.start()), *regex_match.end()))
+}
+
+/// Check if a point is inside of sorted matches that do not overlap
+fn matches_contain(matches: &[Match], point: Point) -> bool {
+    let index = matches.partition_point(|regex_match| *regex_match.start() <= point);
+    index > 0 && matches[index - 1].contains(&point)
+}
+
+/// Formats the OSC 52 reply for the clipboard contents
+pub type ClipboardLoadCallback = Arc<dyn Fn(&str) -> String + Sync + Send + 'static>;
+
 pub struct Terminal {
+    pub bell_limiter: BellLimiter,
+    /// Clipboard read waiting for the user to allow or deny it
+    pub clipboard_load_opt: Option<(ClipboardType, ClipboardLoadCallback)>,
+    /// Program and arguments the terminal was started with, if not from its profile or the
+    /// default shell
+    pub command_opt: Option<Vec<String>>,
     pub context_menu: Option<MenuState>,
+    /// Images shown by placeholder cells
+    pub graphics: Arc<Mutex<Graphics>>,
+    /// Identifies the terminal to `cosmic-term cli`, unique in the window
+    pub id: u64,
     pub metadata_set: IndexSet<Metadata>,
     pub needs_update: bool,
+    pub notification_limiter: NotificationLimiter,
     pub profile_id_opt: Option<ProfileId>,
+    /// Output arrived since the scrollback was last saved
+    pub scrollback_snapshot_pending: bool,
     pub tab_title_override: Option<String>,
     pub term: Arc<FairMutex<Term<EventProxy>>>,
     pub url_regex_search: RegexSearch,
     pub regex_matches: Vec<alacritty_terminal::term::search::Match>,
     pub active_regex_match: Option<alacritty_terminal::term::search::Match>,
     pub active_hyperlink_id: Option<String>,
+    /// When the visual bell was last triggered
+    pub visual_bell_opt: Option<Instant>,
     bold_font_weight: Weight,
     buffer: Arc<Buffer>,
+    copy_config: CopyConfig,
+    cursor_blink_on: bool,
+    full_damage: bool,
+    image_lines: Vec<Vec<ImageCell>>,
     is_focused: bool,
     colors: Colors,
     default_attrs: Attrs<'static>,
     dim_font_weight: Weight,
+    /// Working directory shown to the user, updated when it may have changed
+    directory_label_opt: Option<String>,
+    /// Hint labels laid out at the cells they cover
+    hint_buffer: Arc<Buffer>,
+    /// Cells covered by the hint labels, as viewport line, column and label length
+    hint_cells: Vec<(usize, usize, usize)>,
+    hints_opt: Option<Hints>,
     mouse_reporter: MouseReporter,
     notifier: Notifier,
+    prompt_marks: Vec<PromptMark>,
+    render_state_opt: Option<RenderState>,
+    /// Working directory reported by the shell with OSC 7
+    reported_directory_opt: Option<ReportedDirectory>,
+    scrollback_lines: ScrollbackLines,
+    scrollback_memory_limit_mb: u32,
+    /// Most columns the terminal has had, which the history size is computed for
+    scrollback_widest_columns: usize,
+    /// Set to stop the background search of the current query
+    search_cancel: Arc<AtomicBool>,
+    search_count_pending: bool,
+    search_match_opt: Option<Match>,
+    search_matches_opt: Option<SearchMatches>,
     search_regex_opt: Option<RegexSearch>,
-    search_value: String,
+    search_pattern: String,
+    /// File the scrollback is saved to, if the profile keeps it across restarts
+    scrollback_snapshot_path: Option<PathBuf>,
+    session_log: SharedSessionLog,
+    /// Why the session log was stopped by the PTY reader, until the tab shows it
+    session_log_error: Arc<Mutex<Option<String>>>,
+    shell_events: Arc<Mutex<Vec<ShellEvent>>>,
     shell_pid: Option<u32>,
     size: Size,
+    smart_selection_regexes: Vec<RegexSearch>,
+    smart_selection_rules: Vec<SmartSelectionRule>,
+    term_config: Config,
     use_bright_bold: bool,
     zoom_adj: i8,
 }
@@ -271,11 +1119,13 @@ impl Terminal {
         entity: segmented_button::Entity,
         event_tx: mpsc::UnboundedSender<(pane_grid::Pane, segmented_button::Entity, Event)>,
         config: Config,
-        options: Options,
+        mut options: Options,
         app_config: &AppConfig,
         colors: Colors,
         profile_id_opt: Option<ProfileId>,
         tab_title_override: Option<String>,
+        session_log_opt: Option<SessionLog>,
+        scrollback_snapshot_opt: Option<PathBuf>,
     ) -> Result<Self, io::Error> {
         let font_stretch = app_config.typed_font_stretch();
         let font_weight = app_config.font_weight;
@@ -321,13 +1171,53 @@ impl Terminal {
             cell_width,
             cell_height,
         };
+        let scrollback_lines = app_config.scrollback_lines(profile_id_opt);
+        let scrollback_memory_limit_mb = app_config.scrollback_memory_limit_mb;
+        let mut term_config = config;
+        term_config.default_cursor_style = cursor_style(app_config, profile_id_opt);
+        term_config.semantic_escape_chars = app_config.word_separators(profile_id_opt).to_string();
+        // The cursor of copy mode is drawn as a block by the line builder
+        term_config.vi_mode_cursor_style = Some(CursorStyle {
+            shape: CursorShape::Block,
+            blinking: false,
+        });
+        term_config.scrolling_history =
+            scrolling_history(scrollback_lines, scrollback_memory_limit_mb, size.columns());
+
         let event_proxy = EventProxy(pane, entity, event_tx);
         let term = Arc::new(FairMutex::new(Term::new(
-            config,
+            term_config.clone(),
             &size,
             event_proxy.clone(),
         )));
 
+        // Show the history the profile kept from a previous run above the new shell, preferring
+        // the one of the same tab when restoring a session
+        let scrollback_snapshot_path = profile_id_opt
+            .filter(|profile_id| {
+                app_config
+                    .profiles
+                    .get(profile_id)
+                    .is_some_and(|profile| profile.persist_scrollback)
+            })
+            .and_then(|profile_id| {
+                scrollback_snapshot::claim(profile_id, scrollback_snapshot_opt.as_deref())
+            })
+            .map(|(path, history_opt)| {
+                if let Some(history) = history_opt {
+                    let mut parser: Processor = Processor::new();
+                    parser.advance(&mut *term.lock(), &scrollback_snapshot::replay(&history));
+                }
+                path
+            });
+
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        // Lets programs in the terminal control it with `cosmic-term cli`
+        options
+            .env
+            .insert("COSMIC_TERM_ID".to_string(), id.to_string());
+
         let window_id = 0;
         let pty = tty::new(&options, size.into(), window_id)?;
         #[cfg(not(windows))]
@@ -335,37 +1225,90 @@ impl Terminal {
         #[cfg(windows)]
         let shell_pid = pty.child_watcher().pid().map(|pid| pid.get());
 
+        let graphics = Arc::new(Mutex::new(Graphics::new(
+            app_config.images.enabled,
+            app_config.images.memory_limit_mb,
+        )));
+        let shell_events = Arc::new(Mutex::new(Vec::new()));
+        // Created before the event loop starts so the first output is logged
+        let session_log = Arc::new(Mutex::new(session_log_opt));
+        let session_log_error = Arc::new(Mutex::new(None));
+        let pty = FilterPty::new(
+            pty,
+            graphics.clone(),
+            shell_events.clone(),
+            session_log.clone(),
+            session_log_error.clone(),
+            size.into(),
+            event_proxy.clone(),
+        );
+
         let pty_event_loop =
             EventLoop::new(term.clone(), event_proxy, pty, options.drain_on_exit, false)?;
         let notifier = Notifier(pty_event_loop.channel());
         let _pty_join_handle = pty_event_loop.spawn();
 
-        Ok(Self {
+        let mut terminal = Self {
             active_regex_match: None,
             active_hyperlink_id: None,
+            bell_limiter: BellLimiter::default(),
+            clipboard_load_opt: None,
+            command_opt: None,
+            visual_bell_opt: None,
             url_regex_search: url_regex_search(),
             regex_matches: Vec::new(),
             bold_font_weight: Weight(bold_font_weight),
             buffer: Arc::new(buffer),
             colors,
+            copy_config: app_config.copy,
+            cursor_blink_on: true,
+            full_damage: true,
             context_menu: None,
             default_attrs,
+            graphics,
+            id,
+            image_lines: Vec::new(),
             dim_font_weight: Weight(dim_font_weight),
+            directory_label_opt: None,
+            hint_buffer: Arc::new(Buffer::new_empty(metrics)),
+            hint_cells: Vec::new(),
+            hints_opt: None,
             metadata_set,
             mouse_reporter: Default::default(),
             needs_update: true,
+            notification_limiter: NotificationLimiter::default(),
             notifier,
             profile_id_opt,
+            prompt_marks: Vec::new(),
+            reported_directory_opt: None,
+            render_state_opt: None,
+            scrollback_lines,
+            scrollback_memory_limit_mb,
+            scrollback_widest_columns: size.columns(),
+            search_cancel: Arc::new(AtomicBool::new(false)),
+            search_count_pending: false,
+            search_match_opt: None,
+            search_matches_opt: None,
             search_regex_opt: None,
-            search_value: String::new(),
+            search_pattern: String::new(),
+            scrollback_snapshot_path,
+            scrollback_snapshot_pending: true,
+            session_log,
+            session_log_error,
+            shell_events,
             shell_pid,
             size,
+            smart_selection_regexes: smart_selection_regexes(&app_config.smart_selection),
+            smart_selection_rules: app_config.smart_selection.clone(),
             tab_title_override,
             term,
+            term_config,
             use_bright_bold,
             zoom_adj: Default::default(),
             is_focused: true,
-        })
+        };
+        terminal.directory_label_update();
+        Ok(terminal)
     }
 
     pub fn buffer_weak(&self) -> Weak<Buffer> {
@@ -407,6 +1350,65 @@ impl Terminal {
         self.size
     }
 
+    /// Image placeholder cells of each viewport line
+    pub fn image_lines(&self) -> &[Vec<ImageCell>] {
+        &self.image_lines
+    }
+
+    /// Shell integration marks of each viewport line
+    pub fn prompt_marks(&self) -> &[PromptMark] {
+        &self.prompt_marks
+    }
+
+    /// Takes the events reported by the shell since the last call
+    pub fn take_shell_events(&self) -> Vec<ShellEvent> {
+        mem::take(&mut *self.shell_events.lock().unwrap())
+    }
+
+    /// Path of the session log, if the output is being logged
+    pub fn session_log_path(&self) -> Option<PathBuf> {
+        self.session_log
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|session_log| session_log.path().to_path_buf())
+    }
+
+    /// Start logging the output to a new file
+    pub fn session_log_start(&self, session_log: SessionLog) {
+        *self.session_log.lock().unwrap() = Some(session_log);
+    }
+
+    pub fn session_log_stop(&self) {
+        self.session_log.lock().unwrap().take();
+    }
+
+    /// Takes the error that stopped the session log since the last call
+    pub fn take_session_log_error(&self) -> Option<String> {
+        self.session_log_error.lock().unwrap().take()
+    }
+
+    /// Save the scrollback and screen to restore them after a restart, if the profile keeps them
+    pub fn scrollback_snapshot_save(&mut self, max_size: usize) {
+        self.scrollback_snapshot_pending = false;
+        let Some(path) = self.scrollback_snapshot_path.clone() else {
+            return;
+        };
+        // Full screen programs have no history worth keeping, the last snapshot is kept instead
+        if self.term.lock().mode().contains(TermMode::ALT_SCREEN) {
+            return;
+        }
+        let lines = self.scrollback_tail_lines(max_size);
+        scrollback_snapshot::save_later(path, move || lines.rich_text().ansi(), max_size);
+    }
+
+    /// Remove the saved scrollback, when the tab is closed on purpose
+    pub fn scrollback_snapshot_remove(&mut self) {
+        if let Some(path) = self.scrollback_snapshot_path.take() {
+            scrollback_snapshot::remove_later(path);
+        }
+    }
+
     pub fn zoom_adj(&self) -> i8 {
         self.zoom_adj
     }
@@ -418,6 +1420,7 @@ impl Terminal {
     fn set_focused(&mut self, is_focused: bool) {
         let focus_changed = self.is_focused != is_focused;
         self.is_focused = is_focused;
+        self.reset_cursor_blink();
 
         if focus_changed {
             let report_focus = self.term.lock().mode().contains(TermMode::FOCUS_IN_OUT);
@@ -431,6 +1434,33 @@ impl Terminal {
         }
     }
 
+    /// Returns true if the cursor requested blinking
+    pub fn cursor_blinking(&self) -> bool {
+        self.term.lock().cursor_style().blinking
+    }
+
+    /// Returns false while a blinking cursor is in its hidden phase
+    pub fn cursor_blink_on(&self) -> bool {
+        self.cursor_blink_on
+    }
+
+    /// Advance the cursor blink phase, called every [`CURSOR_BLINK_INTERVAL`]
+    pub fn blink_cursor(&mut self) {
+        let cursor_blink_on = !self.cursor_blinking() || !self.cursor_blink_on;
+        if cursor_blink_on != self.cursor_blink_on {
+            self.cursor_blink_on = cursor_blink_on;
+            self.needs_update = true;
+        }
+    }
+
+    /// Show the cursor again, restarting the blink phase
+    pub fn reset_cursor_blink(&mut self) {
+        if !self.cursor_blink_on {
+            self.cursor_blink_on = true;
+            self.needs_update = true;
+        }
+    }
+
     pub fn redraw(&self) -> bool {
         self.buffer.redraw()
     }
@@ -443,7 +1473,13 @@ impl Terminal {
         self.notifier.notify(input);
     }
 
+    /// Local working directory, preferring the one reported by the shell
     pub fn working_directory(&self) -> Option<PathBuf> {
+        if let Some(directory) = &self.reported_directory_opt {
+            // The path of a remote shell is meaningless locally
+            return (!directory.is_remote(local_host())).then(|| directory.path.clone());
+        }
+
         #[cfg(target_os = "linux")]
         {
             let shell_pid = self.shell_pid?;
@@ -456,6 +1492,38 @@ impl Terminal {
         }
     }
 
+    /// Working directory reported by a shell on another host, like through ssh
+    pub fn remote_directory(&self) -> Option<&ReportedDirectory> {
+        self.reported_directory_opt
+            .as_ref()
+            .filter(|directory| directory.is_remote(local_host()))
+    }
+
+    /// Set the working directory reported by the shell
+    pub fn set_reported_directory(&mut self, directory: ReportedDirectory) {
+        self.reported_directory_opt = Some(directory);
+        self.directory_label_update();
+    }
+
+    /// Working directory shown to the user, prefixed with the host if it is remote
+    pub fn directory_label(&self) -> Option<&str> {
+        self.directory_label_opt.as_deref()
+    }
+
+    /// Look up the working directory label again, which is too slow to do for every frame
+    pub fn directory_label_update(&mut self) {
+        self.directory_label_opt = match self.remote_directory() {
+            Some(directory) => Some(format!(
+                "{}:{}",
+                directory.host.as_deref().unwrap_or_default(),
+                directory.path.display()
+            )),
+            None => self
+                .working_directory()
+                .map(|path| path.display().to_string()),
+        };
+    }
+
     pub fn input_scroll<I: Into<Cow<'static, [u8]>>>(&self, input: I) {
         self.input_no_scroll(input);
         self.scroll(TerminalScroll::Bottom);
@@ -495,6 +1563,10 @@ impl Terminal {
 
             self.notifier.on_resize(self.size.into());
             self.term.lock().resize(self.size);
+            // The memory used by unlimited scrollback depends on the number of columns
+            self.scrollback_widest_columns =
+                self.scrollback_widest_columns.max(self.size.columns());
+            self.update_scrolling_history();
 
             self.with_buffer_mut(|buffer| {
                 buffer.set_size(Some(width as f32), Some(height as f32));
@@ -506,7 +1578,21 @@ impl Terminal {
         }
     }
 
-    pub fn scroll(&self, scroll: TerminalScroll) {
+    fn update_scrolling_history(&mut self) -> bool {
+        let Some(scrolling_history) = scrolling_history_update(
+            self.term_config.scrolling_history,
+            self.scrollback_lines,
+            self.scrollback_memory_limit_mb,
+            self.scrollback_widest_columns,
+        ) else {
+            return false;
+        };
+        self.term_config.scrolling_history = scrolling_history;
+        self.term.lock().set_options(self.term_config.clone());
+        true
+    }
+
+    pub fn scroll(&self, scroll: TerminalScroll) {
         self.term.lock().scroll_display(scroll);
     }
 
@@ -538,27 +1624,32 @@ impl Terminal {
         }
     }
 
-    pub fn search(&mut self, value: &str, forwards: bool) {
-        //TODO: set max lines, run in thread?
+    /// Move to the next or previous match, returning an error if the regex is invalid
+    pub fn search(
+        &mut self,
+        value: &str,
+        options: SearchOptions,
+        forwards: bool,
+    ) -> Result<(), String> {
         {
-            let mut term = self.term.lock();
-
-            if self.search_value != value {
-                match RegexSearch::new(value) {
-                    Ok(search_regex) => {
-                        self.search_regex_opt = Some(search_regex);
-                        self.search_value = value.to_string();
-                        term.selection = None;
-                    }
-                    Err(err) => {
-                        log::warn!("failed to parse regex {:?}: {}", value, err);
-                        return;
-                    }
+            let pattern = options.pattern(value);
+            let new_pattern = self.search_pattern != pattern;
+            let copy_mode = self.copy_mode_active();
+            if new_pattern {
+                let search_regex = options.regex_search(value)?;
+                self.search_reset();
+                self.search_regex_opt = Some(search_regex);
+                self.search_pattern = pattern;
+                self.search_count_pending = true;
+                if !copy_mode {
+                    self.term.lock().selection = None;
                 }
             }
 
+            let mut term = self.term.lock();
+
             let Some(search_regex) = &mut self.search_regex_opt else {
-                return;
+                return Ok(());
             };
 
             // Determine search origin
@@ -568,6 +1659,17 @@ impl Terminal {
                 .as_ref()
                 .and_then(|selection| selection.to_range(&term))
             {
+                // Copy mode searches from its cursor, including it while typing a new query
+                _ if copy_mode => {
+                    let point = term.vi_mode_cursor.point;
+                    if new_pattern {
+                        point
+                    } else if forwards {
+                        point.add(grid, Boundary::Grid, 1)
+                    } else {
+                        point.sub(grid, Boundary::Grid, 1)
+                    }
+                }
                 Some(range) => {
                     //TODO: determine correct search_origin, along with side below
                     if forwards {
@@ -588,35 +1690,429 @@ impl Terminal {
                 }
             };
 
-            // Find next search match
-            if let Some(search_match) = term.search_next(
-                search_regex,
-                search_origin,
-                if forwards {
-                    Direction::Right
-                } else {
-                    Direction::Left
-                },
-                //TODO: determine correct side, along with search_origin above
-                if forwards { Side::Left } else { Side::Right },
-                None,
-            ) {
-                // Scroll to match
-                if forwards {
-                    term.scroll_to_point(*search_match.end());
+            // Find next search match, only looking at nearby lines while holding the lock
+            let history_size = grid.history_size();
+            let search_all = grid.total_lines() <= SEARCH_NEARBY_LINES;
+            let mut search_match_opt = term
+                .search_next(
+                    search_regex,
+                    search_origin,
+                    if forwards {
+                        Direction::Right
+                    } else {
+                        Direction::Left
+                    },
+                    //TODO: determine correct side, along with search_origin above
+                    if forwards { Side::Left } else { Side::Right },
+                    Some(SEARCH_NEARBY_LINES),
+                )
+                .filter(|search_match| {
+                    // A match behind the origin wrapped around inside of the nearby lines
+                    search_all
+                        || if forwards {
+                            *search_match.start() >= search_origin
+                        } else {
+                            *search_match.end() <= search_origin
+                        }
+                });
+
+            // Matches that are further away are found by the background search
+            if search_match_opt.is_none()
+                && let Some(search_matches) = &self.search_matches_opt
+                && let Some(start) =
+                    search_matches.next_start(search_origin, history_size, forwards)
+            {
+                search_match_opt = term.search_next(
+                    search_regex,
+                    start,
+                    Direction::Right,
+                    Side::Left,
+                    Some(MAX_SEARCH_LINES),
+                );
+            }
+
+            if let Some(search_match) = search_match_opt {
+                if copy_mode {
+                    // Move the cursor of copy mode, which extends its selection
+                    term.vi_goto_point(*search_match.start());
                 } else {
-                    term.scroll_to_point(*search_match.start());
-                }
+                    // Scroll to match
+                    if forwards {
+                        term.scroll_to_point(*search_match.end());
+                    } else {
+                        term.scroll_to_point(*search_match.start());
+                    }
 
-                // Set selection to match
-                let mut selection =
-                    Selection::new(SelectionType::Simple, *search_match.start(), Side::Left);
-                selection.update(*search_match.end(), Side::Right);
-                term.selection = Some(selection);
+                    // Set selection to match
+                    let mut selection =
+                        Selection::new(SelectionType::Simple, *search_match.start(), Side::Left);
+                    selection.update(*search_match.end(), Side::Right);
+                    term.selection = Some(selection);
+                }
+                self.search_match_opt = Some(search_match);
             }
         }
 
         self.update();
+        Ok(())
+    }
+
+    /// Search the whole grid for matches on a background thread, if not already started for
+    /// the current search. The matches of each slice of lines are sent to the returned channel.
+    pub fn search_count(&mut self) -> Option<mpsc::UnboundedReceiver<SearchMatches>> {
+        if !mem::take(&mut self.search_count_pending) {
+            return None;
+        }
+
+        let term = self.term.clone();
+        let pattern = self.search_pattern.clone();
+        let cancel = self.search_cancel.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        thread::spawn(move || {
+            // The pattern was already parsed by the search
+            let Ok(mut regex) = RegexSearch::new(&pattern) else {
+                return;
+            };
+            search::search_matches(&term, &pattern, &mut regex, &cancel, |slice| {
+                // Stop when nobody is waiting for the matches anymore
+                if tx.send(slice).is_err() {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            });
+        });
+        Some(rx)
+    }
+
+    /// Add matches found by the background search, if they are for the current search
+    pub fn add_search_matches(&mut self, slice: SearchMatches) {
+        if self.search_regex_opt.is_none() || slice.pattern != self.search_pattern {
+            return;
+        }
+        match &mut self.search_matches_opt {
+            Some(search_matches) => search_matches.extend(slice),
+            None => self.search_matches_opt = Some(slice),
+        }
+    }
+
+    /// Get the position of the current match, the number of matches found so far and the ratio
+    /// of the grid that was searched
+    pub fn search_position(&self) -> Option<(Option<usize>, usize, f32)> {
+        let search_matches = self.search_matches_opt.as_ref()?;
+        let position = self.search_match_opt.as_ref().and_then(|search_match| {
+            search_matches.position(search_match, self.term.lock().grid().history_size())
+        });
+        Some((position, search_matches.len(), search_matches.progress))
+    }
+
+    /// Get the scrollbar tick marks of all matches, once counted
+    pub fn search_ticks(&self) -> &[f32] {
+        self.search_matches_opt
+            .as_ref()
+            .map_or(&[], |search_matches| search_matches.ticks())
+    }
+
+    /// Stop highlighting the matches of the last search
+    pub fn search_clear(&mut self) {
+        if self.search_regex_opt.take().is_some() {
+            self.search_reset();
+            self.search_pattern.clear();
+            self.search_count_pending = false;
+            self.update();
+        }
+    }
+
+    /// Stop the background search and forget the matches of the last search
+    fn search_reset(&mut self) {
+        self.search_cancel.store(true, Ordering::Relaxed);
+        self.search_cancel = Arc::new(AtomicBool::new(false));
+        self.search_match_opt = None;
+        self.search_matches_opt = None;
+    }
+
+    /// Label the matches of the hint patterns on the screen, returns false if there are none
+    pub fn hints_start(&mut self, config: &HintConfig, action: HintAction) -> bool {
+        let mut hints = Hints::new(config, action);
+        hints.update(&self.term.lock());
+        if hints.is_empty() {
+            return false;
+        }
+        self.hints_opt = Some(hints);
+        self.needs_update = true;
+        true
+    }
+
+    pub fn hints_active(&self) -> bool {
+        self.hints_opt.is_some()
+    }
+
+    pub fn hints_cancel(&mut self) {
+        if self.hints_opt.take().is_some() {
+            self.needs_update = true;
+        }
+    }
+
+    /// Type a character of a hint label, returning the action and text of the selected hint
+    pub fn hint_input(&mut self, c: char) -> Option<(HintAction, String)> {
+        let hints = self.hints_opt.as_mut()?;
+        match hints.input(c) {
+            HintInput::Selected(text) => {
+                let action = hints.action;
+                self.hints_cancel();
+                Some((action, text))
+            }
+            HintInput::Pending => {
+                self.needs_update = true;
+                None
+            }
+            HintInput::Ignored => None,
+        }
+    }
+
+    pub fn hint_backspace(&mut self) {
+        if let Some(hints) = &mut self.hints_opt {
+            hints.backspace();
+            self.needs_update = true;
+        }
+    }
+
+    pub fn hint_buffer_weak(&self) -> Weak<Buffer> {
+        Arc::downgrade(&self.hint_buffer)
+    }
+
+    pub fn hint_cells(&self) -> &[(usize, usize, usize)] {
+        &self.hint_cells
+    }
+
+    /// Lay out the labels of the hints that can still be selected
+    fn update_hint_buffer(&mut self) {
+        let mut labels = Vec::new();
+        if let Some(hints) = &self.hints_opt {
+            let display_offset = self.term.lock().grid().display_offset();
+            for (hint, label) in hints.visible() {
+                let point = *hint.regex_match.start();
+                let line = (point.line.0 + display_offset as i32) as usize;
+                labels.push((line, point.column.0, label));
+            }
+        }
+        labels.sort();
+
+        let mut lines = vec![String::new(); self.size.screen_lines()];
+        self.hint_cells.clear();
+        for (line, column, label) in labels {
+            let Some(text) = lines.get_mut(line) else {
+                continue;
+            };
+            // Labels are shorter than matches, but keep any overlapping one readable
+            let len = text.chars().count();
+            if column < len {
+                continue;
+            }
+            text.extend(std::iter::repeat_n(' ', column - len));
+            text.push_str(label);
+            self.hint_cells.push((line, column, label.chars().count()));
+        }
+
+        // Labels get the color of the theme when drawn
+        let attrs = Attrs::new()
+            .family(Family::Monospace)
+            .weight(self.bold_font_weight)
+            .stretch(self.default_attrs.stretch);
+        let metrics = self.buffer.metrics();
+        let mut font_system = font_system().write().unwrap();
+        let hint_buffer = Arc::make_mut(&mut self.hint_buffer);
+        hint_buffer.set_metrics(metrics);
+        hint_buffer.set_wrap(Wrap::None);
+        hint_buffer.set_monospace_width(Some(self.size.cell_width));
+        hint_buffer.set_size(Some(self.size.width as f32), Some(self.size.height as f32));
+        hint_buffer.set_text(&lines.join("\n"), &attrs, Shaping::Advanced, None);
+        hint_buffer.shape_until_scroll(font_system.raw(), true);
+    }
+
+    pub fn copy_mode_active(&self) -> bool {
+        self.term.lock().mode().contains(TermMode::VI)
+    }
+
+    /// Enter copy mode with its cursor on the terminal cursor, or leave it
+    pub fn copy_mode_toggle(&mut self) {
+        let mut term = self.term.lock();
+        term.toggle_vi_mode();
+        if !term.mode().contains(TermMode::VI) {
+            term.selection = None;
+            term.scroll_display(TerminalScroll::Bottom);
+        }
+        self.needs_update = true;
+    }
+
+    /// Type of the selection in copy mode
+    pub fn copy_mode_selection(&self) -> Option<SelectionType> {
+        self.term
+            .lock()
+            .selection
+            .as_ref()
+            .map(|selection| selection.ty)
+    }
+
+    /// Run a command of copy mode
+    ///
+    /// Searching and copying are done by the application, those commands are ignored here.
+    pub fn copy_mode_command(&mut self, command: CopyModeCommand) {
+        {
+            let mut term = self.term.lock();
+            if !term.mode().contains(TermMode::VI) {
+                return;
+            }
+            match command {
+                CopyModeCommand::Motion(motion) => term.vi_motion(motion),
+                CopyModeCommand::Top => {
+                    let point = Point::new(term.topmost_line(), Column(0));
+                    term.vi_goto_point(point);
+                }
+                CopyModeCommand::Bottom => {
+                    let point = Point::new(term.bottommost_line(), Column(0));
+                    term.vi_goto_point(point);
+                    term.vi_motion(ViMotion::FirstOccupied);
+                }
+                CopyModeCommand::ScrollHalfPage { up } => {
+                    let lines = (term.screen_lines() / 2) as i32;
+                    let lines = if up { lines } else { -lines };
+                    term.scroll_display(TerminalScroll::Delta(lines));
+                    // The cursor moves as far as the text, staying on the screen at the ends
+                    let mut point = term.vi_mode_cursor.point;
+                    point.line = (point.line - lines)
+                        .max(term.topmost_line())
+                        .min(term.bottommost_line());
+                    term.vi_goto_point(point);
+                }
+                CopyModeCommand::Select(ty) => {
+                    let point = term.vi_mode_cursor.point;
+                    match term.selection.as_ref().map(|selection| selection.ty) {
+                        // Selecting with the same kind again stops selecting, like vi
+                        Some(selection_ty) if selection_ty == ty => {
+                            term.selection = None;
+                        }
+                        Some(_) => {
+                            if let Some(selection) = &mut term.selection {
+                                selection.ty = ty;
+                            }
+                        }
+                        None => {
+                            let mut selection = Selection::new(ty, point, Side::Left);
+                            selection.include_all();
+                            term.selection = Some(selection);
+                        }
+                    }
+                }
+                CopyModeCommand::Exit => {
+                    term.toggle_vi_mode();
+                    term.selection = None;
+                    term.scroll_display(TerminalScroll::Bottom);
+                }
+                CopyModeCommand::Yank
+                | CopyModeCommand::Search { .. }
+                | CopyModeCommand::SearchNext { .. } => {}
+            }
+        }
+        self.needs_update = true;
+    }
+
+    /// Longest match of the smart selection rules around a point, which a double-click selects
+    /// instead of the word there
+    pub fn smart_selection(&mut self, point: Point) -> Option<Match> {
+        smart_selection_match(&self.term.lock(), &mut self.smart_selection_regexes, point)
+    }
+
+    /// Text of the selection to copy
+    pub fn selection_text(&self) -> Option<String> {
+        selection_text(&self.term.lock(), self.copy_config)
+    }
+
+    /// Selection with its colors
+    pub fn selection_rich_text(&self) -> Option<RichText> {
+        let lines = {
+            let term = self.term.lock();
+            let range = term.selection.as_ref()?.to_range(&term)?;
+            self.rich_text_lines(&term, &range, false)
+        };
+        Some(lines.rich_text())
+    }
+
+    /// Scrollback and screen with their colors, without the empty lines at the end
+    pub fn scrollback_rich_text(&self) -> RichText {
+        self.scrollback_lines().rich_text()
+    }
+
+    /// Lines of the scrollback and screen, to build their rich text on another thread
+    pub fn scrollback_lines(&self) -> RichTextLines {
+        let term = self.term.lock();
+        let range = SelectionRange::new(
+            Point::new(term.topmost_line(), Column(0)),
+            Point::new(term.bottommost_line(), term.last_column()),
+            false,
+        );
+        self.rich_text_lines(&term, &range, true)
+    }
+
+    /// Like [`Self::scrollback_lines`], but starting at the line from which the text at the end
+    /// has at least `max_size` bytes, so long histories are not copied to keep their end
+    pub fn scrollback_tail_lines(&self, max_size: usize) -> RichTextLines {
+        let term = self.term.lock();
+        let grid = term.grid();
+        // Every cell of a line is at least one byte of text
+        let mut start = term.bottommost_line();
+        let mut size = grid[start].line_length().0;
+        while start > term.topmost_line() && size < max_size {
+            start.0 -= 1;
+            size += grid[start].line_length().0;
+        }
+        let range = SelectionRange::new(
+            Point::new(start, Column(0)),
+            Point::new(term.bottommost_line(), term.last_column()),
+            false,
+        );
+        self.rich_text_lines(&term, &range, true)
+    }
+
+    /// Lines shown on the screen with their colors, without the empty lines at the end
+    pub fn visible_rich_text(&self) -> RichText {
+        let lines = {
+            let term = self.term.lock();
+            let display_offset = term.grid().display_offset() as i32;
+            let range = SelectionRange::new(
+                Point::new(Line(-display_offset), Column(0)),
+                Point::new(
+                    Line(term.screen_lines() as i32 - 1 - display_offset),
+                    term.last_column(),
+                ),
+                false,
+            );
+            self.rich_text_lines(&term, &range, true)
+        };
+        lines.rich_text()
+    }
+
+    fn rich_text_lines(
+        &self,
+        term: &Term<EventProxy>,
+        range: &SelectionRange,
+        trim_end_lines: bool,
+    ) -> RichTextLines {
+        let history_size = term.grid().history_size() as i32;
+        RichTextLines {
+            term: self.term.clone(),
+            start_line: (range.start.line.0 + history_size) as usize,
+            end_line: (range.end.line.0 + history_size) as usize,
+            start_column: range.start.column,
+            end_column: range.end.column,
+            is_block: range.is_block,
+            trim_end_lines,
+            colors: self.colors,
+            default_attrs: self.default_attrs.clone(),
+            metadata_set: self.metadata_set.iter().take(1).cloned().collect(),
+            bold_font_weight: self.bold_font_weight,
+            dim_font_weight: self.dim_font_weight,
+            use_bright_bold: self.use_bright_bold,
+            copy_config: self.copy_config,
+        }
     }
 
     pub fn select_all(&mut self) {
@@ -639,6 +2135,49 @@ impl Terminal {
         self.update();
     }
 
+    /// Scroll the previous or next shell prompt to the top of the viewport
+    pub fn scroll_to_prompt(&mut self, forwards: bool) {
+        {
+            let mut term = self.term.lock();
+            let old_display_offset = term.grid().display_offset() as i32;
+            let Some(line) =
+                shell_integration::prompt_line(&term, Line(-old_display_offset), forwards)
+            else {
+                return;
+            };
+            let new_display_offset = (-line.0).max(0);
+            term.scroll_display(TerminalScroll::Delta(
+                new_display_offset - old_display_offset,
+            ));
+        }
+        self.update();
+    }
+
+    /// Select the output of the last finished command, returns false if there is none
+    pub fn select_last_command_output(&mut self) -> bool {
+        {
+            let mut term = self.term.lock();
+            let Some((start, end)) = shell_integration::last_command_output(&term) else {
+                return false;
+            };
+            let end = end.sub(&*term, Boundary::Grid, 1);
+            let mut selection = Selection::new(SelectionType::Simple, start, Side::Left);
+            selection.update(end, Side::Right);
+            term.selection = Some(selection);
+            term.scroll_to_point(start);
+        }
+        self.update();
+        true
+    }
+
+    /// Text printed by the last finished command
+    pub fn last_command_output(&self) -> Option<String> {
+        let term = self.term.lock();
+        let (start, end) = shell_integration::last_command_output(&term)?;
+        let end = end.sub(&*term, Boundary::Grid, 1);
+        Some(term.bounds_to_string(start, end))
+    }
+
     pub fn set_config(
         &mut self,
         config: &AppConfig,
@@ -679,6 +2218,36 @@ impl Terminal {
             update_cell_size = true;
         }
 
+        self.graphics
+            .lock()
+            .unwrap()
+            .set_limits(config.images.enabled, config.images.memory_limit_mb);
+
+        self.copy_config = config.copy;
+
+        let default_cursor_style = cursor_style(config, self.profile_id_opt);
+        let semantic_escape_chars = config.word_separators(self.profile_id_opt);
+        if self.term_config.default_cursor_style != default_cursor_style
+            || self.term_config.semantic_escape_chars != semantic_escape_chars
+        {
+            self.term_config.default_cursor_style = default_cursor_style;
+            self.term_config.semantic_escape_chars = semantic_escape_chars.to_string();
+            self.term.lock().set_options(self.term_config.clone());
+            update = true;
+        }
+
+        if self.smart_selection_rules != config.smart_selection {
+            self.smart_selection_regexes = smart_selection_regexes(&config.smart_selection);
+            self.smart_selection_rules = config.smart_selection.clone();
+        }
+
+        // Changing the history size keeps the shell running, shrinking it drops the oldest lines
+        self.scrollback_lines = config.scrollback_lines(self.profile_id_opt);
+        self.scrollback_memory_limit_mb = config.scrollback_memory_limit_mb;
+        if self.update_scrolling_history() {
+            update = true;
+        }
+
         let metrics = config.metrics(zoom_adj);
         if metrics != self.buffer.metrics() {
             self.with_buffer_mut(|buffer| buffer.set_metrics(metrics));
@@ -707,6 +2276,7 @@ impl Terminal {
         if update_cell_size {
             self.update_cell_size();
         } else if update || default_colors_updated {
+            self.full_damage = true;
             self.update();
         }
     }
@@ -723,6 +2293,8 @@ impl Terminal {
         if updated {
             self.metadata_set.clear();
             let (default_metadata_idx, _) = self.metadata_set.insert_full(new_default_metadata);
+            // Existing lines refer to the old metadata indices
+            self.full_damage = true;
 
             self.default_attrs = Attrs::new()
                 .family(Family::Monospace)
@@ -760,250 +2332,95 @@ impl Terminal {
         };
         self.resize(old_size.width, old_size.height);
 
+        self.full_damage = true;
         self.update();
     }
 
     pub fn update(&mut self) -> bool {
-        // LEFT‑TO‑RIGHT ISOLATE character.
-        // This will be added to the beginning of lines to force the shaper to treat detected RTL
-        // lines as LTR. RTL text would still be rendered correctly. But this fixes the wrong
-        // behavior of it being aligned to the right.
-        const LRI: char = '\u{2066}';
-
         let instant = Instant::now();
 
-        // Only keep default
-        self.metadata_set.truncate(1);
-
-        //TODO: is redraw needed after all events?
-        //TODO: use LineDamageBounds
         {
             let buffer = Arc::make_mut(&mut self.buffer);
+            let mut term = self.term.lock();
 
-            let mut line_i = 0;
-            let mut last_point = None;
-            let mut text = String::from(LRI);
-            let mut attrs_list = AttrsList::new(&self.default_attrs);
+            let mut damage = LineDamage::take(&mut term);
+
+            // Selection, highlighted links and search matches and focus are not part of the
+            // terminal damage, so any change to them requires rebuilding every line. The cursor
+            // shape is only drawn on the cursor line, which the terminal always damages along with
+            // the line the cursor left, so blinking does not rebuild anything else.
+            let render_state = RenderState {
+                selection: term
+                    .selection
+                    .as_ref()
+                    .and_then(|selection| selection.to_range(&term)),
+                cursor_shape: if self.cursor_blink_on {
+                    term.renderable_content().cursor.shape
+                } else {
+                    CursorShape::Hidden
+                },
+                active_regex_match: self.active_regex_match.clone(),
+                active_hyperlink_id: self.active_hyperlink_id.clone(),
+                search_matches: match &mut self.search_regex_opt {
+                    Some(search_regex) => visible_regex_match_iter(&term, search_regex).collect(),
+                    None => Vec::new(),
+                },
+                search_match_opt: self.search_match_opt.clone(),
+                vi_cursor_opt: term
+                    .mode()
+                    .contains(TermMode::VI)
+                    .then_some(term.vi_mode_cursor.point),
+                is_focused: self.is_focused,
+            };
+            if self.full_damage
+                || !self
+                    .render_state_opt
+                    .as_ref()
+                    .is_some_and(|old| old.eq_except_cursor_shape(&render_state))
+                || buffer.lines.len() != term.screen_lines()
+                || self.metadata_set.len() > MAX_METADATA_ENTRIES
             {
-                let mut term = self.term.lock();
-                //TODO: use damage?
-                match term.damage() {
-                    TermDamage::Full => {}
-                    TermDamage::Partial(_damage_lines) => {}
-                }
-                term.reset_damage();
+                damage = LineDamage::Full;
+            }
+            self.full_damage = false;
+            let render_state = self.render_state_opt.insert(render_state);
 
+            if !damage.is_empty() {
                 self.regex_matches.clear();
-                {
-                    let mut regex_matches: Vec<_> =
-                        visible_regex_match_iter(&term, &mut self.url_regex_search).collect();
-                    self.regex_matches
-                        .extend(regex_matches.drain(..).flat_map(|rm| -> Vec<_> {
-                            HintPostProcessor::new(&term, &mut self.url_regex_search, rm).collect()
-                        }));
-                }
-
-                let grid = term.grid();
-                for indexed in grid.display_iter() {
-                    if indexed.point.line != last_point.unwrap_or(indexed.point).line {
-                        while line_i >= buffer.lines.len() {
-                            buffer.lines.push(BufferLine::new(
-                                "",
-                                LineEnding::default(),
-                                AttrsList::new(&self.default_attrs),
-                                Shaping::Advanced,
-                            ));
-                            buffer.set_redraw(true);
-                        }
-
-                        if buffer.lines[line_i].set_text(
-                            text.clone(),
-                            LineEnding::default(),
-                            attrs_list.clone(),
-                        ) {
-                            buffer.set_redraw(true);
-                        }
-                        line_i += 1;
-
-                        text.clear();
-                        text.push(LRI);
-                        attrs_list.clear_spans();
-                    }
-                    //TODO: use indexed.point.column?
-
-                    //TODO: skip leading spacer?
-                    if indexed.cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
-                        // Skip wide spacers (cells after wide characters)
-                        continue;
-                    }
-
-                    let start = text.len();
-                    // Tab skip/stop is handled by alacritty_terminal
-                    text.push(match indexed.cell.c {
-                        '\t' => ' ',
-                        c => c,
-                    });
-                    if let Some(zerowidth) = indexed.cell.zerowidth() {
-                        for &c in zerowidth {
-                            text.push(c);
-                        }
-                    }
-                    let end = text.len();
-
-                    let mut attrs = self.default_attrs.clone();
-
-                    let cell_fg = if indexed.cell.flags.contains(Flags::DIM) {
-                        as_dim(indexed.cell.fg)
-                    } else if self.use_bright_bold && indexed.cell.flags.contains(Flags::BOLD) {
-                        as_bright(indexed.cell.fg)
-                    } else {
-                        indexed.cell.fg
-                    };
-
-                    let (mut fg, mut bg) = if indexed.cell.flags.contains(Flags::INVERSE) {
-                        (
-                            convert_color(&self.colors, indexed.cell.bg),
-                            convert_color(&self.colors, cell_fg),
-                        )
-                    } else {
-                        (
-                            convert_color(&self.colors, cell_fg),
-                            convert_color(&self.colors, indexed.cell.bg),
-                        )
-                    };
-
-                    if indexed.cell.flags.contains(Flags::HIDDEN) {
-                        fg = bg;
-                    }
-
-                    // Change color if cursor
-                    if indexed.point == grid.cursor.point
-                        && term.renderable_content().cursor.shape == CursorShape::Block
-                        && self.is_focused
-                    {
-                        //Use specific cursor color if requested
-                        if term.colors()[NamedColor::Cursor].is_some() {
-                            fg = bg;
-                            bg = convert_color(term.colors(), Color::Named(NamedColor::Cursor));
-                        } else if self.colors[NamedColor::Cursor].is_some() {
-                            //Use specific theme cursor color if exists
-                            fg = bg;
-                            bg = convert_color(&self.colors, Color::Named(NamedColor::Cursor));
-                        } else {
-                            mem::swap(&mut fg, &mut bg);
-                        }
-                        let fg_rgb = Rgb {
-                            r: fg.r(),
-                            g: fg.g(),
-                            b: fg.b(),
-                        };
-                        let bg_rgb = Rgb {
-                            r: bg.r(),
-                            g: bg.g(),
-                            b: bg.b(),
-                        };
-                        let contrast = fg_rgb.contrast(bg_rgb);
-                        if contrast < MIN_CURSOR_CONTRAST {
-                            fg = convert_color(&self.colors, Color::Named(NamedColor::Background));
-                            bg = convert_color(&self.colors, Color::Named(NamedColor::Foreground));
-                        }
-                    }
-
-                    // Change color if selected
-                    if let Some(selection) = &term.selection
-                        && let Some(range) = selection.to_range(&term)
-                        && range.contains(indexed.point)
-                    {
-                        //TODO: better handling of selection
-                        mem::swap(&mut fg, &mut bg);
-                    }
-
-                    // Convert foreground to linear
-                    attrs = attrs.color(fg);
-
-                    let underline_color = indexed
-                        .cell
-                        .underline_color()
-                        .map(|c| convert_color(&self.colors, c))
-                        .unwrap_or(fg);
-
-                    let mut flags = indexed.cell.flags;
-
-                    if let Some(active_match) = &self.active_regex_match
-                        && active_match.contains(&indexed.point)
-                    {
-                        flags |= Flags::UNDERLINE;
-                    }
-                    if let Some(active_id) = &self.active_hyperlink_id {
-                        let mut matches_active = indexed
-                            .cell
-                            .hyperlink()
-                            .is_some_and(|link| link.id() == active_id);
-                        if !matches_active
-                            && indexed.cell.flags.intersects(
-                                Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER,
-                            )
-                            && indexed.point.column.0 > 0
-                        {
-                            matches_active = grid[Point::new(
-                                indexed.point.line,
-                                Column(indexed.point.column.0 - 1),
-                            )]
-                            .hyperlink()
-                            .is_some_and(|link| link.id() == active_id);
-                        }
-                        if matches_active {
-                            flags |= Flags::UNDERLINE;
-                        }
-                    }
-
-                    let metadata = Metadata::new(bg, fg)
-                        .with_flags(flags)
-                        .with_underline_color(underline_color);
-                    let (meta_idx, _) = self.metadata_set.insert_full(metadata);
-                    attrs = attrs.metadata(meta_idx);
-
-                    //TODO: more flags
-                    if indexed.cell.flags.contains(Flags::BOLD) {
-                        attrs = attrs.weight(self.bold_font_weight);
-                    } else if indexed.cell.flags.contains(Flags::DIM) {
-                        // if DIM and !BOLD
-                        attrs = attrs.weight(self.dim_font_weight);
-                    }
-                    if indexed.cell.flags.contains(Flags::ITALIC) {
-                        //TODO: automatically use fake italic
-                        attrs = attrs.cache_key_flags(CacheKeyFlags::FAKE_ITALIC);
-                    }
-                    if attrs != attrs_list.defaults() {
-                        attrs_list.add_span(start..end, &attrs);
-                    }
-
-                    last_point = Some(indexed.point);
+                let mut regex_matches: Vec<_> =
+                    visible_regex_match_iter(&term, &mut self.url_regex_search).collect();
+                self.regex_matches
+                    .extend(regex_matches.drain(..).flat_map(|rm| -> Vec<_> {
+                        HintPostProcessor::new(&term, &mut self.url_regex_search, rm).collect()
+                    }));
+
+                // Output and scrolling move the hints
+                if let Some(hints) = &mut self.hints_opt {
+                    hints.update(&term);
                 }
             }
 
-            //TODO: do not repeat!
-            while line_i >= buffer.lines.len() {
-                buffer.lines.push(BufferLine::new(
-                    "",
-                    LineEnding::default(),
-                    AttrsList::new(&self.default_attrs),
-                    Shaping::Advanced,
-                ));
-                buffer.set_redraw(true);
+            if damage == LineDamage::Full {
+                // Only keep default, lines referencing other entries are all rebuilt
+                self.metadata_set.truncate(1);
             }
 
-            if buffer.lines[line_i].set_text(text, LineEnding::default(), attrs_list) {
-                buffer.set_redraw(true);
-            }
-            line_i += 1;
-
-            if buffer.lines.len() != line_i {
-                buffer.lines.truncate(line_i);
-                buffer.set_redraw(true);
-            }
+            let mut line_builder = LineBuilder {
+                colors: &self.colors,
+                default_attrs: &self.default_attrs,
+                metadata_set: &mut self.metadata_set,
+                image_lines: &mut self.image_lines,
+                prompt_marks: &mut self.prompt_marks,
+                render_state,
+                cursor_point: term.grid().cursor.point,
+                term_colors: term.colors(),
+                bold_font_weight: self.bold_font_weight,
+                dim_font_weight: self.dim_font_weight,
+                use_bright_bold: self.use_bright_bold,
+            };
+            line_builder.update_buffer(&term, buffer, &damage);
 
-            // Shape and trim shape run cache
+            // Shape and trim shape run cache, only lines changed above need shaping
             {
                 let mut font_system = font_system().write().unwrap();
                 buffer.shape_until_scroll(font_system.raw(), true);
@@ -1011,6 +2428,10 @@ impl Terminal {
             }
         }
 
+        if self.hints_opt.is_some() || !self.hint_cells.is_empty() {
+            self.update_hint_buffer();
+        }
+
         log::debug!("buffer update {:?}", instant.elapsed());
 
         self.buffer.redraw()
@@ -1118,7 +2539,7 @@ pub fn visible_regex_match_iter<'a, T>(
 }
 /** Copy of <https://github.com/alacritty/alacritty/blob/4a7728bf7fac06a35f27f6c4f31e0d9214e5152b/alacritty/src/display/hint.rs#L433C1-L572C1> */
 /// Iterator over all post-processed matches inside an existing hint match.
-struct HintPostProcessor<'a, T> {
+pub struct HintPostProcessor<'a, T> {
     /// Regex search DFAs.
     regex: &'a mut RegexSearch,
 
@@ -1137,7 +2558,7 @@ struct HintPostProcessor<'a, T> {
 
 impl<'a, T> HintPostProcessor<'a, T> {
     /// Create a new iterator for an unprocessed match.
-    fn new(
+    pub fn new(
         term: &'a Term<T>,
         regex: &'a mut RegexSearch,
         regex_match: alacritty_terminal::term::search::Match,
@@ -1275,5 +2696,415 @@ impl Drop for Terminal {
         if let Err(err) = self.notifier.0.send(Msg::Shutdown) {
             log::warn!("Failed to send shutdown message on dropped terminal: {err}");
         }
+        self.search_cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        LRI, LineBuilder, LineDamage, Metadata, RenderState, RichTextLines, Size, matches_contain,
+        scrolling_history, scrolling_history_update, selection_text, smart_selection_match,
+        smart_selection_regexes,
+    };
+    use crate::{
+        config::{Config as AppConfig, CopyConfig, ScrollbackLines},
+        graphics::{CursorMovement, ImageCell, placeholder_text},
+        search,
+    };
+    use alacritty_terminal::{
+        Term,
+        event::VoidListener,
+        grid::Dimensions,
+        index::{Column, Line, Point, Side},
+        selection::{Selection, SelectionType},
+        sync::FairMutex,
+        term::{Config, cell::Cell, color::Colors},
+        vte::ansi::{CursorShape, Processor},
+    };
+    use cosmic_text::{Attrs, AttrsList, Buffer, BufferLine, LineEnding, Metrics, Shaping, Weight};
+    use indexmap::IndexSet;
+    use std::sync::Arc;
+
+    const COLUMNS: usize = 10;
+    const LINES: usize = 5;
+
+    fn test_term() -> Term<VoidListener> {
+        let size = Size {
+            width: COLUMNS as u32,
+            height: LINES as u32,
+            cell_width: 1.0,
+            cell_height: 1.0,
+        };
+        Term::new(Config::default(), &size, VoidListener)
+    }
+
+    fn write(term: &mut Term<VoidListener>, bytes: &[u8]) {
+        let mut parser: Processor = Processor::new();
+        parser.advance(term, bytes);
+    }
+
+    fn update_buffer(
+        term: &Term<VoidListener>,
+        buffer: &mut Buffer,
+        damage: &LineDamage,
+    ) -> Vec<Vec<ImageCell>> {
+        let colors = Colors::default();
+        let default_attrs = Attrs::new();
+        let mut metadata_set = IndexSet::new();
+        metadata_set.insert(Metadata::new(
+            cosmic_text::Color::rgb(0, 0, 0),
+            cosmic_text::Color::rgb(0xFF, 0xFF, 0xFF),
+        ));
+        let render_state = RenderState {
+            selection: None,
+            cursor_shape: CursorShape::Block,
+            active_regex_match: None,
+            active_hyperlink_id: None,
+            search_matches: Vec::new(),
+            search_match_opt: None,
+            vi_cursor_opt: None,
+            is_focused: false,
+        };
+        let mut image_lines = vec![Vec::new(); buffer.lines.len()];
+        LineBuilder {
+            colors: &colors,
+            default_attrs: &default_attrs,
+            metadata_set: &mut metadata_set,
+            image_lines: &mut image_lines,
+            prompt_marks: &mut Vec::new(),
+            render_state: &render_state,
+            cursor_point: term.grid().cursor.point,
+            term_colors: term.colors(),
+            bold_font_weight: Weight::BOLD,
+            dim_font_weight: Weight::LIGHT,
+            use_bright_bold: false,
+        }
+        .update_buffer(term, buffer, damage);
+        image_lines
+    }
+
+    #[test]
+    fn damage_only_contains_written_lines() {
+        let mut term = test_term();
+        // A new terminal has not been drawn yet
+        assert_eq!(LineDamage::take(&mut term), LineDamage::Full);
+        // The cursor line is always damaged
+        assert_eq!(LineDamage::take(&mut term), LineDamage::Partial(vec![0]));
+
+        // Move the cursor to the third line and write there
+        write(&mut term, b"\x1b[3;1Habc");
+        // The line the cursor left and the written line
+        assert_eq!(LineDamage::take(&mut term), LineDamage::Partial(vec![0, 2]));
+        assert_eq!(LineDamage::take(&mut term), LineDamage::Partial(vec![2]));
+    }
+
+    #[test]
+    fn cursor_shape_changes_do_not_need_full_damage() {
+        let render_state = RenderState {
+            selection: None,
+            cursor_shape: CursorShape::Block,
+            active_regex_match: None,
+            active_hyperlink_id: None,
+            search_matches: Vec::new(),
+            search_match_opt: None,
+            vi_cursor_opt: None,
+            is_focused: true,
+        };
+        let hidden = RenderState {
+            cursor_shape: CursorShape::Hidden,
+            ..render_state.clone()
+        };
+        assert!(render_state.eq_except_cursor_shape(&hidden));
+        let unfocused = RenderState {
+            is_focused: false,
+            ..hidden.clone()
+        };
+        assert!(!render_state.eq_except_cursor_shape(&unfocused));
+    }
+
+    #[test]
+    fn partial_update_leaves_undamaged_lines_untouched() {
+        let mut term = test_term();
+        let mut buffer = Buffer::new_empty(Metrics::new(1.0, 1.0));
+        for _ in 0..LINES {
+            buffer.lines.push(BufferLine::new(
+                "untouched",
+                LineEnding::default(),
+                AttrsList::new(&Attrs::new()),
+                Shaping::Advanced,
+            ));
+        }
+
+        let _ = LineDamage::take(&mut term);
+        write(&mut term, b"\x1b[3;1Habc");
+        update_buffer(&term, &mut buffer, &LineDamage::Partial(vec![2]));
+
+        for (i, line) in buffer.lines.iter().enumerate() {
+            if i == 2 {
+                assert_eq!(line.text(), format!("{LRI}abc{}", " ".repeat(COLUMNS - 3)));
+            } else {
+                assert_eq!(line.text(), "untouched");
+            }
+        }
+    }
+
+    #[test]
+    fn full_update_rebuilds_all_lines() {
+        let mut term = test_term();
+        let mut buffer = Buffer::new_empty(Metrics::new(1.0, 1.0));
+
+        write(&mut term, b"abc");
+        update_buffer(&term, &mut buffer, &LineDamage::Full);
+
+        assert_eq!(buffer.lines.len(), LINES);
+        assert_eq!(
+            buffer.lines[0].text(),
+            format!("{LRI}abc{}", " ".repeat(COLUMNS - 3))
+        );
+        for line in &buffer.lines[1..] {
+            assert_eq!(line.text(), format!("{LRI}{}", " ".repeat(COLUMNS)));
+        }
+    }
+
+    #[test]
+    fn placeholders_become_image_cells() {
+        let mut term = test_term();
+        let mut buffer = Buffer::new_empty(Metrics::new(1.0, 1.0));
+
+        write(&mut term, b"ab");
+        write(
+            &mut term,
+            &placeholder_text(0x12_3456, 2, 3, 2, CursorMovement::AfterImage),
+        );
+        let image_lines = update_buffer(&term, &mut buffer, &LineDamage::Full);
+
+        // Placeholders are shown as blank cells under the image
+        assert_eq!(
+            buffer.lines[1].text(),
+            format!("{LRI}  {}", " ".repeat(COLUMNS - 2))
+        );
+        assert_eq!(image_lines.len(), LINES);
+        let cells: Vec<_> = image_lines[1]
+            .iter()
+            .map(|cell| {
+                (
+                    cell.column,
+                    cell.image_id,
+                    cell.placement_id,
+                    cell.row,
+                    cell.col,
+                )
+            })
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                (2, 0x12_3456, 2, 1, 0),
+                (3, 0x12_3456, 2, 1, 1),
+                (4, 0x12_3456, 2, 1, 2),
+            ]
+        );
+        assert!(image_lines[2].is_empty());
+    }
+
+    #[test]
+    fn scrollback_size_applies_live() {
+        assert_eq!(
+            scrolling_history(ScrollbackLines::Limited(1_000), 1, COLUMNS),
+            1_000
+        );
+        // Unlimited scrollback is capped by memory, so wider terminals keep fewer lines
+        let unlimited = scrolling_history(ScrollbackLines::Unlimited, 1, COLUMNS);
+        assert_eq!(
+            unlimited,
+            1024 * 1024 / (COLUMNS * std::mem::size_of::<Cell>())
+        );
+        assert!(scrolling_history(ScrollbackLines::Unlimited, 1, COLUMNS * 2) < unlimited);
+
+        assert_eq!(
+            scrolling_history_update(unlimited, ScrollbackLines::Limited(1_000), 1, COLUMNS),
+            Some(1_000)
+        );
+
+        let mut term = test_term();
+        let mut config = Config {
+            scrolling_history: 10,
+            ..Config::default()
+        };
+        term.set_options(config.clone());
+        write(&mut term, "line\r\n".repeat(50).as_bytes());
+        assert_eq!(term.grid().history_size(), 10);
+
+        config.scrolling_history = 3;
+        term.set_options(config);
+        assert_eq!(term.grid().history_size(), 3);
+    }
+
+    #[test]
+    fn scrollback_memory_stays_within_limit_after_resizing() {
+        let memory_limit = 1024 * 1024;
+        let memory = |term: &Term<VoidListener>| {
+            term.grid().history_size() * term.columns() * std::mem::size_of::<Cell>()
+        };
+        // Like the terminal, the history is sized for the widest it has been after resizing
+        fn resize(
+            term: &mut Term<VoidListener>,
+            config: &mut Config,
+            widest: &mut usize,
+            columns: usize,
+        ) {
+            term.resize(Size {
+                width: columns as u32,
+                height: LINES as u32,
+                cell_width: 1.0,
+                cell_height: 1.0,
+            });
+            *widest = (*widest).max(columns);
+            if let Some(lines) = scrolling_history_update(
+                config.scrolling_history,
+                ScrollbackLines::Unlimited,
+                1,
+                *widest,
+            ) {
+                config.scrolling_history = lines;
+                term.set_options(config.clone());
+            }
+        }
+
+        let mut term = test_term();
+        let mut config = Config {
+            scrolling_history: scrolling_history(ScrollbackLines::Unlimited, 1, COLUMNS),
+            ..Config::default()
+        };
+        term.set_options(config.clone());
+        let mut widest = COLUMNS;
+
+        // Narrowing does not let more lines in, so widening again keeps them all
+        resize(&mut term, &mut config, &mut widest, 2);
+        let text = "x\r\n".repeat(config.scrolling_history * 2);
+        write(&mut term, text.as_bytes());
+        let lines = term.grid().history_size();
+        assert_eq!(lines, config.scrolling_history);
+        resize(&mut term, &mut config, &mut widest, COLUMNS);
+        assert_eq!(term.grid().history_size(), lines);
+        assert!(memory(&term) <= memory_limit);
+
+        // Getting wider than ever drops the oldest lines
+        resize(&mut term, &mut config, &mut widest, COLUMNS * 3);
+        assert!(term.grid().history_size() < lines);
+        assert!(memory(&term) <= memory_limit);
+    }
+
+    #[test]
+    fn search_matches_contain_points() {
+        let point = |line, column| Point::new(Line(line), Column(column));
+        let matches = [point(-3, 2)..=point(-3, 4), point(0, 8)..=point(1, 1)];
+        assert!(matches_contain(&matches, point(-3, 2)));
+        assert!(matches_contain(&matches, point(-3, 4)));
+        assert!(!matches_contain(&matches, point(-3, 5)));
+        assert!(matches_contain(&matches, point(0, 9)));
+        assert!(matches_contain(&matches, point(1, 0)));
+        assert!(!matches_contain(&matches, point(1, 2)));
+        assert!(!matches_contain(&matches, point(-4, 0)));
+        assert!(!matches_contain(&[], point(0, 0)));
+    }
+
+    #[test]
+    fn block_selection_trims_lines() {
+        let mut term = test_term();
+        write(&mut term, b"PID  CMD\r\n1    init\r\n42   sh\r\n");
+        let point = |line, column| Point::new(Line(line), Column(column));
+
+        // The first column of the table, with the padding up to the second one
+        let mut selection = Selection::new(SelectionType::Block, point(0, 0), Side::Left);
+        selection.update(point(2, 4), Side::Right);
+        term.selection = Some(selection);
+        let copy = CopyConfig::default();
+        assert_eq!(selection_text(&term, copy).as_deref(), Some("PID\n1\n42"));
+
+        // Other selections keep their whitespace
+        let mut selection = Selection::new(SelectionType::Simple, point(0, 0), Side::Left);
+        selection.update(point(0, 4), Side::Right);
+        term.selection = Some(selection);
+        assert_eq!(selection_text(&term, copy).as_deref(), Some("PID  "));
+    }
+
+    #[test]
+    fn copy_options_trim_and_join() {
+        let mut term = test_term();
+        // The first line wraps after ten columns
+        write(&mut term, b"0123456789abc\r\nd   e\r\n");
+        let point = |line, column| Point::new(Line(line), Column(column));
+        let mut selection = Selection::new(SelectionType::Simple, point(0, 0), Side::Left);
+        selection.update(point(2, 3), Side::Right);
+        term.selection = Some(selection);
+
+        let copy = CopyConfig::default();
+        assert_eq!(
+            selection_text(&term, copy).as_deref(),
+            Some("0123456789abc\nd   ")
+        );
+        let copy = CopyConfig {
+            trim_trailing_whitespace: true,
+            join_wrapped_lines: false,
+            ..copy
+        };
+        assert_eq!(
+            selection_text(&term, copy).as_deref(),
+            Some("0123456789\nabc\nd")
+        );
+    }
+
+    #[test]
+    fn smart_selection_selects_whole_paths() {
+        let mut term = test_term();
+        write(&mut term, b"a/b.rs:12\r\nme@ex.io\r\nword");
+        let mut regexes = smart_selection_regexes(&AppConfig::default().smart_selection);
+        let point = |line, column| Point::new(Line(line), Column(column));
+        let mut bounds = |point| {
+            smart_selection_match(&term, &mut regexes, point)
+                .map(|regex_match| (*regex_match.start(), *regex_match.end()))
+        };
+
+        // The path with its line number, although the default separators split it
+        assert_eq!(bounds(point(0, 7)), Some((point(0, 0), point(0, 8))));
+        assert_eq!(bounds(point(1, 0)), Some((point(1, 0), point(1, 7))));
+        // Other words are left to the word separators
+        assert_eq!(bounds(point(2, 1)), None);
+    }
+
+    #[test]
+    fn rich_text_lines_are_copied_in_slices() {
+        let mut term = test_term();
+        let lines = search::SLICE_LINES + 10;
+        for i in 0..lines {
+            write(&mut term, format!("{}\r\n", i).as_bytes());
+        }
+        let history_size = term.grid().history_size();
+        let rich_text_lines = RichTextLines {
+            term: Arc::new(FairMutex::new(term)),
+            start_line: 0,
+            end_line: history_size + LINES - 1,
+            start_column: Column(0),
+            end_column: Column(COLUMNS - 1),
+            is_block: false,
+            trim_end_lines: true,
+            colors: Colors::default(),
+            default_attrs: Attrs::new(),
+            metadata_set: IndexSet::from([Metadata::new(
+                cosmic_text::Color::rgb(0, 0, 0),
+                cosmic_text::Color::rgb(0xFF, 0xFF, 0xFF),
+            )]),
+            bold_font_weight: Weight::BOLD,
+            dim_font_weight: Weight::LIGHT,
+            use_bright_bold: false,
+            copy_config: CopyConfig::default(),
+        };
+
+        // No line is lost or split between slices
+        let text = rich_text_lines.rich_text().text();
+        let expected: Vec<String> = (0..lines).map(|i| i.to_string()).collect();
+        assert_eq!(text.lines().collect::<Vec<_>>(), expected);
     }
 }
@@ -14,6 +14,7 @@ use cosmic::{
         Border, Shell,
         border::Radius,
         clipboard::Clipboard,
+        image::{self as core_image, Renderer as _},
         input_method::{self, InputMethod},
         keyboard::key::Named,
         layout::{self, Layout},
@@ -33,8 +34,8 @@ use cosmic::{
         mouse::{self, Button, Event as MouseEvent, ScrollDelta},
     },
     theme::Theme,
+    widget::menu::key_bind::KeyBind,
 };
-use cosmic::{iced::core::SmolStr, widget::menu::key_bind::KeyBind};
 use cosmic_text::LayoutGlyph;
 use indexmap::IndexSet;
 use std::{
@@ -46,7 +47,15 @@ use std::{
     time::{Duration, Instant},
 };
 
-use crate::{Action, Terminal, TerminalScroll, menu::MenuState, terminal::Metadata};
+use crate::{
+    Action, Terminal, TerminalScroll,
+    bell::VISUAL_BELL_DURATION,
+    config::HintAction,
+    kitty_keyboard::{self, KeyEventKind, KeyInput},
+    menu::MenuState,
+    shell_integration,
+    terminal::Metadata,
+};
 
 const AUTOSCROLL_INTERVAL: Duration = Duration::from_millis(100);
 
@@ -119,6 +128,8 @@ pub struct TerminalBox<'a, Message> {
     mouse_inside_boundary: Option<bool>,
     on_middle_click: Option<Box<dyn Fn() -> Message + 'a>>,
     on_open_hyperlink: Option<Box<dyn Fn(String) -> Message + 'a>>,
+    on_hint: Option<Box<dyn Fn(HintAction, String) -> Message + 'a>>,
+    on_selection: Option<Box<dyn Fn() -> Message + 'a>>,
     on_window_focused: Option<Box<dyn Fn() -> Message + 'a>>,
     on_window_unfocused: Option<Box<dyn Fn() -> Message + 'a>>,
     key_binds: &'a HashMap<KeyBind, Action>,
@@ -147,6 +158,8 @@ where
             on_middle_click: None,
             key_binds,
             on_open_hyperlink: None,
+            on_hint: None,
+            on_selection: None,
             on_window_focused: None,
             on_window_unfocused: None,
             sharp_corners: false,
@@ -225,6 +238,16 @@ where
         self
     }
 
+    pub fn on_hint(mut self, on_hint: impl Fn(HintAction, String) -> Message + 'a) -> Self {
+        self.on_hint = Some(Box::new(on_hint));
+        self
+    }
+
+    pub fn on_selection(mut self, on_selection: impl Fn() -> Message + 'a) -> Self {
+        self.on_selection = Some(Box::new(on_selection));
+        self
+    }
+
     pub fn on_window_focused(mut self, on_window_focused: impl Fn() -> Message + 'a) -> Self {
         self.on_window_focused = Some(Box::new(on_window_focused));
         self
@@ -252,6 +275,10 @@ where
         if self.context_menu.is_some() {
             return InputMethod::Disabled;
         }
+        // The cursor of copy mode can be in the scrollback, outside of the buffer
+        if terminal.copy_mode_active() {
+            return InputMethod::Disabled;
+        }
 
         let view_position = layout.position() + [self.padding.left, self.padding.top].into();
 
@@ -723,13 +750,87 @@ where
             }
         });
 
+        // Images are drawn in the same layer as the text, which always renders on top of them
+        let text_bounds = Rectangle::new(view_position, Size::new(view_w as f32, view_h as f32));
+        draw_images(renderer, &terminal, view_position, text_bounds, false);
+
         renderer.fill_raw(Raw {
             buffer: terminal.buffer_weak(),
             position: view_position,
             color: Color::from_rgba(1.0, 1.0, 1.0, 1.0), // TODO
-            clip_bounds: Rectangle::new(view_position, Size::new(view_w as f32, view_h as f32)),
+            clip_bounds: text_bounds,
         });
 
+        renderer.with_layer(text_bounds, |renderer| {
+            draw_images(renderer, &terminal, view_position, text_bounds, true);
+        });
+
+        // Draw hint labels over the start of their matches
+        if !terminal.hint_cells().is_empty() {
+            renderer.with_layer(text_bounds, |renderer| {
+                let size = terminal.size();
+                for &(line, column, len) in terminal.hint_cells() {
+                    renderer.fill_quad(
+                        Quad {
+                            bounds: Rectangle::new(
+                                view_position
+                                    + Vector::new(
+                                        (column as f32 * size.cell_width).floor(),
+                                        (line as f32 * size.cell_height).floor(),
+                                    ),
+                                Size::new(len as f32 * size.cell_width, size.cell_height),
+                            ),
+                            border: Border {
+                                radius: 2.0.into(),
+                                width: 0.0,
+                                color: Color::TRANSPARENT,
+                            },
+                            ..Default::default()
+                        },
+                        Color::from(cosmic_theme.accent.base),
+                    );
+                }
+                renderer.fill_raw(Raw {
+                    buffer: terminal.hint_buffer_weak(),
+                    position: view_position,
+                    color: Color::from(cosmic_theme.accent.on),
+                    clip_bounds: text_bounds,
+                });
+            });
+        }
+
+        // Draw exit status of finished commands in the left padding, on the line after the output
+        if self.padding.left >= 2.0 {
+            let marker_w = (self.padding.left / 2.0).floor().max(2.0);
+            let marker_x = layout.position().x + ((self.padding.left - marker_w) / 2.0).floor();
+            let cell_height = terminal.size().cell_height;
+            for (line_i, mark) in terminal.prompt_marks().iter().enumerate() {
+                let Some(exit_status) = mark.exit_status.filter(|_| mark.command_end) else {
+                    continue;
+                };
+                let color = if exit_status == 0 {
+                    cosmic_theme.success.base
+                } else {
+                    cosmic_theme.destructive.base
+                };
+                renderer.fill_quad(
+                    Quad {
+                        bounds: Rectangle::new(
+                            [marker_x, view_position.y + line_i as f32 * cell_height].into(),
+                            Size::new(marker_w, cell_height),
+                        ),
+                        border: Border {
+                            radius: (marker_w / 2.0).into(),
+                            width: 0.0,
+                            color: Color::TRANSPARENT,
+                        },
+                        ..Default::default()
+                    },
+                    Color::from(color),
+                );
+            }
+        }
+
         // Draw scrollbar
         if let Some((start, end)) = terminal.scrollbar() {
             let scrollbar_y = start * view_h as f32;
@@ -799,6 +900,25 @@ where
                 scrollbar_color,
             );
 
+            // Draw tick marks where the find bar query matches
+            let tick_h = 2.0;
+            for tick in terminal.search_ticks() {
+                renderer.fill_quad(
+                    Quad {
+                        bounds: Rectangle::new(
+                            [
+                                view_position.x + view_w as f32,
+                                view_position.y + tick * view_h as f32 - tick_h / 2.0,
+                            ]
+                            .into(),
+                            Size::new(scrollbar_w, tick_h),
+                        ),
+                        ..Default::default()
+                    },
+                    Color::from(cosmic_theme.warning.base),
+                );
+            }
+
             state.scrollbar_rect.set(scrollbar_rect);
         } else {
             state.scrollbar_rect.set(Rectangle::default())
@@ -809,14 +929,16 @@ where
             let term = terminal.term.lock();
             let display_offset = term.grid().display_offset();
             let cursor = term.renderable_content().cursor;
+            let copy_mode = term.mode().contains(TermMode::VI);
             drop(term);
 
-            // Skip drawing cursor when scrolled - the cursor is below the visible viewport
-            if display_offset > 0 {
+            // Skip drawing cursor when scrolled - the cursor is below the visible viewport, except
+            // for the cursor of copy mode, which is always on the screen
+            if display_offset > 0 && !copy_mode {
                 // Cursor is off-screen when scrolled up
             } else {
                 let col = cursor.point.column.0;
-                let line = cursor.point.line.0;
+                let line = cursor.point.line.0 + display_offset as i32;
                 let color = terminal.term.lock().colors()[NamedColor::Cursor]
                     .or(terminal.colors()[NamedColor::Cursor])
                     .map(|rgb| Color::from_rgb8(rgb.r, rgb.g, rgb.b))
@@ -825,10 +947,20 @@ where
                 let height = terminal.size().cell_height;
                 let top_left = view_position
                     + Vector::new((col as f32 * width).floor(), (line as f32 * height).floor());
-                match cursor.shape {
+                let shape = if !terminal.cursor_blink_on() {
+                    CursorShape::Hidden
+                } else if !state.is_focused && cursor.shape != CursorShape::Hidden {
+                    // Unfocused cursors are always drawn hollow
+                    CursorShape::HollowBlock
+                } else {
+                    cursor.shape
+                };
+                // Beam and underline thickness scales with the cell width
+                let thickness = (width / 8.0).round().max(1.0);
+                match shape {
                     CursorShape::Beam => {
                         let quad = Quad {
-                            bounds: Rectangle::new(top_left, Size::new(1.0, height)),
+                            bounds: Rectangle::new(top_left, Size::new(thickness, height)),
                             ..Default::default()
                         };
                         renderer.fill_quad(quad, color);
@@ -839,26 +971,14 @@ where
                                 view_position
                                     + Vector::new(
                                         (col as f32 * width).floor(),
-                                        ((line + 1) as f32 * height).floor(),
+                                        ((line + 1) as f32 * height).floor() - thickness,
                                     ),
-                                Size::new(width, 1.0),
+                                Size::new(width, thickness),
                             ),
                             ..Default::default()
                         };
                         renderer.fill_quad(quad, color);
                     }
-                    CursorShape::Block if !state.is_focused => {
-                        let quad = Quad {
-                            bounds: Rectangle::new(top_left, Size::new(width, height)),
-                            border: Border {
-                                width: 1.0,
-                                color,
-                                ..Default::default()
-                            },
-                            ..Default::default()
-                        };
-                        renderer.fill_quad(quad, Color::TRANSPARENT);
-                    }
                     CursorShape::HollowBlock => {
                         let quad = Quad {
                             bounds: Rectangle::new(top_left, Size::new(width, height)),
@@ -876,6 +996,28 @@ where
             }
         }
 
+        // Flash the pane for the visual bell, fading out over its duration
+        if let Some(visual_bell) = terminal.visual_bell_opt {
+            let elapsed = visual_bell.elapsed();
+            if elapsed < VISUAL_BELL_DURATION {
+                let fg = terminal.effective_color(NamedColor::Foreground as usize);
+                let alpha =
+                    0.3 * (1.0 - elapsed.as_secs_f32() / VISUAL_BELL_DURATION.as_secs_f32());
+                renderer.fill_quad(
+                    Quad {
+                        bounds: layout.bounds(),
+                        border: Border {
+                            radius: border_radius,
+                            ..Default::default()
+                        },
+                        snap: true,
+                        ..Default::default()
+                    },
+                    Color::from_rgba8(fg.r, fg.g, fg.b, alpha),
+                );
+            }
+        }
+
         let duration = instant.elapsed();
         log::trace!("redraw {}, {}: {:?}", view_w, view_h, duration);
     }
@@ -899,8 +1041,118 @@ where
         let mut terminal = self.terminal.lock().unwrap();
         let buffer_size = terminal.with_buffer(|buffer| buffer.size());
 
-        let is_app_cursor = terminal.term.lock().mode().contains(TermMode::APP_CURSOR);
-        let is_mouse_mode = terminal.term.lock().mode().intersects(TermMode::MOUSE_MODE);
+        // Keys select hints instead of reaching the shell while hint labels are shown
+        if state.is_focused
+            && terminal.hints_active()
+            && let Event::Keyboard(key_event) = event
+        {
+            if let KeyEvent::KeyPressed { key, text, .. } = key_event {
+                match key {
+                    Key::Named(Named::Escape) => terminal.hints_cancel(),
+                    Key::Named(Named::Backspace) => terminal.hint_backspace(),
+                    _ => {
+                        if let Some(c) = text.as_ref().and_then(|text| text.chars().next())
+                            && let Some((hint_action, text)) = terminal.hint_input(c)
+                            && let Some(on_hint) = &self.on_hint
+                        {
+                            shell.publish(on_hint(hint_action, text));
+                        }
+                    }
+                }
+            }
+            if terminal.needs_update {
+                shell.request_redraw();
+            }
+            shell.capture_event();
+            return;
+        }
+
+        let mode = *terminal.term.lock().mode();
+
+        // Keys of copy mode are handled by the application and never reach the shell, escape
+        // is left uncaptured so it leaves copy mode
+        if state.is_focused
+            && mode.contains(TermMode::VI)
+            && let Event::Keyboard(key_event) = event
+        {
+            if !matches!(
+                key_event,
+                KeyEvent::KeyPressed {
+                    key: Key::Named(Named::Escape),
+                    ..
+                }
+            ) {
+                shell.capture_event();
+            }
+            return;
+        }
+
+        let is_mouse_mode = mode.intersects(TermMode::MOUSE_MODE);
+
+        // Keys are encoded with the enhancements of the kitty keyboard protocol the application
+        // enabled, or like xterm does without them
+        if state.is_focused {
+            let input_opt = match event {
+                Event::Keyboard(KeyEvent::KeyPressed {
+                    key,
+                    modified_key,
+                    physical_key,
+                    location,
+                    modifiers,
+                    text,
+                    ..
+                }) => Some((
+                    KeyInput {
+                        key,
+                        modified_key,
+                        location: *location,
+                        modifiers: *modifiers,
+                        text: text.as_deref(),
+                        kind: KeyEventKind::Press,
+                    },
+                    physical_key,
+                )),
+                Event::Keyboard(KeyEvent::KeyReleased {
+                    key,
+                    modified_key,
+                    physical_key,
+                    location,
+                    modifiers,
+                    ..
+                }) => Some((
+                    KeyInput {
+                        key,
+                        modified_key,
+                        location: *location,
+                        modifiers: *modifiers,
+                        text: None,
+                        kind: KeyEventKind::Release,
+                    },
+                    physical_key,
+                )),
+                _ => None,
+            };
+            if let Some((input, physical_key)) = input_opt {
+                let is_press = input.kind == KeyEventKind::Press;
+                if self.key_binds.keys().any(|key_bind| {
+                    key_bind.matches(input.modifiers, input.key, Some(physical_key))
+                }) {
+                    // Key binds are handled by the application
+                    if is_press {
+                        shell.capture_event();
+                    }
+                } else if is_local_key(&terminal, &input) {
+                    if is_press {
+                        local_key_press(&mut terminal, &input);
+                        shell.capture_event();
+                    }
+                } else if let Some(escape_code) = kitty_keyboard::encode(mode, &input) {
+                    terminal.input_scroll(escape_code);
+                    shell.capture_event();
+                }
+            }
+        }
+
         match event {
             Event::Window(event) => match event {
                 cosmic::iced::window::Event::Focused => {
@@ -929,6 +1181,13 @@ where
                             shell.request_redraw();
                         }
                     }
+                    if let Some(visual_bell) = terminal.visual_bell_opt {
+                        if visual_bell.elapsed() < VISUAL_BELL_DURATION {
+                            shell.request_redraw();
+                        } else {
+                            terminal.visual_bell_opt = None;
+                        }
+                    }
                     shell.request_input_method(&self.input_method(state, layout, &terminal));
                 }
                 cosmic::iced::window::Event::Unfocused => {
@@ -940,146 +1199,6 @@ where
                 }
                 _ => {}
             },
-            Event::Keyboard(KeyEvent::KeyPressed {
-                key: Key::Named(named),
-                physical_key,
-                modified_key: Key::Named(modified_named),
-                modifiers,
-                text,
-                ..
-            }) if state.is_focused && named == modified_named => {
-                for key_bind in self.key_binds.keys() {
-                    if key_bind.matches(*modifiers, &Key::Named(*named), Some(physical_key)) {
-                        shell.capture_event();
-                        return;
-                    }
-                }
-
-                let mod_no = calculate_modifier_number(state);
-                let escape_code = match named {
-                    Named::Insert => csi("2", "~", mod_no),
-                    Named::Delete => csi("3", "~", mod_no),
-                    Named::PageUp => {
-                        if modifiers.shift() {
-                            terminal.scroll(TerminalScroll::PageUp);
-                            None
-                        } else {
-                            csi("5", "~", mod_no)
-                        }
-                    }
-                    Named::PageDown => {
-                        if modifiers.shift() {
-                            terminal.scroll(TerminalScroll::PageDown);
-                            None
-                        } else {
-                            csi("6", "~", mod_no)
-                        }
-                    }
-                    Named::ArrowUp => {
-                        if is_app_cursor {
-                            ss3("A", mod_no)
-                        } else {
-                            csi2("A", mod_no)
-                        }
-                    }
-                    Named::ArrowDown => {
-                        if is_app_cursor {
-                            ss3("B", mod_no)
-                        } else {
-                            csi2("B", mod_no)
-                        }
-                    }
-                    Named::ArrowRight => {
-                        if is_app_cursor {
-                            ss3("C", mod_no)
-                        } else {
-                            csi2("C", mod_no)
-                        }
-                    }
-                    Named::ArrowLeft => {
-                        if is_app_cursor {
-                            ss3("D", mod_no)
-                        } else {
-                            csi2("D", mod_no)
-                        }
-                    }
-                    Named::End => {
-                        if modifiers.shift() {
-                            terminal.scroll(TerminalScroll::Bottom);
-                            None
-                        } else if is_app_cursor {
-                            ss3("F", mod_no)
-                        } else {
-                            csi2("F", mod_no)
-                        }
-                    }
-                    Named::Home => {
-                        if modifiers.shift() {
-                            terminal.scroll(TerminalScroll::Top);
-                            None
-                        } else if is_app_cursor {
-                            ss3("H", mod_no)
-                        } else {
-                            csi2("H", mod_no)
-                        }
-                    }
-                    Named::F1 => ss3("P", mod_no),
-                    Named::F2 => ss3("Q", mod_no),
-                    Named::F3 => ss3("R", mod_no),
-                    Named::F4 => ss3("S", mod_no),
-                    Named::F5 => csi("15", "~", mod_no),
-                    Named::F6 => csi("17", "~", mod_no),
-                    Named::F7 => csi("18", "~", mod_no),
-                    Named::F8 => csi("19", "~", mod_no),
-                    Named::F9 => csi("20", "~", mod_no),
-                    Named::F10 => csi("21", "~", mod_no),
-                    Named::F11 => csi("23", "~", mod_no),
-                    Named::F12 => csi("24", "~", mod_no),
-                    _ => None,
-                };
-                if let Some(escape_code) = escape_code {
-                    terminal.input_scroll(escape_code);
-                    shell.capture_event();
-
-                    return;
-                }
-
-                //Special handle Enter, Escape, Backspace and Tab as described in
-                //https://sw.kovidgoyal.net/kitty/keyboard-protocol/#legacy-key-event-encoding
-                //Also special handle Ctrl-_ to behave like xterm
-                let alt_prefix = if modifiers.alt() { "\x1B" } else { "" };
-                match named {
-                    Named::Backspace => {
-                        let code = if modifiers.control() { "\x08" } else { "\x7f" };
-                        terminal.input_scroll(format!("{alt_prefix}{code}").into_bytes());
-                        shell.capture_event();
-                    }
-                    Named::Enter => {
-                        terminal.input_scroll(format!("{}{}", alt_prefix, "\x0D").into_bytes());
-                        shell.capture_event();
-                    }
-                    Named::Escape => {
-                        //Escape with any modifier will cancel selection
-                        let had_selection = {
-                            let mut term = terminal.term.lock();
-                            term.selection.take().is_some()
-                        };
-                        if had_selection {
-                            terminal.update();
-                        } else {
-                            terminal.input_scroll(format!("{}{}", alt_prefix, "\x1B").into_bytes());
-                        }
-                        shell.capture_event();
-                    }
-
-                    Named::Tab => {
-                        let code = if modifiers.shift() { "\x1b[Z" } else { "\x09" };
-                        terminal.input_scroll(format!("{alt_prefix}{code}").into_bytes());
-                        shell.capture_event();
-                    }
-                    _ => {}
-                }
-            }
             Event::Keyboard(KeyEvent::ModifiersChanged(modifiers)) => {
                 state.modifiers = *modifiers;
 
@@ -1105,115 +1224,6 @@ where
                     update_active_regex_match(&mut terminal, location, Some(&state.modifiers));
                 }
             }
-            Event::Keyboard(KeyEvent::KeyPressed {
-                text,
-                modifiers,
-                key,
-                physical_key,
-                ..
-            }) if state.is_focused && *key == Key::Character(SmolStr::new(" ")) => {
-                //Special handle Enter, Escape, Backspace and Tab as described in
-                //https://sw.kovidgoyal.net/kitty/keyboard-protocol/#legacy-key-event-encoding
-                //Also special handle Ctrl-_ to behave like xterm
-                let alt_prefix = if modifiers.alt() { "\x1B" } else { "" };
-
-                // Keep this instead of hardcoding the space to allow for dead keys
-                let character = text
-                    .as_ref()
-                    .and_then(|c| c.chars().next())
-                    .unwrap_or_default();
-
-                if modifiers.control() {
-                    // Send NUL character (\x00) for Ctrl + Space
-                    terminal.input_scroll(b"\x00".to_vec());
-                } else {
-                    terminal.input_scroll(format!("{}{}", alt_prefix, character).into_bytes());
-                }
-                shell.capture_event();
-            }
-            Event::Keyboard(KeyEvent::KeyPressed {
-                text,
-                modifiers,
-                key,
-                physical_key,
-                modified_key,
-                ..
-            }) if state.is_focused => {
-                for key_bind in self.key_binds.keys() {
-                    if key_bind.matches(*modifiers, key, Some(physical_key)) {
-                        shell.capture_event();
-
-                        return;
-                    }
-                }
-                let character = text
-                    .as_ref()
-                    .and_then(|c| c.chars().next())
-                    .unwrap_or_default();
-                match (
-                    modifiers.logo(),
-                    modifiers.control(),
-                    modifiers.alt(),
-                    modifiers.shift(),
-                ) {
-                    (true, _, _, _) => {
-                        // Ignore super
-                    }
-                    (false, true, true, _) => {
-                        // Handle ctrl-alt for non-control characters
-                        // and control characters 0-32
-                        if !character.is_control() || (character as u32) < 32 {
-                            // Handle alt for non-control characters
-                            let mut buf = [0x1B, 0, 0, 0, 0];
-                            let len = {
-                                let str = character.encode_utf8(&mut buf[1..]);
-                                str.len() + 1
-                            };
-                            terminal.input_scroll(buf[..len].to_vec());
-                            shell.capture_event();
-                        }
-                    }
-                    (false, true, _, false) => {
-                        // Handle ctrl for control characters (Ctrl-A to Ctrl-Z)
-                        if character.is_control() {
-                            let mut buf = [0, 0, 0, 0];
-                            let str = character.encode_utf8(&mut buf);
-                            terminal.input_scroll(str.as_bytes().to_vec());
-                            shell.capture_event();
-                        }
-                    }
-                    (false, true, _, true) => {
-                        // Ctrl+Shift+<key>: send 0x1F (C-_) on '_', matching xterm/gnome-terminal.
-                        // Use `modified_key` so this works regardless of which physical key
-                        // produces '_' on the user's layout.
-                        if *modified_key == Key::Character("_".into()) {
-                            terminal.input_scroll(b"\x1F".as_slice());
-                            shell.capture_event();
-                        }
-                    }
-                    (false, false, true, _) => {
-                        if !character.is_control() {
-                            // Handle alt for non-control characters
-                            let mut buf = [0x1B, 0, 0, 0, 0];
-                            let len = {
-                                let str = character.encode_utf8(&mut buf[1..]);
-                                str.len() + 1
-                            };
-                            terminal.input_scroll(buf[..len].to_vec());
-                            shell.capture_event();
-                        }
-                    }
-                    (false, false, false, _) => {
-                        // Handle no modifiers for non-control characters
-                        if !character.is_control() {
-                            let mut buf = [0, 0, 0, 0];
-                            let str = character.encode_utf8(&mut buf);
-                            terminal.input_scroll(str.as_bytes().to_vec());
-                            shell.capture_event();
-                        }
-                    }
-                }
-            }
             Event::InputMethod(event) => match event {
                 input_method::Event::Opened | input_method::Event::Closed => {
                     state.preedit = matches!(event, input_method::Event::Opened)
@@ -1288,6 +1298,9 @@ where
                                 } else {
                                     TermSide::Right
                                 };
+                                // Where the selection ends until the mouse moves
+                                let mut drag_point = location;
+                                let mut drag_side = side;
                                 // Check if shift is pressed and there's an existing selection to extend
                                 if state.modifiers.shift() {
                                     let mut term = terminal.term.lock();
@@ -1301,13 +1314,41 @@ where
                                         ));
                                     }
                                 } else {
+                                    // Paths, URLs and similar are selected as a whole
+                                    let smart_match_opt = match click_kind {
+                                        ClickKind::Double => terminal.smart_selection(location),
+                                        _ => None,
+                                    };
                                     let selection = match click_kind {
+                                        // Alt or Ctrl drag selects a block, for columns of tables
+                                        ClickKind::Single
+                                            if state.modifiers.alt()
+                                                || state.modifiers.control() =>
+                                        {
+                                            Selection::new(SelectionType::Block, location, side)
+                                        }
                                         ClickKind::Single => {
                                             Selection::new(SelectionType::Simple, location, side)
                                         }
-                                        ClickKind::Double => {
-                                            Selection::new(SelectionType::Semantic, location, side)
-                                        }
+                                        ClickKind::Double => match smart_match_opt {
+                                            Some(smart_match) => {
+                                                let mut selection = Selection::new(
+                                                    SelectionType::Simple,
+                                                    *smart_match.start(),
+                                                    TermSide::Left,
+                                                );
+                                                selection
+                                                    .update(*smart_match.end(), TermSide::Right);
+                                                drag_point = *smart_match.end();
+                                                drag_side = TermSide::Right;
+                                                selection
+                                            }
+                                            None => Selection::new(
+                                                SelectionType::Semantic,
+                                                location,
+                                                side,
+                                            ),
+                                        },
                                         ClickKind::Triple => {
                                             Selection::new(SelectionType::Lines, location, side)
                                         }
@@ -1321,8 +1362,8 @@ where
                                     edge_scroll_remainder: 0.0,
                                     last_edge_direction: EdgeScrollDirection::None,
                                     last_edge_overshoot: 0.0,
-                                    last_point: location,
-                                    last_side: side,
+                                    last_point: drag_point,
+                                    last_side: drag_side,
                                 });
                             } else if scrollbar_rect.contains(Point::new(x, y)) {
                                 if let Some(start_scroll) = terminal.scrollbar() {
@@ -1401,13 +1442,19 @@ where
                         ..
                     } = dragging
                 {
-                    {
+                    let selected = {
                         let mut term = terminal.term.lock();
                         if let Some(selection) = &mut term.selection {
                             selection.update(last_point, last_side);
                         }
-                    }
+                        term.selection
+                            .as_ref()
+                            .is_some_and(|selection| !selection.is_empty())
+                    };
                     terminal.needs_update = true;
+                    if selected && let Some(on_selection) = &self.on_selection {
+                        shell.publish(on_selection());
+                    }
                 }
                 if let Some(p) = cursor_position.position_in(layout.bounds()) {
                     let x = p.x - self.padding.left;
@@ -1622,6 +1669,72 @@ where
     }
 }
 
+/// Draw the images shown by placeholder cells, either with negative z-index under the text
+/// or the rest over it
+fn draw_images(
+    renderer: &mut Renderer,
+    terminal: &Terminal,
+    view_position: Point,
+    clip_bounds: Rectangle,
+    over_text: bool,
+) {
+    let graphics = terminal.graphics.lock().unwrap();
+    let size = terminal.size();
+    let cell_size = Size::new(size.cell_width, size.cell_height);
+    for (line_i, image_cells) in terminal.image_lines().iter().enumerate() {
+        let mut cells = image_cells.iter().peekable();
+        while let Some(first) = cells.next() {
+            // Adjacent cells of the same image row are drawn together
+            let mut last = first;
+            while let Some(next) = cells.next_if(|next| {
+                next.column == last.column + 1
+                    && next.image_id == first.image_id
+                    && next.placement_id == first.placement_id
+                    && next.row == first.row
+                    && next.col == last.col + 1
+            }) {
+                last = next;
+            }
+
+            let Some((image, placement)) = graphics.get(first.image_id, first.placement_id) else {
+                continue;
+            };
+            if (placement.z_index >= 0) != over_text {
+                continue;
+            }
+
+            // Top left corner of the whole placement, which may be outside of the view
+            let origin = Vector::new(
+                view_position.x + (first.column as f32 - first.col as f32) * cell_size.width,
+                view_position.y + (line_i as f32 - first.row as f32) * cell_size.height,
+            );
+            let cells_bounds = Rectangle::new(
+                view_position
+                    + Vector::new(
+                        first.column as f32 * cell_size.width,
+                        line_i as f32 * cell_size.height,
+                    ),
+                Size::new(
+                    (last.column - first.column + 1) as f32 * cell_size.width,
+                    cell_size.height,
+                ),
+            );
+            let (image_bounds, visible_bounds) = placement.bounds(image, cell_size);
+            let Some(image_clip) = cells_bounds
+                .intersection(&(visible_bounds + origin))
+                .and_then(|bounds| bounds.intersection(&clip_bounds))
+            else {
+                continue;
+            };
+            renderer.draw_image(
+                core_image::Image::new(image.handle.clone()),
+                image_bounds + origin,
+                image_clip,
+            );
+        }
+    }
+}
+
 fn get_hyperlink(
     terminal: &std::sync::MutexGuard<'_, Terminal>,
     location: TermPoint,
@@ -1658,8 +1771,9 @@ fn osc8_hyperlink_at(
     }
     let grid = term.grid();
     let cell = &grid[location];
+    // Shell integration marks are stored as hyperlinks but cannot be opened
     if let Some(link) = cell.hyperlink() {
-        return Some(link);
+        return (!shell_integration::is_mark(&link)).then_some(link);
     }
     if cell
         .flags
@@ -1667,11 +1781,39 @@ fn osc8_hyperlink_at(
         && location.column.0 > 0
     {
         let left = TermPoint::new(location.line, TermColumn(location.column.0 - 1));
-        return grid[left].hyperlink();
+        return grid[left]
+            .hyperlink()
+            .filter(|link| !shell_integration::is_mark(link));
     }
     None
 }
 
+/// Keys that act on the terminal view instead of being sent to the application
+fn is_local_key(terminal: &Terminal, input: &KeyInput) -> bool {
+    match input.key {
+        Key::Named(Named::PageUp | Named::PageDown | Named::Home | Named::End) => {
+            input.modifiers.shift()
+        }
+        // Escape clears the selection
+        Key::Named(Named::Escape) => terminal.term.lock().selection.is_some(),
+        _ => false,
+    }
+}
+
+fn local_key_press(terminal: &mut Terminal, input: &KeyInput) {
+    match input.key {
+        Key::Named(Named::PageUp) => terminal.scroll(TerminalScroll::PageUp),
+        Key::Named(Named::PageDown) => terminal.scroll(TerminalScroll::PageDown),
+        Key::Named(Named::Home) => terminal.scroll(TerminalScroll::Top),
+        Key::Named(Named::End) => terminal.scroll(TerminalScroll::Bottom),
+        Key::Named(Named::Escape) => {
+            terminal.term.lock().selection = None;
+            terminal.update();
+        }
+        _ => {}
+    }
+}
+
 fn update_active_regex_match(
     terminal: &mut std::sync::MutexGuard<'_, Terminal>,
     location: Option<TermPoint>,
@@ -1989,62 +2131,6 @@ impl operation::Focusable for State {
     }
 }
 
-/*
- shift     0b1         (1)
-alt       0b10        (2)
-ctrl      0b100       (4)
-super     0b1000      (8)
-hyper     0b10000     (16)
-meta      0b100000    (32)
-caps_lock 0b1000000   (64)
-num_lock  0b10000000  (128)
-*/
-fn calculate_modifier_number(state: &State) -> u8 {
-    let mut mod_no = 0;
-    if state.modifiers.shift() {
-        mod_no |= 1;
-    }
-    if state.modifiers.alt() {
-        mod_no |= 2;
-    }
-    if state.modifiers.control() {
-        mod_no |= 4;
-    }
-    if state.modifiers.logo() {
-        mod_no |= 8;
-    }
-    mod_no + 1
-}
-
-#[inline(always)]
-fn csi(code: &str, suffix: &str, modifiers: u8) -> Option<Vec<u8>> {
-    if modifiers == 1 {
-        Some(format!("\x1B[{code}{suffix}").into_bytes())
-    } else {
-        Some(format!("\x1B[{code};{modifiers}{suffix}").into_bytes())
-    }
-}
-// https://sw.kovidgoyal.net/kitty/keyboard-protocol/#legacy-functional-keys
-// CSI 1 ; modifier {ABCDEFHPQS}
-// code is ABCDEFHPQS
-#[inline(always)]
-fn csi2(code: &str, modifiers: u8) -> Option<Vec<u8>> {
-    if modifiers == 1 {
-        Some(format!("\x1B[{code}").into_bytes())
-    } else {
-        Some(format!("\x1B[1;{modifiers}{code}").into_bytes())
-    }
-}
-
-#[inline(always)]
-fn ss3(code: &str, modifiers: u8) -> Option<Vec<u8>> {
-    if modifiers == 1 {
-        Some(format!("\x1B\x4F{code}").into_bytes())
-    } else {
-        Some(format!("\x1B[1;{modifiers}{code}").into_bytes())
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::{EdgeScrollDirection, accumulate_wheel_lines, edge_scroll_adjustment};
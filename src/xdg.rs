@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Directory of the app in an XDG base directory, from its variable or its default under the
+/// home directory
+fn app_dir(variable: &str, home_default: &str) -> Option<PathBuf> {
+    let dir = env::var_os(variable)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(home_default)))?;
+    Some(dir.join("cosmic-term"))
+}
+
+/// Directory for files written by users, like layouts
+pub fn config_dir() -> Option<PathBuf> {
+    app_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// Directory for files created by users, like saved sessions
+pub fn data_dir() -> Option<PathBuf> {
+    app_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// Directory for files kept between runs, like logs and the last session
+pub fn state_dir() -> Option<PathBuf> {
+    app_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// Directory for sockets, which only exists while the user is logged in
+pub fn runtime_dir() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty())?;
+    Some(PathBuf::from(dir).join("cosmic-term"))
+}